@@ -6,6 +6,35 @@ use serde::{Deserialize, Serialize};
 
 import_types!("../example.json");
 
+/// The same types with settings given to the macro.
+mod patched {
+    use serde::{Deserialize, Serialize};
+
+    typify::import_types!(
+        schema = "../example.json",
+        derives = [PartialEq],
+        patch = { "Veggie" = { rename = "Vegetable", derives = [Eq] } },
+    );
+}
+
+/// Types from a schema given inline.
+mod inline {
+    use serde::{Deserialize, Serialize};
+
+    typify::import_types!(
+        schema_str = r#"{
+            "title": "Point",
+            "description": "A point on the plane",
+            "type": "object",
+            "required": ["x", "y"],
+            "properties": {
+                "x": { "type": "number" },
+                "y": { "type": "number" }
+            }
+        }"#
+    );
+}
+
 fn main() {
     let veg = Veggie {
         veggie_name: String::from("carrots"),
@@ -16,4 +45,13 @@ fn main() {
         vegetables: vec![veg],
     };
     println!("{:?}", veggies);
+
+    let veg = patched::Vegetable {
+        veggie_name: String::from("carrots"),
+        veggie_like: true,
+    };
+    assert_eq!(veg, veg.clone());
+
+    let point = inline::Point { x: 1.0, y: 2.0 };
+    println!("{:?}", point);
 }
@@ -0,0 +1,10 @@
+// Copyright 2021 Oxide Computer Company
+
+fn main() {
+    // Cargo runs `cargo typify ...` as `cargo-typify typify ...`.
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("typify") {
+        args.next();
+    }
+    std::process::exit(typify_cli::run(args));
+}
@@ -0,0 +1,571 @@
+// Copyright 2021 Oxide Computer Company
+
+//! Generate Rust types from JSON Schema documents on the command line, e.g. to
+//! check the generated code into a repository.
+
+use std::path::PathBuf;
+
+use typify::{
+    AllOfStrategy, DateTimeCrate, DecimalImpl, MapType, NamedSimpleTypes, NonExhaustive,
+    PatternEngine, ReadWriteOnly, SkipSerializingNone, TypeSpace, TypeSpaceSettings, UnknownValues,
+};
+
+pub const USAGE: &str = "\
+Usage: cargo typify [OPTIONS] <SCHEMA>...
+
+Generate Rust types for the JSON Schema documents at the SCHEMA paths.
+
+Options:
+  -o, --output <FILE>             Write the code to FILE rather than stdout
+      --check                     Fail if FILE differs from the code rather than
+                                  writing it
+      --derive <PATH>             Derive PATH for every type; may be repeated
+      --replace <NAME>=<PATH>[:<TRAIT>+...]
+                                  Use the type PATH, which implements the
+                                  TRAITs, rather than generating NAME; may be
+                                  repeated
+      --rename <NAME>=<NEW_NAME>  Name the type NAME as NEW_NAME; may be repeated
+      --crate <CRATE>=<VERSION>   Use types that schemas name in x-rust-type
+                                  extensions from CRATE at VERSION; may be
+                                  repeated
+      --root <NAME>               Generate only the type NAME and those it's
+                                  made of; may be repeated
+      --map-type <TYPE>           hash-map (the default), btree-map, or index-map
+      --all-of-strategy <STRATEGY>
+                                  merge (the default) or flatten
+      --named-simple-types <MODE> newtype (the default), alias, or inline for
+                                  definitions that are arrays, maps, primitives
+                                  and the like
+      --datetime-crate <CRATE>    chrono (the default) or time
+      --decimal-type <TYPE>       serde-json-number, rust-decimal, or
+                                  rust-decimal-float
+      --type-prefix <PREFIX>      Begin the name of every generated type with
+                                  PREFIX
+      --type-suffix <SUFFIX>      End the name of every generated type with
+                                  SUFFIX
+      --module <NAME>             Wrap the code in pub mod NAME
+      --type-visibility <VIS>     Declare types VIS, e.g. pub(crate), not pub
+      --field-visibility <VIS>    Declare fields VIS, e.g. pub(crate), not pub
+      --field-accessors           Generate a method returning each field
+      --extra-properties-name <NAME>
+                                  Name members that capture extra properties NAME
+      --capture-extra-properties  Capture additional properties that objects
+                                  permit without a schema
+      --array-validation          Check array lengths
+      --number-validation         Check number bounds
+      --object-validation         Check the properties that dependentRequired
+                                  requires along with others
+      --string-validation         Check string lengths and patterns
+      --pattern-engine <CRATE>    regress (the default) or regex to check string
+                                  patterns
+      --struct-builder            Generate a builder for each struct
+      --conversion-error <PATH>   Use the type PATH as the error of conversions
+                                  that can fail rather than generating one
+      --skip-serializing-defaults Omit properties with their default values when
+                                  serializing
+      --skip-serializing-none <MODE>
+                                  always (the default), never, or
+                                  unless-nullable to omit optional properties
+                                  that are None when serializing
+      --serialize-empty           Serialize optional arrays and maps that are
+                                  empty rather than omitting them
+      --redacted-debug            Show *** for password and writeOnly properties
+                                  in Debug output
+      --redact <PROPERTY>         Show *** for properties named PROPERTY in Debug
+                                  output
+      --read-write-only <MODE>    ignore (the default), optional, or split for
+                                  readOnly and writeOnly properties
+      --unknown-values <MODE>     reject (the default), unknown, or other for
+                                  values that string enums don't list
+      --non-exhaustive <TYPES>    Mark enums or enums-and-structs non_exhaustive
+      --nonzero-integers          Use NonZero types for integers that can't be 0
+      --minimal-derives           Derive comparison and hashing traits only
+                                  where the code needs them, and never Copy
+      --distinct-inline-types     Generate a type for each inline subschema even
+                                  if it's identical to another
+      --raw-encoded-strings       Keep base64 and base16 strings as String rather
+                                  than Vec<u8>
+      --raw-json-strings          Keep strings of JSON documents as String rather
+                                  than parsing them
+      --ignore-nullable           Ignore the OpenAPI nullable keyword
+      --explicit-nulls            Tell null from absence for optional nullable
+                                  properties with Option<Option<T>> fields
+      --no-std                    Generate code for no_std crates with alloc
+      --draft04                   Treat all documents as draft-04 ones
+      --deny-lossy                Fail where the types wouldn't represent part
+                                  of a schema rather than warning
+      --json-schema-impls         Implement schemars::JsonSchema to give each
+                                  type's original schema (requires the
+                                  schemars feature)
+      --arbitrary-impls           Implement proptest's Arbitrary for each type
+                                  (requires the proptest feature)
+  -h, --help                      Print this message
+
+Exit status is 0 on success, 1 if a schema can't be converted or a --check
+fails, and 2 for invalid arguments.";
+
+/// What to generate, and where.
+#[derive(Debug)]
+pub struct Args {
+    pub schemas: Vec<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub check: bool,
+    /// Whether the code is wrapped in a module, which has its own `use`s.
+    pub module: bool,
+    pub settings: TypeSpaceSettings,
+}
+
+/// Parse the arguments (excluding the program name). The result is `None` if
+/// help was requested.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Args>, String> {
+    let mut args = args.into_iter();
+    let mut schemas = Vec::new();
+    let mut output = None;
+    let mut check = false;
+    let mut module = false;
+    let mut settings = TypeSpaceSettings::default();
+
+    while let Some(arg) = args.next() {
+        // Options may be given as `--option value` or `--option=value`.
+        let (option, inline_value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", option))
+        };
+
+        match option.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "--check" => check = true,
+            "--derive" => {
+                settings.with_derive(value()?);
+            }
+            "--root" => {
+                settings.with_roots([value()?]);
+            }
+            "--replace" => {
+                let (type_name, replace_type) = pair(&option, &value()?)?;
+                let (replace_type, impls) = split_impls(&replace_type);
+                settings.with_replacement(type_name, replace_type, impls);
+            }
+            "--rename" => {
+                let (type_name, rename) = pair(&option, &value()?)?;
+                settings.with_patch(type_name, typify::TypePatch::default().with_rename(rename));
+            }
+            "--crate" => {
+                let (crate_name, version) = pair(&option, &value()?)?;
+                settings.with_crate(crate_name, version);
+            }
+            "--map-type" => {
+                settings.with_map_type(match value()?.as_str() {
+                    "hash-map" => MapType::HashMap,
+                    "btree-map" => MapType::BTreeMap,
+                    "index-map" => MapType::IndexMap,
+                    other => return Err(format!("unknown map type {:?}", other)),
+                });
+            }
+            "--named-simple-types" => {
+                settings.with_named_simple_types(match value()?.as_str() {
+                    "newtype" => NamedSimpleTypes::Newtype,
+                    "alias" => NamedSimpleTypes::Alias,
+                    "inline" => NamedSimpleTypes::Inline,
+                    other => return Err(format!("unknown mode for simple types {:?}", other)),
+                });
+            }
+            "--all-of-strategy" => {
+                settings.with_all_of_strategy(match value()?.as_str() {
+                    "merge" => AllOfStrategy::Merge,
+                    "flatten" => AllOfStrategy::Flatten,
+                    other => return Err(format!("unknown allOf strategy {:?}", other)),
+                });
+            }
+            "--datetime-crate" => {
+                settings.with_datetime_crate(match value()?.as_str() {
+                    "chrono" => DateTimeCrate::Chrono,
+                    "time" => DateTimeCrate::Time,
+                    other => return Err(format!("unknown datetime crate {:?}", other)),
+                });
+            }
+            "--decimal-type" => {
+                settings.with_decimal_type(match value()?.as_str() {
+                    "serde-json-number" => DecimalImpl::SerdeJsonNumber,
+                    "rust-decimal" => DecimalImpl::RustDecimal,
+                    "rust-decimal-float" => DecimalImpl::RustDecimalFloat,
+                    other => return Err(format!("unknown decimal type {:?}", other)),
+                });
+            }
+            "--extra-properties-name" => {
+                settings.with_extra_properties_name(value()?);
+            }
+            "--capture-extra-properties" => {
+                settings.with_capture_extra_properties(true);
+            }
+            "--array-validation" => {
+                settings.with_array_validation(true);
+            }
+            "--number-validation" => {
+                settings.with_number_validation(true);
+            }
+            "--object-validation" => {
+                settings.with_object_validation(true);
+            }
+            "--string-validation" => {
+                settings.with_string_validation(true);
+            }
+            "--pattern-engine" => {
+                settings.with_pattern_engine(match value()?.as_str() {
+                    "regress" => PatternEngine::Regress,
+                    "regex" => PatternEngine::Regex,
+                    other => return Err(format!("unknown pattern engine {:?}", other)),
+                });
+            }
+            "--struct-builder" => {
+                settings.with_struct_builder(true);
+            }
+            "--conversion-error" => {
+                settings.with_conversion_error(value()?);
+            }
+            "--redacted-debug" => {
+                settings.with_redacted_debug(true);
+            }
+            "--redact" => {
+                settings.with_redacted_property(value()?);
+            }
+            "--skip-serializing-defaults" => {
+                settings.with_skip_serializing_defaults(true);
+            }
+            "--skip-serializing-none" => {
+                settings.with_skip_serializing_none(match value()?.as_str() {
+                    "always" => SkipSerializingNone::Always,
+                    "never" => SkipSerializingNone::Never,
+                    "unless-nullable" => SkipSerializingNone::UnlessNullable,
+                    other => return Err(format!("unknown mode for None properties {:?}", other)),
+                });
+            }
+            "--serialize-empty" => {
+                settings.with_skip_serializing_empty(false);
+            }
+            "--read-write-only" => {
+                settings.with_read_write_only(match value()?.as_str() {
+                    "ignore" => ReadWriteOnly::Ignore,
+                    "optional" => ReadWriteOnly::Optional,
+                    "split" => ReadWriteOnly::Split,
+                    other => return Err(format!("unknown readOnly/writeOnly mode {:?}", other)),
+                });
+            }
+            "--unknown-values" => {
+                settings.with_unknown_values(match value()?.as_str() {
+                    "reject" => UnknownValues::Reject,
+                    "unknown" => UnknownValues::Unknown,
+                    "other" => UnknownValues::Other,
+                    other => return Err(format!("unknown mode for unlisted values {:?}", other)),
+                });
+            }
+            "--non-exhaustive" => {
+                settings.with_non_exhaustive(match value()?.as_str() {
+                    "enums" => NonExhaustive::Enums,
+                    "enums-and-structs" => NonExhaustive::EnumsAndStructs,
+                    other => return Err(format!("unknown non-exhaustive types {:?}", other)),
+                });
+            }
+            "--type-prefix" => {
+                settings.with_type_prefix(value()?);
+            }
+            "--type-suffix" => {
+                settings.with_type_suffix(value()?);
+            }
+            "--module" => {
+                settings.with_module(value()?, "pub");
+                module = true;
+            }
+            "--type-visibility" => {
+                settings.with_type_visibility(value()?);
+            }
+            "--field-visibility" => {
+                settings.with_field_visibility(value()?);
+            }
+            "--field-accessors" => {
+                settings.with_field_accessors(true);
+            }
+            "--nonzero-integers" => {
+                settings.with_nonzero_integers(true);
+            }
+            "--minimal-derives" => {
+                settings.with_minimal_derives(true);
+            }
+            "--distinct-inline-types" => {
+                settings.with_distinct_inline_types(true);
+            }
+            "--raw-encoded-strings" => {
+                settings.with_raw_encoded_strings(true);
+            }
+            "--raw-json-strings" => {
+                settings.with_raw_json_strings(true);
+            }
+            "--ignore-nullable" => {
+                settings.with_ignore_nullable(true);
+            }
+            "--explicit-nulls" => {
+                settings.with_explicit_nulls(true);
+            }
+            "--no-std" => {
+                settings.with_no_std(true);
+            }
+            "--draft04" => {
+                settings.with_draft04(true);
+            }
+            "--deny-lossy" => {
+                settings.with_deny_lossy(true);
+            }
+            "--json-schema-impls" => {
+                settings.with_json_schema_impls(true);
+            }
+            "--arbitrary-impls" => {
+                settings.with_arbitrary_impls(true);
+            }
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option {}", arg));
+            }
+            _ => schemas.push(PathBuf::from(arg)),
+        }
+    }
+
+    if schemas.is_empty() {
+        return Err("a schema is required".to_string());
+    }
+    if check && output.is_none() {
+        return Err("--check requires --output".to_string());
+    }
+
+    Ok(Some(Args {
+        schemas,
+        output,
+        check,
+        module,
+        settings,
+    }))
+}
+
+/// Split `NAME=VALUE`.
+fn pair(option: &str, value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() && !value.is_empty() => {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err(format!(
+            "{} requires a value of the form NAME=VALUE",
+            option
+        )),
+    }
+}
+
+/// Split `PATH:TRAIT+TRAIT` into the path and the traits. The separator is a
+/// lone `:` as paths contain `::`.
+fn split_impls(value: &str) -> (&str, Vec<&str>) {
+    let bytes = value.as_bytes();
+    let separator = (0..bytes.len()).find(|&index| {
+        bytes[index] == b':'
+            && (index == 0 || bytes[index - 1] != b':')
+            && bytes.get(index + 1) != Some(&b':')
+    });
+    match separator {
+        Some(index) => (
+            value[..index].trim(),
+            value[index + 1..]
+                .split('+')
+                .map(str::trim)
+                .filter(|imp| !imp.is_empty())
+                .collect(),
+        ),
+        None => (value, Vec::new()),
+    }
+}
+
+/// The formatted code for the types of the schemas. The conversions'
+/// warnings are printed to stderr.
+pub fn generate(args: &Args) -> Result<String, String> {
+    let mut type_space = TypeSpace::new(&args.settings);
+    type_space
+        .add_schema_files(&args.schemas)
+        .map_err(|e| e.to_string())?;
+    for warning in type_space.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    let pruned = type_space.pruned_types();
+    if !pruned.is_empty() {
+        eprintln!(
+            "note: left out {} types that the roots don't use: {}",
+            pruned.len(),
+            pruned.join(", ")
+        );
+    }
+
+    let code = if args.module {
+        type_space.to_stream().to_string()
+    } else {
+        format!(
+            "use serde::{{Deserialize, Serialize}};\n{}",
+            type_space.to_stream()
+        )
+    };
+    rustfmt_wrapper::rustfmt(code).map_err(|e| format!("couldn't format the code: {:?}", e))
+}
+
+/// Generate the code as the arguments say, returning the exit status.
+pub fn run<I: IntoIterator<Item = String>>(args: I) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return 2;
+        }
+    };
+
+    let code = match generate(&args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return 1;
+        }
+    };
+
+    match (&args.output, args.check) {
+        (None, _) => {
+            print!("{}", code);
+            0
+        }
+        (Some(output), true) => match std::fs::read_to_string(output) {
+            Ok(existing) if existing == code => 0,
+            Ok(_) => {
+                eprintln!(
+                    "error: {} is out of date; rerun without --check to update it",
+                    output.display()
+                );
+                1
+            }
+            Err(e) => {
+                eprintln!("error: couldn't read {}: {}", output.display(), e);
+                1
+            }
+        },
+        (Some(output), false) => match std::fs::write(output, code) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("error: couldn't write {}: {}", output.display(), e);
+                1
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{generate, parse_args, split_impls};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(args(&[
+            "api.json",
+            "-o",
+            "src/generated.rs",
+            "--derive",
+            "PartialEq",
+            "--replace=Id=uuid::Uuid",
+            "--map-type",
+            "btree-map",
+            "--check",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(parsed.schemas, [PathBuf::from("api.json")]);
+        assert_eq!(parsed.output, Some(PathBuf::from("src/generated.rs")));
+        assert!(parsed.check);
+
+        assert!(parse_args(args(&["api.json", "--help"])).unwrap().is_none());
+        for (bad, message) in [
+            (
+                &["api.json", "--frobnicate"][..],
+                "unknown option --frobnicate",
+            ),
+            (&["api.json", "--derive"][..], "--derive requires a value"),
+            (
+                &["api.json", "--replace", "Id"][..],
+                "--replace requires a value",
+            ),
+            (&["api.json", "--map-type", "tree"][..], "unknown map type"),
+            (&["api.json", "--check"][..], "--check requires --output"),
+            (&[][..], "a schema is required"),
+        ] {
+            let error = parse_args(args(bad)).unwrap_err();
+            assert!(error.starts_with(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_split_impls() {
+        assert_eq!(split_impls("uuid::Uuid"), ("uuid::Uuid", vec![]));
+        assert_eq!(
+            split_impls("crate::net::IpNet:Display+FromStr"),
+            ("crate::net::IpNet", vec!["Display", "FromStr"])
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let schema = concat!(env!("CARGO_MANIFEST_DIR"), "/../example.json");
+        let parsed = parse_args(args(&[
+            schema,
+            "--derive",
+            "PartialEq",
+            "--rename=Veggie=Vegetable",
+        ]))
+        .unwrap()
+        .unwrap();
+        let code = generate(&parsed).unwrap();
+        assert!(code.starts_with("use serde::{Deserialize, Serialize};\n"));
+        assert!(code.contains("PartialEq"));
+        assert!(code.contains("pub struct Vegetable {"));
+    }
+
+    #[test]
+    fn test_generate_invalid_settings() {
+        let schema = concat!(env!("CARGO_MANIFEST_DIR"), "/../example.json");
+        for (bad, message) in [
+            (&["--derive", "Foo("][..], "invalid derive \"Foo(\""),
+            (&["--derive", "Hash Eq"][..], "invalid derive \"Hash Eq\""),
+            (
+                &["--type-visibility", "pub("][..],
+                "invalid visibility \"pub(\"",
+            ),
+            (&["--type-prefix", "9-"][..], "invalid type prefix \"9-\""),
+            (&["--module", "a b"][..], "invalid module name \"a b\""),
+            (
+                &["--rename", "Veggie=not valid"][..],
+                "invalid rename \"not valid\"",
+            ),
+        ] {
+            let parsed = parse_args(args(&[&[schema][..], bad].concat()))
+                .unwrap()
+                .unwrap();
+            let error = generate(&parsed).unwrap_err();
+            assert!(error.starts_with(message), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,798 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeSet;
+
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::{
+    type_entry::{
+        NewtypeConstraints, SerdeRules, StructProperty, TypeEntry, TypeEntryAlias, TypeEntryEnum,
+        TypeEntryNewtype, TypeEntryStruct, VariantDetails,
+    },
+    TypeId, TypeSpace,
+};
+
+/// How many levels of a recursive type a strategy generates before it takes
+/// the options, arrays and maps that lead back to the type to be empty.
+const RECURSION_DEPTH: u32 = 4;
+
+/// How many items a strategy generates at most, beyond the minimum, for
+/// arrays and maps.
+const EXTRA_ITEMS: u32 = 4;
+
+/// The most strategies that proptest takes as a tuple.
+const MAX_TUPLE: usize = 12;
+
+/// How a strategy refers to the types of a cycle of types that contain one
+/// another, which pass along the depth of the value being generated.
+struct Recursion<'a> {
+    group: &'a BTreeSet<TypeId>,
+    /// Whether the depth has been reached, so that options, arrays and maps
+    /// of the group's types are empty.
+    done: bool,
+}
+
+impl TypeSpace {
+    /// The `impl proptest::arbitrary::Arbitrary` for a named type, if the
+    /// settings ask for them. Values are valid for the type's constraints
+    /// where that's cheap to arrange; for the rest, e.g. patterns, the
+    /// strategy filters generated values by the type's conversion. Types of
+    /// a cycle take the depth as their parameter, and stop recursing at
+    /// `RECURSION_DEPTH`.
+    pub(crate) fn arbitrary_impl(&self, type_entry: &TypeEntry) -> Option<TokenStream> {
+        if !cfg!(feature = "proptest") || !self.settings.arbitrary_impls {
+            return None;
+        }
+        let name = format_ident!("{}", type_entry.name()?);
+        let type_id = self.type_to_id.get(type_entry).cloned().or_else(|| {
+            self.id_to_entry
+                .iter()
+                .find(|(_, entry)| *entry == type_entry)
+                .map(|(type_id, _)| type_id.clone())
+        })?;
+
+        let group = self.recursive_group(&type_id);
+        let (parameters, depth, strategy) = if group.is_empty() {
+            (
+                quote! { () },
+                quote! { _: Self::Parameters },
+                self.named_strategy(type_entry, None)?,
+            )
+        } else {
+            let deeper = self.named_strategy(
+                type_entry,
+                Some(&Recursion {
+                    group: &group,
+                    done: false,
+                }),
+            )?;
+            let done = self.named_strategy(
+                type_entry,
+                Some(&Recursion {
+                    group: &group,
+                    done: true,
+                }),
+            )?;
+            // Types that don't end the recursion themselves needn't check
+            // the depth.
+            let strategy = if deeper.to_string() == done.to_string() {
+                deeper
+            } else {
+                quote! {
+                    if depth < #RECURSION_DEPTH {
+                        #deeper
+                    } else {
+                        #done
+                    }
+                }
+            };
+            (quote! { u32 }, quote! { depth: Self::Parameters }, strategy)
+        };
+
+        Some(quote! {
+            impl proptest::arbitrary::Arbitrary for #name {
+                type Parameters = #parameters;
+                type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+                fn arbitrary_with(#depth) -> Self::Strategy {
+                    use proptest::strategy::Strategy;
+                    #strategy
+                }
+            }
+        })
+    }
+
+    /// The named types that a type reaches and that reach it in turn,
+    /// including the type itself if it contains itself.
+    fn recursive_group(&self, type_id: &TypeId) -> BTreeSet<TypeId> {
+        let reached = self.reached_types(type_id);
+        reached
+            .iter()
+            .filter(|other| self.reached_types(other).contains(type_id))
+            .cloned()
+            .collect()
+    }
+
+    /// The named types within the members of a type, transitively.
+    fn reached_types(&self, type_id: &TypeId) -> BTreeSet<TypeId> {
+        let mut reached = BTreeSet::new();
+        let mut pending = self.members(type_id);
+        while let Some(type_id) = pending.pop() {
+            match self.id_to_entry.get(&type_id) {
+                Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_))
+                    if reached.insert(type_id.clone()) =>
+                {
+                    pending.extend(self.members(&type_id))
+                }
+                Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)) => {}
+                Some(_) => pending.extend(structural_members(self, &type_id)),
+                None => {}
+            }
+        }
+        reached
+    }
+
+    /// The strategy for the values of a named type.
+    fn named_strategy(
+        &self,
+        type_entry: &TypeEntry,
+        recursion: Option<&Recursion>,
+    ) -> Option<TokenStream> {
+        let strategy = match type_entry {
+            TypeEntry::Struct(TypeEntryStruct { properties, .. }) => {
+                self.properties_strategy(quote! { Self }, properties, recursion)
+            }
+
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => {
+                // Variants that contain the group's types other than by way
+                // of options, arrays and maps don't end the recursion, so we
+                // leave them out once it should end if there are others.
+                let variants = variants
+                    .iter()
+                    .filter_map(|variant| {
+                        let name = format_ident!("{}", variant.name);
+                        let (strategy, members) = match &variant.details {
+                            VariantDetails::Simple | VariantDetails::Integer(_) => {
+                                (quote! { proptest::strategy::Just(Self::#name) }, Vec::new())
+                            }
+                            // Values that the schema doesn't list needn't be
+                            // generated.
+                            VariantDetails::Unknown | VariantDetails::Other(_) => return None,
+                            VariantDetails::Tuple(items) => {
+                                let strategies = items
+                                    .iter()
+                                    .map(|type_id| self.id_strategy(type_id, recursion))
+                                    .collect();
+                                let bindings = (0..items.len())
+                                    .map(|index| format_ident!("item_{}", index))
+                                    .collect::<Vec<_>>();
+                                let (strategy, pattern) = tuple_strategy(strategies, &bindings);
+                                (
+                                    quote! {
+                                        #strategy.prop_map(|#pattern| Self::#name(#(#bindings),*))
+                                    },
+                                    items.clone(),
+                                )
+                            }
+                            VariantDetails::Struct(properties) => (
+                                self.properties_strategy(
+                                    quote! { Self::#name },
+                                    properties,
+                                    recursion,
+                                ),
+                                properties.iter().map(|prop| prop.type_id.clone()).collect(),
+                            ),
+                        };
+                        let ends = match recursion {
+                            Some(recursion) => !members
+                                .iter()
+                                .any(|type_id| self.contains_group(type_id, recursion.group)),
+                            None => true,
+                        };
+                        Some((quote! { #strategy.boxed() }, ends))
+                    })
+                    .collect::<Vec<_>>();
+                if variants.is_empty() {
+                    return None;
+                }
+                let variants = match recursion {
+                    Some(Recursion { done: true, .. })
+                        if variants.iter().any(|(_, ends)| *ends) =>
+                    {
+                        variants
+                            .into_iter()
+                            .filter_map(|(strategy, ends)| ends.then_some(strategy))
+                            .collect::<Vec<_>>()
+                    }
+                    _ => variants.into_iter().map(|(strategy, _)| strategy).collect(),
+                };
+                quote! {
+                    proptest::strategy::Union::new(vec![#(#variants),*])
+                }
+            }
+
+            TypeEntry::Newtype(TypeEntryNewtype {
+                type_id,
+                constraints,
+                ..
+            }) => {
+                let sub_type = self.id_to_entry.get(type_id)?;
+                let sub_type_name = sub_type.type_ident(self, false);
+                let try_from = quote! {
+                    |value| <Self as std::convert::TryFrom<#sub_type_name>>::try_from(value).ok()
+                };
+                match (constraints, sub_type) {
+                    (NewtypeConstraints::Const(_), _) => quote! { proptest::strategy::Just(Self) },
+
+                    (NewtypeConstraints::None, TypeEntry::Tuple(items)) if items.len() > 1 => {
+                        let strategies = items
+                            .iter()
+                            .map(|type_id| self.id_strategy(type_id, recursion))
+                            .collect();
+                        let bindings = (0..items.len())
+                            .map(|index| format_ident!("item_{}", index))
+                            .collect::<Vec<_>>();
+                        let (strategy, pattern) = tuple_strategy(strategies, &bindings);
+                        quote! {
+                            #strategy.prop_map(|#pattern| Self(#(#bindings),*))
+                        }
+                    }
+
+                    (NewtypeConstraints::None | NewtypeConstraints::Json, _) => {
+                        let strategy = self.id_strategy(type_id, recursion);
+                        quote! { #strategy.prop_map(Self) }
+                    }
+
+                    (
+                        NewtypeConstraints::String {
+                            max_length,
+                            min_length,
+                            pattern,
+                        },
+                        _,
+                    ) => {
+                        // Strings of the pattern may still be too long or too
+                        // short.
+                        let strategy = match pattern {
+                            Some(pattern) => quote! {
+                                match proptest::string::string_regex(#pattern) {
+                                    Ok(strategy) => strategy.boxed(),
+                                    Err(_) => proptest::arbitrary::any::<String>().boxed(),
+                                }
+                            },
+                            None => {
+                                let size = size_range(*min_length, *max_length);
+                                quote! {
+                                    proptest::collection::vec(proptest::arbitrary::any::<char>(), #size)
+                                    .prop_map(|chars| chars.into_iter().collect::<String>())
+                                }
+                            }
+                        };
+                        quote! {
+                            #strategy.prop_filter_map("invalid string", #try_from)
+                        }
+                    }
+
+                    (
+                        NewtypeConstraints::Array {
+                            max_items,
+                            min_items,
+                        },
+                        TypeEntry::Array(item_id) | TypeEntry::Set(item_id),
+                    ) => {
+                        let item = self.id_strategy(item_id, recursion);
+                        let size = size_range(*min_items, *max_items);
+                        quote! {
+                            proptest::collection::vec(#item, #size)
+                                .prop_filter_map("invalid array", #try_from)
+                        }
+                    }
+
+                    (
+                        NewtypeConstraints::Integer {
+                            minimum,
+                            maximum,
+                            multiple_of,
+                        },
+                        TypeEntry::Integral(name),
+                    ) => integer_strategy(name, *minimum, *maximum, *multiple_of, &sub_type_name),
+
+                    (
+                        NewtypeConstraints::Number {
+                            minimum,
+                            exclusive_minimum,
+                            maximum,
+                            exclusive_maximum,
+                            multiple_of,
+                        },
+                        _,
+                    ) => {
+                        // Exclusive bounds are filtered out by the conversion.
+                        let maximum = maximum.or(*exclusive_maximum).map(|max| max.value());
+                        let minimum = minimum.or(*exclusive_minimum).map(|min| min.value());
+                        let (minimum, maximum) = match (minimum, maximum) {
+                            (Some(min), Some(max)) => (min, max),
+                            (Some(min), None) => (min, min + FLOAT_SPAN),
+                            (None, Some(max)) => (max - FLOAT_SPAN, max),
+                            (None, None) => (-FLOAT_SPAN, FLOAT_SPAN),
+                        };
+                        let cast = matches!(sub_type, TypeEntry::Float(name) if name != "f64")
+                            .then(|| quote! { as #sub_type_name });
+                        let strategy = match multiple_of.map(|factor| factor.value()) {
+                            Some(factor) => {
+                                let low = Literal::i64_suffixed((minimum / factor).ceil() as i64);
+                                let high = Literal::i64_suffixed((maximum / factor).floor() as i64);
+                                let factor = Literal::f64_suffixed(factor);
+                                quote! {
+                                    (#low..=#high).prop_map(|factor| factor as f64 * #factor)
+                                }
+                            }
+                            None => {
+                                let low = Literal::f64_suffixed(minimum);
+                                let high = Literal::f64_suffixed(maximum);
+                                quote! { (#low..=#high) }
+                            }
+                        };
+                        quote! {
+                            #strategy.prop_filter_map(
+                                "invalid number",
+                                |value| <Self as std::convert::TryFrom<#sub_type_name>>::try_from(
+                                    value #cast,
+                                ).ok(),
+                            )
+                        }
+                    }
+
+                    // Any other constraints are left to the conversion.
+                    _ => {
+                        let strategy = self.id_strategy(type_id, recursion);
+                        quote! {
+                            #strategy.prop_filter_map("invalid value", #try_from)
+                        }
+                    }
+                }
+            }
+
+            _ => return None,
+        };
+        Some(quote! { #strategy.boxed() })
+    }
+
+    /// The strategy for a struct or a struct variant, given the path that
+    /// constructs it.
+    fn properties_strategy(
+        &self,
+        path: TokenStream,
+        properties: &[StructProperty],
+        recursion: Option<&Recursion>,
+    ) -> TokenStream {
+        if properties.is_empty() {
+            return quote! { proptest::strategy::Just(#path {}) };
+        }
+        let strategies = properties
+            .iter()
+            .map(|prop| {
+                let strategy = self.id_strategy(&prop.type_id, recursion);
+                match prop.serde_rules {
+                    SerdeRules::ExplicitNull => quote! { proptest::option::of(#strategy) },
+                    _ => strategy,
+                }
+            })
+            .collect();
+        let names = properties
+            .iter()
+            .map(|prop| format_ident!("{}", prop.name))
+            .collect::<Vec<_>>();
+        let (strategy, pattern) = tuple_strategy(strategies, &names);
+        quote! {
+            #strategy.prop_map(|#pattern| #path { #(#names),* })
+        }
+    }
+
+    /// The strategy for the values of the type with the given id.
+    fn id_strategy(&self, type_id: &TypeId, recursion: Option<&Recursion>) -> TokenStream {
+        let type_entry = self.id_to_entry.get(type_id).unwrap();
+        let type_ident = type_entry.type_ident(self, false);
+
+        // Whether this is an option, array or map that is to be empty, as
+        // it's of the group's types and the recursion ends, or of an enum
+        // without variants, e.g. of a `false` schema.
+        let ends = |type_ids: &[&TypeId]| {
+            type_ids.iter().any(|type_id| {
+                matches!(
+                    self.id_to_entry.get(type_id),
+                    Some(TypeEntry::Enum(TypeEntryEnum { variants, .. })) if variants.is_empty()
+                )
+            }) || recursion.is_some_and(|recursion| {
+                recursion.done
+                    && type_ids
+                        .iter()
+                        .any(|type_id| self.reaches_group(type_id, recursion.group))
+            })
+        };
+
+        match type_entry {
+            TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_) => match recursion {
+                Some(recursion) if recursion.group.contains(type_id) => quote! {
+                    proptest::arbitrary::any_with::<#type_ident>(depth + 1)
+                },
+                _ => quote! { proptest::arbitrary::any::<#type_ident>() },
+            },
+
+            TypeEntry::Option(id) if ends(&[id]) => quote! { proptest::strategy::Just(None) },
+            TypeEntry::Option(id) => match self.id_to_entry.get(id) {
+                // Options of options are flattened.
+                Some(TypeEntry::Option(_)) => self.id_strategy(id, recursion),
+                _ => {
+                    let inner = self.id_strategy(id, recursion);
+                    quote! { proptest::option::of(#inner) }
+                }
+            },
+
+            TypeEntry::Array(id) | TypeEntry::Set(id) if ends(&[id]) => {
+                quote! { proptest::strategy::Just(Vec::new()) }
+            }
+            TypeEntry::Array(id) | TypeEntry::Set(id) => {
+                let item = self.id_strategy(id, recursion);
+                let size = size_range(None, None);
+                quote! { proptest::collection::vec(#item, #size) }
+            }
+
+            TypeEntry::Map(key_id, value_id) if ends(&[key_id, value_id]) => {
+                quote! { proptest::strategy::Just(<#type_ident>::default()) }
+            }
+            TypeEntry::Map(key_id, value_id) => {
+                let key = self.id_strategy(key_id, recursion);
+                let value = self.id_strategy(value_id, recursion);
+                let size = size_range(None, None);
+                quote! {
+                    proptest::collection::vec((#key, #value), #size)
+                        .prop_map(|entries| entries.into_iter().collect::<#type_ident>())
+                }
+            }
+
+            TypeEntry::FixedArray(_, 0) => quote! { proptest::strategy::Just([]) },
+            TypeEntry::FixedArray(id, len) => {
+                let item = self.id_strategy(id, recursion);
+                let uniform = format_ident!("uniform{}", len);
+                quote! { proptest::array::#uniform(#item) }
+            }
+
+            TypeEntry::Box(id) => {
+                let inner = self.id_strategy(id, recursion);
+                quote! { #inner.prop_map(Box::new) }
+            }
+
+            // An alias's values are those of the type it names.
+            TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }) => self.id_strategy(id, recursion),
+
+            TypeEntry::Tuple(items) => {
+                let strategies = items
+                    .iter()
+                    .map(|type_id| self.id_strategy(type_id, recursion))
+                    .collect::<Vec<_>>();
+                if items.len() <= MAX_TUPLE {
+                    let trailing = (items.len() == 1).then(|| quote! { , });
+                    quote! { (#(#strategies),* #trailing) }
+                } else {
+                    let bindings = (0..items.len())
+                        .map(|index| format_ident!("item_{}", index))
+                        .collect::<Vec<_>>();
+                    let (strategy, pattern) = tuple_strategy(strategies, &bindings);
+                    quote! { #strategy.prop_map(|#pattern| (#(#bindings),*)) }
+                }
+            }
+
+            TypeEntry::Unit => quote! { proptest::strategy::Just(()) },
+
+            TypeEntry::Integral(name) => match name.strip_prefix("std::num::NonZero") {
+                Some(primitive) => {
+                    let primitive = format_ident!("{}", primitive.to_lowercase());
+                    quote! {
+                        proptest::arbitrary::any::<#primitive>()
+                            .prop_filter_map("zero", #type_ident::new)
+                    }
+                }
+                None => quote! { proptest::arbitrary::any::<#type_ident>() },
+            },
+
+            // Values that JSON can represent.
+            TypeEntry::Float(name) => {
+                let float = format_ident!("{}", name);
+                quote! {
+                    proptest::num::#float::ANY.prop_filter("not finite", |value| value.is_finite())
+                }
+            }
+
+            TypeEntry::String => quote! { proptest::arbitrary::any::<String>() },
+
+            TypeEntry::BuiltIn(name) | TypeEntry::BuiltInWith(name, _, _) => builtin_strategy(name)
+                .unwrap_or_else(|| quote! { proptest::arbitrary::any::<#type_ident>() }),
+
+            TypeEntry::BuiltInGeneric(_, _) => {
+                quote! { proptest::arbitrary::any::<#type_ident>() }
+            }
+
+            TypeEntry::Reference(_) => unreachable!(),
+        }
+    }
+
+    /// Whether the values of a type may contain those of the group's types.
+    fn reaches_group(&self, type_id: &TypeId, group: &BTreeSet<TypeId>) -> bool {
+        group.contains(type_id)
+            || match self.id_to_entry.get(type_id) {
+                Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)) => false,
+                Some(_) => structural_members(self, type_id)
+                    .iter()
+                    .any(|member| self.reaches_group(member, group)),
+                None => false,
+            }
+    }
+
+    /// Whether the values of a type always contain those of the group's
+    /// types, i.e. other than by way of options, arrays and maps.
+    fn contains_group(&self, type_id: &TypeId, group: &BTreeSet<TypeId>) -> bool {
+        group.contains(type_id)
+            || match self.id_to_entry.get(type_id) {
+                Some(TypeEntry::Box(id) | TypeEntry::FixedArray(id, _)) => {
+                    self.contains_group(id, group)
+                }
+                Some(TypeEntry::Tuple(items) | TypeEntry::BuiltInGeneric(_, items)) => {
+                    items.iter().any(|item| self.contains_group(item, group))
+                }
+                _ => false,
+            }
+    }
+}
+
+/// The span of values that a strategy generates beyond a number's one
+/// bound, or around zero if it has neither.
+const FLOAT_SPAN: f64 = 1_000_000.0;
+
+/// The types within a type that isn't a named type, e.g. the items of an
+/// array.
+fn structural_members(type_space: &TypeSpace, type_id: &TypeId) -> Vec<TypeId> {
+    match type_space.id_to_entry.get(type_id) {
+        Some(
+            TypeEntry::Option(id)
+            | TypeEntry::Array(id)
+            | TypeEntry::FixedArray(id, _)
+            | TypeEntry::Set(id)
+            | TypeEntry::Box(id)
+            | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }),
+        ) => vec![id.clone()],
+        Some(TypeEntry::Map(key_id, value_id)) => vec![key_id.clone(), value_id.clone()],
+        Some(TypeEntry::Tuple(items) | TypeEntry::BuiltInGeneric(_, items)) => items.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// A strategy of the given strategies as a tuple, nested if there are more
+/// than proptest takes, and the pattern that binds their values to the
+/// given names.
+fn tuple_strategy(strategies: Vec<TokenStream>, names: &[Ident]) -> (TokenStream, TokenStream) {
+    if strategies.is_empty() {
+        return (quote! { proptest::strategy::Just(()) }, quote! { () });
+    }
+    if strategies.len() <= MAX_TUPLE {
+        let trailing = (strategies.len() == 1).then(|| quote! { , });
+        return (
+            quote! { (#(#strategies),* #trailing) },
+            quote! { (#(#names),* #trailing) },
+        );
+    }
+    let chunk_size = strategies.len().div_ceil(MAX_TUPLE);
+    let (strategies, patterns): (Vec<_>, Vec<_>) = strategies
+        .chunks(chunk_size)
+        .zip(names.chunks(chunk_size))
+        .map(|(strategies, names)| tuple_strategy(strategies.to_vec(), names))
+        .unzip();
+    (quote! { (#(#strategies),*) }, quote! { (#(#patterns),*) })
+}
+
+/// The sizes of an array or string of the given bounds.
+fn size_range(min: Option<u32>, max: Option<u32>) -> TokenStream {
+    let min = min.unwrap_or(0);
+    let max = max.unwrap_or_else(|| min.saturating_add(EXTRA_ITEMS));
+    let min = Literal::usize_unsuffixed(min as usize);
+    let max = Literal::usize_unsuffixed(max as usize);
+    quote! { #min..=#max }
+}
+
+/// The strategy for an integer newtype, generating multiples of its factor
+/// within its bounds and those of its integer type.
+fn integer_strategy(
+    name: &str,
+    minimum: Option<i128>,
+    maximum: Option<i128>,
+    multiple_of: Option<i128>,
+    sub_type_name: &TokenStream,
+) -> TokenStream {
+    let nonzero = name.strip_prefix("std::num::NonZero");
+    let primitive = nonzero.map_or_else(|| name.to_string(), str::to_lowercase);
+    let (type_min, type_max) = match integer_bounds(&primitive) {
+        Some(bounds) => bounds,
+        None => {
+            let type_ident = syn::parse_str::<syn::TypePath>(name).unwrap();
+            return quote! {
+                proptest::arbitrary::any::<#type_ident>().prop_filter_map(
+                    "invalid integer",
+                    |value| <Self as std::convert::TryFrom<#sub_type_name>>::try_from(value).ok(),
+                )
+            };
+        }
+    };
+    let factor = multiple_of.filter(|factor| *factor > 0).unwrap_or(1);
+    let minimum = minimum.map_or(type_min, |min| min.max(type_min));
+    let maximum = maximum.map_or(type_max, |max| max.min(type_max));
+    let low = Literal::i128_suffixed(-(-minimum).div_euclid(factor));
+    let high = Literal::i128_suffixed(maximum.div_euclid(factor));
+    let scaled = match factor {
+        1 => quote! { factor },
+        factor => {
+            let factor = Literal::i128_suffixed(factor);
+            quote! { factor * #factor }
+        }
+    };
+    let primitive = format_ident!("{}", primitive);
+    let wrap = nonzero.map(|_| {
+        let type_ident = syn::parse_str::<syn::TypePath>(name).unwrap();
+        quote! { let value = #type_ident::new(value)?; }
+    });
+    quote! {
+        (#low..=#high).prop_filter_map("invalid integer", |factor| {
+            let value = <#primitive as std::convert::TryFrom<i128>>::try_from(#scaled).ok()?;
+            #wrap
+            <Self as std::convert::TryFrom<#sub_type_name>>::try_from(value).ok()
+        })
+    }
+}
+
+/// The bounds of the integer types that we generate.
+fn integer_bounds(name: &str) -> Option<(i128, i128)> {
+    Some(match name {
+        "u8" => (u8::MIN.into(), u8::MAX.into()),
+        "u16" => (u16::MIN.into(), u16::MAX.into()),
+        "u32" => (u32::MIN.into(), u32::MAX.into()),
+        "u64" => (u64::MIN.into(), u64::MAX.into()),
+        "i8" => (i8::MIN.into(), i8::MAX.into()),
+        "i16" => (i16::MIN.into(), i16::MAX.into()),
+        "i32" => (i32::MIN.into(), i32::MAX.into()),
+        "i64" => (i64::MIN.into(), i64::MAX.into()),
+        _ => return None,
+    })
+}
+
+/// Strategies for the built-in types from other crates, which don't
+/// implement `Arbitrary`.
+fn builtin_strategy(name: &str) -> Option<TokenStream> {
+    Some(match name {
+        "serde_json::Value" => quote! {
+            proptest::strategy::Union::new(vec![
+                proptest::strategy::Just(serde_json::Value::Null).boxed(),
+                proptest::arbitrary::any::<bool>().prop_map(serde_json::Value::Bool).boxed(),
+                proptest::arbitrary::any::<i64>().prop_map(serde_json::Value::from).boxed(),
+                proptest::arbitrary::any::<String>().prop_map(serde_json::Value::String).boxed(),
+            ])
+        },
+        "serde_json::Number" => quote! {
+            proptest::arbitrary::any::<i64>().prop_map(serde_json::Number::from)
+        },
+        "uuid::Uuid" => quote! {
+            proptest::arbitrary::any::<u128>().prop_map(uuid::Uuid::from_u128)
+        },
+        "rust_decimal::Decimal" => quote! {
+            proptest::arbitrary::any::<i64>().prop_map(rust_decimal::Decimal::from)
+        },
+        // Times within the years 1970 to 9999.
+        "chrono::DateTime<chrono::offset::Utc>" => quote! {
+            (0i64..=253_402_300_799).prop_map(|secs| {
+                chrono::TimeZone::timestamp_opt(&chrono::offset::Utc, secs, 0).unwrap()
+            })
+        },
+        "chrono::NaiveDate" => quote! {
+            (1i32..=3_652_059).prop_map(|days| {
+                chrono::NaiveDate::from_num_days_from_ce_opt(days).unwrap()
+            })
+        },
+        "chrono::NaiveTime" => quote! {
+            (0u32..86_400).prop_map(|secs| {
+                chrono::NaiveTime::from_num_seconds_from_midnight_opt(secs, 0).unwrap()
+            })
+        },
+        "time::OffsetDateTime" => quote! {
+            (0i64..=253_402_300_799)
+                .prop_map(|secs| time::OffsetDateTime::from_unix_timestamp(secs).unwrap())
+        },
+        "time::Date" => quote! {
+            (1_721_426i32..=5_373_484).prop_map(|day| time::Date::from_julian_day(day).unwrap())
+        },
+        "time::Time" => quote! {
+            (0u8..24, 0u8..60, 0u8..60).prop_map(|(hour, minute, second)| {
+                time::Time::from_hms(hour, minute, second).unwrap()
+            })
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{test_util::assert_output, TypeSpace, TypeSpaceSettings};
+
+    fn generate(schema: serde_json::Value) -> TypeSpace {
+        let schema = serde_json::from_value::<RootSchema>(schema).unwrap();
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default()
+                .with_string_validation(true)
+                .with_number_validation(true)
+                .with_arbitrary_impls(true),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        type_space
+    }
+
+    #[test]
+    fn test_arbitrary_impls() {
+        let type_space = generate(json!({
+            "definitions": {
+                "Reading": {
+                    "type": "object",
+                    "properties": {
+                        "level": { "$ref": "#/definitions/Level" },
+                        "percent": { "$ref": "#/definitions/Percent" },
+                        "code": { "$ref": "#/definitions/Code" }
+                    },
+                    "required": ["level", "percent"]
+                },
+                "Level": {
+                    "type": "string",
+                    "enum": ["low", "high"]
+                },
+                "Percent": {
+                    "type": "integer",
+                    "format": "uint8",
+                    "maximum": 100,
+                    "multipleOf": 5
+                },
+                "Code": {
+                    "type": "string",
+                    "minLength": 2,
+                    "maxLength": 4
+                }
+            }
+        }));
+        // Multiples of 5 within the bounds of the type and the schema.
+        if cfg!(feature = "proptest") {
+            assert_output(&type_space, "arbitrary/arbitrary_impls-proptest");
+        } else {
+            assert_output(&type_space, "arbitrary/arbitrary_impls");
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_recursion() {
+        let type_space = generate(json!({
+            "definitions": {
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "children": {
+                            "type": "array",
+                            "items": { "$ref": "#/definitions/Node" }
+                        },
+                        "parent": { "$ref": "#/definitions/Node" }
+                    }
+                }
+            }
+        }));
+        // The depth ends the recursion with an empty array and no parent.
+        if cfg!(feature = "proptest") {
+            assert_output(&type_space, "arbitrary/arbitrary_recursion-proptest");
+        }
+    }
+}
@@ -0,0 +1,1164 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use convert_case::{Case, Casing};
+use schemars::schema::{RootSchema, Schema};
+use serde_json::Value;
+
+use crate::{depth, draft04, Error, PathSegment, Result, SchemaLocation};
+
+/// The definitions of some schema documents and those they reference in
+/// other documents, the root schemas of the documents, and the paths of all
+/// the documents that were loaded.
+pub(crate) struct Bundle {
+    pub definitions: schemars::Map<String, Schema>,
+    pub schemas: Vec<Schema>,
+    pub paths: Vec<PathBuf>,
+    /// The paths of the documents of `schemas`.
+    pub roots: Vec<PathBuf>,
+    /// Where each definition came from.
+    pub locations: BTreeMap<String, SchemaLocation>,
+}
+
+impl Bundle {
+    /// Locate an error in one of the definitions within the document where
+    /// the definition was.
+    pub fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::At {
+                mut location,
+                error,
+            } => {
+                self.locate_path(&mut location);
+                Error::At { location, error }
+            }
+            error => error,
+        }
+    }
+
+    /// Make a location within one of the definitions a location within the
+    /// document where the definition was.
+    pub fn locate_path(&self, location: &mut SchemaLocation) {
+        let key = match location.path.as_slice() {
+            [PathSegment::Key(definitions), PathSegment::Key(key), ..]
+                if definitions == "definitions" =>
+            {
+                key
+            }
+            _ => return,
+        };
+        if let Some(definition) = self.locations.get(key) {
+            location.path.splice(0..2, definition.path.iter().cloned());
+            location.file = definition.file.clone();
+        }
+    }
+}
+
+/// What a reference refers to within a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Fragment {
+    /// The whole document.
+    Document,
+    /// One of the document's definitions.
+    Definition(String),
+    /// Some other subschema, by its JSON pointer.
+    Pointer(String),
+}
+
+impl Fragment {
+    fn pointer(&self) -> String {
+        match self {
+            Fragment::Document => String::new(),
+            Fragment::Definition(name) => format!("/definitions/{}", escape(name)),
+            Fragment::Pointer(pointer) => pointer.clone(),
+        }
+    }
+}
+
+type Target = (PathBuf, Fragment);
+
+/// Load the schema documents at `paths` and the documents to which their
+/// `$ref`s refer, resolving relative paths against the directory of the
+/// referring document. Each document is loaded once regardless of how it's
+/// referenced so cycles between documents are fine. The definitions of the
+/// given documents keep their names, and a reference to a definition that
+/// isn't in its own (given) document refers to the definition of that name
+/// in another, or to one of the `known` definitions that were added before;
+/// two different definitions of the same name are an error, while identical
+/// ones are the same definition. Referenced definitions from other documents
+/// are added under their own names (or the file stem for whole documents)
+/// and references to them are rewritten accordingly.
+pub(crate) fn load<P: AsRef<Path>>(
+    paths: &[P],
+    draft04: bool,
+    known: &BTreeSet<String>,
+) -> Result<Bundle> {
+    let roots = paths
+        .iter()
+        .map(|path| canonicalize(path.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+    let mut bundler = Bundler::new(roots.clone(), draft04, known);
+    for root_path in &roots {
+        bundler.document(root_path)?;
+    }
+    let (schemas, definitions) = bundler.bundle()?;
+
+    let describe = || {
+        roots
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let schemas = schemas
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<serde_json::Result<_>>()
+        .map_err(|e| Error::Document(describe(), e.to_string()))?;
+    let definitions = serde_json::from_value(Value::Object(definitions))
+        .map_err(|e| Error::Document(describe(), e.to_string()))?;
+
+    let locations = bundler
+        .targets
+        .iter()
+        .map(|(key, (doc_path, fragment))| {
+            let location = SchemaLocation {
+                file: Some(doc_path.clone()),
+                path: pointer_path(&fragment.pointer()),
+            };
+            (key.clone(), location)
+        })
+        .collect();
+
+    Ok(Bundle {
+        definitions,
+        schemas,
+        paths: bundler.paths,
+        roots,
+        locations,
+    })
+}
+
+/// Resolve the references within a schema document that we were given rather
+/// than loaded; references to other documents are left alone, as are those
+/// to the `known` definitions that the document doesn't have.
+pub(crate) fn resolve(
+    root: RootSchema,
+    draft04: bool,
+    known: &BTreeSet<String>,
+) -> Result<RootSchema> {
+    // Most schemas refer just to their own definitions by name, which
+    // resolving would leave as they are, so we avoid converting those to
+    // JSON values and back.
+    if is_resolved(&root, draft04, known) {
+        return Ok(root);
+    }
+
+    let mut bundler = Bundler::new(vec![PathBuf::new()], draft04, known);
+    let meta_schema = root.meta_schema.clone();
+    bundler.insert(PathBuf::new(), serde_json::to_value(root).unwrap());
+    let (mut schemas, definitions) = bundler.bundle()?;
+
+    let invalid = |e: serde_json::Error| Error::Document("the schema".to_string(), e.to_string());
+    Ok(RootSchema {
+        meta_schema,
+        schema: serde_json::from_value(schemas.remove(0)).map_err(invalid)?,
+        definitions: serde_json::from_value(Value::Object(definitions)).map_err(invalid)?,
+    })
+}
+
+/// Whether resolving the references within a schema would leave it as it is:
+/// every reference is to a definition that it (or a `known` one) has by a
+/// name that needs no escaping, and no subschema has an id or anchor by which
+/// we'd resolve a reference instead.
+fn is_resolved(root: &RootSchema, draft04: bool, known: &BTreeSet<String>) -> bool {
+    let draft04 = draft04
+        || root.meta_schema.as_ref().is_some_and(|meta_schema| {
+            draft04::is_draft04(&serde_json::json!({ "$schema": meta_schema }))
+        });
+    if draft04 {
+        return false;
+    }
+
+    let mut pending = vec![&root.schema];
+    pending.extend(root.definitions.values().filter_map(|schema| match schema {
+        Schema::Object(object) => Some(object),
+        Schema::Bool(_) => None,
+    }));
+    while let Some(object) = pending.pop() {
+        if let Some(reference) = &object.reference {
+            match reference.strip_prefix("#/definitions/") {
+                Some(name)
+                    if !name.contains(['/', '~'])
+                        && (root.definitions.contains_key(name) || known.contains(name)) => {}
+                _ => return false,
+            }
+        }
+
+        // Rewriting looks within the values of keywords too.
+        let metadata = object.metadata.as_deref();
+        let values = object
+            .extensions
+            .values()
+            .chain(object.const_value.as_ref())
+            .chain(object.enum_values.iter().flatten())
+            .chain(metadata.and_then(|metadata| metadata.default.as_ref()))
+            .chain(metadata.into_iter().flat_map(|metadata| &metadata.examples));
+        if metadata.is_some_and(|metadata| metadata.id.is_some())
+            || object.extensions.keys().any(|key| is_identifying(key))
+            || values.into_iter().any(has_identifying_key)
+        {
+            return false;
+        }
+
+        pending.extend(depth::subschemas(object).into_iter().filter_map(
+            |(_, schema)| match schema {
+                Schema::Object(object) => Some(object),
+                Schema::Bool(_) => None,
+            },
+        ));
+    }
+    true
+}
+
+/// Keywords that refer to or identify subschemas, or that hold definitions.
+fn is_identifying(key: &str) -> bool {
+    matches!(key, "$ref" | "$id" | "$anchor" | "$defs" | "definitions")
+}
+
+fn has_identifying_key(value: &Value) -> bool {
+    let mut pending = vec![value];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Object(object) => {
+                if object.keys().any(|key| is_identifying(key)) {
+                    return true;
+                }
+                pending.extend(object.values());
+            }
+            Value::Array(items) => pending.extend(items),
+            _ => {}
+        }
+    }
+    false
+}
+
+struct Bundler {
+    /// The documents we were given, whose definitions keep their names.
+    roots: Vec<PathBuf>,
+    /// Treat all documents as draft-04 ones.
+    draft04: bool,
+    /// The names of definitions added before, to which references from
+    /// documents that lack them refer.
+    known: BTreeSet<String>,
+    documents: HashMap<PathBuf, Value>,
+    /// The document and JSON pointer for each subschema identified by an
+    /// absolute URI, from its `$id`, or by a URI with a plain-name fragment,
+    /// from its `$anchor`.
+    ids: HashMap<String, (PathBuf, String)>,
+    paths: Vec<PathBuf>,
+    keys: HashMap<Target, String>,
+    targets: BTreeMap<String, Target>,
+    pending: Vec<(String, Target)>,
+}
+
+impl Bundler {
+    fn new(roots: Vec<PathBuf>, draft04: bool, known: &BTreeSet<String>) -> Self {
+        Self {
+            roots,
+            draft04,
+            known: known.clone(),
+            documents: Default::default(),
+            ids: Default::default(),
+            paths: Default::default(),
+            keys: Default::default(),
+            targets: Default::default(),
+            pending: Default::default(),
+        }
+    }
+
+    /// The root documents with the references within them and within the
+    /// subschemas they (transitively) reference rewritten to definitions,
+    /// and those definitions: the definitions of the root documents and the
+    /// subschemas they reference.
+    fn bundle(&mut self) -> Result<(Vec<Value>, serde_json::Map<String, Value>)> {
+        let roots = self.roots.clone();
+
+        // All the definitions of the root documents keep their names.
+        for root_path in &roots {
+            let document = self.documents.get(root_path).unwrap();
+            if let Some(Value::Object(definitions)) = document.get("definitions") {
+                for name in definitions.keys().cloned().collect::<Vec<_>>() {
+                    let target = (root_path.clone(), Fragment::Definition(name.clone()));
+                    self.claim(name, target)?;
+                }
+            }
+        }
+
+        let mut root_values = Vec::new();
+        for root_path in &roots {
+            let mut root = self.documents.get(root_path).unwrap().clone();
+            let base = document_base(root_path);
+            self.rewrite(&mut root, root_path, &base)?;
+            root_values.push(root);
+        }
+
+        // Rewriting definitions may reference further definitions, which we
+        // then rewrite in turn.
+        let mut imported = BTreeMap::new();
+        while let Some((key, target)) = self.pending.pop() {
+            let mut schema = self.target_schema(&target)?.into_owned();
+            let document = self.documents.get(&target.0).unwrap();
+            let base = base_at(document, &document_base(&target.0), &target.1.pointer());
+            self.rewrite(&mut schema, &target.0, &base)?;
+            imported.insert(key, (target, schema));
+        }
+
+        // Subschemas that we imported by their pointers become references
+        // where they appear so that they aren't also converted in place.
+        let pointers = imported
+            .iter()
+            .filter_map(|(key, ((doc_path, fragment), _))| match fragment {
+                Fragment::Pointer(pointer) => {
+                    Some((doc_path.clone(), pointer.clone(), key.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (doc_path, pointer, key) in pointers {
+            let reference = serde_json::json!({ "$ref": format!("#/definitions/{}", key) });
+            for (root_path, root) in roots.iter().zip(root_values.iter_mut()) {
+                if *root_path == doc_path {
+                    replace_at(root, "", &pointer, &reference);
+                }
+            }
+            for ((other_path, fragment), schema) in imported.values_mut() {
+                if *other_path == doc_path {
+                    replace_at(schema, &fragment.pointer(), &pointer, &reference);
+                }
+            }
+        }
+
+        let mut definitions = serde_json::Map::new();
+        for root in &mut root_values {
+            if let Value::Object(object) = root {
+                object.remove("$schema");
+                if let Some(Value::Object(root_definitions)) = object.remove("definitions") {
+                    definitions.extend(root_definitions);
+                }
+            }
+        }
+        definitions.extend(imported.into_iter().map(|(key, (_, schema))| (key, schema)));
+
+        Ok((root_values, definitions))
+    }
+
+    fn document(&mut self, path: &Path) -> Result<&Value> {
+        if !self.documents.contains_key(path) {
+            let describe = || path.display().to_string();
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| Error::Document(describe(), e.to_string()))?;
+            let mut document =
+                parse(path, &content).map_err(|reason| Error::Document(describe(), reason))?;
+            merge_defs(&mut document).map_err(|reason| Error::Document(describe(), reason))?;
+            self.insert(path.to_path_buf(), document);
+            self.paths.push(path.to_path_buf());
+        }
+        Ok(self.documents.get(path).unwrap())
+    }
+
+    /// Add a document, normalizing it if it's a draft-04 one, and indexing
+    /// the ids and anchors of its subschemas.
+    fn insert(&mut self, path: PathBuf, mut document: Value) {
+        if self.draft04 || draft04::is_draft04(&document) {
+            draft04::normalize(&mut document);
+        }
+        let mut ids = Vec::new();
+        index(&document, &document_base(&path), String::new(), &mut ids);
+        for (uri, pointer) in ids {
+            self.ids
+                .entry(uri)
+                .or_insert_with(|| (path.clone(), pointer));
+        }
+        self.documents.insert(path, document);
+    }
+
+    /// The subschema to which a target refers, borrowed from its document
+    /// where it appears there as it is.
+    fn target_schema(&self, target: &Target) -> Result<Cow<'_, Value>> {
+        let (doc_path, fragment) = target;
+        let document = self.documents.get(doc_path).unwrap();
+        let doc_name = if doc_path.as_os_str().is_empty() {
+            String::new()
+        } else {
+            self.describe(doc_path)
+        };
+        match fragment {
+            Fragment::Document => {
+                let mut schema = document.clone();
+                if let Value::Object(object) = &mut schema {
+                    object.remove("definitions");
+                    object.remove("$schema");
+                }
+                Ok(Cow::Owned(schema))
+            }
+            Fragment::Definition(name) => document
+                .get("definitions")
+                .and_then(|definitions| definitions.get(name))
+                .map(Cow::Borrowed)
+                .ok_or_else(|| {
+                    Error::UnresolvedRef(
+                        format!("{}#{}", doc_name, fragment.pointer()),
+                        format!("there's no definition named {}", name),
+                    )
+                }),
+            Fragment::Pointer(pointer) => {
+                document.pointer(pointer).map(Cow::Borrowed).ok_or_else(|| {
+                    // Find the longest part of the pointer that does resolve.
+                    let segments = pointer.split('/').collect::<Vec<_>>();
+                    let prefix = (1..segments.len())
+                        .rev()
+                        .map(|len| segments[..len].join("/"))
+                        .find(|prefix| document.pointer(prefix).is_some())
+                        .unwrap_or_default();
+                    Error::UnresolvedRef(
+                        format!("{}#{}", doc_name, pointer),
+                        format!(
+                            "the reference doesn't resolve beyond {}#{}",
+                            doc_name, prefix
+                        ),
+                    )
+                })
+            }
+        }
+    }
+
+    fn describe(&self, doc_path: &Path) -> String {
+        if doc_path.as_os_str().is_empty() {
+            "the schema".to_string()
+        } else {
+            doc_path.display().to_string()
+        }
+    }
+
+    /// Assign a definition name for the target; it's an error if another
+    /// target already has it, unless both are identical definitions.
+    fn claim(&mut self, key: String, target: Target) -> Result<()> {
+        if let Some(other) = self.targets.get(&key) {
+            if matches!(
+                (&target.1, &other.1),
+                (Fragment::Definition(_), Fragment::Definition(_))
+            ) && self.target_schema(&target)? == self.target_schema(other)?
+            {
+                self.keys.insert(target, key);
+                return Ok(());
+            }
+            return Err(Error::Document(
+                self.describe(&target.0),
+                format!(
+                    "the definition {} conflicts with the different definition of the same name from {}",
+                    key,
+                    self.describe(&other.0),
+                ),
+            ));
+        }
+        self.targets.insert(key.clone(), target.clone());
+        self.keys.insert(target, key);
+        Ok(())
+    }
+
+    /// The definition name for a target, loading its document and queuing it
+    /// to be imported if we haven't seen it before.
+    fn key(&mut self, target: Target) -> Result<String> {
+        if let Some(key) = self.keys.get(&target) {
+            return Ok(key.clone());
+        }
+        self.document(&target.0)?;
+        let key = match &target.1 {
+            Fragment::Document => target
+                .0
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Fragment::Definition(name) => name.clone(),
+            // Synthesize a name from the pointer, avoiding those in use.
+            Fragment::Pointer(pointer) => {
+                let name = pointer_name(pointer);
+                (1..)
+                    .map(|n| match n {
+                        1 => name.clone(),
+                        n => format!("{}{}", name, n),
+                    })
+                    .find(|key| !self.targets.contains_key(key) && !self.known.contains(key))
+                    .unwrap()
+            }
+        };
+        self.claim(key.clone(), target.clone())?;
+        self.pending.push((key.clone(), target));
+        Ok(key)
+    }
+
+    /// Rewrite each `$ref` within a schema from the document at `doc_path`,
+    /// whose base URI is `base` where the schema appears, to refer to a
+    /// definition of the root document.
+    fn rewrite(&mut self, value: &mut Value, doc_path: &Path, base: &str) -> Result<()> {
+        match value {
+            Value::Object(object) => {
+                let base = id_base(object, base);
+                if let Some(Value::String(reference)) = object.get_mut("$ref") {
+                    if let Some(new_reference) = self.resolve(reference, doc_path, &base)? {
+                        *reference = new_reference;
+                    }
+                }
+                for (_, child) in object.iter_mut() {
+                    self.rewrite(child, doc_path, &base)?;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.rewrite(item, doc_path, base)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The rewritten form of a reference, or None to leave it alone.
+    fn resolve(&mut self, reference: &str, doc_path: &Path, base: &str) -> Result<Option<String>> {
+        // References to the ids and anchors of subschemas we've already loaded
+        // take precedence, following the base URI rather than the file.
+        let uri = join_uri(base, reference);
+        let (absolute, fragment) = match uri.split_once('#') {
+            Some((absolute, fragment)) => (absolute, fragment),
+            None => (uri.as_str(), ""),
+        };
+        let identified = if fragment.is_empty() || fragment.starts_with('/') {
+            self.ids
+                .get(absolute)
+                .map(|(path, pointer)| (path.clone(), format!("{}{}", pointer, fragment)))
+        } else {
+            self.ids.get(&uri).cloned()
+        };
+        if let Some((target_path, pointer)) = identified {
+            return self.reference_to(doc_path, target_path, parse_fragment(&pointer).unwrap());
+        }
+
+        let (file, fragment) = match reference.split_once('#') {
+            Some((file, fragment)) => (file, fragment),
+            None => (reference, ""),
+        };
+        let unsupported = || {
+            Error::Unsupported(format!(
+                "the reference {} in {}",
+                reference,
+                self.describe(doc_path)
+            ))
+        };
+
+        let target_path = if file.is_empty() {
+            doc_path.to_path_buf()
+        } else if doc_path.as_os_str().is_empty() {
+            // We can't load other documents for a schema we were given.
+            return Ok(None);
+        } else if file.contains("://") {
+            return Err(unsupported());
+        } else {
+            let dir = doc_path.parent().unwrap_or_else(|| Path::new(""));
+            canonicalize(&dir.join(file))?
+        };
+
+        let fragment = parse_fragment(fragment).ok_or_else(unsupported)?;
+        self.reference_to(doc_path, target_path, fragment)
+    }
+
+    fn reference_to(
+        &mut self,
+        doc_path: &Path,
+        target_path: PathBuf,
+        fragment: Fragment,
+    ) -> Result<Option<String>> {
+        // A root document as a whole has no definition.
+        if target_path == doc_path
+            && self.roots.contains(&target_path)
+            && fragment == Fragment::Document
+        {
+            return Ok(None);
+        }
+
+        // A root document may refer to the definitions of another by name.
+        if let Fragment::Definition(name) = &fragment {
+            let defined = self
+                .documents
+                .get(&target_path)
+                .and_then(|document| document.get("definitions")?.get(name))
+                .is_some();
+            if !defined && self.roots.contains(&target_path) {
+                if let Some((other_path, Fragment::Definition(_))) = self.targets.get(name) {
+                    if self.roots.contains(other_path) {
+                        return Ok(Some(format!("#/definitions/{}", escape(name))));
+                    }
+                }
+                if self.known.contains(name) {
+                    return Ok(Some(format!("#/definitions/{}", escape(name))));
+                }
+            }
+        }
+
+        let key = self.key((target_path, fragment))?;
+        Ok(Some(format!("#/definitions/{}", escape(&key))))
+    }
+}
+
+/// Parse the fragment of a reference, a JSON pointer, treating `$defs` as
+/// `definitions` as we've merged them. Other fragments, such as anchors,
+/// aren't supported.
+fn parse_fragment(fragment: &str) -> Option<Fragment> {
+    if fragment.is_empty() {
+        return Some(Fragment::Document);
+    }
+    if !fragment.starts_with('/') {
+        return None;
+    }
+
+    let pointer = match fragment.strip_prefix("/$defs/") {
+        Some(rest) => format!("/definitions/{}", rest),
+        None => fragment.to_string(),
+    };
+    match pointer.strip_prefix("/definitions/") {
+        Some(name) if !name.contains('/') => Some(Fragment::Definition(unescape(name))),
+        _ => Some(Fragment::Pointer(pointer)),
+    }
+}
+
+/// A name for the subschema at a JSON pointer from its path, e.g.
+/// `ConfigRetries` for `/properties/config/properties/retries`.
+fn pointer_name(pointer: &str) -> String {
+    let name = pointer
+        .split('/')
+        .skip(1)
+        .map(unescape)
+        .filter(|segment| !matches!(segment.as_str(), "properties" | "definitions"))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_case(Case::Pascal);
+    if name.is_empty() {
+        "Subschema".to_string()
+    } else {
+        name
+    }
+}
+
+/// Replace the subschema at `pointer` within `schema`, which is itself at
+/// `base`, if `pointer` is within it.
+fn replace_at(schema: &mut Value, base: &str, pointer: &str, replacement: &Value) {
+    if let Some(rest) = pointer.strip_prefix(base) {
+        if rest.starts_with('/') {
+            if let Some(value) = schema.pointer_mut(rest) {
+                *value = replacement.clone();
+            }
+        }
+    }
+}
+
+/// The base URI of a document, from which its `$id` (if any) is resolved.
+fn document_base(doc_path: &Path) -> String {
+    if doc_path.as_os_str().is_empty() {
+        String::new()
+    } else {
+        format!("file://{}", doc_path.display())
+    }
+}
+
+/// The base URI within a schema whose enclosing base URI is `base`, which
+/// its `$id` may change.
+fn id_base(schema: &serde_json::Map<String, Value>, base: &str) -> String {
+    match schema.get("$id") {
+        // Ids that are just fragments are anchors (as in draft-07).
+        Some(Value::String(id)) if !id.starts_with('#') => {
+            let uri = join_uri(base, id);
+            match uri.split_once('#') {
+                Some((absolute, _)) => absolute.to_string(),
+                None => uri,
+            }
+        }
+        _ => base.to_string(),
+    }
+}
+
+/// The base URI where the subschema at `pointer` appears (i.e. not
+/// considering its own `$id`) in a document whose base URI is `base`.
+fn base_at(document: &Value, base: &str, pointer: &str) -> String {
+    let mut base = base.to_string();
+    let mut value = document;
+    for segment in pointer.split('/').skip(1) {
+        let child = match value {
+            Value::Object(object) => {
+                base = id_base(object, &base);
+                object.get(&unescape(segment))
+            }
+            Value::Array(items) => segment.parse().ok().and_then(|i: usize| items.get(i)),
+            _ => None,
+        };
+        match child {
+            Some(child) => value = child,
+            None => break,
+        }
+    }
+    base
+}
+
+/// Collect the URIs of subschemas with an `$id` or `$anchor` (or an `$id`
+/// that's a fragment) within the schema at `pointer`.
+fn index(value: &Value, base: &str, pointer: String, ids: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            let schema_base = id_base(object, base);
+            if let Some(Value::String(id)) = object.get("$id") {
+                let uri = join_uri(base, id);
+                if !id.starts_with('#') {
+                    ids.push((schema_base.clone(), pointer.clone()));
+                }
+                if uri.contains('#') {
+                    ids.push((uri, pointer.clone()));
+                }
+            }
+            if let Some(Value::String(anchor)) = object.get("$anchor") {
+                ids.push((format!("{}#{}", schema_base, anchor), pointer.clone()));
+            }
+            for (key, child) in object {
+                // These hold values rather than schemas.
+                if !matches!(key.as_str(), "const" | "default" | "enum" | "examples") {
+                    index(
+                        child,
+                        &schema_base,
+                        format!("{}/{}", pointer, escape(key)),
+                        ids,
+                    );
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                index(item, base, format!("{}/{}", pointer, i), ids);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The parts of a URI reference; see RFC 3986.
+struct UriParts<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> UriParts<'a> {
+    fn parse(uri: &'a str) -> Self {
+        let (rest, fragment) = match uri.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (uri, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (scheme, rest) = match rest.split_once(':') {
+            Some((scheme, rest))
+                if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) =>
+            {
+                (Some(scheme), rest)
+            }
+            _ => (None, rest),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, rest),
+        };
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+/// Resolve a URI reference against a base URI as described in RFC 3986.
+fn join_uri(base: &str, reference: &str) -> String {
+    let r = UriParts::parse(reference);
+    let b = UriParts::parse(base);
+
+    let (scheme, authority, path, query) = if r.scheme.is_some() {
+        (r.scheme, r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.authority.is_some() {
+        (b.scheme, r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.path.is_empty() {
+        (
+            b.scheme,
+            b.authority,
+            b.path.to_string(),
+            r.query.or(b.query),
+        )
+    } else if r.path.starts_with('/') {
+        (b.scheme, b.authority, remove_dot_segments(r.path), r.query)
+    } else {
+        let merged = if b.authority.is_some() && b.path.is_empty() {
+            format!("/{}", r.path)
+        } else {
+            match b.path.rfind('/') {
+                Some(i) => format!("{}{}", &b.path[..=i], r.path),
+                None => r.path.to_string(),
+            }
+        };
+        (b.scheme, b.authority, remove_dot_segments(&merged), r.query)
+    };
+
+    let mut uri = String::new();
+    if let Some(scheme) = scheme {
+        uri.push_str(scheme);
+        uri.push(':');
+    }
+    if let Some(authority) = authority {
+        uri.push_str("//");
+        uri.push_str(authority);
+    }
+    uri.push_str(&path);
+    if let Some(query) = query {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    if let Some(fragment) = r.fragment {
+        uri.push('#');
+        uri.push_str(fragment);
+    }
+    uri
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let segments = path.split('/').collect::<Vec<_>>();
+    let mut output: Vec<&str> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i == segments.len() - 1;
+        match *segment {
+            "." => {}
+            ".." => {
+                // Never remove the empty segment that makes the path absolute.
+                if output.len() > 1 || output.first().is_some_and(|s| !s.is_empty()) {
+                    output.pop();
+                }
+            }
+            segment => {
+                output.push(segment);
+                continue;
+            }
+        }
+        if last {
+            output.push("");
+        }
+    }
+    output.join("/")
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// The path of keys and indices of a JSON pointer. Segments are indices
+/// where they follow keywords whose values are arrays of subschemas.
+fn pointer_path(pointer: &str) -> Vec<PathSegment> {
+    let mut path = Vec::new();
+    for segment in pointer.split('/').skip(1).map(unescape) {
+        let in_array = matches!(
+            path.last(),
+            Some(PathSegment::Key(key))
+                if matches!(key.as_str(), "allOf" | "anyOf" | "oneOf" | "items" | "prefixItems")
+        );
+        match segment.parse() {
+            Ok(index) if in_array => path.push(PathSegment::Index(index)),
+            _ => path.push(PathSegment::Key(segment)),
+        }
+    }
+    path
+}
+
+/// Draft 2019-09 and later put definitions under `$defs` rather than
+/// `definitions`; we merge the two so that a document may use both.
+fn merge_defs(document: &mut Value) -> std::result::Result<(), String> {
+    if let Value::Object(object) = document {
+        let defs = match object.remove("$defs") {
+            None => return Ok(()),
+            Some(Value::Object(defs)) => defs,
+            Some(_) => return Err("$defs isn't an object".to_string()),
+        };
+        let definitions = object
+            .entry("definitions")
+            .or_insert_with(|| Value::Object(Default::default()));
+        let definitions = match definitions {
+            Value::Object(definitions) => definitions,
+            _ => return Err("definitions isn't an object".to_string()),
+        };
+        for (name, schema) in defs {
+            if definitions.contains_key(&name) {
+                return Err(format!("{} is in both definitions and $defs", name));
+            }
+            definitions.insert(name, schema);
+        }
+    }
+    Ok(())
+}
+
+/// Parse a document as YAML if its extension says it is, and JSON otherwise.
+fn parse(path: &Path, content: &str) -> std::result::Result<Value, String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "serde_yaml")]
+        Some("yaml" | "yml") => crate::yaml::from_str(content),
+        #[cfg(not(feature = "serde_yaml"))]
+        Some("yaml" | "yml") => Err("YAML requires the serde_yaml feature".to_string()),
+        _ => serde_json::from_str(content).map_err(|e| e.to_string()),
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .map_err(|e| Error::Document(path.display().to_string(), e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, path::Path};
+
+    use schemars::schema::Schema;
+    use serde_json::json;
+
+    use super::{is_resolved, join_uri, load};
+
+    #[test]
+    fn test_load_external_refs() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/root.json");
+        let bundle = load(&[&path], false, &BTreeSet::new()).unwrap();
+
+        // The common document is loaded once despite the different paths
+        // used to reference it, and the cycle back to the root is fine.
+        let names = bundle
+            .paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["root.json", "common.json", "color.json"]);
+
+        let definitions = &bundle.definitions;
+        assert_eq!(
+            definitions.keys().collect::<Vec<_>>(),
+            ["Item", "Tag", "color"]
+        );
+        let reference = |name: &str, property: &str| match definitions.get(name).unwrap() {
+            Schema::Object(object) => match object
+                .object
+                .as_ref()
+                .unwrap()
+                .properties
+                .get(property)
+                .unwrap()
+            {
+                Schema::Object(object) => object.reference.clone().unwrap(),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(reference("Item", "tag"), "#/definitions/Tag");
+        assert_eq!(reference("Item", "other_tag"), "#/definitions/Tag");
+        assert_eq!(reference("Tag", "color"), "#/definitions/color");
+        assert_eq!(reference("Tag", "item"), "#/definitions/Item");
+    }
+
+    #[test]
+    fn test_load_missing_definition() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/missing.json");
+        let err = load(&[&path], false, &BTreeSet::new())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.ends_with("common.json#/definitions/Missing: there's no definition named Missing")
+        );
+    }
+
+    #[test]
+    fn test_load_defs() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/defs.json");
+        let bundle = load(&[&path], false, &BTreeSet::new()).unwrap();
+        assert_eq!(
+            bundle.definitions.keys().collect::<Vec<_>>(),
+            ["Holder", "Item", "Name", "Tag", "color"]
+        );
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/defs_conflict.json");
+        let err = load(&[&path], false, &BTreeSet::new())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.ends_with("defs_conflict.json: Name is in both definitions and $defs"));
+    }
+
+    #[test]
+    fn test_load_pointer() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/pointer.json");
+        let bundle = load(&[&path], false, &BTreeSet::new()).unwrap();
+        assert_eq!(
+            bundle.definitions.keys().collect::<Vec<_>>(),
+            ["Item", "Label", "Tag", "TagItem", "color"]
+        );
+        // The subschema is replaced by a reference where it appears.
+        let tag = serde_json::to_value(&bundle.definitions["Tag"]).unwrap();
+        assert_eq!(
+            tag.pointer("/properties/item/$ref"),
+            Some(&json!("#/definitions/TagItem"))
+        );
+
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/pointer_missing.json");
+        let err = load(&[&path], false, &BTreeSet::new())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("common.json#/definitions/Tag/properties/size"));
+        assert!(err.ends_with("common.json#/definitions/Tag/properties"));
+    }
+
+    #[test]
+    fn test_load_multiple() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/multi");
+        let bundle = load(
+            &[dir.join("a.json"), dir.join("b.json")],
+            false,
+            &BTreeSet::new(),
+        )
+        .unwrap();
+
+        // Shared definitions are loaded once.
+        assert_eq!(
+            bundle.definitions.keys().collect::<Vec<_>>(),
+            ["Item", "Part", "Tag", "Widget", "color"]
+        );
+        assert_eq!(bundle.schemas.len(), 2);
+        let part = serde_json::to_value(&bundle.definitions["Part"]).unwrap();
+        assert_eq!(
+            part.pointer("/properties/used_in/$ref"),
+            Some(&json!("#/definitions/Widget"))
+        );
+
+        let err = load(
+            &[dir.join("a.json"), dir.join("b.json"), dir.join("c.json")],
+            false,
+            &BTreeSet::new(),
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(err.contains("c.json"), "{}", err);
+        assert!(err.contains("b.json"), "{}", err);
+
+        // Identical definitions of the same name are one definition.
+        let bundle = load(
+            &[dir.join("a.json"), dir.join("b.json"), dir.join("d.json")],
+            false,
+            &BTreeSet::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            bundle.definitions.keys().collect::<Vec<_>>(),
+            ["Assembly", "Item", "Part", "Tag", "Widget", "color"]
+        );
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn test_load_yaml() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external");
+        let bundle = load(&[dir.join("pet.yaml")], false, &BTreeSet::new()).unwrap();
+        assert_eq!(
+            bundle.definitions.keys().collect::<Vec<_>>(),
+            ["Item", "Pet", "Tag", "color"]
+        );
+    }
+
+    #[test]
+    fn test_join_uri() {
+        let base = "http://a/b/c/d;p?q";
+        for (reference, expected) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../../../g", "http://a/g"),
+            ("g/./h/../i", "http://a/b/c/g/i"),
+        ] {
+            assert_eq!(join_uri(base, reference), expected, "{}", reference);
+        }
+        assert_eq!(join_uri("", "#/definitions/X"), "#/definitions/X");
+    }
+
+    #[test]
+    fn test_is_resolved() {
+        let known = vec!["Known".to_string()]
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        let resolved = |value| is_resolved(&serde_json::from_value(value).unwrap(), false, &known);
+
+        assert!(resolved(json!({
+            "type": "array",
+            "items": { "$ref": "#/definitions/Item" },
+            "definitions": {
+                "Item": {
+                    "properties": {
+                        "known": { "$ref": "#/definitions/Known" },
+                        "items": { "$ref": "#/definitions/Item" }
+                    }
+                }
+            }
+        })));
+        for value in [
+            json!({ "$ref": "#/definitions/Missing" }),
+            json!({ "$ref": "#/definitions/Item/properties/name" }),
+            json!({ "$ref": "#/$defs/Item" }),
+            json!({ "items": { "$ref": "#/definitions/a~1b" } }),
+            json!({ "items": { "$id": "http://example.com/item.json" } }),
+            json!({ "items": { "$anchor": "item" } }),
+            json!({ "prefixItems": [{ "$ref": "#/definitions/Known" }] }),
+            json!({
+                "$schema": "http://json-schema.org/draft-04/schema#",
+                "type": "object"
+            }),
+        ] {
+            assert!(!resolved(value.clone()), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2021 Oxide Computer Company
+
+use crate::type_entry::{SerdeNaming, StructProperty, Variant, VariantDetails};
+
+/// The rules of serde's `rename_all` that we use in place of renaming each
+/// field or variant; they apply to fields and variants as serde's do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenameRule {
+    Lower,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+}
+
+/// The rules we consider for the fields of structs, whose names are
+/// snake_case, in order of preference.
+const FIELD_RULES: &[RenameRule] = &[
+    RenameRule::Camel,
+    RenameRule::Pascal,
+    RenameRule::Kebab,
+    RenameRule::ScreamingSnake,
+];
+
+/// The rules we consider for variants, whose names are PascalCase, in order
+/// of preference.
+const VARIANT_RULES: &[RenameRule] = &[
+    RenameRule::Snake,
+    RenameRule::Kebab,
+    RenameRule::Camel,
+    RenameRule::ScreamingSnake,
+    RenameRule::Lower,
+];
+
+impl RenameRule {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            RenameRule::Lower => "lowercase",
+            RenameRule::Pascal => "PascalCase",
+            RenameRule::Camel => "camelCase",
+            RenameRule::Snake => "snake_case",
+            RenameRule::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+            RenameRule::Kebab => "kebab-case",
+        }
+    }
+
+    /// The name in JSON of a field of the given name.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => lower_first(&RenameRule::Pascal.apply_to_field(field)),
+            RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Kebab => field.replace('_', "-"),
+        }
+    }
+
+    /// The name in JSON of a variant of the given name.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Camel => lower_first(variant),
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+}
+
+fn lower_first(s: &str) -> String {
+    match s.chars().next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + &s[first.len_utf8()..],
+        None => String::new(),
+    }
+}
+
+/// The rule, if any, that gives the names in JSON of the most of the given
+/// names and JSON names, provided that it and the renames it leaves take
+/// fewer attributes than the renames without it.
+fn best_rule(
+    rules: &[RenameRule],
+    names: &[(&str, &str)],
+    apply: fn(RenameRule, &str) -> String,
+) -> Option<RenameRule> {
+    let unrenamed = names.iter().filter(|(name, json)| name == json).count();
+    rules
+        .iter()
+        .map(|rule| {
+            let matched = names
+                .iter()
+                .filter(|(name, json)| apply(*rule, name) == *json)
+                .count();
+            (*rule, matched)
+        })
+        // The attribute for the rule is one too, so a rule must spare more
+        // than one rename.
+        .filter(|(_, matched)| *matched > unrenamed + 1)
+        .fold(
+            None,
+            |best: Option<(RenameRule, usize)>, (rule, matched)| match best {
+                Some((_, best_matched)) if best_matched >= matched => best,
+                _ => Some((rule, matched)),
+            },
+        )
+        .map(|(rule, _)| rule)
+}
+
+/// The `rename_all` rule for a struct's properties, if one spares renaming
+/// enough of them, and the properties with the renames they need under it.
+pub(crate) fn rename_all_properties(
+    properties: &[StructProperty],
+) -> (Option<RenameRule>, Vec<StructProperty>) {
+    let serde_names = properties
+        .iter()
+        .map(StructProperty::serde_name)
+        .collect::<Vec<_>>();
+    let names = properties
+        .iter()
+        .zip(&serde_names)
+        .filter(|(prop, _)| prop.serde_naming != SerdeNaming::Flatten)
+        .map(|(prop, json)| (unraw_field(&prop.name), json.as_str()))
+        .collect::<Vec<_>>();
+    let rule = match best_rule(FIELD_RULES, &names, RenameRule::apply_to_field) {
+        Some(rule) => rule,
+        None => return (None, properties.to_vec()),
+    };
+
+    let properties = properties
+        .iter()
+        .zip(serde_names)
+        .map(|(prop, json)| {
+            let mut prop = prop.clone();
+            if prop.serde_naming != SerdeNaming::Flatten {
+                prop.serde_naming = if rule.apply_to_field(unraw_field(&prop.name)) == json {
+                    SerdeNaming::None
+                } else {
+                    SerdeNaming::Rename(json)
+                };
+            }
+            prop
+        })
+        .collect();
+    (Some(rule), properties)
+}
+
+/// The `rename_all` rule for an enum's variants, if one spares renaming
+/// enough of them, and the variants with the renames they need under it.
+/// Untagged variants, whose names don't appear in JSON, keep theirs.
+pub(crate) fn rename_all_variants(variants: &[Variant]) -> (Option<RenameRule>, Vec<Variant>) {
+    let tagged = |variant: &Variant| !matches!(variant.details, VariantDetails::Other(_));
+    let names = variants
+        .iter()
+        .filter(|variant| tagged(variant))
+        .map(|variant| {
+            (
+                variant.name.as_str(),
+                variant.rename.as_deref().unwrap_or(&variant.name),
+            )
+        })
+        .collect::<Vec<_>>();
+    let rule = match best_rule(VARIANT_RULES, &names, RenameRule::apply_to_variant) {
+        Some(rule) => rule,
+        None => return (None, variants.to_vec()),
+    };
+
+    let variants = variants
+        .iter()
+        .map(|variant| {
+            let mut variant = variant.clone();
+            if tagged(&variant) {
+                let json = variant
+                    .rename
+                    .take()
+                    .unwrap_or_else(|| variant.name.clone());
+                if rule.apply_to_variant(&variant.name) != json {
+                    variant.rename = Some(json);
+                }
+            }
+            variant
+        })
+        .collect();
+    (Some(rule), variants)
+}
+
+/// The name serde gives a field, without the `r#` of a raw identifier.
+fn unraw_field(name: &str) -> &str {
+    name.strip_prefix("r#").unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenameRule;
+
+    #[test]
+    fn test_rename_rules() {
+        assert_eq!(RenameRule::Camel.apply_to_field("foo_bar"), "fooBar");
+        assert_eq!(RenameRule::Pascal.apply_to_field("foo_bar"), "FooBar");
+        assert_eq!(RenameRule::Kebab.apply_to_field("foo_bar"), "foo-bar");
+        assert_eq!(
+            RenameRule::ScreamingSnake.apply_to_field("foo_bar"),
+            "FOO_BAR"
+        );
+        assert_eq!(RenameRule::Camel.apply_to_field("_2_fa"), "2Fa");
+
+        assert_eq!(RenameRule::Snake.apply_to_variant("FooBar"), "foo_bar");
+        assert_eq!(RenameRule::Kebab.apply_to_variant("FooBar"), "foo-bar");
+        assert_eq!(RenameRule::Camel.apply_to_variant("FooBar"), "fooBar");
+        assert_eq!(
+            RenameRule::ScreamingSnake.apply_to_variant("FooBar"),
+            "FOO_BAR"
+        );
+        assert_eq!(RenameRule::Lower.apply_to_variant("FooBar"), "foobar");
+    }
+}
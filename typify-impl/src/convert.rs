@@ -1,18 +1,31 @@
 // Copyright 2021 Oxide Computer Company
 
+use crate::merge::{merge_all, MergeError};
 use crate::type_entry::{
-    EnumTagType, TypeEntry, TypeEntryEnum, TypeEntryStruct, Variant, VariantDetails,
+    EnumTagType, FloatBits, NewtypeConstraints, TypeEntry, TypeEntryEnum, TypeEntryNewtype,
+    TypeEntryStruct, Variant, VariantDetails,
+};
+use crate::util::{
+    all_mutually_exclusive, enum_descriptions, enum_names, metadata_deprecated, metadata_title,
+    non_null_subschemas, path_type_name, recase, retain_enum_values, schema_matches_fragment,
+    value_matches_type, versions_compatible,
 };
-use crate::util::{all_mutually_exclusive, recase};
 use convert_case::Case;
 use schemars::schema::{
-    ArrayValidation, InstanceType, Metadata, ObjectValidation, Schema, SchemaObject, SingleOrVec,
-    SubschemaValidation,
+    ArrayValidation, InstanceType, Metadata, NumberValidation, ObjectValidation, Schema,
+    SchemaObject, SingleOrVec, StringValidation, SubschemaValidation,
 };
 
 use crate::util::get_type_name;
 
-use crate::{Error, Name, Result, TypeSpace};
+use crate::{
+    AllOfStrategy, DateTimeCrate, DecimalImpl, Error, Name, PathSegment, PatternEngine, Result,
+    TypeSpace, UnknownValues,
+};
+
+/// The largest array for which serde implements `Serialize` and
+/// `Deserialize`.
+const MAX_FIXED_ARRAY_LEN: u32 = 32;
 
 impl TypeSpace {
     pub(crate) fn convert_schema<'a>(
@@ -23,9 +36,7 @@ impl TypeSpace {
         match schema {
             Schema::Bool(true) => self.convert_permissive(&None),
             Schema::Object(obj) => self.convert_schema_object(type_name, obj),
-
-            // TODO Not sure what to do here... need to return something toxic?
-            Schema::Bool(false) => todo!(),
+            Schema::Bool(false) => self.convert_never(type_name),
         }
     }
 
@@ -34,7 +45,67 @@ impl TypeSpace {
         type_name: Name,
         schema: &'a SchemaObject,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        // Schemas of the shapes given conversions for are the given types.
+        if let Some((_, replacement)) = self
+            .settings
+            .conversion
+            .iter()
+            .find(|(fragment, _)| schema_matches_fragment(schema, fragment))
+        {
+            return Ok((
+                TypeEntry::BuiltIn(replacement.replace_type.clone()),
+                &schema.metadata,
+            ));
+        }
+
+        // Schemas generated from Rust types may name the type.
+        if let Some(rust_type) = schema.extensions.get("x-rust-type") {
+            if let Some(type_entry) = self.convert_rust_type(rust_type)? {
+                return Ok((type_entry, &schema.metadata));
+            }
+        }
+
+        for keyword in ["unevaluatedProperties", "unevaluatedItems"] {
+            if schema.extensions.contains_key(keyword) {
+                self.warn(keyword, format!("{} isn't supported or enforced", keyword));
+            }
+        }
+
         match schema {
+            // OpenAPI 3.0 marks schemas that also accept null as nullable
+            // rather than with a null type.
+            SchemaObject {
+                metadata,
+                enum_values,
+                extensions,
+                ..
+            } if !self.settings.ignore_nullable
+                && extensions.get("nullable") == Some(&serde_json::Value::Bool(true)) =>
+            {
+                let (enum_values, mut extensions) =
+                    retain_enum_values(enum_values, extensions, |value| !value.is_null());
+                extensions.remove("nullable");
+                let ss = Schema::Object(SchemaObject {
+                    enum_values,
+                    extensions,
+                    ..schema.clone()
+                });
+                self.convert_option(type_name, metadata, &ss)
+            }
+
+            // Conditionals
+            SchemaObject {
+                metadata,
+                subschemas: Some(subschemas),
+                reference: None,
+                ..
+            } if subschemas.if_schema.is_some()
+                || subschemas.then_schema.is_some()
+                || subschemas.else_schema.is_some() =>
+            {
+                self.convert_conditional(type_name, metadata, schema, subschemas)
+            }
+
             // If we have a schema that has an instance type array that's
             // exactly two elements and one of them is Null, we have the
             // equivalent of an Option<T> where T is the type defined by the
@@ -43,21 +114,18 @@ impl TypeSpace {
                 metadata,
                 instance_type: Some(SingleOrVec::Vec(multiple)),
                 enum_values,
+                extensions,
                 ..
             } if multiple.len() == 2 && multiple.contains(&InstanceType::Null) => {
                 if let Some(other_type) = multiple.iter().find(|t| t != &&InstanceType::Null) {
                     // In the sensible case where only one of the instance
                     // types is null.
-                    let enum_values = enum_values.clone().map(|values| {
-                        values
-                            .iter()
-                            .cloned()
-                            .filter(|value| !value.is_null())
-                            .collect()
-                    });
+                    let (enum_values, extensions) =
+                        retain_enum_values(enum_values, extensions, |value| !value.is_null());
                     let ss = Schema::Object(SchemaObject {
                         instance_type: Some(SingleOrVec::from(*other_type)),
                         enum_values,
+                        extensions,
                         ..schema.clone()
                     });
                     self.convert_option(type_name, metadata, &ss)
@@ -76,6 +144,16 @@ impl TypeSpace {
                 }
             }
 
+            // A schema with several instance types becomes an untagged enum
+            // with a variant per type.
+            SchemaObject {
+                metadata,
+                instance_type: Some(SingleOrVec::Vec(multiple)),
+                subschemas: None,
+                reference: None,
+                ..
+            } => self.convert_multi_type(type_name, metadata, multiple, schema),
+
             // Strings
             SchemaObject {
                 metadata,
@@ -89,9 +167,9 @@ impl TypeSpace {
                 array: None,
                 object: None,
                 reference: None,
-                extensions: _,
+                extensions,
             } if single.as_ref() == &InstanceType::String => {
-                self.convert_string(metadata, format, validation)
+                self.convert_string(type_name, metadata, format, validation, extensions)
             }
 
             // Simple string enum
@@ -107,9 +185,30 @@ impl TypeSpace {
                 array: None,
                 object: None,
                 reference: None,
-                extensions: _,
+                extensions,
             } if single.as_ref() == &InstanceType::String => {
-                self.convert_enum_string(type_name, metadata, enum_values)
+                self.convert_enum_string(type_name, metadata, enum_values, extensions)
+            }
+
+            // Integer enum
+            SchemaObject {
+                metadata,
+                instance_type: Some(SingleOrVec::Single(single)),
+                format,
+                enum_values: Some(enum_values),
+                const_value: None,
+                subschemas: None,
+                number: validation,
+                string: None,
+                array: None,
+                object: None,
+                reference: None,
+                extensions,
+            } if single.as_ref() == &InstanceType::Integer => {
+                match self.convert_enum_integer(&type_name, metadata, enum_values, extensions)? {
+                    Some(type_entry) => Ok((type_entry, metadata)),
+                    None => self.convert_integer(type_name, metadata, validation, format),
+                }
             }
 
             // Integers
@@ -127,7 +226,7 @@ impl TypeSpace {
                 reference: None,
                 extensions: _,
             } if single.as_ref() == &InstanceType::Integer => {
-                self.convert_integer(metadata, validation, format)
+                self.convert_integer(type_name, metadata, validation, format)
             }
 
             // Numbers
@@ -145,7 +244,7 @@ impl TypeSpace {
                 reference: None,
                 extensions: _,
             } if single.as_ref() == &InstanceType::Number => {
-                self.convert_number(metadata, validation, format)
+                self.convert_number(type_name, metadata, validation, format)
             }
 
             // Boolean
@@ -179,9 +278,30 @@ impl TypeSpace {
                 array: None,
                 object: validation,
                 reference: None,
-                extensions: _,
+                extensions,
             } if single.as_ref() == &InstanceType::Object => {
-                self.convert_object(type_name, metadata, validation)
+                self.convert_object(type_name, metadata, validation, extensions)
+            }
+
+            // Tuples in the style of draft 2020-12, which schemars doesn't
+            // know about.
+            SchemaObject {
+                metadata,
+                instance_type: Some(SingleOrVec::Single(single)),
+                format: None,
+                enum_values: None,
+                const_value: None,
+                subschemas: None,
+                number: None,
+                string: None,
+                array,
+                object: None,
+                reference: None,
+                extensions,
+            } if single.as_ref() == &InstanceType::Array
+                && extensions.contains_key("prefixItems") =>
+            {
+                self.convert_prefix_items(type_name, metadata, array, &extensions["prefixItems"])
             }
 
             // Arrays
@@ -279,8 +399,8 @@ impl TypeSpace {
                 array: None,
                 object: None,
                 reference: None,
-                extensions: _,
-            } => self.convert_unknown_enum(type_name, metadata, enum_values),
+                extensions,
+            } => self.convert_unknown_enum(type_name, metadata, enum_values, extensions),
 
             // Subschemas
             SchemaObject {
@@ -295,7 +415,7 @@ impl TypeSpace {
                 array: None,
                 object: None,
                 reference: None,
-                extensions: _,
+                extensions,
             } => match subschemas.as_ref() {
                 SubschemaValidation {
                     all_of: Some(subschemas),
@@ -323,22 +443,52 @@ impl TypeSpace {
                     if_schema: None,
                     then_schema: None,
                     else_schema: None,
-                } => self.convert_one_of(type_name, metadata, subschemas),
-
-                // Unknown
-                _ => todo!("{:#?}", subschemas),
+                } => match extensions.get("discriminator") {
+                    Some(discriminator) => self
+                        .discriminated_enum(type_name, metadata, subschemas, discriminator)
+                        .map(|ty| (ty, metadata)),
+                    None => self.within(["oneOf".into()], |type_space| {
+                        type_space.convert_one_of(type_name, metadata, subschemas)
+                    }),
+                },
+
+                _ => Err(Error::Unsupported(format!(
+                    "the combination of subschemas in {}",
+                    serde_json::to_string(subschemas).unwrap()
+                ))),
             },
 
-            // TODO let's not bother with const values at the moment. In the
-            // future we could create types that have a single value with a
-            // newtype wrapper, but it's too much of a mess for too little
-            // value at the moment. Instead, we act as though this const_value
-            // field were None.
+            // A constant becomes a unit struct that (de)serializes only as the
+            // value. We need a name for it; otherwise we act as though this
+            // const_value field were None.
             SchemaObject {
                 metadata,
-                const_value: Some(_),
+                const_value: Some(value),
                 ..
             } => {
+                if !value.is_null() && get_type_name(&type_name, metadata, Case::Pascal).is_some() {
+                    // The struct holds no value but we note the value's type.
+                    let value_type = match value {
+                        serde_json::Value::String(_) => TypeEntry::String,
+                        serde_json::Value::Bool(_) => TypeEntry::new_integer("bool"),
+                        value if value.is_i64() => TypeEntry::new_integer("i64"),
+                        _ => {
+                            self.uses_serde_json = true;
+                            TypeEntry::new_builtin("serde_json::Value")
+                        }
+                    };
+                    let value_type_id = self.assign_type(value_type);
+                    return Ok((
+                        TypeEntryNewtype::from_metadata_with_constraints(
+                            type_name,
+                            metadata,
+                            value_type_id,
+                            NewtypeConstraints::Const(value.to_string()),
+                        ),
+                        metadata,
+                    ));
+                }
+
                 let new_schema = SchemaObject {
                     const_value: None,
                     ..schema.clone()
@@ -351,66 +501,192 @@ impl TypeSpace {
                     })
             }
 
-            // Unknown
-            SchemaObject { .. } => todo!("{:#?}", schema),
+            // Constraints alongside an allOf apply in addition to its
+            // subschemas so we merge them all together.
+            SchemaObject {
+                metadata,
+                subschemas: Some(subschemas),
+                reference: None,
+                ..
+            } if matches!(
+                subschemas.as_ref(),
+                SubschemaValidation {
+                    all_of: Some(_),
+                    any_of: None,
+                    one_of: None,
+                    not: None,
+                    if_schema: None,
+                    then_schema: None,
+                    else_schema: None,
+                }
+            ) =>
+            {
+                let mut all_of = vec![Schema::Object(SchemaObject {
+                    metadata: None,
+                    subschemas: None,
+                    ..schema.clone()
+                })];
+                all_of.extend(subschemas.all_of.iter().flatten().cloned());
+                self.convert_all_of(type_name, metadata, &all_of)
+            }
+
+            SchemaObject { .. } => Err(Error::Unsupported(format!(
+                "the schema {}",
+                serde_json::to_string(schema).unwrap()
+            ))),
         }
     }
 
     fn convert_string<'a>(
         &mut self,
+        type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
         format: &Option<String>,
-        _validation: &Option<Box<schemars::schema::StringValidation>>,
+        validation: &Option<Box<StringValidation>>,
+        extensions: &schemars::Map<String, serde_json::Value>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
-        trait OptionIsNoneOrDefault {
-            fn is_none_or_default(&self) -> bool;
+        // Binary data in a string becomes bytes that serde helpers encode
+        // and decode; OpenAPI's `byte` format is base64.
+        let encoding = match extensions.get("contentEncoding") {
+            Some(encoding) => encoding.as_str(),
+            None if format.as_deref() == Some("byte") => Some("base64"),
+            None => None,
+        };
+        if let Some(encoding @ ("base64" | "base16")) = encoding {
+            if self.settings.raw_encoded_strings {
+                return Ok((TypeEntry::String, metadata));
+            }
+            return Ok((
+                TypeEntry::new_builtin_with("Vec<u8>", format!("content_encoding::{}", encoding)),
+                metadata,
+            ));
         }
 
-        impl<T> OptionIsNoneOrDefault for Option<T>
-        where
-            T: Default + PartialEq,
+        let media_type = extensions.get("contentMediaType");
+        if media_type.and_then(serde_json::Value::as_str) == Some("application/json")
+            && !self.settings.raw_json_strings
         {
-            fn is_none_or_default(&self) -> bool {
-                match self {
-                    Some(t) => t == &T::default(),
-                    None => true,
+            return self.convert_json_string(type_name, metadata, extensions.get("contentSchema"));
+        }
+
+        // Only strings without formats are validated, and only with string
+        // validation.
+        let unchecked = match format {
+            Some(format) => Some(format!("isn't checked for strings of format {:?}", format)),
+            None if !self.settings.string_validation => {
+                Some("isn't checked without string validation".to_string())
+            }
+            None => None,
+        };
+        if let (Some(validation), Some(unchecked)) = (validation.as_deref(), unchecked) {
+            let keywords = [
+                (
+                    "maxLength",
+                    validation.max_length.map(|len| len.to_string()),
+                ),
+                (
+                    "minLength",
+                    validation.min_length.map(|len| len.to_string()),
+                ),
+                (
+                    "pattern",
+                    validation.pattern.as_ref().map(|p| format!("{:?}", p)),
+                ),
+            ];
+            for (keyword, value) in keywords {
+                if let Some(value) = value {
+                    self.warn(keyword, format!("{} {} {}", keyword, value, unchecked));
                 }
             }
         }
+
         match format.as_ref().map(String::as_str) {
-            None => {
-                // TODO we'll need to deal with strings with lengths and
-                // patterns, but it seems like a pain in the neck so I'm
-                // punting for now.
-                // assert!(validation.is_none_or_default(), "{:#?}", validation);
-                Ok((TypeEntry::String, metadata))
-            }
+            None => match validation.as_deref() {
+                Some(validation)
+                    if self.settings.string_validation
+                        && (validation.max_length.is_some()
+                            || validation.min_length.is_some()
+                            || validation.pattern.is_some()) =>
+                {
+                    self.convert_validated_string(type_name, metadata, validation)
+                }
+                _ => Ok((TypeEntry::String, metadata)),
+            },
 
-            Some("uuid") => {
+            // Only emit `uuid::Uuid` if the consumer has opted into the
+            // dependency; otherwise the value is just a string.
+            Some("uuid") if cfg!(feature = "uuid") => {
                 self.uses_uuid = true;
                 Ok((TypeEntry::new_builtin("uuid::Uuid"), metadata))
             }
+            Some("uuid") => Ok((TypeEntry::String, metadata)),
+
+            Some("date-time") => match self.settings.datetime_crate {
+                Some(DateTimeCrate::Chrono) => {
+                    self.uses_chrono = true;
+                    Ok((
+                        TypeEntry::new_builtin("chrono::DateTime<chrono::offset::Utc>"),
+                        metadata,
+                    ))
+                }
+                Some(DateTimeCrate::Time) => {
+                    self.uses_time = true;
+                    Ok((
+                        TypeEntry::new_builtin_with("time::OffsetDateTime", "time::serde::rfc3339"),
+                        metadata,
+                    ))
+                }
+                None => Ok((TypeEntry::String, metadata)),
+            },
 
-            Some("date") => {
-                self.uses_chrono = true;
-                Ok((
-                    TypeEntry::new_builtin("chrono::Date<chrono::offset::Utc>"),
-                    metadata,
-                ))
-            }
+            Some("date") => match self.settings.datetime_crate {
+                Some(DateTimeCrate::Chrono) => {
+                    self.uses_chrono = true;
+                    Ok((TypeEntry::new_builtin("chrono::NaiveDate"), metadata))
+                }
+                Some(DateTimeCrate::Time) => {
+                    self.uses_time = true;
+                    Ok((
+                        TypeEntry::new_builtin_with("time::Date", "time_serde::date"),
+                        metadata,
+                    ))
+                }
+                None => Ok((TypeEntry::String, metadata)),
+            },
 
-            Some("date-time") => {
-                self.uses_chrono = true;
-                Ok((
-                    TypeEntry::new_builtin("chrono::DateTime<chrono::offset::Utc>"),
-                    metadata,
-                ))
-            }
+            Some("time") => match self.settings.datetime_crate {
+                Some(DateTimeCrate::Chrono) => {
+                    self.uses_chrono = true;
+                    Ok((TypeEntry::new_builtin("chrono::NaiveTime"), metadata))
+                }
+                Some(DateTimeCrate::Time) => {
+                    self.uses_time = true;
+                    Ok((
+                        TypeEntry::new_builtin_with("time::Time", "time_serde::time"),
+                        metadata,
+                    ))
+                }
+                None => Ok((TypeEntry::String, metadata)),
+            },
+
+            Some("ipv4") => Ok((TypeEntry::new_builtin("std::net::Ipv4Addr"), metadata)),
+            Some("ipv6") => Ok((TypeEntry::new_builtin("std::net::Ipv6Addr"), metadata)),
+            // Not a standard format, but a common one for either address kind.
+            Some("ip") => Ok((TypeEntry::new_builtin("std::net::IpAddr"), metadata)),
 
             // TODO random types I'm not sure what to do with
-            Some("uri" | "uri-template" | "email" | "ip") => Ok((TypeEntry::String, metadata)),
+            Some("uri" | "uri-template" | "email") => Ok((TypeEntry::String, metadata)),
+            // OpenAPI's format for values to hide, and those the settings
+            // would redact.
+            Some("password") => Ok((TypeEntry::String, metadata)),
+            Some(format) if self.settings.redacted_formats.contains(format) => {
+                Ok((TypeEntry::String, metadata))
+            }
 
-            unhandled => todo!("{:#?}", unhandled),
+            Some(format) => Err(Error::Unsupported(format!(
+                "the string format {:?}",
+                format
+            ))),
         }
     }
 
@@ -419,7 +695,15 @@ impl TypeSpace {
         type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
         enum_values: &[serde_json::Value],
+        extensions: &schemars::Map<String, serde_json::Value>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        // Variants may be named by an extension, keeping their values as
+        // their serialized names.
+        let describe = get_type_name(&type_name, metadata, Case::Pascal)
+            .unwrap_or_else(|| "an anonymous type".to_string());
+        let names = enum_names(&describe, extensions, enum_values)?;
+        let descriptions = enum_descriptions(&describe, extensions, enum_values)?;
+
         // We expect all enum values to be either a string **or** a null. We
         // gather them all up and then choose to either be an enum of simple
         // variants, or an Option of an enum of string variants depending on if
@@ -434,7 +718,8 @@ impl TypeSpace {
 
         let variants = enum_values
             .iter()
-            .flat_map(|value| match value {
+            .enumerate()
+            .flat_map(|(index, value)| match value {
                 // It would be odd to have multiple null values, but we don't
                 // need to worry about it.
                 serde_json::Value::Null => {
@@ -442,17 +727,29 @@ impl TypeSpace {
                     None
                 }
                 serde_json::Value::String(value) => {
-                    let (name, rename) = recase(value.clone(), Case::Pascal);
+                    let (name, rename) = match &names {
+                        Some(names) => {
+                            let name = names[index].clone();
+                            let rename = (&name != value).then(|| value.clone());
+                            (name, rename)
+                        }
+                        None => recase(value.clone(), Case::Pascal),
+                    };
                     Some(Ok(Variant {
                         name,
                         rename,
-                        description: None,
+                        description: descriptions[index].clone(),
+                        deprecated: false,
                         details: VariantDetails::Simple,
                     }))
                 }
                 _ => Some(Err(Error::BadValue("string".to_string(), value.clone()))),
             })
             .collect::<Result<Vec<Variant>>>()?;
+        let mut variants = variants;
+        if let Some(variant) = self.unknown_values_variant(&type_name, metadata, &variants) {
+            variants.push(variant);
+        }
         let mut ty = TypeEntryEnum::from_metadata(
             type_name,
             metadata,
@@ -468,124 +765,585 @@ impl TypeSpace {
         Ok((ty, metadata))
     }
 
+    /// The catch-all variant, if any, for the values of a string enum that
+    /// its schema doesn't list, as its patch or else the settings prescribe.
+    fn unknown_values_variant(
+        &mut self,
+        type_name: &Name,
+        metadata: &Option<Box<Metadata>>,
+        variants: &[Variant],
+    ) -> Option<Variant> {
+        let patch = get_type_name(type_name, metadata, Case::Pascal)
+            .and_then(|name| self.settings.patch.get(&name))
+            .and_then(|patch| patch.unknown_values);
+        let (name, details) = match patch.or(self.settings.unknown_values)? {
+            UnknownValues::Reject => return None,
+            UnknownValues::Unknown => ("Unknown", VariantDetails::Unknown),
+            UnknownValues::Other => (
+                "Other",
+                VariantDetails::Other(self.assign_type(TypeEntry::String)),
+            ),
+        };
+
+        // Don't collide with the listed values.
+        let mut name = name.to_string();
+        while variants.iter().any(|variant| variant.name == name) {
+            name.push('_');
+        }
+        Some(Variant {
+            name,
+            rename: None,
+            description: Some("A value that the schema doesn't list.".to_string()),
+            deprecated: false,
+            details,
+        })
+    }
+
+    /// A string with a pattern or length bounds becomes a newtype that checks
+    /// those constraints on construction. We can only generate such a type if
+    /// we have a name for it; otherwise we fall back to an unconstrained
+    /// `String`.
+    pub(crate) fn convert_validated_string<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        validation: &StringValidation,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let name = match get_type_name(&type_name, metadata, Case::Pascal) {
+            Some(name) => name,
+            None => return Ok((TypeEntry::String, metadata)),
+        };
+
+        if let Some(pattern) = &validation.pattern {
+            // Make sure the generated code won't panic trying to compile it.
+            if self.settings.no_std {
+                // The pattern would be compiled once in a `once_cell::sync::Lazy`.
+                return Err(Error::RequiresStd(format!("the pattern of {}", name)));
+            }
+            let invalid =
+                |reason: String| Error::InvalidPattern(name.clone(), pattern.clone(), reason);
+            match self.settings.pattern_engine {
+                None | Some(PatternEngine::Regress) => {
+                    regress::Regex::new(pattern).map_err(|e| invalid(e.to_string()))?;
+                    self.uses_regress = true;
+                }
+                Some(PatternEngine::Regex) => {
+                    regex::Regex::new(pattern).map_err(|e| invalid(e.to_string()))?;
+                    self.uses_regex = true;
+                }
+            }
+        }
+
+        let type_id = self.assign_type(TypeEntry::String);
+        let type_entry = TypeEntryNewtype::from_metadata_with_constraints(
+            type_name,
+            metadata,
+            type_id,
+            NewtypeConstraints::String {
+                max_length: validation.max_length,
+                min_length: validation.min_length,
+                pattern: validation.pattern.clone(),
+            },
+        );
+
+        Ok((type_entry, metadata))
+    }
+
+    /// A string of JSON becomes a newtype of the type of its `contentSchema`,
+    /// or of `serde_json::Value` without one, that (de)serializes the
+    /// document in the string. It needs a name; otherwise it's a `String`.
+    fn convert_json_string<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        content_schema: Option<&serde_json::Value>,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let name = match get_type_name(&type_name, metadata, Case::Pascal) {
+            Some(name) => name,
+            None => return Ok((TypeEntry::String, metadata)),
+        };
+
+        let type_id = match content_schema {
+            Some(content_schema) => {
+                let content_schema = serde_json::from_value::<Schema>(content_schema.clone())
+                    .map_err(|e| Error::InvalidSchema(name.clone(), e.to_string()))?;
+                let sub_type_name = Name::Suggested(format!("{}Content", name));
+                self.within(["contentSchema".into()], |type_space| {
+                    type_space
+                        .id_for_schema(sub_type_name, &content_schema)
+                        .map(|(type_id, _)| type_id)
+                })?
+            }
+            None => self.id_for_schema(Name::Unknown, &Schema::Bool(true))?.0,
+        };
+        self.uses_serde_json = true;
+
+        let type_entry = TypeEntryNewtype::from_metadata_with_constraints(
+            type_name,
+            metadata,
+            type_id,
+            NewtypeConstraints::Json,
+        );
+        Ok((type_entry, metadata))
+    }
+
+    /// An integer `enum` becomes an enum of unit variants whose
+    /// discriminants are the values. The variants are named by an
+    /// `x-enumNames` or `x-enum-varnames` extension if there is one, and
+    /// otherwise after their values, e.g. `Value0` and `ValueMinus1`. We
+    /// return `None` for an anonymous enum or for values that aren't `i64`s;
+    /// those are integers.
+    fn convert_enum_integer(
+        &mut self,
+        type_name: &Name,
+        metadata: &Option<Box<Metadata>>,
+        enum_values: &[serde_json::Value],
+        extensions: &schemars::Map<String, serde_json::Value>,
+    ) -> Result<Option<TypeEntry>> {
+        let describe = match get_type_name(type_name, metadata, Case::Pascal) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let values = match enum_values
+            .iter()
+            .map(serde_json::Value::as_i64)
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(values) => values,
+            None => return Ok(None),
+        };
+
+        let names = enum_names(&describe, extensions, enum_values)?.unwrap_or_else(|| {
+            values
+                .iter()
+                .map(|value| match value {
+                    value if *value < 0 => format!("ValueMinus{}", value.unsigned_abs()),
+                    value => format!("Value{}", value),
+                })
+                .collect()
+        });
+
+        let descriptions = enum_descriptions(&describe, extensions, enum_values)?;
+
+        let mut variants: Vec<Variant> = Vec::new();
+        for ((name, value), description) in names.into_iter().zip(values).zip(descriptions) {
+            if variants
+                .iter()
+                .any(|variant| variant.details == VariantDetails::Integer(value))
+            {
+                continue;
+            }
+            variants.push(Variant {
+                name,
+                rename: None,
+                description,
+                deprecated: false,
+                details: VariantDetails::Integer(value),
+            });
+        }
+
+        Ok(Some(TypeEntryEnum::from_metadata(
+            type_name.clone(),
+            metadata,
+            EnumTagType::External,
+            variants,
+            false,
+        )))
+    }
+
     fn convert_integer<'a>(
-        &self,
+        &mut self,
+        type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
-        validation: &Option<Box<schemars::schema::NumberValidation>>,
+        validation: &Option<Box<NumberValidation>>,
         format: &Option<String>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
-        let (mut min, mut max, multiple) = if let Some(validation) = validation {
+        // Fractional bounds round to the nearest integers within them.
+        let (min, max, multiple_of) = if let Some(validation) = validation {
             let min = match (&validation.minimum, &validation.exclusive_minimum) {
                 (None, None) => None,
-                (None, Some(value)) => Some(value + 1.0),
-                (Some(value), None) => Some(*value),
-                (Some(min), Some(emin)) => Some(min.max(emin + 1.0)),
+                (None, Some(value)) => Some(value.floor() + 1.0),
+                (Some(value), None) => Some(value.ceil()),
+                (Some(min), Some(emin)) => Some(min.ceil().max(emin.floor() + 1.0)),
             };
             let max = match (&validation.maximum, &validation.exclusive_maximum) {
                 (None, None) => None,
-                (None, Some(value)) => Some(value - 1.0),
-                (Some(value), None) => Some(*value),
-                (Some(max), Some(emax)) => Some(max.min(emax - 1.0)),
+                (None, Some(value)) => Some(value.ceil() - 1.0),
+                (Some(value), None) => Some(value.floor()),
+                (Some(max), Some(emax)) => Some(max.floor().min(emax.ceil() - 1.0)),
             };
             (min, max, validation.multiple_of)
         } else {
             (None, None, None)
         };
 
-        // Ordered from most- to least-restrictive.
-        let formats: &[(&str, &str, f64, f64)] = &[
-            ("int8", "i8", i8::MIN as f64, i8::MAX as f64),
+        let (ty, imin, imax) = self.integer_type(min, max, format);
+
+        // Only check the bounds that the type itself doesn't enforce.
+        let minimum = min.filter(|min| *min > imin).map(|min| min as i128);
+        let maximum = max.filter(|max| *max < imax).map(|max| max as i128);
+        let multiple_of = match multiple_of {
+            Some(multiple_of) if multiple_of.fract() != 0.0 => {
+                self.warn(
+                    "multipleOf",
+                    format!(
+                        "integer multipleOf {} is not an integer; ignoring it",
+                        multiple_of
+                    ),
+                );
+                None
+            }
+            // Every integer is a multiple of 1.
+            Some(1.0) => None,
+            Some(multiple_of) => Some(multiple_of as i128),
+            None => None,
+        };
+
+        if !self.settings.number_validation {
+            if let Some(validation) = validation {
+                self.warn_unchecked_numbers(
+                    validation,
+                    minimum.is_some(),
+                    maximum.is_some(),
+                    multiple_of.is_some(),
+                );
+            }
+            return Ok((TypeEntry::new_integer(ty), metadata));
+        }
+
+        if minimum.is_none() && maximum.is_none() && multiple_of.is_none() {
+            return Ok((TypeEntry::new_integer(ty), metadata));
+        }
+
+        self.validated_newtype(
+            type_name,
+            metadata,
+            TypeEntry::new_integer(ty),
+            NewtypeConstraints::Integer {
+                minimum,
+                maximum,
+                multiple_of,
+            },
+        )
+    }
+
+    /// Select the integer type for the given (inclusive) bounds and format.
+    /// Returns the type along with the range of values it can represent.
+    fn integer_type(
+        &mut self,
+        min: Option<f64>,
+        max: Option<f64>,
+        format: &Option<String>,
+    ) -> (&'static str, f64, f64) {
+        // Ordered from most- to least-restrictive; for a given width we
+        // prefer unsigned types.
+        let formats: &[(&str, &'static str, f64, f64)] = &[
             ("", "std::num::NonZeroU8", 1.0, u8::MAX as f64),
             ("uint8", "u8", u8::MIN as f64, u8::MAX as f64),
-            ("int16", "i16", i16::MIN as f64, i16::MAX as f64),
+            ("int8", "i8", i8::MIN as f64, i8::MAX as f64),
             ("", "std::num::NonZeroU16", 1.0, u16::MAX as f64),
             ("uint16", "u16", u16::MIN as f64, u16::MAX as f64),
-            ("int", "i32", i32::MIN as f64, i32::MAX as f64),
-            ("int32", "i32", i32::MIN as f64, i32::MAX as f64),
+            ("int16", "i16", i16::MIN as f64, i16::MAX as f64),
             ("", "std::num::NonZeroU32", 1.0, u32::MAX as f64),
             ("uint", "u32", u32::MIN as f64, u32::MAX as f64),
             ("uint32", "u32", u32::MIN as f64, u32::MAX as f64),
-            ("int64", "i64", i64::MIN as f64, i64::MAX as f64),
+            ("int", "i32", i32::MIN as f64, i32::MAX as f64),
+            ("int32", "i32", i32::MIN as f64, i32::MAX as f64),
             ("", "std::num::NonZeroU64", 1.0, u64::MAX as f64),
             ("uint64", "u64", u64::MIN as f64, u64::MAX as f64),
+            ("int64", "i64", i64::MIN as f64, i64::MAX as f64),
         ];
 
-        if let Some(format) = format {
-            if let Some((_, ty, imin, imax)) = formats
+        let nonzero_integers = self.settings.nonzero_integers;
+
+        // When there's a format as well as bounds, the narrower of the two
+        // wins. If the bounds exceed the range of the format we use the
+        // format's type, but warn about the conflict.
+        if let Some((_, ty, imin, imax)) = format.as_ref().and_then(|format| {
+            formats
                 .iter()
                 .find(|(int_format, _, _, _)| int_format == format)
-            {
-                // If the type matches with other constraints, we're done.
-                if multiple.is_none()
-                    && (min.is_none() || min == Some(*imin))
-                    && (max.is_none() || max == Some(*imax))
-                {
-                    return Ok((TypeEntry::new_integer(ty), metadata));
-                }
-
-                if min.is_none() {
-                    min = Some(*imin);
-                }
-                if max.is_none() {
-                    max = Some(*imax);
+        }) {
+            if min.is_some_and(|min| min < *imin) || max.is_some_and(|max| max > *imax) {
+                self.warn(
+                    "format",
+                    format!(
+                        "integer bounds {} to {} exceed the range of format {}; using {}",
+                        min.unwrap_or(*imin),
+                        max.unwrap_or(*imax),
+                        format.as_ref().unwrap(),
+                        ty,
+                    ),
+                );
+            } else {
+                // Only unsigned formats may become `NonZero` types, which
+                // are unsigned too.
+                let min = min.unwrap_or(*imin);
+                let max = max.unwrap_or(*imax);
+                let narrower = formats.iter().find(|(int_format, _, nmin, nmax)| {
+                    ((nonzero_integers && *imin == 0.0) || !int_format.is_empty())
+                        && nmax + f64::EPSILON >= max
+                        && nmin - f64::EPSILON <= min
+                        && nmax - nmin < imax - imin
+                });
+                if let Some((_, ty, nmin, nmax)) = narrower {
+                    return (ty, *nmin, *nmax);
                 }
             }
+
+            // Unsigned formats whose bounds exclude zero may still be
+            // `NonZero` types.
+            return match min {
+                Some(min) if nonzero_integers && *imin == 0.0 && min >= 1.0 => formats
+                    .iter()
+                    .find(|(int_format, _, nmin, nmax)| {
+                        int_format.is_empty() && *nmin == 1.0 && nmax == imax
+                    })
+                    .map_or((ty, *imin, *imax), |(_, nonzero, nmin, nmax)| {
+                        (nonzero, *nmin, *nmax)
+                    }),
+                _ => (ty, *imin, *imax),
+            };
         }
 
-        // See if the value bounds fit within a known type.
-        let maybe_type = match (min, max) {
-            (None, Some(max)) => formats.iter().find_map(|(_, ty, _, imax)| {
-                if imax + f64::EPSILON >= max {
-                    Some(ty.to_string())
-                } else {
-                    None
-                }
-            }),
-            (Some(min), None) => formats.iter().find_map(|(_, ty, imin, _)| {
-                if imin - f64::EPSILON <= min {
-                    Some(ty.to_string())
-                } else {
-                    None
-                }
-            }),
-            (Some(min), Some(max)) => formats.iter().find_map(|(_, ty, imin, imax)| {
-                if imax + f64::EPSILON >= max && imin - f64::EPSILON <= min {
-                    Some(ty.to_string())
-                } else {
-                    None
-                }
-            }),
-            (None, None) => None,
-        };
+        if min.is_none() && max.is_none() {
+            return ("i64", i64::MIN as f64, i64::MAX as f64);
+        }
 
-        // TODO we should do something with `multiple`
-        if let Some(ty) = maybe_type {
-            Ok((TypeEntry::new_integer(ty), metadata))
+        // Missing bounds are as wide as the widest type that could satisfy
+        // the other bound.
+        let unsigned = min.is_some_and(|min| min >= 0.0);
+        let min = min.unwrap_or(i64::MIN as f64);
+        let max = max.unwrap_or(if unsigned {
+            u64::MAX as f64
         } else {
-            // TODO we could construct a type that itself enforces the various
-            // bounds.
-            // TODO failing that we should find the type that most tightly
-            // matches these bounds.
-            Ok((TypeEntry::new_integer("i64"), metadata))
+            i64::MAX as f64
+        });
+
+        // Find the smallest type that covers the range.
+        let maybe_type = formats.iter().find(|(int_format, _, imin, imax)| {
+            (nonzero_integers || !int_format.is_empty())
+                && imax + f64::EPSILON >= max
+                && imin - f64::EPSILON <= min
+        });
+
+        match maybe_type {
+            Some((_, ty, imin, imax)) => (ty, *imin, *imax),
+            None => {
+                let (ty, imin, imax) = if unsigned {
+                    ("u64", u64::MIN as f64, u64::MAX as f64)
+                } else {
+                    ("i64", i64::MIN as f64, i64::MAX as f64)
+                };
+                self.warn(
+                    if max > imax { "maximum" } else { "minimum" },
+                    format!(
+                        "integer bounds {} to {} exceed the range of any integer type; using {}",
+                        min, max, ty,
+                    ),
+                );
+                (ty, imin, imax)
+            }
         }
     }
 
-    // TODO deal with metadata and format
+    // TODO deal with metadata
     fn convert_number<'a>(
-        &self,
-        _metadata: &'a Option<Box<Metadata>>,
-        validation: &Option<Box<schemars::schema::NumberValidation>>,
-        _format: &Option<String>,
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        validation: &Option<Box<NumberValidation>>,
+        format: &Option<String>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
-        if let Some(validation) = validation {
-            assert!(validation.multiple_of.is_none());
-            assert!(validation.maximum.is_none());
-            assert!(validation.exclusive_maximum.is_none());
-            assert!(validation.minimum.is_none());
-            assert!(validation.exclusive_minimum.is_none());
-        }
-
-        Ok((TypeEntry::new_float("f64"), &None))
+        let decimal = format.as_deref() == Some("decimal")
+            || validation
+                .as_deref()
+                .and_then(|validation| validation.multiple_of)
+                .is_some_and(|multiple_of| multiple_of.fract() != 0.0);
+        if decimal {
+            if let Some(type_entry) = self.decimal_type() {
+                return Ok((type_entry, metadata));
+            }
+            // A fractional `multipleOf` alone is a decimal only by inference,
+            // so it's no surprise that it's an `f64` by default.
+            let keyword = if format.as_deref() == Some("decimal") {
+                "format"
+            } else {
+                "multipleOf"
+            };
+            match self.settings.decimal_type {
+                Some(_) => self.warn(
+                    keyword,
+                    "decimal numbers are f64s without the rust_decimal cargo feature",
+                ),
+                None if keyword == "format" => self.warn(
+                    keyword,
+                    "decimal numbers are f64s without a decimal type selected",
+                ),
+                None => {}
+            }
+        }
+
+        let ty = match format.as_deref() {
+            Some("float") => {
+                let out_of_range = validation.as_deref().is_some_and(|validation| {
+                    [
+                        validation.minimum,
+                        validation.exclusive_minimum,
+                        validation.maximum,
+                        validation.exclusive_maximum,
+                    ]
+                    .iter()
+                    .flatten()
+                    .any(|bound| bound.abs() > f32::MAX as f64)
+                });
+                if out_of_range {
+                    self.warn(
+                        "format",
+                        "number bounds exceed the range of format \"float\"; using f32",
+                    );
+                }
+                "f32"
+            }
+            _ => "f64",
+        };
+
+        match validation.as_deref() {
+            Some(NumberValidation {
+                multiple_of,
+                maximum,
+                exclusive_maximum,
+                minimum,
+                exclusive_minimum,
+            }) if self.settings.number_validation
+                && (multiple_of.is_some()
+                    || maximum.is_some()
+                    || exclusive_maximum.is_some()
+                    || minimum.is_some()
+                    || exclusive_minimum.is_some()) =>
+            {
+                if multiple_of.is_some() && self.settings.no_std {
+                    // `f64::fract` is only in `std`.
+                    return Err(Error::RequiresStd(
+                        "checking the multipleOf of a number".to_string(),
+                    ));
+                }
+                self.validated_newtype(
+                    type_name,
+                    metadata,
+                    TypeEntry::new_float(ty),
+                    NewtypeConstraints::Number {
+                        minimum: minimum.map(FloatBits::from),
+                        exclusive_minimum: exclusive_minimum.map(FloatBits::from),
+                        maximum: maximum.map(FloatBits::from),
+                        exclusive_maximum: exclusive_maximum.map(FloatBits::from),
+                        multiple_of: multiple_of.map(FloatBits::from),
+                    },
+                )
+            }
+            Some(validation) => {
+                if !self.settings.number_validation {
+                    self.warn_unchecked_numbers(
+                        validation,
+                        validation.minimum.is_some() || validation.exclusive_minimum.is_some(),
+                        validation.maximum.is_some() || validation.exclusive_maximum.is_some(),
+                        validation.multiple_of.is_some(),
+                    );
+                }
+                Ok((TypeEntry::new_float(ty), &None))
+            }
+            None => Ok((TypeEntry::new_float(ty), &None)),
+        }
+    }
+
+    /// Warn about the values of a container that are of a built-in type with
+    /// a serde module but that the module doesn't (de)serialize.
+    pub(crate) fn warn_unreached_with(&mut self, ty: &TypeEntry) {
+        if let Some(type_name) = ty.unreached_with(self) {
+            let message = format!(
+                "{} values nested this deeply use the default serde format of the type",
+                type_name
+            );
+            self.warn("format", message);
+        }
+    }
+
+    /// Warn that number validation is off for the bounds and `multipleOf`
+    /// that the type itself doesn't enforce.
+    fn warn_unchecked_numbers(
+        &mut self,
+        validation: &NumberValidation,
+        minimum: bool,
+        maximum: bool,
+        multiple_of: bool,
+    ) {
+        let keywords = [
+            (minimum, "minimum", validation.minimum),
+            (minimum, "exclusiveMinimum", validation.exclusive_minimum),
+            (maximum, "maximum", validation.maximum),
+            (maximum, "exclusiveMaximum", validation.exclusive_maximum),
+            (multiple_of, "multipleOf", validation.multiple_of),
+        ];
+        for (unchecked, keyword, value) in keywords {
+            if let (true, Some(value)) = (unchecked, value) {
+                self.warn(
+                    keyword,
+                    format!(
+                        "{} {} isn't checked without number validation",
+                        keyword, value
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The type for decimal numbers, if one has been selected (and is
+    /// available).
+    fn decimal_type(&mut self) -> Option<TypeEntry> {
+        match self.settings.decimal_type? {
+            DecimalImpl::SerdeJsonNumber => {
+                self.uses_serde_json = true;
+                Some(TypeEntry::new_builtin("serde_json::Number"))
+            }
+            DecimalImpl::RustDecimal if cfg!(feature = "rust_decimal") => {
+                self.uses_rust_decimal = true;
+                Some(TypeEntry::new_builtin("rust_decimal::Decimal"))
+            }
+            DecimalImpl::RustDecimalFloat if cfg!(feature = "rust_decimal") => {
+                self.uses_rust_decimal = true;
+                Some(TypeEntry::new_builtin_with_option(
+                    "rust_decimal::Decimal",
+                    "rust_decimal::serde::float",
+                    "rust_decimal::serde::float_option",
+                ))
+            }
+            DecimalImpl::RustDecimal | DecimalImpl::RustDecimalFloat => None,
+        }
+    }
+
+    /// Produce a newtype that checks the given constraints on the inner type.
+    /// We can only generate such a type if we have a name for it; otherwise we
+    /// fall back to the unconstrained inner type.
+    fn validated_newtype<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        inner: TypeEntry,
+        constraints: NewtypeConstraints,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        if get_type_name(&type_name, metadata, Case::Pascal).is_none() {
+            return Ok((inner, metadata));
+        }
+
+        let type_id = self.assign_type(inner);
+        let type_entry = TypeEntryNewtype::from_metadata_with_constraints(
+            type_name,
+            metadata,
+            type_id,
+            constraints,
+        );
+
+        Ok((type_entry, metadata))
     }
 
     /// If we have a schema that's just the Null instance type, it represents a
@@ -602,8 +1360,48 @@ impl TypeSpace {
         type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
         validation: &Option<Box<ObjectValidation>>,
+        extensions: &schemars::Map<String, serde_json::Value>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
         match validation.as_ref().map(Box::as_ref) {
+            // Maps whose keys must match a pattern.
+            Some(ObjectValidation {
+                max_properties: None,
+                min_properties: None,
+                required,
+                properties,
+                pattern_properties,
+                additional_properties,
+                property_names: None,
+            }) if required.is_empty()
+                && properties.is_empty()
+                && !pattern_properties.is_empty() =>
+            {
+                self.make_pattern_map(
+                    type_name,
+                    metadata,
+                    pattern_properties,
+                    additional_properties,
+                )
+            }
+
+            // Maps whose keys are constrained by a propertyNames schema.
+            Some(ObjectValidation {
+                max_properties: None,
+                min_properties: None,
+                required,
+                properties,
+                pattern_properties,
+                additional_properties,
+                property_names: Some(property_names),
+            }) if required.is_empty()
+                && properties.is_empty()
+                && pattern_properties.is_empty()
+                && additional_properties.as_ref().map(AsRef::as_ref)
+                    != Some(&Schema::Bool(false)) =>
+            {
+                self.make_key_map(type_name, metadata, property_names, additional_properties)
+            }
+
             // Maps have an empty properties set, and a non-null schema for the
             // additional_properties field.
             Some(ObjectValidation {
@@ -620,21 +1418,32 @@ impl TypeSpace {
                 && additional_properties.as_ref().map(AsRef::as_ref)
                     != Some(&Schema::Bool(false)) =>
             {
-                self.make_map(type_name.into_option(), additional_properties)
+                let (ty, _) = self.make_map(type_name.into_option(), additional_properties)?;
+                Ok((ty, metadata))
+            }
+            None => {
+                let (ty, _) = self.make_map(type_name.into_option(), &None)?;
+                Ok((ty, metadata))
             }
-            None => self.make_map(type_name.into_option(), &None),
 
             // The typical case
             Some(validation) => {
                 let tmp_type_name = get_type_name(&type_name, metadata, Case::Pascal);
                 let (properties, deny_unknown_fields) =
                     self.struct_members(tmp_type_name, validation)?;
-                let ty = TypeEntryStruct::from_metadata(
+                let mut ty = TypeEntryStruct::from_metadata(
                     type_name,
                     metadata,
                     properties,
                     deny_unknown_fields,
                 );
+                // schemars doesn't know about dependentRequired.
+                if let (TypeEntry::Struct(type_struct), Some(rules)) =
+                    (&mut ty, extensions.get("dependentRequired"))
+                {
+                    type_struct.dependent_required =
+                        self.dependent_required(&type_struct.properties, rules);
+                }
                 Ok((ty, &None))
             }
         }
@@ -649,7 +1458,14 @@ impl TypeSpace {
             Some(idx) => &ref_name[idx + 1..],
             None => ref_name,
         };
-        let type_id = self.ref_to_id.get(key).unwrap();
+        // Root schemas' references are resolved as they're added, but those
+        // of schemas added alone may refer to definitions never added.
+        let type_id = self.ref_to_id.get(key).ok_or_else(|| {
+            Error::UnresolvedRef(
+                ref_name.to_string(),
+                "no definition of that name has been added".to_string(),
+            )
+        })?;
         let ty = TypeEntry::Reference(type_id.clone());
         Ok((ty, metadata))
     }
@@ -661,12 +1477,44 @@ impl TypeSpace {
         subschemas: &[Schema],
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
         if subschemas.len() == 1 {
-            let (ty, _) = self.convert_schema(type_name, subschemas.first().unwrap())?;
+            let (ty, _) = self.within(["allOf".into(), 0.into()], |type_space| {
+                type_space.convert_schema(type_name, subschemas.first().unwrap())
+            })?;
             return Ok((ty, metadata));
         }
 
+        // With the flatten strategy, an object extending named types embeds
+        // them rather than their properties.
+        if self.settings.all_of_strategy == AllOfStrategy::Flatten {
+            if let Some(ty) = self.maybe_all_of_subclass(type_name.clone(), metadata, subschemas)? {
+                return Ok((ty, metadata));
+            }
+        }
+
+        // Where we can, we merge the subschemas into a single schema; this
+        // gives a flat struct for the common case of an object extending
+        // another.
+        match merge_all(subschemas, &self.definitions) {
+            Ok(merged) => {
+                let merged = Schema::Object(SchemaObject {
+                    metadata: metadata.clone(),
+                    ..merged
+                });
+                let (ty, _) = self.convert_schema(type_name, &merged)?;
+                return Ok((ty, metadata));
+            }
+            Err(MergeError::Conflict(reason)) => {
+                return Err(Error::InvalidSchema(
+                    get_type_name(&type_name, metadata, Case::Pascal)
+                        .unwrap_or_else(|| "an anonymous type".to_string()),
+                    reason,
+                ))
+            }
+            Err(MergeError::Unsupported) => {}
+        }
+
         // TODO make this look more like the other maybe clauses
-        if let Some(ty) = self.maybe_all_of_subclass(type_name.clone(), metadata, subschemas) {
+        if let Some(ty) = self.maybe_all_of_subclass(type_name.clone(), metadata, subschemas)? {
             return Ok((ty, metadata));
         }
 
@@ -695,16 +1543,53 @@ impl TypeSpace {
         metadata: &'a Option<Box<Metadata>>,
         subschemas: &[Schema],
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        // A subschema that matches anything makes the others moot, while one
+        // that matches nothing adds nothing.
+        if subschemas.contains(&Schema::Bool(true)) {
+            return self.convert_permissive(metadata);
+        }
+        if subschemas.contains(&Schema::Bool(false)) {
+            let rest = subschemas
+                .iter()
+                .filter(|schema| **schema != Schema::Bool(false))
+                .cloned()
+                .collect::<Vec<_>>();
+            return if rest.is_empty() {
+                Ok((self.convert_never(type_name)?.0, metadata))
+            } else {
+                self.convert_any_of(type_name, metadata, &rest)
+            };
+        }
+
         if subschemas.len() == 1 {
-            let (ty, _) = self.convert_schema(type_name, subschemas.first().unwrap())?;
+            let (ty, _) = self.within(["anyOf".into(), 0.into()], |type_space| {
+                type_space.convert_schema(type_name, subschemas.first().unwrap())
+            })?;
             return Ok((ty, metadata));
         }
 
+        // A null subschema makes the rest optional.
+        if let Some(non_nulls) = non_null_subschemas(subschemas) {
+            let non_null = match non_nulls.as_slice() {
+                [non_null] => non_null.clone(),
+                _ => Schema::Object(SchemaObject {
+                    subschemas: Some(Box::new(SubschemaValidation {
+                        any_of: Some(non_nulls),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }),
+            };
+            return self.convert_option(type_name, metadata, &non_null);
+        }
+
         // Check if this could be more precisely handled as a "one-of". This
         // occurs if each subschema is mutually exclusive i.e. so that exactly
         // one of them can match.
         if all_mutually_exclusive(subschemas, &self.definitions) {
-            self.convert_one_of(type_name, metadata, subschemas)
+            self.within(["anyOf".into()], |type_space| {
+                type_space.convert_one_of(type_name, metadata, subschemas)
+            })
         } else {
             // We'll want to build a struct that looks like this:
             // struct Name {
@@ -772,26 +1657,202 @@ impl TypeSpace {
     /// variant.
     ///
     /// Untagged enums apply to any set of subschemas so must be applied last.
+    ///
+    /// Errors give the index of the subschema at fault, but not the keyword
+    /// since callers may have gathered the subschemas from others.
     pub(crate) fn convert_one_of<'a>(
         &mut self,
         type_name: Name,
         metadata: &'a Option<Box<schemars::schema::Metadata>>,
         subschemas: &[Schema],
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        // A subschema that matches nothing can't be the one that matches. One
+        // that matches anything becomes a `serde_json::Value` variant, last as
+        // serde tries the variants of untagged enums in order.
+        if subschemas
+            .iter()
+            .any(|schema| matches!(schema, Schema::Bool(_)))
+        {
+            let (anything, mut rest) = subschemas
+                .iter()
+                .filter(|schema| **schema != Schema::Bool(false))
+                .cloned()
+                .partition::<Vec<_>, _>(|schema| *schema == Schema::Bool(true));
+            return match (anything.is_empty(), rest.is_empty()) {
+                (true, true) => Ok((self.convert_never(type_name)?.0, metadata)),
+                (true, false) => self.convert_one_of(type_name, metadata, &rest),
+                (false, true) => self.convert_permissive(metadata),
+                (false, false) => {
+                    rest.push(Schema::Bool(true));
+                    Ok((self.untagged_enum(type_name, metadata, &rest)?, metadata))
+                }
+            };
+        }
+
         if subschemas.len() == 1 {
-            let (ty, _) = self.convert_schema(type_name, subschemas.first().unwrap())?;
+            let (ty, _) = self.within([0.into()], |type_space| {
+                type_space.convert_schema(type_name, subschemas.first().unwrap())
+            })?;
             return Ok((ty, metadata));
         }
         let ty = self
-            .maybe_option_as_enum(type_name.clone(), metadata, subschemas)
+            .maybe_ip_addr(subschemas)
+            .or_else(|| self.maybe_option_as_enum(type_name.clone(), metadata, subschemas))
             .or_else(|| self.maybe_externally_tagged_enum(type_name.clone(), metadata, subschemas))
             .or_else(|| self.maybe_adjacently_tagged_enum(type_name.clone(), metadata, subschemas))
             .or_else(|| self.maybe_internally_tagged_enum(type_name.clone(), metadata, subschemas))
             .map_or_else(|| self.untagged_enum(type_name, metadata, subschemas), Ok)?;
 
+        // Variants are deprecated if their subschemas are. We can match them
+        // up when each subschema produced one variant.
+        let ty = match ty {
+            TypeEntry::Enum(mut type_enum) if type_enum.variants.len() == subschemas.len() => {
+                for (variant, schema) in type_enum.variants.iter_mut().zip(subschemas) {
+                    if let Schema::Object(SchemaObject { metadata, .. }) = schema {
+                        variant.deprecated = metadata_deprecated(metadata);
+                    }
+                }
+                TypeEntry::Enum(type_enum)
+            }
+            ty => ty,
+        };
+
         Ok((ty, metadata))
     }
 
+    /// An `if`/`then`/`else` is a choice between values that match the `if`
+    /// schema (and the `then` schema) and values that don't (and instead
+    /// match the `else` schema). We support the common case of an `if` schema
+    /// that requires a single property to have a constant value, e.g. "if
+    /// `kind` is "a" then `a` is required": values that don't match have any
+    /// other value for that property. We merge each choice with the rest of
+    /// the schema and convert the pair as a "one of", so that a property with
+    /// a constant value in one choice and other enumerated values in the
+    /// other becomes the tag of an enum.
+    fn convert_conditional<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        schema: &SchemaObject,
+        subschemas: &SubschemaValidation,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let name = get_type_name(&type_name, metadata, Case::Pascal)
+            .unwrap_or_else(|| "an anonymous type".to_string());
+
+        // The rest of the schema applies in either case.
+        let other_subschemas = SubschemaValidation {
+            if_schema: None,
+            then_schema: None,
+            else_schema: None,
+            ..subschemas.clone()
+        };
+        let base = SchemaObject {
+            subschemas: (other_subschemas != SubschemaValidation::default())
+                .then(|| Box::new(other_subschemas)),
+            ..schema.clone()
+        };
+
+        // Without an if schema, the then and else schemas have no effect.
+        let if_schema = match &subschemas.if_schema {
+            Some(if_schema) => if_schema.as_ref(),
+            None => {
+                let (ty, _) = self.convert_schema_object(type_name, &base)?;
+                return Ok((ty, metadata));
+            }
+        };
+
+        let (property, value) = conditional_property(if_schema, &base).ok_or_else(|| {
+            Error::Unsupported(format!(
+                "the if schema of {} doesn't require a single property to have a constant value",
+                name
+            ))
+        })?;
+
+        let base = Schema::Object(base);
+        let mut matching = vec![base.clone(), if_schema.clone()];
+        matching.extend(subschemas.then_schema.as_deref().cloned());
+        let mut other = vec![base];
+        other.extend(subschemas.else_schema.as_deref().cloned());
+
+        let matching = self.conditional_branch(&name, &matching, None)?;
+        let other = self.conditional_branch(&name, &other, Some((&property, &value)))?;
+
+        match (matching, other) {
+            (Some(matching), Some(other)) => self
+                .convert_one_of(
+                    type_name,
+                    metadata,
+                    &[Schema::Object(matching), Schema::Object(other)],
+                )
+                .map_err(|e| match e {
+                    // The choices are those of the then and else schemas.
+                    Error::At {
+                        mut location,
+                        error,
+                    } => {
+                        if let Some(PathSegment::Index(index)) = location.path.first() {
+                            location.path[0] = if *index == 0 { "then" } else { "else" }.into();
+                        }
+                        Error::At { location, error }
+                    }
+                    error => error,
+                }),
+            (Some(branch), None) | (None, Some(branch)) => {
+                let branch = SchemaObject {
+                    metadata: metadata.clone(),
+                    ..branch
+                };
+                let (ty, _) = self.convert_schema_object(type_name, &branch)?;
+                Ok((ty, metadata))
+            }
+            (None, None) => Err(Error::InvalidSchema(
+                name,
+                "no value satisfies either branch of its if/then/else".to_string(),
+            )),
+        }
+    }
+
+    /// Merge the schemas for one choice of a conditional, excluding a value
+    /// for a property if given. The result is None if no value could satisfy
+    /// them.
+    fn conditional_branch(
+        &self,
+        name: &str,
+        schemas: &[Schema],
+        excluded: Option<(&str, &serde_json::Value)>,
+    ) -> Result<Option<SchemaObject>> {
+        let mut merged = match merge_all(schemas, &self.definitions) {
+            Ok(merged) => merged,
+            Err(MergeError::Conflict(_)) => return Ok(None),
+            Err(MergeError::Unsupported) => {
+                return Err(Error::Unsupported(format!(
+                    "the if/then/else of {} can't be merged with the rest of its schema",
+                    name
+                )))
+            }
+        };
+
+        if let Some((property, value)) = excluded {
+            if let Some(Schema::Object(property_schema)) = merged
+                .object
+                .as_mut()
+                .and_then(|validation| validation.properties.get_mut(property))
+            {
+                if property_schema.const_value.as_ref() == Some(value) {
+                    return Ok(None);
+                }
+                if let Some(values) = &mut property_schema.enum_values {
+                    values.retain(|other| other != value);
+                    if values.is_empty() {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+
+        Ok(Some(merged))
+    }
+
     fn convert_array<'a>(
         &mut self,
         type_name: Name,
@@ -799,48 +1860,193 @@ impl TypeSpace {
         validation: &ArrayValidation,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
         match validation {
-            // A tuple.
+            // A tuple; additional items are allowed unless they're
+            // explicitly excluded.
             ArrayValidation {
                 items: Some(SingleOrVec::Vec(items)),
-                additional_items: None,
-                max_items: Some(max_items),
-                min_items: Some(min_items),
+                additional_items,
+                max_items,
+                min_items,
                 unique_items: None,
                 contains: None,
-            } if max_items == min_items && *max_items as usize == items.len() => {
-                let types = items
-                    .iter()
-                    .map(|schema| Ok(self.id_for_schema(Name::Unknown, schema)?.0))
-                    .collect::<Result<Vec<_>>>()?;
-
-                Ok((TypeEntry::Tuple(types), metadata))
+            } => {
+                let closed = matches!(additional_items.as_deref(), Some(Schema::Bool(false)))
+                    || *max_items == Some(items.len() as u32);
+                self.convert_tuple(type_name, metadata, "items", items, closed, *min_items)
             }
 
             // Arrays and sets.
             ArrayValidation {
                 items: Some(SingleOrVec::Single(item)),
                 additional_items: None,
-                max_items: _, // TODO enforce size limitations
-                min_items: _, // TODO enforce size limitations
+                max_items,
+                min_items,
                 unique_items,
                 contains: None,
             } => {
+                // The items of an array property are named for it unless
+                // the array has a name of its own, as validated arrays and
+                // titled ones may, or another type has the name already.
+                let validated = self.settings.array_validation
+                    && (min_items.is_some_and(|min_items| min_items > 0) || max_items.is_some());
                 let tmp_type_name = match get_type_name(&type_name, metadata, Case::Pascal) {
-                    Some(s) => Name::Suggested(format!("{}Item", s)),
+                    Some(s)
+                        if matches!(type_name, Name::Suggested(_))
+                            && metadata_title(metadata).is_none()
+                            && !validated
+                            && !self.name_taken(&s) =>
+                    {
+                        Name::Suggested(s)
+                    }
+                    Some(s) => Name::Suggested(path_type_name(&[s.as_str(), "item"])),
                     None => Name::Unknown,
                 };
-                let (type_id, _) = self.id_for_schema(tmp_type_name, item.as_ref())?;
+                let (type_id, _) = self.within(["items".into()], |type_space| {
+                    type_space.id_for_schema(tmp_type_name, item.as_ref())
+                })?;
+
+                // If items are unique, this is a Set; if there's an exact
+                // number of items (that serde can handle), it's a fixed-size
+                // array; otherwise it's an Array.
+                let ty = match (unique_items, min_items, max_items) {
+                    (Some(true), _, _) => TypeEntry::Set(type_id),
+                    (_, Some(min_items), Some(max_items))
+                        if min_items == max_items && *max_items <= MAX_FIXED_ARRAY_LEN =>
+                    {
+                        let ty = TypeEntry::FixedArray(type_id, *max_items as usize);
+                        self.warn_unreached_with(&ty);
+                        return Ok((ty, metadata));
+                    }
+                    _ => TypeEntry::Array(type_id),
+                };
+                self.warn_unreached_with(&ty);
 
-                // If items are unique, this is a Set; otherwise it's an Array.
-                match unique_items {
-                    Some(true) => Ok((TypeEntry::Set(type_id), metadata)),
-                    _ => Ok((TypeEntry::Array(type_id), metadata)),
+                let min_items = min_items.filter(|min_items| *min_items > 0);
+                if !self.settings.array_validation || (min_items.is_none() && max_items.is_none()) {
+                    return Ok((ty, metadata));
                 }
+
+                // A name for a property or item is just a suggestion so we can
+                // call out the common case of arrays that may not be empty.
+                let type_name = match (type_name, min_items, max_items) {
+                    (Name::Suggested(name), Some(1), None) => {
+                        Name::Suggested(format!("non_empty_{}", name))
+                    }
+                    (type_name, _, _) => type_name,
+                };
+                self.validated_newtype(
+                    type_name,
+                    metadata,
+                    ty,
+                    NewtypeConstraints::Array {
+                        max_items: *max_items,
+                        min_items,
+                    },
+                )
+            }
+
+            _ => Err(Error::Unsupported(format!(
+                "the array validation {}",
+                serde_json::to_string(validation).unwrap()
+            ))),
+        }
+    }
+
+    fn convert_prefix_items<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        validation: &Option<Box<ArrayValidation>>,
+        prefix_items: &serde_json::Value,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let items = serde_json::from_value::<Vec<Schema>>(prefix_items.clone())
+            .map_err(|_| Error::BadValue("prefixItems".to_string(), prefix_items.clone()))?;
+
+        match validation.as_deref() {
+            None => self.convert_tuple(type_name, metadata, "prefixItems", &items, false, None),
+
+            // Here `items` is the schema for the items that follow those in
+            // `prefixItems` so additional items are only excluded if it's
+            // the schema that matches nothing.
+            Some(ArrayValidation {
+                items: rest,
+                additional_items: None,
+                max_items,
+                min_items,
+                unique_items: None,
+                contains: None,
+            }) => {
+                let closed = matches!(rest, Some(SingleOrVec::Single(rest)) if rest.as_ref() == &Schema::Bool(false))
+                    || *max_items == Some(items.len() as u32);
+                self.convert_tuple(
+                    type_name,
+                    metadata,
+                    "prefixItems",
+                    &items,
+                    closed,
+                    *min_items,
+                )
             }
 
-            _ => todo!("unhandled array validation {:#?}", validation),
+            Some(validation) => Err(Error::Unsupported(format!(
+                "the array validation {}",
+                serde_json::to_string(validation).unwrap()
+            ))),
+        }
+    }
+
+    fn convert_tuple<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        keyword: &str,
+        items: &[Schema],
+        closed: bool,
+        min_items: Option<u32>,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let tuple_name = get_type_name(&type_name, metadata, Case::Pascal);
+        let describe = || {
+            tuple_name
+                .as_deref()
+                .unwrap_or("anonymous tuple")
+                .to_string()
+        };
+
+        // Rust tuples can neither have extra items nor omit items.
+        if !closed {
+            return Err(Error::Unsupported(format!(
+                "{} allows items beyond its {} positional items",
+                describe(),
+                items.len(),
+            )));
         }
+        if min_items.is_some_and(|min_items| (min_items as usize) < items.len()) {
+            return Err(Error::Unsupported(format!(
+                "{} has optional positional items",
+                describe(),
+            )));
+        }
+
+        let types = items
+            .iter()
+            .enumerate()
+            .map(|(index, schema)| {
+                let item_name = match &tuple_name {
+                    Some(name) => Name::Suggested(format!("{}Item{}", name, index)),
+                    None => Name::Unknown,
+                };
+                let (type_id, _) = self.within([keyword.into(), index.into()], |type_space| {
+                    type_space.id_for_schema(item_name, schema)
+                })?;
+                Ok(type_id)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let ty = TypeEntry::Tuple(types);
+        self.warn_unreached_with(&ty);
+        Ok((ty, metadata))
     }
+
     fn convert_array_of_any<'a>(
         &mut self,
         metadata: &'a Option<Box<Metadata>>,
@@ -861,6 +2067,63 @@ impl TypeSpace {
         Ok((TypeEntry::new_integer("bool"), metadata))
     }
 
+    /// The type named by an `x-rust-type` extension, e.g.
+    /// `{ "crate": "chrono", "version": "0.4", "path": "chrono::NaiveDate" }`,
+    /// with `parameters` giving the schemas of any type parameters. We only
+    /// use types from the standard library or from crates that the settings
+    /// permit, in compatible versions, so that a schema can't name arbitrary
+    /// paths; otherwise we convert the schema as usual.
+    fn convert_rust_type(&mut self, rust_type: &serde_json::Value) -> Result<Option<TypeEntry>> {
+        let field = |name| rust_type.get(name).and_then(serde_json::Value::as_str);
+        let (crate_name, version, path) = match (field("crate"), field("version"), field("path")) {
+            (Some(crate_name), Some(version), Some(path)) => (crate_name, version, path),
+            _ => return Ok(None),
+        };
+        let parameters = match rust_type.get("parameters") {
+            None => Vec::new(),
+            Some(parameters) => match serde_json::from_value::<Vec<Schema>>(parameters.clone()) {
+                Ok(parameters) => parameters,
+                Err(_) => return Ok(None),
+            },
+        };
+
+        let permitted = match crate_name {
+            "std" | "core" | "alloc" => true,
+            _ => self
+                .settings
+                .crates
+                .get(crate_name)
+                .is_some_and(|permitted| versions_compatible(permitted, version)),
+        };
+        let within_crate = syn::parse_str::<syn::Path>(path).is_ok_and(|parsed| {
+            parsed.leading_colon.is_none()
+                && parsed.segments.first().unwrap().ident == crate_name.replace('-', "_")
+                && parsed
+                    .segments
+                    .iter()
+                    .all(|segment| segment.arguments.is_empty())
+        });
+        if !permitted || !within_crate {
+            return Ok(None);
+        }
+
+        if parameters.is_empty() {
+            return Ok(Some(TypeEntry::BuiltIn(path.to_string())));
+        }
+        let mut parameter_ids = Vec::new();
+        for (index, parameter) in parameters.iter().enumerate() {
+            let (type_entry, _) = self.within(
+                ["x-rust-type".into(), "parameters".into(), index.into()],
+                |type_space| type_space.convert_schema(Name::Unknown, parameter),
+            )?;
+            parameter_ids.push(self.assign_type(type_entry));
+        }
+        Ok(Some(TypeEntry::BuiltInGeneric(
+            path.to_string(),
+            parameter_ids,
+        )))
+    }
+
     fn convert_permissive<'a>(
         &mut self,
         metadata: &'a Option<Box<Metadata>>,
@@ -869,11 +2132,28 @@ impl TypeSpace {
         Ok((TypeEntry::new_builtin("serde_json::Value"), metadata))
     }
 
+    /// The schema that matches nothing becomes an enum without variants: it
+    /// can't be constructed and fails to deserialize from any value.
+    fn convert_never<'a>(
+        &mut self,
+        type_name: Name,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let type_name = match type_name {
+            Name::Unknown => Name::Required("Never".to_string()),
+            type_name => type_name,
+        };
+        let ty =
+            TypeEntryEnum::from_metadata(type_name, &None, EnumTagType::External, vec![], false);
+
+        Ok((ty, &None))
+    }
+
     fn convert_unknown_enum<'a>(
         &mut self,
         type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
         enum_values: &[serde_json::Value],
+        extensions: &schemars::Map<String, serde_json::Value>,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
         // We're here because the schema didn't have a type; that seems busted,
         // but we'll do our best to roll with the punches.
@@ -885,50 +2165,274 @@ impl TypeSpace {
             .map(|v| match v {
                 serde_json::Value::Null => InstanceType::Null,
                 serde_json::Value::Bool(_) => InstanceType::Boolean,
+                serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => InstanceType::Integer,
                 serde_json::Value::Number(_) => InstanceType::Number,
                 serde_json::Value::String(_) => InstanceType::String,
                 serde_json::Value::Array(_) => InstanceType::Array,
                 serde_json::Value::Object(_) => InstanceType::Object,
             })
+            .collect::<std::collections::BTreeSet<_>>();
+        // Numbers include integers.
+        let instance_types = if instance_types.contains(&InstanceType::Number) {
+            instance_types
+                .into_iter()
+                .filter(|instance_type| instance_type != &InstanceType::Integer)
+                .collect()
+        } else {
+            instance_types.into_iter().collect::<Vec<_>>()
+        };
+
+        // Convert the values as those of a schema with their types.
+        let instance_type = match instance_types.as_slice() {
+            [instance_type] => SingleOrVec::Single(Box::new(*instance_type)),
+            _ => SingleOrVec::Vec(instance_types),
+        };
+        let schema = SchemaObject {
+            metadata: metadata.clone(),
+            instance_type: Some(instance_type),
+            enum_values: Some(enum_values.to_vec()),
+            extensions: extensions.clone(),
+            ..Default::default()
+        };
+        let (type_entry, _) = self.convert_schema_object(type_name, &schema)?;
+        Ok((type_entry, metadata))
+    }
+
+    /// Split a schema with an array of instance types into a schema for each
+    /// type, each with the validation that applies to it, and make an untagged
+    /// enum of those. If one of the types is null, the enum is optional.
+    fn convert_multi_type<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        instance_types: &[InstanceType],
+        schema: &SchemaObject,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        // For values that could match several types (e.g. 1 is both an
+        // integer and a number) the first variant wins.
+        let order = [
+            InstanceType::Boolean,
+            InstanceType::Integer,
+            InstanceType::Number,
+            InstanceType::String,
+            InstanceType::Array,
+            InstanceType::Object,
+        ];
+        let types = order
+            .iter()
+            .filter(|instance_type| instance_types.contains(instance_type))
             .collect::<Vec<_>>();
+        let nullable = instance_types.contains(&InstanceType::Null);
+        let type_name = if nullable {
+            option_inner_name(type_name)
+        } else {
+            type_name
+        };
+
+        let type_schema = |instance_type: &InstanceType| {
+            let (enum_values, extensions) =
+                retain_enum_values(&schema.enum_values, &schema.extensions, |value| {
+                    value_matches_type(value, instance_type)
+                });
+            SchemaObject {
+                metadata: None,
+                instance_type: Some(SingleOrVec::from(*instance_type)),
+                format: schema.format.clone(),
+                enum_values,
+                const_value: schema
+                    .const_value
+                    .clone()
+                    .filter(|value| value_matches_type(value, instance_type)),
+                subschemas: None,
+                number: match instance_type {
+                    InstanceType::Integer | InstanceType::Number => schema.number.clone(),
+                    _ => None,
+                },
+                string: match instance_type {
+                    InstanceType::String => schema.string.clone(),
+                    _ => None,
+                },
+                array: match instance_type {
+                    InstanceType::Array => schema.array.clone(),
+                    _ => None,
+                },
+                object: match instance_type {
+                    InstanceType::Object => schema.object.clone(),
+                    _ => None,
+                },
+                reference: None,
+                extensions,
+            }
+        };
 
-        match (instance_types.len(), instance_types.first()) {
-            (1, Some(InstanceType::String)) => {
-                self.convert_enum_string(type_name, metadata, enum_values)
+        let type_entry = match (types.as_slice(), &type_name) {
+            ([], _) => TypeEntry::Unit,
+            ([instance_type], _) => {
+                let (type_entry, _) =
+                    self.convert_schema_object(type_name, &type_schema(instance_type))?;
+                type_entry
             }
-            (1, Some(InstanceType::Boolean)) => {
-                self.convert_bool(metadata, &Some(enum_values.into()))
+            (_, Name::Unknown) if metadata_title(metadata).is_none() => {
+                // We can't name the enum so the best we can do is accept any
+                // value.
+                self.uses_serde_json = true;
+                TypeEntry::new_builtin("serde_json::Value")
+            }
+            _ => {
+                let enum_name = get_type_name(&type_name, metadata, Case::Pascal).unwrap();
+                let variants = types
+                    .iter()
+                    .map(|instance_type| {
+                        let variant_name = format!("{:?}", instance_type);
+                        let (type_id, _) = self.id_for_schema(
+                            Name::Suggested(format!("{}{}", enum_name, variant_name)),
+                            &Schema::Object(type_schema(instance_type)),
+                        )?;
+                        Ok(Variant {
+                            name: variant_name,
+                            rename: None,
+                            description: None,
+                            deprecated: false,
+                            details: VariantDetails::Tuple(vec![type_id]),
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                TypeEntryEnum::from_metadata(
+                    type_name,
+                    metadata,
+                    EnumTagType::Untagged,
+                    variants,
+                    false,
+                )
             }
-            _ => panic!(),
+        };
+
+        if nullable {
+            Ok((self.type_to_option(type_entry), metadata))
+        } else {
+            Ok((type_entry, metadata))
         }
     }
 
-    pub(crate) fn convert_option<'a, 'b>(
+    pub(crate) fn convert_option<'a>(
         &mut self,
         type_name: Name,
         metadata: &'a Option<Box<Metadata>>,
-        schema: &'b Schema,
+        schema: &Schema,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
-        let (ty, _) = self.convert_schema(type_name, schema)?;
+        let (ty, _) = self.convert_schema(option_inner_name(type_name), schema)?;
         let ty = self.type_to_option(ty);
 
         Ok((ty, metadata))
     }
 }
 
+/// The property and its value for an `if` schema that requires a single
+/// property to have a constant value, e.g.
+/// `{ "properties": { "kind": { "const": "a" } }, "required": ["kind"] }`.
+/// The property must be required either here or by the schema with the `if`
+/// as otherwise objects without it would match as well.
+fn conditional_property(
+    if_schema: &Schema,
+    base: &SchemaObject,
+) -> Option<(String, serde_json::Value)> {
+    let validation = match if_schema {
+        Schema::Object(SchemaObject {
+            metadata: _,
+            instance_type,
+            format: None,
+            enum_values: None,
+            const_value: None,
+            subschemas: None,
+            number: None,
+            string: None,
+            array: None,
+            object: Some(validation),
+            reference: None,
+            extensions: _,
+        }) if instance_type
+            .as_ref()
+            .is_none_or(|it| it == &SingleOrVec::from(InstanceType::Object)) =>
+        {
+            validation
+        }
+        _ => return None,
+    };
+
+    // The property and whether it's required are all we can handle.
+    let rest = ObjectValidation {
+        properties: Default::default(),
+        required: Default::default(),
+        ..validation.as_ref().clone()
+    };
+    if validation.properties.len() != 1 || rest != ObjectValidation::default() {
+        return None;
+    }
+
+    let (property, property_schema) = validation.properties.iter().next()?;
+    if validation
+        .required
+        .iter()
+        .any(|required| required != property)
+    {
+        return None;
+    }
+
+    let value = match property_schema {
+        Schema::Object(SchemaObject {
+            enum_values: None,
+            const_value: Some(value),
+            ..
+        }) => value.clone(),
+        Schema::Object(SchemaObject {
+            enum_values: Some(values),
+            const_value: None,
+            ..
+        }) if values.len() == 1 => values.first()?.clone(),
+        _ => return None,
+    };
+
+    let required = validation.required.contains(property)
+        || base
+            .object
+            .as_ref()
+            .is_some_and(|validation| validation.required.contains(property));
+
+    required.then(|| (property.clone(), value))
+}
+
+/// A required name belongs to the (newtype of the) `Option` so the type
+/// within it needs a different one.
+fn option_inner_name(type_name: Name) -> Name {
+    match type_name {
+        Name::Required(name) => Name::Suggested(format!("{}Inner", name)),
+        type_name => type_name,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8};
 
-    use schemars::{schema_for, JsonSchema};
-
-    use crate::{validate_builtin, Name, TypeSpace};
+    use quote::{quote, ToTokens};
+    use schemars::{
+        schema::{RootSchema, Schema, SchemaObject},
+        schema_for, JsonSchema,
+    };
+    use serde_json::json;
+
+    use crate::{
+        test_util::assert_output, validate_builtin, DateTimeCrate, DecimalImpl, Error, Name,
+        PatternEngine, TypePatch, TypeSpace, TypeSpaceSettings, UnknownValues,
+    };
     use paste::paste;
 
     fn int_helper<T: JsonSchema>() {
         let schema = schema_for!(T);
 
-        let mut type_space = TypeSpace::default();
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_nonzero_integers(true));
         type_space
             .add_ref_types(schema.definitions.clone())
             .unwrap();
@@ -940,10 +2444,17 @@ mod tests {
             .rsplit_once("::")
             .map(|(_, x)| x.trim())
             .unwrap_or(&output);
-        let expected = std::any::type_name::<T>()
-            .rsplit_once("::")
-            .map(|(_, x)| x.trim())
-            .unwrap_or(&output);
+        // Newer compilers report `NonZeroU8` as `core::num::NonZero<u8>`, so
+        // we map the generic form back to the type alias before comparing.
+        let full_name = std::any::type_name::<T>();
+        let expected = match full_name.split_once("NonZero<") {
+            Some((_, inner)) => format!("NonZero{}", inner.trim_end_matches('>').to_uppercase()),
+            None => full_name
+                .rsplit_once("::")
+                .map(|(_, x)| x.trim())
+                .unwrap_or(&output)
+                .to_string(),
+        };
         assert_eq!(actual, expected);
     }
 
@@ -1008,4 +2519,1542 @@ mod tests {
     fn test_set() {
         validate_builtin!(std::collections::BTreeSet<u32>);
     }
+
+    #[test]
+    fn test_uuid_format() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "id": {
+                    "type": "string",
+                    "format": "uuid"
+                },
+                "either": {
+                    "oneOf": [
+                        { "type": "string", "format": "uuid" },
+                        { "type": "integer" }
+                    ]
+                },
+                "ids": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string", "format": "uuid" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_eq!(type_space.uses_uuid(), cfg!(feature = "uuid"));
+        if cfg!(feature = "uuid") {
+            assert_output(&type_space, "convert/uuid_format-uuid");
+        } else {
+            assert_output(&type_space, "convert/uuid_format");
+        }
+    }
+
+    #[test]
+    fn test_datetime_formats() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "timestamp": {
+                    "type": "string",
+                    "format": "date-time"
+                },
+                "event": {
+                    "type": "object",
+                    "required": ["day", "at"],
+                    "properties": {
+                        "day": { "type": "string", "format": "date" },
+                        "at": { "type": "string", "format": "time" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(!type_space.uses_chrono());
+        assert_output(&type_space, "convert/datetime_formats");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Chrono));
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(type_space.uses_chrono());
+        assert_output(&type_space, "convert/datetime_formats-chrono");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Time));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(type_space.uses_time());
+        assert!(!type_space.uses_chrono());
+        assert_output(&type_space, "convert/datetime_formats-time");
+    }
+
+    #[test]
+    fn test_datetime_nested() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "schedule": {
+                    "type": "object",
+                    "properties": {
+                        "next": {
+                            "oneOf": [
+                                { "type": "string", "format": "date-time" },
+                                { "type": "integer" }
+                            ]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Time));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Only the helpers that are needed are generated.
+        assert_output(&type_space, "convert/datetime_nested");
+    }
+
+    #[test]
+    fn test_datetime_collections() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "Calendar": {
+                    "type": "object",
+                    "required": ["holidays", "reminders"],
+                    "properties": {
+                        "holidays": {
+                            "type": "array",
+                            "items": { "type": "string", "format": "date" }
+                        },
+                        "reminders": {
+                            "type": "object",
+                            "additionalProperties": { "type": "string", "format": "date-time" }
+                        },
+                        "postponed": {
+                            "type": ["array", "null"],
+                            "items": { "type": "string", "format": "date-time" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Time));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        let file = syn::parse2::<syn::File>(type_space.to_stream()).unwrap();
+        let calendar = file
+            .items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Struct(item) if item.ident == "Calendar" => Some(item),
+                _ => None,
+            })
+            .unwrap();
+        let expected = quote! {
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+            pub struct Calendar {
+                #[serde(with = "nested_serde::time_serde_date::seq")]
+                pub holidays: Vec<time::Date>,
+                #[serde(
+                    default,
+                    skip_serializing_if = "Option::is_none",
+                    with = "nested_serde::time_serde_rfc3339::option_seq"
+                )]
+                pub postponed: Option<Vec<time::OffsetDateTime>>,
+                #[serde(with = "nested_serde::time_serde_rfc3339::map")]
+                pub reminders: std::collections::HashMap<String, time::OffsetDateTime>,
+            }
+        };
+        assert_eq!(
+            calendar.to_token_stream().to_string(),
+            syn::parse2::<syn::ItemStruct>(expected)
+                .unwrap()
+                .to_token_stream()
+                .to_string()
+        );
+
+        let nested = file
+            .items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Mod(item) if item.ident == "nested_serde" => item.content.as_ref(),
+                _ => None,
+            })
+            .unwrap();
+        let modules = nested
+            .1
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(item) => Some(item.ident.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(modules, ["time_serde_rfc3339", "time_serde_date"]);
+    }
+
+    #[test]
+    fn test_datetime_nested_collections() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "Schedule": {
+                    "type": "object",
+                    "required": ["weeks", "gaps", "dates"],
+                    "properties": {
+                        "weeks": {
+                            "type": "array",
+                            "items": {
+                                "type": "array",
+                                "items": { "type": "string", "format": "date" }
+                            }
+                        },
+                        "gaps": {
+                            "type": "array",
+                            "items": { "type": ["string", "null"], "format": "date" }
+                        },
+                        "dates": {
+                            "type": "array",
+                            "items": { "type": "string", "format": "date" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Time));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The values of arrays of arrays and of options aren't in RFC 3339
+        // formats, unlike those of plain arrays.
+        let warnings = type_space
+            .warnings()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+        let message =
+            "time::Date values nested this deeply use the default serde format of the type";
+        assert_eq!(
+            warnings,
+            [
+                format!("definitions.Schedule.properties.gaps: {}", message),
+                format!("definitions.Schedule.properties.weeks: {}", message),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ip_formats() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "host": {
+                    "type": "object",
+                    "required": ["four", "six", "any", "either"],
+                    "properties": {
+                        "four": { "type": "string", "format": "ipv4" },
+                        "six": { "type": "string", "format": "ipv6" },
+                        "any": { "type": "string", "format": "ip" },
+                        "either": {
+                            "oneOf": [
+                                { "type": "string", "format": "ipv4" },
+                                { "type": "string", "format": "ipv6" }
+                            ]
+                        },
+                        "maybe": {
+                            "anyOf": [
+                                { "type": "string", "format": "ipv6" },
+                                { "type": "string", "format": "ipv4" }
+                            ]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/ip_formats");
+    }
+
+    #[test]
+    fn test_string_pattern() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "host-label": {
+                    "type": "string",
+                    "pattern": "^[a-z0-9-]{1,63}$"
+                },
+                "quoted": {
+                    "type": "string",
+                    "pattern": "^\"[^\"\\\\]*\"$"
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(!type_space.uses_regex());
+        assert_output(&type_space, "convert/string_pattern");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(type_space.uses_regress());
+        assert!(!type_space.uses_regex());
+        let file = syn::parse2::<syn::File>(type_space.to_stream()).unwrap();
+        // The newtypes, their impls, and the error module.
+        assert_eq!(file.items.len(), 15);
+        assert_output(&type_space, "convert/string_pattern-regress");
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default()
+                .with_string_validation(true)
+                .with_pattern_engine(PatternEngine::Regex),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(type_space.uses_regex());
+        assert!(!type_space.uses_regress());
+        assert_output(&type_space, "convert/string_pattern-regex");
+    }
+
+    #[test]
+    fn test_string_pattern_engines() {
+        // Lookahead is ECMA-262 syntax that only regress supports.
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "password": {
+                    "type": "string",
+                    "pattern": "^(?=.*[0-9]).{8,}$"
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_string_validation(true);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(type_space.uses_regress());
+
+        settings.with_pattern_engine(PatternEngine::Regex);
+        let mut type_space = TypeSpace::new(&settings);
+        let err = type_space.add_ref_types(schema.definitions).unwrap_err();
+        match err.reason() {
+            Error::InvalidPattern(name, _, reason) => {
+                assert_eq!(name, "Password");
+                assert!(reason.contains("look-around"), "{}", reason);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        // Each engine checks patterns as it compiles them: named
+        // backreferences and control escapes are ECMA-262 syntax, and inline
+        // flags are the regex crate's.
+        let cases = vec![
+            (r"^(?<x>a)\k<x>$", true, false),
+            (r"^\cJ$", true, false),
+            ("(?i)^abc$", false, true),
+        ];
+        for (pattern, regress, regex) in cases {
+            let schema: RootSchema = serde_json::from_value(json!({
+                "definitions": {
+                    "thing": { "type": "string", "pattern": pattern }
+                }
+            }))
+            .unwrap();
+            for &(engine, valid) in &[
+                (PatternEngine::Regress, regress),
+                (PatternEngine::Regex, regex),
+            ] {
+                let mut type_space = TypeSpace::new(
+                    TypeSpaceSettings::default()
+                        .with_string_validation(true)
+                        .with_pattern_engine(engine),
+                );
+                let result = type_space.add_ref_types(schema.definitions.clone());
+                assert_eq!(result.is_ok(), valid, "{} with {:?}", pattern, engine);
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_pattern_invalid() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "broken": {
+                    "type": "string",
+                    "pattern": "^[a-z"
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        match type_space
+            .add_ref_types(schema.definitions)
+            .as_ref()
+            .map_err(Error::reason)
+        {
+            Err(Error::InvalidPattern(name, pattern, _)) => {
+                assert_eq!(name, "Broken");
+                assert_eq!(pattern, "^[a-z");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_length() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "name": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": 64
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(!type_space.uses_regex());
+        assert_output(&type_space, "convert/string_length");
+    }
+
+    #[test]
+    fn test_int_bounds() {
+        let cases = [
+            (json!({ "minimum": 0, "maximum": 255 }), "u8"),
+            (json!({ "minimum": 0, "maximum": 256 }), "u16"),
+            (json!({ "minimum": -1, "maximum": 127 }), "i8"),
+            (json!({ "minimum": -1, "maximum": 128 }), "i16"),
+            (json!({ "minimum": -1, "maximum": 2147483647 }), "i32"),
+            (json!({ "minimum": -1, "maximum": 2147483648i64 }), "i64"),
+            (json!({ "minimum": 0, "maximum": 2147483648i64 }), "u32"),
+            (json!({ "minimum": 0, "exclusiveMaximum": 256 }), "u8"),
+            (json!({ "exclusiveMinimum": -129, "maximum": 0 }), "i8"),
+            (json!({ "minimum": -0.5, "maximum": 255.5 }), "u8"),
+            (
+                json!({ "exclusiveMinimum": -0.5, "exclusiveMaximum": 256.5 }),
+                "u16",
+            ),
+            (
+                json!({ "exclusiveMinimum": -128.5, "maximum": 127.9 }),
+                "i8",
+            ),
+            (json!({ "minimum": 0 }), "u64"),
+            (json!({ "maximum": 100 }), "i64"),
+            (json!({}), "i64"),
+            // The narrower of a format and bounds wins.
+            (
+                json!({ "format": "int32", "minimum": 0, "maximum": 10 }),
+                "u8",
+            ),
+            (json!({ "format": "uint64", "maximum": 10 }), "u8"),
+            (json!({ "format": "int32", "minimum": -1 }), "i32"),
+            (json!({ "format": "int64", "maximum": 2147483647 }), "i64"),
+            (
+                json!({ "format": "int64", "minimum": -1, "maximum": 2147483647 }),
+                "i32",
+            ),
+            (json!({ "format": "int64", "minimum": 0 }), "i64"),
+            (json!({ "format": "uint32", "minimum": 0 }), "u32"),
+        ];
+
+        for (bounds, expected) in cases {
+            let mut schema = json!({ "type": "integer" });
+            schema
+                .as_object_mut()
+                .unwrap()
+                .extend(bounds.as_object().unwrap().clone());
+            let schema: SchemaObject = serde_json::from_value(schema).unwrap();
+
+            let mut type_space = TypeSpace::default();
+            let (ty, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(ty.type_name(&type_space), expected, "{:?}", bounds);
+            // Bounds that the type doesn't enforce are only unchecked.
+            assert!(type_space
+                .warnings()
+                .iter()
+                .all(|warning| warning.message.ends_with("without number validation")));
+        }
+    }
+
+    #[test]
+    fn test_int_bounds_overflow() {
+        let cases = [
+            (json!({ "minimum": 0, "maximum": 1e20 }), "u64"),
+            (json!({ "minimum": -1e20 }), "i64"),
+            (json!({ "format": "uint8", "maximum": 1000 }), "u8"),
+            (
+                json!({ "format": "int32", "maximum": 1099511627776i64 }),
+                "i32",
+            ),
+        ];
+
+        for (bounds, expected) in cases {
+            let mut schema = json!({ "type": "integer" });
+            schema
+                .as_object_mut()
+                .unwrap()
+                .extend(bounds.as_object().unwrap().clone());
+            let schema: SchemaObject = serde_json::from_value(schema).unwrap();
+
+            let mut type_space = TypeSpace::default();
+            let (ty, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(ty.type_name(&type_space), expected, "{:?}", bounds);
+            assert_eq!(type_space.warnings().len(), 1, "{:?}", bounds);
+        }
+
+        let schema: SchemaObject = serde_json::from_value(json!({
+            "type": "integer",
+            "format": "uint8",
+            "maximum": 1000
+        }))
+        .unwrap();
+        let mut type_space = TypeSpace::default();
+        type_space
+            .convert_schema_object(Name::Unknown, &schema)
+            .unwrap();
+        assert_eq!(
+            type_space.warnings()[0].message,
+            "integer bounds 0 to 1000 exceed the range of format uint8; using u8"
+        );
+    }
+
+    #[test]
+    fn test_int_fractional_bounds() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "inclusive": {
+                    "type": "integer",
+                    "minimum": 0.5,
+                    "maximum": 9.5
+                },
+                "exclusive": {
+                    "type": "integer",
+                    "exclusiveMinimum": -2.5,
+                    "exclusiveMaximum": 10.5
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_number_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The bounds are those of the integers within them: 1 to 9, and -2
+        // to 10.
+        assert_output(&type_space, "convert/int_fractional_bounds");
+    }
+
+    #[test]
+    fn test_int_nonzero() {
+        let cases = [
+            (json!({ "minimum": 1 }), "u64", "std::num::NonZeroU64"),
+            (
+                json!({ "exclusiveMinimum": 0 }),
+                "u64",
+                "std::num::NonZeroU64",
+            ),
+            (
+                json!({ "minimum": 1, "maximum": 255 }),
+                "u8",
+                "std::num::NonZeroU8",
+            ),
+            (
+                json!({ "minimum": 1, "maximum": 256 }),
+                "u16",
+                "std::num::NonZeroU16",
+            ),
+            (
+                json!({ "format": "uint32", "minimum": 1 }),
+                "u32",
+                "std::num::NonZeroU32",
+            ),
+            // Signed formats stay signed even if their bounds exclude zero.
+            (json!({ "format": "int32", "minimum": 1 }), "i32", "i32"),
+            (
+                json!({ "format": "int64", "exclusiveMinimum": 0 }),
+                "i64",
+                "i64",
+            ),
+            (json!({ "format": "int32", "minimum": 0 }), "i32", "i32"),
+            (json!({ "minimum": 0, "maximum": 255 }), "u8", "u8"),
+        ];
+
+        for (bounds, default, nonzero) in cases {
+            let mut schema = json!({ "type": "integer" });
+            schema
+                .as_object_mut()
+                .unwrap()
+                .extend(bounds.as_object().unwrap().clone());
+            let schema: SchemaObject = serde_json::from_value(schema).unwrap();
+
+            let mut type_space = TypeSpace::default();
+            let (ty, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(ty.type_name(&type_space), default, "{:?}", bounds);
+
+            let mut type_space =
+                TypeSpace::new(TypeSpaceSettings::default().with_nonzero_integers(true));
+            let (ty, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(
+                ty.type_name(&type_space).replace(' ', ""),
+                nonzero,
+                "{:?}",
+                bounds
+            );
+        }
+    }
+
+    #[test]
+    fn test_int_nonzero_optional() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "page": {
+                    "type": "object",
+                    "properties": {
+                        "size": { "type": "integer", "format": "uint32", "minimum": 1 }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_nonzero_integers(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/int_nonzero_optional");
+    }
+
+    #[test]
+    fn test_number_validation() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "percent": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 100
+                },
+                "byte": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 255
+                },
+                "ratio": {
+                    "type": "number",
+                    "exclusiveMinimum": 0,
+                    "maximum": 1
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/number_validation");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_number_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The bounds of a byte are exactly those of u8; there's nothing to check.
+        assert_output(&type_space, "convert/number_validation-checked");
+    }
+
+    #[test]
+    fn test_number_formats() {
+        let cases = [
+            (json!({}), "f64"),
+            (json!({ "format": "double" }), "f64"),
+            (json!({ "format": "float" }), "f32"),
+            (json!({ "format": "float", "maximum": 1.5 }), "f32"),
+        ];
+
+        for (extra, expected) in cases {
+            let mut schema = json!({ "type": "number" });
+            schema
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            let schema: SchemaObject = serde_json::from_value(schema).unwrap();
+
+            let mut type_space = TypeSpace::default();
+            let (ty, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(ty.type_name(&type_space), expected, "{:?}", extra);
+            assert!(type_space
+                .warnings()
+                .iter()
+                .all(|warning| warning.message.ends_with("without number validation")));
+        }
+
+        let schema: SchemaObject = serde_json::from_value(json!({
+            "type": "number",
+            "format": "float",
+            "maximum": 1e300
+        }))
+        .unwrap();
+        let mut type_space = TypeSpace::default();
+        let (ty, _) = type_space
+            .convert_schema_object(Name::Unknown, &schema)
+            .unwrap();
+        assert_eq!(ty.type_name(&type_space), "f32");
+        let keywords = type_space
+            .warnings()
+            .iter()
+            .map(|warning| warning.keyword.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keywords, ["format", "maximum"]);
+    }
+
+    #[test]
+    fn test_decimal() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "price": {
+                    "type": "object",
+                    "required": ["amount", "cents"],
+                    "properties": {
+                        "amount": { "type": "number", "format": "decimal" },
+                        "cents": { "type": "number", "multipleOf": 0.01 },
+                        "tax": { "type": "number", "format": "decimal" },
+                        "weight": { "type": "number" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/decimal");
+        // The fallback to f64 gives a warning.
+        let fallbacks = |type_space: &TypeSpace| {
+            type_space
+                .warnings()
+                .iter()
+                .filter(|warning| warning.message.starts_with("decimal numbers are f64s"))
+                .map(|warning| warning.keyword.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fallbacks(&type_space), ["format"]);
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_decimal_type(DecimalImpl::SerdeJsonNumber),
+        );
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/decimal-serde_json_number");
+        assert!(fallbacks(&type_space).is_empty());
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_decimal_type(DecimalImpl::RustDecimal),
+        );
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_eq!(
+            type_space.uses_rust_decimal(),
+            cfg!(feature = "rust_decimal")
+        );
+        // Without the feature, decimals fall back to floats.
+        if cfg!(feature = "rust_decimal") {
+            assert_output(&type_space, "convert/decimal-rust_decimal");
+        } else {
+            assert_output(&type_space, "convert/decimal");
+        }
+        if cfg!(feature = "rust_decimal") {
+            assert!(fallbacks(&type_space).is_empty());
+        } else {
+            assert_eq!(fallbacks(&type_space), ["format", "multipleOf"]);
+        }
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_decimal_type(DecimalImpl::RustDecimalFloat),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        if cfg!(feature = "rust_decimal") {
+            assert_output(&type_space, "convert/decimal-rust_decimal_float");
+        } else {
+            assert_output(&type_space, "convert/decimal");
+        }
+    }
+
+    #[test]
+    fn test_type_array() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "label": {
+                    "type": ["string", "integer"],
+                    "maxLength": 8,
+                    "minimum": 0
+                },
+                "maybe": {
+                    "type": ["string", "null"]
+                },
+                "maybe_label": {
+                    "type": ["null", "string", "integer"],
+                    "maxLength": 8
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/type_array");
+    }
+
+    #[test]
+    fn test_any_and_never() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "container": {
+                    "type": "object",
+                    "required": ["any", "anything", "nothing"],
+                    "properties": {
+                        "any": {},
+                        "anything": true,
+                        "nothing": false,
+                        "values": {
+                            "type": "object",
+                            "additionalProperties": {}
+                        },
+                        "empty": {
+                            "type": "object",
+                            "additionalProperties": false
+                        },
+                        "list": {
+                            "type": "array",
+                            "items": true
+                        },
+                        "none": {
+                            "type": "array",
+                            "items": false
+                        }
+                    }
+                },
+                "impossible": false
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(type_space.uses_serde_json());
+        assert_output(&type_space, "convert/any_and_never");
+
+        // An anonymous schema that matches nothing still needs a name.
+        let mut type_space = TypeSpace::default();
+        let ty = type_space.add_type(&Schema::Bool(false)).unwrap();
+        assert_eq!(ty.name(), "Never");
+    }
+
+    #[test]
+    fn test_fixed_size_array() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "triangle": {
+                    "type": "object",
+                    "required": ["vertices", "origin"],
+                    "properties": {
+                        "origin": {
+                            "type": "array",
+                            "items": { "type": "number" },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "vertices": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["x", "y"],
+                                "properties": {
+                                    "x": { "type": "number" },
+                                    "y": { "type": "number" }
+                                }
+                            },
+                            "minItems": 3,
+                            "maxItems": 3
+                        },
+                        "big": {
+                            "type": "array",
+                            "items": { "type": "boolean" },
+                            "minItems": 33,
+                            "maxItems": 33
+                        },
+                        "some": {
+                            "type": "array",
+                            "items": { "type": "boolean" },
+                            "minItems": 1,
+                            "maxItems": 2
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/fixed_size_array");
+    }
+
+    #[test]
+    fn test_tuple() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "entry": {
+                    "type": "array",
+                    "prefixItems": [
+                        { "type": "string" },
+                        { "type": "integer" },
+                        { "type": "boolean" }
+                    ],
+                    "items": false
+                },
+                "pair": {
+                    "type": "array",
+                    "items": [
+                        { "$ref": "#/definitions/entry" },
+                        { "$ref": "#/definitions/entry" }
+                    ],
+                    "additionalItems": false
+                },
+                "record": {
+                    "type": "object",
+                    "required": ["position"],
+                    "properties": {
+                        "position": {
+                            "type": "array",
+                            "prefixItems": [
+                                { "type": "number" },
+                                { "type": "number" }
+                            ],
+                            "maxItems": 2
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/tuple");
+    }
+
+    #[test]
+    fn test_tuple_open() {
+        for schema in [
+            json!({
+                "type": "array",
+                "prefixItems": [{ "type": "string" }, { "type": "integer" }]
+            }),
+            json!({
+                "type": "array",
+                "prefixItems": [{ "type": "string" }, { "type": "integer" }],
+                "items": { "type": "string" }
+            }),
+            json!({
+                "type": "array",
+                "items": [{ "type": "string" }, { "type": "integer" }]
+            }),
+            json!({
+                "type": "array",
+                "items": [{ "type": "string" }, { "type": "integer" }],
+                "additionalItems": false,
+                "minItems": 1
+            }),
+        ] {
+            let schema: Schema = serde_json::from_value(schema).unwrap();
+            let mut type_space = TypeSpace::default();
+            assert!(matches!(
+                type_space.add_type(&schema),
+                Err(Error::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_array_validation() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "batch": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "maxItems": 10
+                },
+                "order": {
+                    "type": "object",
+                    "required": ["lines"],
+                    "properties": {
+                        "lines": {
+                            "type": "array",
+                            "items": { "type": "integer" },
+                            "minItems": 1
+                        },
+                        "notes": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "minItems": 0
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/array_validation");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_array_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/array_validation-checked");
+    }
+
+    #[test]
+    fn test_all_of_merge() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string", "maxLength": 20 },
+                        "tag": { "type": "string" }
+                    }
+                },
+                "Dog": {
+                    "description": "A good dog.",
+                    "allOf": [
+                        { "$ref": "#/definitions/Pet" },
+                        {
+                            "type": "object",
+                            "required": ["breed", "tag"],
+                            "properties": {
+                                "breed": { "type": "string" },
+                                "name": { "minLength": 1 }
+                            }
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/all_of_merge");
+    }
+
+    #[test]
+    fn test_all_of_conflict() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Id": { "type": "string" },
+                "NumericId": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Id" },
+                        { "type": "integer" }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        match type_space
+            .add_ref_types(schema.definitions)
+            .as_ref()
+            .map_err(Error::reason)
+        {
+            Err(err @ Error::InvalidSchema(_, _)) => assert_eq!(
+                err.to_string(),
+                "invalid schema for NumericId: type string conflicts with type integer"
+            ),
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_if_then_else() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Payment": {
+                    "type": "object",
+                    "required": ["method"],
+                    "properties": {
+                        "method": { "type": "string", "enum": ["card", "cash"] },
+                        "card_number": { "type": "string" },
+                        "change": { "type": "boolean" }
+                    },
+                    "if": { "properties": { "method": { "const": "card" } } },
+                    "then": { "required": ["card_number"] },
+                    "else": { "required": ["change"] }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/if_then_else");
+    }
+
+    #[test]
+    fn test_if_unsupported() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "properties": {
+                        "a": { "type": "string" },
+                        "b": { "type": "string" }
+                    },
+                    "if": { "required": ["a"] },
+                    "then": { "required": ["b"] }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        match type_space
+            .add_ref_types(schema.definitions)
+            .as_ref()
+            .map_err(Error::reason)
+        {
+            Err(err @ Error::Unsupported(_)) => assert_eq!(
+                err.to_string(),
+                "unsupported schema: the if schema of Thing doesn't require a single \
+                 property to have a constant value"
+            ),
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_nullable() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Color": {
+                    "type": "string",
+                    "enum": ["red", "green", null],
+                    "nullable": true
+                },
+                "Point": {
+                    "type": "object",
+                    "required": ["x"],
+                    "properties": { "x": { "type": "number" } }
+                },
+                "Thing": {
+                    "type": "object",
+                    "required": ["name", "color", "point"],
+                    "properties": {
+                        "name": { "type": "string", "nullable": true },
+                        "count": { "type": "integer", "nullable": true },
+                        "color": { "$ref": "#/definitions/Color" },
+                        "point": { "$ref": "#/definitions/Point", "nullable": true }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/nullable");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_ignore_nullable(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/nullable-ignored");
+    }
+
+    #[test]
+    fn test_conversion() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Order": {
+                    "type": "object",
+                    "required": ["total", "lines"],
+                    "properties": {
+                        "total": {
+                            "description": "The sum of the lines.",
+                            "type": "string",
+                            "format": "money"
+                        },
+                        "lines": {
+                            "type": "array",
+                            "items": { "type": "string", "format": "money", "pattern": "^[0-9.]+$" }
+                        },
+                        "note": { "type": "string" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let money = serde_json::from_value::<SchemaObject>(json!({
+            "type": "string",
+            "format": "money"
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_conversion(money, "crate::Money", ["PartialEq"]);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/conversion");
+    }
+
+    #[test]
+    fn test_rust_type() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Job": {
+                    "type": "object",
+                    "required": ["timeout", "started", "names", "trusted"],
+                    "properties": {
+                        "timeout": {
+                            "type": "object",
+                            "x-rust-type": {
+                                "crate": "std",
+                                "version": "*",
+                                "path": "std::time::Duration"
+                            }
+                        },
+                        "started": {
+                            "type": "string",
+                            "x-rust-type": {
+                                "crate": "chrono",
+                                "version": "0.4.38",
+                                "path": "chrono::NaiveDate"
+                            }
+                        },
+                        "names": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "x-rust-type": {
+                                "crate": "std",
+                                "version": "*",
+                                "path": "std::collections::BTreeSet",
+                                "parameters": [{ "$ref": "#/definitions/Name" }]
+                            }
+                        },
+                        "trusted": {
+                            "type": "string",
+                            "x-rust-type": {
+                                "crate": "chrono",
+                                "version": "0.4",
+                                "path": "evil::Type"
+                            }
+                        }
+                    }
+                },
+                "Name": { "type": "string", "maxLength": 8 }
+            }
+        }))
+        .unwrap();
+
+        let generate = |settings: &TypeSpaceSettings, name: &str| {
+            let mut type_space = TypeSpace::new(settings);
+            type_space
+                .add_ref_types(schema.definitions.clone())
+                .unwrap();
+            assert_output(&type_space, name);
+        };
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_crate("chrono", "0.4");
+        generate(&settings, "convert/rust_type");
+
+        // Crates must be permitted, in a compatible version.
+        settings.with_crate("chrono", "0.3");
+        generate(&settings, "convert/rust_type-unpermitted");
+        generate(
+            &TypeSpaceSettings::default(),
+            "convert/rust_type-unpermitted",
+        );
+    }
+
+    #[test]
+    fn test_untyped_enum() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "Letter": { "enum": ["a", "b"] },
+                "Size": { "enum": [1, 2] },
+                "Choice": { "enum": ["yes", null] },
+                "Mixed": { "enum": ["a", 1] }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The values convert as those of schemas with their distinct types.
+        if cfg!(feature = "serde_repr") {
+            assert_output(&type_space, "convert/untyped_enum-serde_repr");
+        } else {
+            assert_output(&type_space, "convert/untyped_enum");
+        }
+
+        // Enums of numbers aren't supported with or without a type.
+        let schema = serde_json::from_value::<SchemaObject>(json!({ "enum": [0.5, 1] })).unwrap();
+        let mut type_space = TypeSpace::default();
+        assert!(matches!(
+            type_space.convert_schema_object(Name::Unknown, &schema),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_integer_enum() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Level": {
+                    "type": "integer",
+                    "enum": [-1, 0, 2]
+                },
+                "Priority": {
+                    "type": "integer",
+                    "enum": [1, 10],
+                    "x-enum-varnames": ["low", "high"]
+                },
+                "Holder": {
+                    "type": "object",
+                    "required": ["size"],
+                    "properties": {
+                        "size": { "type": "integer", "enum": [1, 2.5] }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Values that aren't all integers are just integers.
+        if cfg!(feature = "serde_repr") {
+            assert_output(&type_space, "convert/integer_enum-serde_repr");
+        } else {
+            assert_output(&type_space, "convert/integer_enum");
+        }
+    }
+
+    #[test]
+    fn test_enum_names() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Status": {
+                    "type": "string",
+                    "enum": ["in-progress", "done"],
+                    "x-enumNames": ["Working", "finished"]
+                },
+                "Code": {
+                    "type": ["integer", "null"],
+                    "enum": [200, null, 404],
+                    "x-enum-varnames": ["ok", "none", "not_found"]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        if cfg!(feature = "serde_repr") {
+            assert_output(&type_space, "convert/enum_names-serde_repr");
+        } else {
+            assert_output(&type_space, "convert/enum_names");
+        }
+
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Status": {
+                    "type": "string",
+                    "enum": ["in-progress", "done"],
+                    "x-enumNames": ["Working"]
+                }
+            }
+        }))
+        .unwrap();
+        let error = TypeSpace::default()
+            .add_ref_types(schema.definitions)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "definitions.Status.x-enumNames: invalid schema for Status: \
+             x-enumNames has 1 names for 2 values"
+        );
+    }
+
+    #[test]
+    fn test_const_unit_structs() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Doc": {
+                    "type": "object",
+                    "required": ["version", "flag"],
+                    "properties": {
+                        "version": { "const": "v1" },
+                        "flag": { "const": true },
+                        "count": { "const": 3 }
+                    }
+                },
+                "Shape": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["kind", "radius"],
+                            "properties": {
+                                "kind": { "const": "circle" },
+                                "radius": { "type": "number" }
+                            }
+                        },
+                        {
+                            "type": "object",
+                            "required": ["kind", "side"],
+                            "properties": {
+                                "kind": { "const": "square" },
+                                "side": { "type": "number" }
+                            }
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/const_unit_structs");
+    }
+
+    #[test]
+    fn test_unknown_values() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Color": { "type": "string", "enum": ["red", "green"] },
+                "Size": { "type": "string", "enum": ["small", "other"] }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default()
+                .with_unknown_values(UnknownValues::Unknown)
+                .with_patch(
+                    "Size",
+                    TypePatch::default().with_unknown_values(UnknownValues::Other),
+                ),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/unknown_values");
+    }
+
+    #[test]
+    fn test_encoded_strings() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "Blob": {
+                    "type": "object",
+                    "required": ["data"],
+                    "properties": {
+                        "data": { "type": "string", "contentEncoding": "base64" },
+                        "digest": { "type": "string", "contentEncoding": "base16" },
+                        "icon": { "type": "string", "format": "byte" },
+                        "text": { "type": "string", "contentEncoding": "quoted-printable" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "convert/encoded_strings");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_raw_encoded_strings(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/encoded_strings-raw");
+    }
+
+    #[test]
+    fn test_json_strings() {
+        let schema: RootSchema = serde_json::from_value(json!({
+            "definitions": {
+                "Request": {
+                    "type": "object",
+                    "properties": {
+                        "filter": {
+                            "type": "string",
+                            "contentMediaType": "application/json",
+                            "contentSchema": {
+                                "type": "array",
+                                "items": { "type": "integer", "format": "int32" }
+                            },
+                            "default": "[1,2]"
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        // The default is built from the document in the string.
+        assert_output(&type_space, "convert/json_strings");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_raw_json_strings(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "convert/json_strings-raw");
+    }
 }
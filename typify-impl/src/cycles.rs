@@ -0,0 +1,211 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeSet;
+
+use crate::{
+    type_entry::{
+        TypeEntry, TypeEntryAlias, TypeEntryEnum, TypeEntryNewtype, TypeEntryStruct, VariantDetails,
+    },
+    TypeId, TypeSpace,
+};
+
+impl TypeSpace {
+    /// Named types that contain themselves, directly or through other types,
+    /// would be infinitely sized. We search the types depth-first, in order of
+    /// their names so that the result is deterministic, and box each member
+    /// that closes a cycle. `Vec`s and maps already provide the indirection
+    /// so only members that hold their types inline, including in `Option`s,
+    /// tuples, and fixed-size arrays, form cycles.
+    pub(crate) fn break_cycles(&mut self) {
+        let mut named = self
+            .id_to_entry
+            .iter()
+            .filter_map(|(type_id, ty)| Some((ty.name()?.clone(), type_id.clone())))
+            .collect::<Vec<_>>();
+        named.sort();
+
+        let mut visited = BTreeSet::new();
+        for (_, type_id) in named {
+            self.visit(type_id, &mut visited, &mut Vec::new());
+        }
+    }
+
+    fn visit(&mut self, type_id: TypeId, visited: &mut BTreeSet<TypeId>, stack: &mut Vec<TypeId>) {
+        if !visited.insert(type_id.clone()) {
+            return;
+        }
+        stack.push(type_id.clone());
+
+        for (index, member_id) in self.members(&type_id).into_iter().enumerate() {
+            let mut member_id = member_id;
+            for target in self.contained(&member_id) {
+                if stack.contains(&target) {
+                    member_id = self.box_target(&member_id, &target);
+                    self.set_member(&type_id, index, member_id.clone());
+                } else {
+                    self.visit(target, visited, stack);
+                }
+            }
+        }
+
+        stack.pop();
+    }
+
+    /// The types of the members of a named type, in order.
+    pub(crate) fn members(&self, type_id: &TypeId) -> Vec<TypeId> {
+        self.id_to_entry
+            .get(type_id)
+            .map_or_else(Vec::new, TypeEntry::members)
+    }
+
+    /// Replace the type of a member of a named type, indexed as by
+    /// `members`.
+    pub(crate) fn set_member(&mut self, type_id: &TypeId, index: usize, member_id: TypeId) {
+        let mut ids = match self.id_to_entry.get_mut(type_id) {
+            Some(TypeEntry::Struct(TypeEntryStruct { properties, .. })) => properties
+                .iter_mut()
+                .map(|prop| &mut prop.type_id)
+                .collect::<Vec<_>>(),
+            Some(TypeEntry::Enum(TypeEntryEnum { variants, .. })) => variants
+                .iter_mut()
+                .flat_map(|variant| match &mut variant.details {
+                    VariantDetails::Simple
+                    | VariantDetails::Integer(_)
+                    | VariantDetails::Unknown => Vec::new(),
+                    VariantDetails::Other(type_id) => vec![type_id],
+                    VariantDetails::Tuple(items) => items.iter_mut().collect(),
+                    VariantDetails::Struct(properties) => properties
+                        .iter_mut()
+                        .map(|prop| &mut prop.type_id)
+                        .collect(),
+                })
+                .collect(),
+            Some(
+                TypeEntry::Newtype(TypeEntryNewtype { type_id, .. })
+                | TypeEntry::Alias(TypeEntryAlias { type_id, .. }),
+            ) => vec![type_id],
+            _ => Vec::new(),
+        };
+        *ids[index] = member_id;
+    }
+
+    /// The named types that a value of the given type holds inline.
+    fn contained(&self, type_id: &TypeId) -> Vec<TypeId> {
+        match self.id_to_entry.get(type_id) {
+            // An alias is a named type here so the cycle is broken within
+            // it, which a type alias can't be part of.
+            Some(TypeEntry::Enum(_))
+            | Some(TypeEntry::Struct(_))
+            | Some(TypeEntry::Newtype(_))
+            | Some(TypeEntry::Alias(_)) => vec![type_id.clone()],
+            Some(TypeEntry::Option(inner)) | Some(TypeEntry::FixedArray(inner, _)) => {
+                self.contained(inner)
+            }
+            Some(TypeEntry::Tuple(items)) => {
+                let mut contained = Vec::new();
+                for item in items {
+                    for target in self.contained(item) {
+                        if !contained.contains(&target) {
+                            contained.push(target);
+                        }
+                    }
+                }
+                contained
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The given type with the named type `target` within it boxed.
+    fn box_target(&mut self, type_id: &TypeId, target: &TypeId) -> TypeId {
+        let ty = match self.id_to_entry.get(type_id).cloned() {
+            _ if type_id == target => TypeEntry::Box(target.clone()),
+            Some(TypeEntry::Option(inner)) => TypeEntry::Option(self.box_target(&inner, target)),
+            Some(TypeEntry::FixedArray(inner, len)) => {
+                TypeEntry::FixedArray(self.box_target(&inner, target), len)
+            }
+            Some(TypeEntry::Tuple(items)) => TypeEntry::Tuple(
+                items
+                    .iter()
+                    .map(|item| {
+                        if self.contained(item).contains(target) {
+                            self.box_target(item, target)
+                        } else {
+                            item.clone()
+                        }
+                    })
+                    .collect(),
+            ),
+            _ => return type_id.clone(),
+        };
+        self.assign_type(ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{test_util::assert_output, TypeSpace};
+
+    fn generate(schema: serde_json::Value) -> TypeSpace {
+        let schema = serde_json::from_value::<RootSchema>(schema).unwrap();
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        type_space
+    }
+
+    #[test]
+    fn test_self_recursion() {
+        let type_space = generate(json!({
+            "definitions": {
+                "Node": {
+                    "type": "object",
+                    "required": ["children", "first"],
+                    "properties": {
+                        "children": { "type": "array", "items": { "$ref": "#/definitions/Node" } },
+                        "parent": { "$ref": "#/definitions/Node" },
+                        "first": { "$ref": "#/definitions/Node" },
+                        "pair": {
+                            "type": "array",
+                            "items": [{ "$ref": "#/definitions/Node" }, { "type": "string" }],
+                            "maxItems": 2
+                        }
+                    }
+                }
+            }
+        }));
+        assert_output(&type_space, "cycles/self_recursion");
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        let schema = json!({
+            "definitions": {
+                "B": {
+                    "type": "object",
+                    "properties": { "a": { "$ref": "#/definitions/A" } }
+                },
+                "A": {
+                    "type": "object",
+                    "properties": {
+                        "b": { "$ref": "#/definitions/B" },
+                        "c": { "$ref": "#/definitions/C" }
+                    }
+                },
+                "C": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "$ref": "#/definitions/A" }
+                    ]
+                }
+            }
+        });
+
+        // The same edges are boxed every time.
+        for _ in 0..3 {
+            assert_output(&generate(schema.clone()), "cycles/mutual_recursion");
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2021 Oxide Computer Company
+
+use schemars::schema::{Metadata, SchemaObject, SingleOrVec};
+
+use crate::{
+    util::{metadata_title, metadata_type_description, sanitize},
+    TypeId, TypeSpace,
+};
+use convert_case::Case;
+
+/// A type converted from an inline subschema, which identical subschemas
+/// share.
+#[derive(Debug, Clone)]
+pub(crate) struct InlineType {
+    pub type_id: TypeId,
+    /// Whether the type's name came from a title.
+    pub titled: bool,
+    /// Whether the conversion gave the subschema's metadata, rather than
+    /// none, for its users.
+    pub own_metadata: bool,
+}
+
+/// The key by which inline subschemas are identical: their JSON without the
+/// metadata and comments that don't alter the type, and with the forms of
+/// keywords that mean the same written one way, e.g. `"type": "string"` for
+/// `"type": ["string"]`. As the properties of schemas are ordered by name,
+/// it doesn't matter how the document orders them.
+pub(crate) fn inline_key(schema: &SchemaObject) -> String {
+    let mut schema = schema.clone();
+    if let Some(metadata) = schema.metadata.as_mut() {
+        metadata.title = None;
+        metadata.description = None;
+        metadata.examples = Vec::new();
+    }
+    if let Some(SingleOrVec::Vec(types)) = &schema.instance_type {
+        if let [instance_type] = types.as_slice() {
+            schema.instance_type = Some(SingleOrVec::Single(Box::new(*instance_type)));
+        }
+    }
+    schema.extensions.remove("$comment");
+    serde_json::to_string(&schema).unwrap()
+}
+
+impl TypeSpace {
+    /// Name the type of an inline subschema after the title of another
+    /// occurrence of it if it's named otherwise, e.g. after the property of
+    /// its first occurrence, and no other type has that name.
+    pub(crate) fn prefer_title(&mut self, key: &str, metadata: &Option<Box<Metadata>>) {
+        let title = match metadata_title(metadata) {
+            Some(title) => self.settings.type_name(&sanitize(&title, Case::Pascal)),
+            None => return,
+        };
+        let inline = match self.inline_types.get_mut(key) {
+            Some(inline) if !inline.titled => inline,
+            _ => return,
+        };
+        if self.name_to_id.contains_key(&title) {
+            return;
+        }
+
+        inline.titled = true;
+        let type_id = inline.type_id.clone();
+        if let Some((name, _)) = self
+            .id_to_entry
+            .get_mut(&type_id)
+            .and_then(|type_entry| type_entry.name_mut())
+        {
+            self.name_to_id.remove(name);
+            *name = title.clone();
+            self.name_to_id.insert(title, type_id);
+        }
+    }
+
+    /// Document the type of an inline subschema with the description of
+    /// another occurrence of it if the first had none, so that sharing the
+    /// type doesn't lose the documentation.
+    pub(crate) fn prefer_description(&mut self, key: &str, metadata: &Option<Box<Metadata>>) {
+        let description = match metadata_type_description(metadata) {
+            Some(description) => description,
+            None => return,
+        };
+        let type_id = match self.inline_types.get(key) {
+            Some(inline) => inline.type_id.clone(),
+            None => return,
+        };
+        if let Some(type_description @ None) = self
+            .id_to_entry
+            .get_mut(&type_id)
+            .and_then(|type_entry| type_entry.description_mut())
+        {
+            *type_description = Some(description);
+        }
+    }
+}
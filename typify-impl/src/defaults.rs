@@ -0,0 +1,1223 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use convert_case::{Case, Casing};
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use schemars::schema::{Schema, SchemaObject};
+use serde_json::Value;
+
+use crate::{
+    type_entry::{
+        EnumTagType, NewtypeConstraints, SerdeNaming, SerdeRules, StructProperty, TypeEntry,
+        TypeEntryAlias, TypeEntryEnum, TypeEntryNewtype, TypeEntryStruct, Variant, VariantDetails,
+    },
+    util::unraw,
+    Error, PathSegment, Result, TypeId, TypeSpace,
+};
+
+/// An expression for a default value, `None` if we can't express values of
+/// the type, or an error saying why the value isn't valid for the type.
+type ValueExpr = std::result::Result<Option<TokenStream>, String>;
+
+impl TypeSpace {
+    /// Make sure that the defaults the schemas give are valid for their
+    /// types: each type's own default and then the defaults of properties,
+    /// which may be those of their types, so that an invalid default is
+    /// reported where the schema gives it. That of an inline type is
+    /// reported at the property of the type that uses it.
+    pub(crate) fn check_defaults(&self) -> Result<()> {
+        let definitions = self
+            .ref_to_id
+            .iter()
+            .map(|(ref_name, type_id)| (type_id, ref_name))
+            .collect::<BTreeMap<_, _>>();
+        let within = |type_id: &TypeId, error: Error, segments: Vec<PathSegment>| match definitions
+            .get(type_id)
+        {
+            Some(ref_name) => {
+                let mut path = vec!["definitions".into(), PathSegment::from(*ref_name)];
+                path.extend(segments);
+                path.into_iter()
+                    .rev()
+                    .fold(error, |error, segment| error.within([segment]))
+            }
+            None => error,
+        };
+
+        // The properties of structs and of enum variants, by the types they
+        // belong to, along with where they are in the schemas of those types.
+        let properties = self
+            .id_to_entry
+            .iter()
+            .flat_map(|(type_id, type_entry)| {
+                let properties = match type_entry {
+                    TypeEntry::Struct(TypeEntryStruct { properties, .. }) => properties
+                        .iter()
+                        .map(|prop| {
+                            let path = vec!["properties".into(), (&prop.serde_name()).into()];
+                            (prop, path)
+                        })
+                        .collect(),
+                    TypeEntry::Enum(TypeEntryEnum { variants, .. }) => variants
+                        .iter()
+                        .flat_map(|variant| match &variant.details {
+                            VariantDetails::Struct(properties) => properties.iter().collect(),
+                            _ => Vec::new(),
+                        })
+                        .map(|prop| {
+                            let ref_name = definitions.get(type_id).copied();
+                            (
+                                prop,
+                                self.variant_property_path(ref_name, &prop.serde_name()),
+                            )
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                properties
+                    .into_iter()
+                    .map(move |(prop, path)| (type_id, prop, path))
+            })
+            .collect::<Vec<_>>();
+
+        for (type_id, type_entry) in &self.id_to_entry {
+            if let Some((name, default)) = type_entry.name().zip(type_entry.default()) {
+                self.check_default(name, type_entry, default).map_err(|e| {
+                    let user = properties
+                        .iter()
+                        .filter(|_| !definitions.contains_key(type_id))
+                        .find(|(_, prop, _)| self.unwrap_option(&prop.type_id) == type_id);
+                    match user {
+                        Some((user_id, _, path)) => within(user_id, e, path.clone()),
+                        None => within(type_id, e, Vec::new()),
+                    }
+                })?;
+            }
+        }
+
+        for (type_id, prop, path) in &properties {
+            if let Some(default) = &prop.default {
+                let prop_type = self.id_to_entry.get(&prop.type_id).unwrap();
+                self.check_default(&prop.name, prop_type, default)
+                    .map_err(|e| within(type_id, e, path.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The type of values of an optional (or boxed) type, or the type itself.
+    fn unwrap_option<'a>(&'a self, type_id: &'a TypeId) -> &'a TypeId {
+        match self.id_to_entry.get(type_id) {
+            Some(TypeEntry::Option(inner) | TypeEntry::Box(inner)) => self.unwrap_option(inner),
+            _ => type_id,
+        }
+    }
+
+    /// The path within the definition of an enum of a property of one of
+    /// its variants: that of the property in the first `oneOf` or `anyOf`
+    /// subschema with it, if any. Variants of referenced subschemas are
+    /// located by the definition alone.
+    fn variant_property_path(&self, ref_name: Option<&String>, name: &str) -> Vec<PathSegment> {
+        let subschemas = match ref_name.and_then(|ref_name| self.definitions.get(ref_name)) {
+            Some(Schema::Object(SchemaObject {
+                subschemas: Some(subschemas),
+                ..
+            })) => subschemas,
+            _ => return Vec::new(),
+        };
+        [("oneOf", &subschemas.one_of), ("anyOf", &subschemas.any_of)]
+            .iter()
+            .flat_map(|(keyword, schemas)| {
+                schemas
+                    .iter()
+                    .flatten()
+                    .enumerate()
+                    .map(move |(index, schema)| (*keyword, index, schema))
+            })
+            .find_map(|(keyword, index, schema)| match schema {
+                Schema::Object(SchemaObject {
+                    object: Some(object),
+                    ..
+                }) if object.properties.contains_key(name) => Some(vec![
+                    keyword.into(),
+                    index.into(),
+                    "properties".into(),
+                    name.into(),
+                ]),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    fn check_default(&self, name: &str, type_entry: &TypeEntry, default: &str) -> Result<()> {
+        let value = serde_json::from_str::<Value>(default).unwrap();
+        self.entry_value(type_entry, &value)
+            .map(|_| ())
+            .map_err(|reason| Error::InvalidDefault(name.to_string(), default.to_string(), reason))
+    }
+
+    /// The `impl Default` for a named type. Types whose schemas have a
+    /// default use it. Structs with defaults for some properties use them,
+    /// provided that the other properties are optional or of types that
+    /// implement `Default`.
+    pub(crate) fn default_impl(&self, type_entry: &TypeEntry) -> Option<TokenStream> {
+        let name = format_ident!("{}", type_entry.name()?);
+        let value = match (type_entry, type_entry.default()) {
+            (_, Some(default)) => {
+                let value = serde_json::from_str::<Value>(default).unwrap();
+                self.entry_value(type_entry, &value).ok()??
+            }
+            (TypeEntry::Struct(TypeEntryStruct { properties, .. }), None) => {
+                self.properties_default(properties, &self.default_types())?
+            }
+            _ => return None,
+        };
+
+        Some(quote! {
+            impl Default for #name {
+                fn default() -> Self {
+                    #value
+                }
+            }
+        })
+    }
+
+    /// The expression for the default of a property, if it has a default we
+    /// can express.
+    pub(crate) fn property_default(&self, prop: &StructProperty) -> Option<TokenStream> {
+        let value = serde_json::from_str::<Value>(prop.default.as_ref()?).unwrap();
+        self.value(&prop.type_id, &value).ok()?
+    }
+
+    /// The `defaults` module of functions for `#[serde(default = "...")]`
+    /// and `#[serde(skip_serializing_if = "...")]` on properties with
+    /// defaults.
+    pub(crate) fn defaults_module(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let fns = self
+            .id_to_entry
+            .iter()
+            .filter(|(type_id, _)| reachable.contains(type_id))
+            .flat_map(|(_, type_entry)| match type_entry {
+                TypeEntry::Struct(TypeEntryStruct {
+                    name, properties, ..
+                }) => properties.iter().map(|prop| (name.clone(), prop)).collect(),
+                TypeEntry::Enum(TypeEntryEnum { name, variants, .. }) => variants
+                    .iter()
+                    .flat_map(|variant| match &variant.details {
+                        VariantDetails::Struct(properties) => properties
+                            .iter()
+                            .map(|prop| (format!("{}{}", name, variant.name), prop))
+                            .collect(),
+                        _ => Vec::new(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .filter(|(_, prop)| prop.serde_rules == SerdeRules::Default)
+            .map(|(owner, prop)| {
+                let prop_type = self
+                    .id_to_entry
+                    .get(&prop.type_id)
+                    .unwrap()
+                    .type_ident(self, false);
+                let default_fn = self.default_fn(&owner, prop).map(|default_fn| {
+                    let default = prop.default.as_ref().unwrap();
+                    let value = self.property_default(prop).unwrap_or_else(|| {
+                        quote! { serde_json::from_str::<#prop_type>(#default).unwrap() }
+                    });
+                    let default_fn = format_ident!("{}", default_fn);
+                    quote! {
+                        pub(super) fn #default_fn() -> #prop_type {
+                            #value
+                        }
+                    }
+                });
+                let is_default_fn = self.is_default_fn(&owner, prop).map(|is_default_fn| {
+                    let value = match self.default_fn(&owner, prop) {
+                        Some(default_fn) => {
+                            let default_fn = format_ident!("{}", default_fn);
+                            quote! { #default_fn() }
+                        }
+                        None => quote! { <#prop_type as Default>::default() },
+                    };
+                    let is_default_fn = format_ident!("{}", is_default_fn);
+                    quote! {
+                        pub(super) fn #is_default_fn(value: &#prop_type) -> bool {
+                            *value == #value
+                        }
+                    }
+                });
+                quote! {
+                    #default_fn
+                    #is_default_fn
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if fns.iter().all(TokenStream::is_empty) {
+            quote! {}
+        } else {
+            quote! {
+                /// The default values of properties.
+                #[allow(deprecated)]
+                mod defaults {
+                    #[allow(unused_imports)]
+                    use super::*;
+
+                    #(#fns)*
+                }
+            }
+        }
+    }
+
+    /// The name of the function in the `defaults` module that gives the
+    /// default value of a property of the owner (a struct or a struct
+    /// variant), or `None` if `#[serde(default)]` gives it because it's the
+    /// type's `Default::default()`.
+    pub(crate) fn default_fn(&self, owner: &str, prop: &StructProperty) -> Option<String> {
+        let value = serde_json::from_str::<Value>(prop.default.as_ref()?).unwrap();
+        if self.is_default_value(&prop.type_id, &value) {
+            None
+        } else {
+            Some(format!(
+                "{}_{}",
+                owner.to_case(Case::Snake),
+                unraw(&prop.name)
+            ))
+        }
+    }
+
+    /// The name of the function in the `defaults` module that says whether a
+    /// property has its default value, if we skip serializing those and the
+    /// property's type implements `PartialEq`.
+    pub(crate) fn is_default_fn(&self, owner: &str, prop: &StructProperty) -> Option<String> {
+        (self.settings.skip_serializing_defaults
+            && prop.serde_rules == SerdeRules::Default
+            && self.type_id_traits(&prop.type_id).is_partial_eq())
+        .then(|| {
+            format!(
+                "{}_{}_is_default",
+                owner.to_case(Case::Snake),
+                unraw(&prop.name)
+            )
+        })
+    }
+
+    /// Whether the value is the type's `Default::default()`.
+    fn is_default_value(&self, type_id: &TypeId, value: &Value) -> bool {
+        match self.id_to_entry.get(type_id) {
+            Some(
+                TypeEntry::Box(id)
+                | TypeEntry::Reference(id)
+                | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }),
+            ) => self.is_default_value(id, value),
+            Some(TypeEntry::Option(_) | TypeEntry::Unit) => value.is_null(),
+            Some(TypeEntry::Integral(name)) if name == "bool" => value == &Value::Bool(false),
+            Some(TypeEntry::Integral(name)) => {
+                !name.starts_with("std::num::NonZero")
+                    && (value.as_i64() == Some(0) || value.as_u64() == Some(0))
+            }
+            Some(TypeEntry::Float(_)) => value.as_f64() == Some(0.0),
+            Some(TypeEntry::String) => value.as_str() == Some(""),
+            Some(TypeEntry::Array(_) | TypeEntry::Set(_)) => {
+                value.as_array().is_some_and(Vec::is_empty)
+            }
+            Some(TypeEntry::Map(_, _)) => value.as_object().is_some_and(|object| object.is_empty()),
+            // A named type whose `Default` implementation gives the value.
+            Some(
+                type_entry @ (TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)),
+            ) => type_entry.default().is_some_and(|default| {
+                serde_json::from_str::<Value>(default).unwrap() == *value
+                    && self.default_impl(type_entry).is_some()
+            }),
+            _ => false,
+        }
+    }
+
+    /// The struct expression for structs with defaults for some properties,
+    /// given the named types that implement `Default`.
+    fn properties_default(
+        &self,
+        properties: &[StructProperty],
+        default_types: &BTreeSet<TypeId>,
+    ) -> Option<TokenStream> {
+        if properties.iter().all(|prop| prop.default.is_none()) {
+            return None;
+        }
+        let fields = properties
+            .iter()
+            .map(|prop| {
+                let name = format_ident!("{}", prop.name);
+                let value = match self.property_default(prop) {
+                    Some(value) => value,
+                    None if self.is_default(&prop.type_id, default_types) => {
+                        quote! { Default::default() }
+                    }
+                    None => return None,
+                };
+                Some(quote! { #name: #value })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(quote! { Self { #(#fields),* } })
+    }
+
+    /// The named types that implement `Default`. Named types without their
+    /// own defaults may contain one another so we start with none of them and
+    /// add types whose members all implement `Default` until nothing changes.
+    fn default_types(&self) -> BTreeSet<TypeId> {
+        let mut default_types = self
+            .id_to_entry
+            .iter()
+            .filter(|(_, type_entry)| {
+                type_entry.name().is_some()
+                    && type_entry.default().is_some()
+                    && self.default_impl(type_entry).is_some()
+            })
+            .map(|(type_id, _)| type_id.clone())
+            .collect::<BTreeSet<_>>();
+
+        loop {
+            let added = self
+                .id_to_entry
+                .iter()
+                .filter(|(type_id, type_entry)| {
+                    !default_types.contains(type_id)
+                        && matches!(type_entry, TypeEntry::Struct(TypeEntryStruct { properties, .. })
+                            if self.properties_default(properties, &default_types).is_some())
+                })
+                .map(|(type_id, _)| type_id.clone())
+                .collect::<Vec<_>>();
+            if added.is_empty() {
+                break;
+            }
+            default_types.extend(added);
+        }
+
+        default_types
+    }
+
+    /// Whether the type implements `Default`, given the named types that do.
+    fn is_default(&self, type_id: &TypeId, default_types: &BTreeSet<TypeId>) -> bool {
+        match self.id_to_entry.get(type_id) {
+            Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)) => {
+                default_types.contains(type_id)
+            }
+            Some(
+                TypeEntry::Option(_)
+                | TypeEntry::Array(_)
+                | TypeEntry::Set(_)
+                | TypeEntry::Map(_, _)
+                | TypeEntry::Unit
+                | TypeEntry::String
+                | TypeEntry::Float(_),
+            ) => true,
+            Some(TypeEntry::Integral(name)) => !name.starts_with("std::num::NonZero"),
+            Some(
+                TypeEntry::Box(id)
+                | TypeEntry::Reference(id)
+                | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }),
+            ) => self.is_default(id, default_types),
+            Some(TypeEntry::FixedArray(id, len)) => {
+                *len <= 32 && self.is_default(id, default_types)
+            }
+            Some(TypeEntry::Tuple(ids)) => {
+                ids.len() <= 12 && ids.iter().all(|id| self.is_default(id, default_types))
+            }
+            Some(TypeEntry::BuiltIn(name)) => {
+                matches!(
+                    name.as_str(),
+                    "serde_json::Value" | "uuid::Uuid" | "rust_decimal::Decimal"
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// An expression for the value of the given type.
+    fn value(&self, type_id: &TypeId, value: &Value) -> ValueExpr {
+        self.entry_value(self.id_to_entry.get(type_id).unwrap(), value)
+    }
+
+    fn entry_value(&self, type_entry: &TypeEntry, value: &Value) -> ValueExpr {
+        match type_entry {
+            TypeEntry::Enum(type_enum) => self.enum_value(type_enum, value),
+            TypeEntry::Struct(TypeEntryStruct {
+                name,
+                properties,
+                deny_unknown_fields,
+                ..
+            }) => {
+                let name = format_ident!("{}", name);
+                let object = value.as_object().ok_or("not an object")?;
+                Ok(self
+                    .properties_value(properties, object, *deny_unknown_fields)?
+                    .map(|fields| quote! { #name { #(#fields),* } }))
+            }
+            TypeEntry::Newtype(type_newtype) => self.newtype_value(type_newtype, value),
+
+            TypeEntry::Option(id) => match value {
+                Value::Null => Ok(Some(quote! { None })),
+                _ => Ok(self.value(id, value)?.map(|inner| quote! { Some(#inner) })),
+            },
+            TypeEntry::Box(id) => Ok(self
+                .value(id, value)?
+                .map(|inner| quote! { Box::new(#inner) })),
+            TypeEntry::Reference(id) | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }) => {
+                self.value(id, value)
+            }
+            TypeEntry::Array(id) | TypeEntry::Set(id) => {
+                let items = value.as_array().ok_or("not an array")?;
+                Ok(self
+                    .items_value(&vec![id.clone(); items.len()], items)?
+                    .map(|items| quote! { vec![#(#items),*] }))
+            }
+            TypeEntry::FixedArray(id, len) => {
+                let items = value.as_array().ok_or("not an array")?;
+                if items.len() != *len {
+                    return Err(format!("not an array of {} items", len));
+                }
+                Ok(self
+                    .items_value(&vec![id.clone(); *len], items)?
+                    .map(|items| quote! { [#(#items),*] }))
+            }
+            TypeEntry::Tuple(ids) => {
+                let items = value.as_array().ok_or("not an array")?;
+                if items.len() != ids.len() {
+                    return Err(format!("not an array of {} items", ids.len()));
+                }
+                let trailing = (ids.len() == 1).then(|| quote! { , });
+                Ok(self
+                    .items_value(ids, items)?
+                    .map(|items| quote! { (#(#items),* #trailing) }))
+            }
+            TypeEntry::Map(key_id, value_id) => {
+                let object = value.as_object().ok_or("not an object")?;
+                let mut entries = Vec::new();
+                for (key, value) in object {
+                    let key = match self.value(key_id, &Value::String(key.clone()))? {
+                        Some(key) => key,
+                        None => return Ok(None),
+                    };
+                    let value = match self.value(value_id, value)? {
+                        Some(value) => value,
+                        None => return Ok(None),
+                    };
+                    entries.push(quote! { (#key, #value) });
+                }
+                Ok(Some(quote! { vec![#(#entries),*].into_iter().collect() }))
+            }
+            TypeEntry::Unit => match value {
+                Value::Null => Ok(Some(quote! { () })),
+                _ => Err("not null".to_string()),
+            },
+            TypeEntry::String => {
+                let s = value.as_str().ok_or("not a string")?;
+                Ok(Some(quote! { #s.to_string() }))
+            }
+            TypeEntry::Integral(name) => integral_value(name, value).map(Some),
+            TypeEntry::Float(_) => {
+                let number = value.as_f64().ok_or("not a number")?;
+                let lit = Literal::f64_unsuffixed(number);
+                Ok(Some(quote! { #lit }))
+            }
+            TypeEntry::BuiltIn(name) => builtin_value(name, value),
+            TypeEntry::BuiltInGeneric(_, _) | TypeEntry::BuiltInWith(_, _, _) => Ok(None),
+        }
+    }
+
+    fn items_value(
+        &self,
+        ids: &[TypeId],
+        items: &[Value],
+    ) -> std::result::Result<Option<Vec<TokenStream>>, String> {
+        let mut exprs = Vec::new();
+        for (index, (id, item)) in ids.iter().zip(items).enumerate() {
+            match self
+                .value(id, item)
+                .map_err(|reason| format!("item {}: {}", index, reason))?
+            {
+                Some(expr) => exprs.push(expr),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(exprs))
+    }
+
+    /// The `field: value` pairs for struct properties from an object. Absent
+    /// properties have their own defaults or, if they're optional, are
+    /// empty.
+    fn properties_value(
+        &self,
+        properties: &[StructProperty],
+        object: &serde_json::Map<String, Value>,
+        deny_unknown_fields: bool,
+    ) -> std::result::Result<Option<Vec<TokenStream>>, String> {
+        let mut fields = Vec::new();
+        let mut extra = None;
+        for prop in properties {
+            let name = format_ident!("{}", prop.name);
+            if prop.serde_naming == SerdeNaming::Flatten {
+                match self.id_to_entry.get(&prop.type_id) {
+                    Some(TypeEntry::Map(_, _)) => {
+                        extra = Some(prop);
+                        continue;
+                    }
+                    _ => return Ok(None),
+                }
+            }
+
+            let serde_name = prop.serde_name();
+            let value = match (object.get(&serde_name), &prop.default) {
+                (Some(value), _) => self
+                    .value(&prop.type_id, value)
+                    .map_err(|reason| format!("property {:?}: {}", serde_name, reason))?,
+                (None, Some(_)) => self.property_default(prop),
+                (None, None)
+                    if matches!(
+                        prop.serde_rules,
+                        SerdeRules::Optional | SerdeRules::ExplicitNull
+                    ) =>
+                {
+                    Some(quote! { Default::default() })
+                }
+                (None, None) => return Err(format!("no value for property {:?}", serde_name)),
+            };
+            match value {
+                Some(value) => fields.push(quote! { #name: #value }),
+                None => return Ok(None),
+            }
+        }
+
+        let unknown = object
+            .iter()
+            .filter(|(key, _)| {
+                !properties.iter().any(|prop| {
+                    prop.serde_naming != SerdeNaming::Flatten && &prop.serde_name() == *key
+                })
+            })
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        match extra {
+            Some(prop) => {
+                let name = format_ident!("{}", prop.name);
+                match self.value(&prop.type_id, &Value::Object(unknown))? {
+                    Some(value) => fields.push(quote! { #name: #value }),
+                    None => return Ok(None),
+                }
+            }
+            None if deny_unknown_fields && !unknown.is_empty() => {
+                let keys = unknown.keys().cloned().collect::<Vec<_>>();
+                return Err(format!("unknown properties {:?}", keys));
+            }
+            None => {}
+        }
+
+        Ok(Some(fields))
+    }
+
+    fn enum_value(&self, type_enum: &TypeEntryEnum, value: &Value) -> ValueExpr {
+        let TypeEntryEnum {
+            name,
+            tag_type,
+            variants,
+            ..
+        } = type_enum;
+        let type_name = format_ident!("{}", name);
+        let find = |variant_name: &str| {
+            variants
+                .iter()
+                .find(|variant| variant.serde_name() == variant_name)
+                .ok_or_else(|| format!("no variant {:?}", variant_name))
+        };
+
+        let expr = match tag_type {
+            EnumTagType::External if type_enum.is_integer() => {
+                let value = value.as_i64().ok_or("not an integer")?;
+                let variant = variants
+                    .iter()
+                    .find(|variant| variant.details == VariantDetails::Integer(value))
+                    .ok_or_else(|| format!("no variant {}", value))?;
+                self.variant_value(variant, None, &serde_json::Map::new())?
+            }
+            EnumTagType::External => match value {
+                Value::String(variant_name) => {
+                    self.variant_value(find(variant_name)?, None, &serde_json::Map::new())?
+                }
+                Value::Object(object) if object.len() == 1 => {
+                    let (variant_name, content) = object.iter().next().unwrap();
+                    self.variant_value(find(variant_name)?, Some(content), &serde_json::Map::new())?
+                }
+                _ => return Err("not a string or an object with one property".to_string()),
+            },
+            EnumTagType::Internal { tag } => {
+                let mut object = value.as_object().ok_or("not an object")?.clone();
+                let variant_name = object.remove(tag);
+                let variant_name = variant_name
+                    .as_ref()
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("no string tag {:?}", tag))?;
+                match find(variant_name)? {
+                    variant @ Variant {
+                        details: VariantDetails::Simple | VariantDetails::Struct(_),
+                        ..
+                    } => self.variant_value(variant, None, &object)?,
+                    _ => None,
+                }
+            }
+            EnumTagType::Adjacent { tag, content } => {
+                let object = value.as_object().ok_or("not an object")?;
+                let variant_name = object
+                    .get(tag)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("no string tag {:?}", tag))?;
+                self.variant_value(
+                    find(variant_name)?,
+                    object.get(content),
+                    &serde_json::Map::new(),
+                )?
+            }
+            EnumTagType::Untagged => {
+                let mut reasons = Vec::new();
+                let mut matched = None;
+                for variant in variants {
+                    let content = match &variant.details {
+                        VariantDetails::Simple if value.is_null() => None,
+                        VariantDetails::Simple => continue,
+                        _ => Some(value),
+                    };
+                    match self.variant_value(variant, content, &serde_json::Map::new()) {
+                        Ok(expr) => {
+                            matched = Some(expr);
+                            break;
+                        }
+                        Err(reason) => reasons.push(format!("{}: {}", variant.name, reason)),
+                    }
+                }
+                match matched {
+                    Some(expr) => expr,
+                    None => return Err(format!("matches no variant ({})", reasons.join("; "))),
+                }
+            }
+        };
+
+        Ok(expr.map(|variant| quote! { #type_name::#variant }))
+    }
+
+    /// The variant and its contents, e.g. `Circle { radius: 1.0 }`, from its
+    /// content or, for internally tagged enums, the object's other
+    /// properties.
+    fn variant_value(
+        &self,
+        variant: &Variant,
+        content: Option<&Value>,
+        properties: &serde_json::Map<String, Value>,
+    ) -> ValueExpr {
+        let name = format_ident!("{}", variant.name);
+        match (&variant.details, content) {
+            (
+                VariantDetails::Simple | VariantDetails::Integer(_) | VariantDetails::Unknown,
+                None,
+            ) => Ok(Some(quote! { #name })),
+            (
+                VariantDetails::Simple | VariantDetails::Integer(_) | VariantDetails::Unknown,
+                Some(_),
+            ) => Err("unexpected content".to_string()),
+            // Values that the schema lists are never those of the catch-all.
+            (VariantDetails::Other(_), _) => Err("not a listed value".to_string()),
+            (VariantDetails::Tuple(ids), Some(content)) if ids.len() == 1 => Ok(self
+                .value(&ids[0], content)?
+                .map(|item| quote! { #name(#item) })),
+            (VariantDetails::Tuple(ids), Some(content)) => {
+                let items = content.as_array().ok_or("not an array")?;
+                if items.len() != ids.len() {
+                    return Err(format!("not an array of {} items", ids.len()));
+                }
+                Ok(self
+                    .items_value(ids, items)?
+                    .map(|items| quote! { #name(#(#items),*) }))
+            }
+            (VariantDetails::Struct(props), content) => {
+                let object = match content {
+                    Some(content) => content.as_object().ok_or("not an object")?,
+                    None => properties,
+                };
+                Ok(self
+                    .properties_value(props, object, false)?
+                    .map(|fields| quote! { #name { #(#fields),* } }))
+            }
+            (VariantDetails::Tuple(_), None) => Err("no content".to_string()),
+        }
+    }
+
+    fn newtype_value(&self, type_newtype: &TypeEntryNewtype, value: &Value) -> ValueExpr {
+        let TypeEntryNewtype {
+            name,
+            type_id,
+            constraints,
+            ..
+        } = type_newtype;
+        let type_name = format_ident!("{}", name);
+        let sub_type = self.id_to_entry.get(type_id).unwrap();
+
+        check_constraints(constraints, value)?;
+        match (constraints, sub_type) {
+            (NewtypeConstraints::Const(_), _) => Ok(Some(quote! { #type_name })),
+            (NewtypeConstraints::None, TypeEntry::Tuple(ids)) if ids.len() > 1 => {
+                let items = value.as_array().ok_or("not an array")?;
+                if items.len() != ids.len() {
+                    return Err(format!("not an array of {} items", ids.len()));
+                }
+                Ok(self
+                    .items_value(ids, items)?
+                    .map(|items| quote! { #type_name(#(#items),*) }))
+            }
+            // The default is the document's JSON in a string.
+            (NewtypeConstraints::Json, _) => {
+                let json = serde_json::from_str(value.as_str().unwrap())
+                    .map_err(|e| format!("invalid JSON: {}", e))?;
+                Ok(self
+                    .entry_value(sub_type, &json)?
+                    .map(|inner| quote! { #type_name(#inner) }))
+            }
+            (NewtypeConstraints::None, _) => Ok(self
+                .entry_value(sub_type, value)?
+                .map(|inner| quote! { #type_name(#inner) })),
+            // We've checked the constraints so the conversion succeeds.
+            _ => {
+                let sub_type_name = sub_type.type_ident(self, false);
+                Ok(self.entry_value(sub_type, value)?.map(|inner| {
+                    quote! {
+                        <#type_name as std::convert::TryFrom<#sub_type_name>>::try_from(#inner)
+                            .unwrap()
+                    }
+                }))
+            }
+        }
+    }
+}
+
+impl TypeEntry {
+    /// The named type's default value from its schema, as JSON.
+    pub(crate) fn default(&self) -> Option<&String> {
+        match self {
+            TypeEntry::Enum(TypeEntryEnum { default, .. })
+            | TypeEntry::Struct(TypeEntryStruct { default, .. })
+            | TypeEntry::Newtype(TypeEntryNewtype { default, .. }) => default.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl StructProperty {
+    /// The name of the property in JSON.
+    pub(crate) fn serde_name(&self) -> String {
+        match &self.serde_naming {
+            SerdeNaming::Rename(name) => name.clone(),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+impl Variant {
+    /// The name of the variant in JSON.
+    fn serde_name(&self) -> &str {
+        self.rename.as_ref().unwrap_or(&self.name)
+    }
+}
+
+fn integral_value(name: &str, value: &Value) -> std::result::Result<TokenStream, String> {
+    if name == "bool" {
+        let b = value.as_bool().ok_or("not a boolean")?;
+        return Ok(quote! { #b });
+    }
+
+    let (nonzero, int_type) = match name.strip_prefix("std::num::NonZero") {
+        Some(int_type) => (true, int_type.to_lowercase()),
+        None => (false, name.to_string()),
+    };
+    let (min, max) = match int_type.as_str() {
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        "i64" => (i64::MIN as i128, i64::MAX as i128),
+        "u8" => (0, u8::MAX as i128),
+        "u16" => (0, u16::MAX as i128),
+        "u32" => (0, u32::MAX as i128),
+        "u64" => (0, u64::MAX as i128),
+        _ => return Err(format!("unexpected integer type {}", name)),
+    };
+    let number = match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        _ => None,
+    }
+    .ok_or("not an integer")?;
+    if number < min || number > max || (nonzero && number == 0) {
+        return Err(format!("out of range for {}", name));
+    }
+
+    let lit = Literal::i128_unsuffixed(number);
+    if nonzero {
+        let path = syn::parse_str::<syn::TypePath>(name).unwrap();
+        Ok(quote! { #path::new(#lit).unwrap() })
+    } else {
+        Ok(quote! { #lit })
+    }
+}
+
+/// Values of the built-in types we can check and express.
+fn builtin_value(name: &str, value: &Value) -> ValueExpr {
+    let parse = |valid: bool| {
+        let s = value.as_str().ok_or("not a string")?;
+        if !valid {
+            return Err(format!("not a valid {}", name));
+        }
+        let path = syn::parse_str::<syn::TypePath>(name).unwrap();
+        Ok(Some(quote! { #s.parse::<#path>().unwrap() }))
+    };
+    let s = value.as_str().unwrap_or_default();
+    match name {
+        "serde_json::Value" => {
+            let json = value.to_string();
+            Ok(Some(quote! {
+                serde_json::from_str::<serde_json::Value>(#json).unwrap()
+            }))
+        }
+        "std::net::IpAddr" => parse(s.parse::<std::net::IpAddr>().is_ok()),
+        "std::net::Ipv4Addr" => parse(s.parse::<std::net::Ipv4Addr>().is_ok()),
+        "std::net::Ipv6Addr" => parse(s.parse::<std::net::Ipv6Addr>().is_ok()),
+        "uuid::Uuid" => parse(
+            regex::Regex::new(
+                "^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$",
+            )
+            .unwrap()
+            .is_match(s),
+        ),
+        _ => Ok(None),
+    }
+}
+
+/// Check a value against the constraints of a newtype.
+fn check_constraints(
+    constraints: &NewtypeConstraints,
+    value: &Value,
+) -> std::result::Result<(), String> {
+    match constraints {
+        NewtypeConstraints::None => {}
+        NewtypeConstraints::Json => {
+            value.as_str().ok_or("not a string")?;
+        }
+        NewtypeConstraints::Const(json) => {
+            if serde_json::from_str::<Value>(json).unwrap() != *value {
+                return Err(format!("not {}", json));
+            }
+        }
+        NewtypeConstraints::String {
+            max_length,
+            min_length,
+            pattern,
+        } => {
+            let s = value.as_str().ok_or("not a string")?;
+            let len = s.chars().count();
+            if let Some(max) = max_length {
+                if len > *max as usize {
+                    return Err(format!("longer than maxLength {}", max));
+                }
+            }
+            if let Some(min) = min_length {
+                if len < *min as usize {
+                    return Err(format!("shorter than minLength {}", min));
+                }
+            }
+            // The regex crate lacks the lookaround and backreferences of
+            // the patterns only regress compiles.
+            if let Some(pattern) = pattern {
+                let matches = match regex::Regex::new(pattern) {
+                    Ok(regex) => Some(regex.is_match(s)),
+                    Err(_) => regress::Regex::new(pattern)
+                        .ok()
+                        .map(|regex| regex.find(s).is_some()),
+                };
+                if matches == Some(false) {
+                    return Err(format!("doesn't match pattern {:?}", pattern));
+                }
+            }
+        }
+        NewtypeConstraints::Integer {
+            minimum,
+            maximum,
+            multiple_of,
+        } => {
+            let number = value
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| value.as_u64().map(i128::from))
+                .ok_or("not an integer")?;
+            if let Some(min) = minimum {
+                if number < *min {
+                    return Err(format!("less than minimum {}", min));
+                }
+            }
+            if let Some(max) = maximum {
+                if number > *max {
+                    return Err(format!("greater than maximum {}", max));
+                }
+            }
+            if let Some(factor) = multiple_of {
+                if number % factor != 0 {
+                    return Err(format!("not a multiple of {}", factor));
+                }
+            }
+        }
+        NewtypeConstraints::Number {
+            minimum,
+            exclusive_minimum,
+            maximum,
+            exclusive_maximum,
+            multiple_of,
+        } => {
+            let number = value.as_f64().ok_or("not a number")?;
+            let failed = [
+                (
+                    minimum,
+                    "less than minimum",
+                    number < minimum.map_or(f64::MIN, |b| b.value()),
+                ),
+                (
+                    exclusive_minimum,
+                    "not greater than exclusiveMinimum",
+                    number <= exclusive_minimum.map_or(f64::NEG_INFINITY, |b| b.value()),
+                ),
+                (
+                    maximum,
+                    "greater than maximum",
+                    number > maximum.map_or(f64::MAX, |b| b.value()),
+                ),
+                (
+                    exclusive_maximum,
+                    "not less than exclusiveMaximum",
+                    number >= exclusive_maximum.map_or(f64::INFINITY, |b| b.value()),
+                ),
+                (
+                    multiple_of,
+                    "not a multiple of",
+                    multiple_of.is_some_and(|factor| (number / factor.value()).fract() != 0.0),
+                ),
+            ]
+            .iter()
+            .find(|(_, _, failed)| *failed)
+            .map(|(bound, message, _)| format!("{} {}", message, bound.unwrap().value()));
+            if let Some(reason) = failed {
+                return Err(reason);
+            }
+        }
+        NewtypeConstraints::Array {
+            max_items,
+            min_items,
+        } => {
+            let items = value.as_array().ok_or("not an array")?;
+            if let Some(max) = max_items {
+                if items.len() > *max as usize {
+                    return Err(format!("more than maxItems {}", max));
+                }
+            }
+            if let Some(min) = min_items {
+                if items.len() < *min as usize {
+                    return Err(format!("fewer than minItems {}", min));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{test_util::assert_output, TypeSpace, TypeSpaceSettings};
+
+    fn generate(schema: serde_json::Value) -> crate::Result<TypeSpace> {
+        let schema = serde_json::from_value::<RootSchema>(schema).unwrap();
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        type_space.add_ref_types(schema.definitions)?;
+        Ok(type_space)
+    }
+
+    #[test]
+    fn test_default_impls() {
+        let type_space = generate(json!({
+            "definitions": {
+                "Config": {
+                    "type": "object",
+                    "properties": {
+                        "retries": { "type": "integer", "format": "uint8", "default": 3 },
+                        "name": { "type": "string" },
+                        "mode": { "$ref": "#/definitions/Mode" },
+                        "limits": { "$ref": "#/definitions/Limits", "default": { "soft": 1 } }
+                    },
+                    "required": ["retries", "mode", "limits"]
+                },
+                "Limits": {
+                    "type": "object",
+                    "properties": {
+                        "soft": { "type": "integer", "format": "uint32" },
+                        "hard": { "type": "integer", "format": "uint32" },
+                        "tags": { "type": "array", "items": { "$ref": "#/definitions/Tag" } }
+                    },
+                    "required": ["soft"]
+                },
+                "Mode": {
+                    "type": "string",
+                    "enum": ["fast", "slow"],
+                    "default": "slow"
+                },
+                "Tag": {
+                    "type": "string",
+                    "maxLength": 4
+                },
+                "Tags": {
+                    "type": "array",
+                    "items": { "$ref": "#/definitions/Tag" },
+                    "default": ["a", "bc"]
+                }
+            }
+        }))
+        .unwrap();
+        assert_output(&type_space, "defaults/default_impls");
+    }
+
+    #[test]
+    fn test_invalid_defaults() {
+        for (schema, message) in [
+            (
+                json!({ "type": "string", "enum": ["fast", "slow"], "default": "medium" }),
+                "definitions.Thing: invalid default \"medium\" for Thing: no variant \"medium\"",
+            ),
+            (
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "retries": { "type": "integer", "format": "uint8", "default": 300 }
+                    }
+                }),
+                "definitions.Thing.properties.retries: invalid default 300 for retries: \
+                out of range for u8",
+            ),
+            (
+                json!({ "type": "string", "maxLength": 2, "default": "abc" }),
+                "definitions.Thing: invalid default \"abc\" for Thing: longer than maxLength 2",
+            ),
+            // Inline enums are located by the properties that use them.
+            (
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "mode": { "type": "string", "enum": ["fast", "slow"], "default": "medium" }
+                    }
+                }),
+                "definitions.Thing.properties.mode: invalid default \"medium\" for ThingMode: \
+                no variant \"medium\"",
+            ),
+            (
+                json!({
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["kind"],
+                            "properties": { "kind": { "const": "square" } }
+                        },
+                        {
+                            "type": "object",
+                            "required": ["kind"],
+                            "properties": {
+                                "kind": { "const": "circle" },
+                                "radius": { "type": "integer", "default": "big" }
+                            }
+                        }
+                    ]
+                }),
+                "definitions.Thing.oneOf[1].properties.radius: invalid default \"big\" for \
+                radius: not an integer",
+            ),
+        ] {
+            let error = generate(json!({ "definitions": { "Thing": schema } })).unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_serde_defaults() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "properties": {
+                        "retries": { "type": "integer", "format": "uint8", "default": 3 },
+                        "verbose": { "type": "boolean", "default": false },
+                        "tags": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "default": ["a", "b"]
+                        }
+                    },
+                    "required": ["retries"]
+                }
+            }
+        }))
+        .unwrap();
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_skip_serializing_defaults(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+
+        assert_output(&type_space, "defaults/serde_defaults");
+    }
+
+    #[test]
+    fn test_enum_property_defaults() {
+        let type_space = generate(json!({
+            "definitions": {
+                "Shirt": {
+                    "type": "object",
+                    "required": ["fit"],
+                    "properties": {
+                        "size": { "$ref": "#/definitions/Size" },
+                        "other": { "$ref": "#/definitions/Size", "default": "large" },
+                        "fit": { "$ref": "#/definitions/Size" }
+                    }
+                },
+                "Size": {
+                    "type": "string",
+                    "enum": ["small", "medium", "large"],
+                    "default": "medium"
+                }
+            }
+        }))
+        .unwrap();
+        assert_output(&type_space, "defaults/enum_property_defaults");
+
+        // The error for an invalid default of the enum is for its definition
+        // rather than for the properties that have it too.
+        let error = generate(json!({
+            "definitions": {
+                "Shirt": {
+                    "type": "object",
+                    "properties": { "size": { "$ref": "#/definitions/Size" } }
+                },
+                "Size": { "type": "string", "enum": ["small", "large"], "default": "medium" }
+            }
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "definitions.Size: invalid default \"medium\" for Size: no variant \"medium\""
+        );
+    }
+}
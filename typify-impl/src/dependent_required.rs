@@ -0,0 +1,223 @@
+// Copyright 2021 Oxide Computer Company
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use serde_json::Value;
+
+use crate::{
+    structs::output_struct_property,
+    type_entry::{SerdeNaming, SerdeRules, StructProperty, TypeEntry},
+    TypeSpace,
+};
+
+impl TypeSpace {
+    /// The `dependentRequired` rules of an object with the given properties
+    /// that its struct checks with `TypeSpaceSettings::with_object_validation`:
+    /// the JSON names of properties, each with those of the properties that
+    /// must be present along with it. Properties that are always present,
+    /// i.e. that are required or have defaults, need no check.
+    pub(crate) fn dependent_required(
+        &mut self,
+        properties: &[StructProperty],
+        rules: &Value,
+    ) -> Vec<(String, Vec<String>)> {
+        if !self.settings.object_validation {
+            self.warn(
+                "dependentRequired",
+                "dependentRequired isn't checked without object validation",
+            );
+            return Vec::new();
+        }
+        let rules = match rules.as_object() {
+            Some(rules) => rules,
+            None => {
+                self.warn(
+                    "dependentRequired",
+                    "dependentRequired isn't an object so it isn't checked",
+                );
+                return Vec::new();
+            }
+        };
+
+        let property = |name: &str| {
+            properties
+                .iter()
+                .find(|prop| prop.serde_naming != SerdeNaming::Flatten && prop.serde_name() == name)
+        };
+        let mut checked = Vec::new();
+        for (trigger, dependents) in rules {
+            match property(trigger) {
+                None => {
+                    self.warn(
+                        "dependentRequired",
+                        format!(
+                            "the dependentRequired of {} isn't checked as it isn't a property",
+                            trigger
+                        ),
+                    );
+                    continue;
+                }
+                Some(prop) if prop.serde_rules == SerdeRules::Default => {
+                    self.warn(
+                        "dependentRequired",
+                        format!(
+                            "the dependentRequired of {} isn't checked as its default hides \
+                             its absence",
+                            trigger
+                        ),
+                    );
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            let mut missing = Vec::new();
+            for dependent in dependents.as_array().into_iter().flatten() {
+                match dependent.as_str().map(|name| (name, property(name))) {
+                    Some((name, Some(prop)))
+                        if matches!(
+                            prop.serde_rules,
+                            SerdeRules::Optional | SerdeRules::ExplicitNull
+                        ) =>
+                    {
+                        missing.push(name.to_string())
+                    }
+                    Some((_, Some(_))) => {}
+                    _ => self.warn(
+                        "dependentRequired",
+                        format!(
+                            "{} of the dependentRequired of {} isn't checked as it isn't a \
+                             property",
+                            dependent, trigger
+                        ),
+                    ),
+                }
+            }
+            if !missing.is_empty() {
+                checked.push((trigger.clone(), missing));
+            }
+        }
+        checked
+    }
+}
+
+/// The code that checks the `dependentRequired` rules of a struct, if it has
+/// any: its `validate` method, and a private mirror of the struct that serde
+/// deserializes and converts to it through `validate`. It returns the
+/// `try_from` option for the struct's `serde` attribute too. The mirror
+/// takes the given options of the struct.
+pub(crate) fn output_struct_validation(
+    name: &str,
+    properties: &[StructProperty],
+    output_properties: &[StructProperty],
+    rules: &[(String, Vec<String>)],
+    serde_options: &[TokenStream],
+    type_space: &TypeSpace,
+) -> Option<(TokenStream, TokenStream)> {
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|prop| prop.serde_naming != SerdeNaming::Flatten && prop.serde_name() == name)
+    };
+    // A split type may lack some of the properties.
+    let checks = rules
+        .iter()
+        .filter_map(|(trigger, dependents)| {
+            let trigger_prop = property(trigger)?;
+            let dependents = dependents
+                .iter()
+                .filter_map(|dependent| Some((dependent, property(dependent)?)))
+                .map(|(dependent, prop)| {
+                    let absent = presence(prop, false, type_space);
+                    quote! {
+                        if #absent {
+                            missing.push(#dependent);
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            if dependents.is_empty() {
+                return None;
+            }
+            let check = quote! {
+                let mut missing = Vec::<&str>::new();
+                #( #dependents )*
+                if !missing.is_empty() {
+                    return Err(format!("{} requires {}", #trigger, missing.join(", ")).into());
+                }
+            };
+            Some(match trigger_prop.serde_rules {
+                SerdeRules::None => quote! { { #check } },
+                _ => {
+                    let present = presence(trigger_prop, true, type_space);
+                    quote! {
+                        if #present {
+                            #check
+                        }
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    if checks.is_empty() {
+        return None;
+    }
+
+    let type_name = format_ident!("{}", name);
+    let raw_name = format_ident!("{}Raw", name);
+    let conversion_error = type_space.conversion_error();
+    let fields = output_properties
+        .iter()
+        .map(|prop| output_struct_property(prop, name, type_space, false));
+    let prop_names = properties
+        .iter()
+        .map(|prop| format_ident!("{}", prop.name))
+        .collect::<Vec<_>>();
+    let serde = (!serde_options.is_empty()).then(|| quote! { #[serde( #( #serde_options ),* )] });
+    let raw_path = raw_name.to_string();
+
+    let code = quote! {
+        impl #type_name {
+            /// Checks the properties that the presence of others requires.
+            pub fn validate(&self) -> Result<(), #conversion_error> {
+                #( #checks )*
+                Ok(())
+            }
+        }
+
+        #[derive(Deserialize)]
+        #serde
+        struct #raw_name {
+            #( #fields )*
+        }
+
+        impl std::convert::TryFrom<#raw_name> for #type_name {
+            type Error = #conversion_error;
+
+            fn try_from(value: #raw_name) -> Result<Self, Self::Error> {
+                let value = Self {
+                    #( #prop_names: value.#prop_names, )*
+                };
+                value.validate()?;
+                Ok(value)
+            }
+        }
+    };
+    Some((quote! { try_from = #raw_path }, code))
+}
+
+/// An expression of whether the optional property is present in `self`, or
+/// absent if not `present`.
+fn presence(prop: &StructProperty, present: bool, type_space: &TypeSpace) -> TokenStream {
+    let name = format_ident!("{}", prop.name);
+    let empty = matches!(
+        type_space.id_to_entry.get(&prop.type_id),
+        Some(TypeEntry::Array(_) | TypeEntry::Map(_, _))
+    ) && prop.serde_rules == SerdeRules::Optional;
+    match (empty, present) {
+        (true, true) => quote! { !self.#name.is_empty() },
+        (true, false) => quote! { self.#name.is_empty() },
+        (false, true) => quote! { self.#name.is_some() },
+        (false, false) => quote! { self.#name.is_none() },
+    }
+}
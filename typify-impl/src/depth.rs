@@ -0,0 +1,172 @@
+// Copyright 2021 Oxide Computer Company
+
+use schemars::schema::{Schema, SchemaObject, SingleOrVec};
+
+use crate::{Error, PathSegment, Result, SchemaLocation};
+
+/// How deeply subschemas may be nested unless the settings say otherwise.
+/// Conversion recurses through each level, and in a debug build a spawned
+/// thread's 2 MiB stack overflows after some thousands of levels of nested
+/// properties, fewer for costlier constructs. Schemas seldom nest more than a
+/// dozen levels, so 64 leaves a wide margin on both sides.
+pub(crate) const DEFAULT_RECURSION_LIMIT: usize = 64;
+
+/// Make sure that no subschema is nested more than `limit` levels deep so
+/// that deeply nested schemas produce an error rather than overflowing the
+/// stack during conversion. This doesn't follow references: a definition
+/// that refers to itself is a cycle of types rather than nesting.
+pub(crate) fn check(schema: &Schema, path: Vec<PathSegment>, limit: usize) -> Result<()> {
+    match schema {
+        Schema::Object(object) => check_object(object, path, limit),
+        Schema::Bool(_) => Ok(()),
+    }
+}
+
+/// As [check] does for a schema object, e.g. that of a root schema.
+pub(crate) fn check_object(
+    object: &SchemaObject,
+    path: Vec<PathSegment>,
+    limit: usize,
+) -> Result<()> {
+    // We visit the subschemas with our own stack rather than recursing.
+    let mut pending = vec![(0, path, object)];
+    while let Some((depth, path, object)) = pending.pop() {
+        if depth > limit {
+            return Err(Error::At {
+                location: SchemaLocation { file: None, path },
+                error: Box::new(Error::RecursionLimit { depth: limit }),
+            });
+        }
+        for (segments, subschema) in subschemas(object) {
+            if let Schema::Object(subobject) = subschema {
+                let mut subpath = path.clone();
+                subpath.extend(segments);
+                pending.push((depth + 1, subpath, subobject));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The subschemas of a schema along with the path segments to each.
+pub(crate) fn subschemas(object: &SchemaObject) -> Vec<(Vec<PathSegment>, &Schema)> {
+    let mut subschemas = Vec::new();
+    let mut push = |segments: Vec<PathSegment>, schema| subschemas.push((segments, schema));
+
+    if let Some(subschema) = &object.subschemas {
+        for (keyword, schemas) in [
+            ("allOf", &subschema.all_of),
+            ("anyOf", &subschema.any_of),
+            ("oneOf", &subschema.one_of),
+        ] {
+            for (index, schema) in schemas.iter().flatten().enumerate() {
+                push(vec![keyword.into(), PathSegment::Index(index)], schema);
+            }
+        }
+        for (keyword, schema) in [
+            ("not", &subschema.not),
+            ("if", &subschema.if_schema),
+            ("then", &subschema.then_schema),
+            ("else", &subschema.else_schema),
+        ] {
+            if let Some(schema) = schema {
+                push(vec![keyword.into()], schema);
+            }
+        }
+    }
+    if let Some(array) = &object.array {
+        match &array.items {
+            Some(SingleOrVec::Single(schema)) => push(vec!["items".into()], schema),
+            Some(SingleOrVec::Vec(schemas)) => {
+                for (index, schema) in schemas.iter().enumerate() {
+                    push(vec!["items".into(), PathSegment::Index(index)], schema);
+                }
+            }
+            None => {}
+        }
+        for (keyword, schema) in [
+            ("additionalItems", &array.additional_items),
+            ("contains", &array.contains),
+        ] {
+            if let Some(schema) = schema {
+                push(vec![keyword.into()], schema);
+            }
+        }
+    }
+    if let Some(validation) = &object.object {
+        for (keyword, schemas) in [
+            ("properties", &validation.properties),
+            ("patternProperties", &validation.pattern_properties),
+        ] {
+            for (name, schema) in schemas {
+                push(vec![keyword.into(), name.into()], schema);
+            }
+        }
+        for (keyword, schema) in [
+            ("additionalProperties", &validation.additional_properties),
+            ("propertyNames", &validation.property_names),
+        ] {
+            if let Some(schema) = schema {
+                push(vec![keyword.into()], schema);
+            }
+        }
+    }
+    subschemas
+}
+
+/// Drop a schema without recursing, as dropping one nested too deeply to
+/// convert could overflow the stack itself.
+pub(crate) fn dismantle(schema: Schema) {
+    let mut pending = vec![schema];
+    while let Some(schema) = pending.pop() {
+        let mut object = match schema {
+            Schema::Object(object) => object,
+            Schema::Bool(_) => continue,
+        };
+        if let Some(subschema) = object.subschemas.take() {
+            let subschema = *subschema;
+            pending.extend(
+                vec![subschema.all_of, subschema.any_of, subschema.one_of]
+                    .into_iter()
+                    .flatten()
+                    .flatten(),
+            );
+            pending.extend(
+                vec![
+                    subschema.not,
+                    subschema.if_schema,
+                    subschema.then_schema,
+                    subschema.else_schema,
+                ]
+                .into_iter()
+                .flatten()
+                .map(|schema| *schema),
+            );
+        }
+        if let Some(array) = object.array.take() {
+            let array = *array;
+            match array.items {
+                Some(SingleOrVec::Single(schema)) => pending.push(*schema),
+                Some(SingleOrVec::Vec(schemas)) => pending.extend(schemas),
+                None => {}
+            }
+            pending.extend(
+                vec![array.additional_items, array.contains]
+                    .into_iter()
+                    .flatten()
+                    .map(|schema| *schema),
+            );
+        }
+        if let Some(validation) = object.object.take() {
+            let validation = *validation;
+            pending.extend(validation.properties.into_values());
+            pending.extend(validation.pattern_properties.into_values());
+            pending.extend(
+                vec![validation.additional_properties, validation.property_names]
+                    .into_iter()
+                    .flatten()
+                    .map(|schema| *schema),
+            );
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{
+    type_entry::{
+        NewtypeConstraints, TypeEntry, TypeEntryAlias, TypeEntryEnum, TypeEntryNewtype,
+        VariantDetails,
+    },
+    TypeId, TypeSpace,
+};
+
+/// The comparison and hashing traits, and `Copy`, that a type implements, as
+/// a set of bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Traits(u8);
+
+impl Traits {
+    pub const NONE: Self = Self(0);
+    const PARTIAL_ORD: Self = Self(1);
+    const ORD: Self = Self(2);
+    const PARTIAL_EQ: Self = Self(4);
+    const EQ: Self = Self(8);
+    const HASH: Self = Self(16);
+    const COPY: Self = Self(32);
+    pub const COMPARISONS: Self = Self(31);
+    pub const ALL: Self = Self(63);
+
+    fn and(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    fn or(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn is_copy(self) -> bool {
+        self.and(Self::COPY) == Self::COPY
+    }
+
+    pub fn is_partial_eq(self) -> bool {
+        self.and(Self::PARTIAL_EQ) == Self::PARTIAL_EQ
+    }
+
+    /// The derives for the comparison and hashing traits, in the order in
+    /// which we've always emitted them.
+    pub fn derives(self) -> Vec<TokenStream> {
+        vec![
+            (Self::PARTIAL_ORD, quote! { PartialOrd }),
+            (Self::ORD, quote! { Ord }),
+            (Self::PARTIAL_EQ, quote! { PartialEq }),
+            (Self::EQ, quote! { Eq }),
+            (Self::HASH, quote! { Hash }),
+        ]
+        .into_iter()
+        .filter(|(traits, _)| self.and(*traits) == *traits)
+        .map(|(_, derive)| derive)
+        .collect()
+    }
+}
+
+impl TypeSpace {
+    /// The traits we can derive for a named type: those that all of its
+    /// members implement. Types may contain themselves so we start by
+    /// assuming that every named type reachable from this one implements
+    /// every trait and remove traits that some member lacks until nothing
+    /// changes.
+    pub(crate) fn derivable_traits(&self, ty: &TypeEntry) -> Traits {
+        self.members_derivable_traits(&ty.members())
+    }
+
+    /// The traits that a type implements, given those that we derive for
+    /// named types.
+    pub(crate) fn type_id_traits(&self, type_id: &TypeId) -> Traits {
+        self.members_derivable_traits(std::slice::from_ref(type_id))
+    }
+
+    fn members_derivable_traits(&self, member_ids: &[TypeId]) -> Traits {
+        let mut traits = BTreeMap::new();
+        let mut pending = Vec::new();
+        for member_id in member_ids {
+            self.named_types(member_id, &mut pending);
+        }
+        while let Some(named_id) = pending.pop() {
+            if traits.insert(named_id.clone(), Traits::ALL).is_none() {
+                for member_id in self.members(&named_id) {
+                    self.named_types(&member_id, &mut pending);
+                }
+            }
+        }
+
+        if self.settings.minimal_derives {
+            let derives = impls_traits(&self.settings.derives);
+            for (named_id, named_traits) in traits.iter_mut() {
+                let type_entry = self.id_to_entry.get(named_id).unwrap();
+                *named_traits = minimal_traits(type_entry).or(derives);
+            }
+            return self.members_traits(member_ids, &traits);
+        }
+
+        loop {
+            let mut changed = false;
+            let named_ids = traits.keys().cloned().collect::<Vec<_>>();
+            for named_id in named_ids {
+                let named_traits = self.members_traits(&self.members(&named_id), &traits);
+                let entry = traits.get_mut(&named_id).unwrap();
+                if *entry != named_traits {
+                    *entry = named_traits;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        self.members_traits(member_ids, &traits)
+    }
+
+    fn members_traits(&self, member_ids: &[TypeId], named: &BTreeMap<TypeId, Traits>) -> Traits {
+        member_ids.iter().fold(Traits::ALL, |acc, member_id| {
+            acc.and(self.type_traits(member_id, named))
+        })
+    }
+
+    /// The named types that a type is made of, stopping at named types.
+    pub(crate) fn named_types(&self, type_id: &TypeId, named: &mut Vec<TypeId>) {
+        match self.id_to_entry.get(type_id) {
+            Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)) => {
+                named.push(type_id.clone())
+            }
+            Some(
+                TypeEntry::Option(id)
+                | TypeEntry::Array(id)
+                | TypeEntry::FixedArray(id, _)
+                | TypeEntry::Set(id)
+                | TypeEntry::Box(id)
+                | TypeEntry::Reference(id)
+                | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }),
+            ) => self.named_types(id, named),
+            Some(TypeEntry::Map(key_id, value_id)) => {
+                self.named_types(key_id, named);
+                self.named_types(value_id, named);
+            }
+            Some(TypeEntry::Tuple(ids)) => ids.iter().for_each(|id| self.named_types(id, named)),
+            _ => {}
+        }
+    }
+
+    /// The traits a type implements given those of the named types.
+    fn type_traits(&self, type_id: &TypeId, named: &BTreeMap<TypeId, Traits>) -> Traits {
+        match self.id_to_entry.get(type_id) {
+            Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)) => {
+                named.get(type_id).copied().unwrap_or(Traits::NONE)
+            }
+            Some(
+                TypeEntry::Option(id)
+                | TypeEntry::FixedArray(id, _)
+                | TypeEntry::Reference(id)
+                | TypeEntry::Alias(TypeEntryAlias { type_id: id, .. }),
+            ) => self.type_traits(id, named),
+            // Types on the heap can't be `Copy`.
+            Some(TypeEntry::Array(id) | TypeEntry::Set(id) | TypeEntry::Box(id)) => {
+                Traits::COMPARISONS.and(self.type_traits(id, named))
+            }
+            Some(TypeEntry::Map(key_id, value_id)) => {
+                // Only `BTreeMap`s can be ordered or hashed.
+                let map_traits = if self.map_type().ends_with("::collections::BTreeMap") {
+                    Traits::COMPARISONS
+                } else {
+                    Traits::PARTIAL_EQ.or(Traits::EQ)
+                };
+                map_traits
+                    .and(self.type_traits(key_id, named))
+                    .and(self.type_traits(value_id, named))
+            }
+            // The standard library implements the comparison and hashing
+            // traits for tuples of up to 12 items, and `Copy` for any tuple.
+            Some(TypeEntry::Tuple(ids)) => {
+                let tuple_traits = if ids.len() <= 12 {
+                    Traits::ALL
+                } else {
+                    Traits::COPY
+                };
+                tuple_traits.and(self.members_traits(ids, named))
+            }
+            Some(TypeEntry::Unit | TypeEntry::Integral(_)) => Traits::ALL,
+            Some(TypeEntry::String) => Traits::COMPARISONS,
+            Some(TypeEntry::Float(_)) => {
+                Traits::PARTIAL_EQ.or(Traits::PARTIAL_ORD).or(Traits::COPY)
+            }
+            Some(TypeEntry::BuiltIn(path) | TypeEntry::BuiltInWith(path, _, _)) => {
+                match self
+                    .settings
+                    .replace
+                    .values()
+                    .chain(
+                        self.settings
+                            .conversion
+                            .iter()
+                            .map(|(_, conversion)| conversion),
+                    )
+                    .find(|replacement| &replacement.replace_type == path)
+                {
+                    Some(replacement) => impls_traits(&replacement.impls),
+                    None => builtin_traits(path),
+                }
+            }
+            _ => Traits::NONE,
+        }
+    }
+}
+
+/// The traits we derive for a named type with minimal derives: enums of unit
+/// variants and string types that may be map keys need the comparison and
+/// hashing traits.
+pub(crate) fn minimal_traits(type_entry: &TypeEntry) -> Traits {
+    match type_entry {
+        TypeEntry::Enum(TypeEntryEnum { variants, .. })
+            if variants.iter().all(|variant| {
+                matches!(
+                    variant.details,
+                    VariantDetails::Simple
+                        | VariantDetails::Integer(_)
+                        | VariantDetails::Unknown
+                        | VariantDetails::Other(_)
+                )
+            }) =>
+        {
+            Traits::COMPARISONS
+        }
+        TypeEntry::Newtype(TypeEntryNewtype {
+            constraints: NewtypeConstraints::String { .. },
+            ..
+        }) => Traits::COMPARISONS,
+        _ => Traits::NONE,
+    }
+}
+
+/// The traits of the built-in types we produce.
+fn builtin_traits(path: &str) -> Traits {
+    match path {
+        "chrono::DateTime<chrono::offset::Utc>"
+        | "chrono::NaiveDate"
+        | "chrono::NaiveTime"
+        | "time::OffsetDateTime"
+        | "time::Date"
+        | "time::Time"
+        | "uuid::Uuid"
+        | "std::net::IpAddr"
+        | "std::net::Ipv4Addr"
+        | "std::net::Ipv6Addr"
+        | "rust_decimal::Decimal" => Traits::ALL,
+        "Vec<u8>" => Traits::COMPARISONS,
+        "serde_json::Value" | "serde_json::Number" => Traits::PARTIAL_EQ.or(Traits::EQ),
+        _ => Traits::NONE,
+    }
+}
+
+/// The traits among those that a type used in place of a generated one is
+/// said to implement, given by name or path, e.g. `Hash` or `std::hash::Hash`.
+fn impls_traits(impls: &[String]) -> Traits {
+    impls.iter().fold(Traits::NONE, |acc, imp| {
+        let name = imp.rsplit("::").next().unwrap().trim();
+        acc.or(match name {
+            "PartialOrd" => Traits::PARTIAL_ORD,
+            "Ord" => Traits::ORD,
+            "PartialEq" => Traits::PARTIAL_EQ,
+            "Eq" => Traits::EQ,
+            "Hash" => Traits::HASH,
+            "Copy" => Traits::COPY,
+            _ => Traits::NONE,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{MapType, TypeSpace, TypeSpaceSettings};
+
+    fn derives(schema: serde_json::Value, settings: &TypeSpaceSettings) -> Vec<String> {
+        let schema = serde_json::from_value::<RootSchema>(schema).unwrap();
+        let mut type_space = TypeSpace::new(settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        let code = type_space.to_stream().to_string().replace(' ', "");
+        code.split("#[derive(")
+            .skip(1)
+            .map(|rest| rest.split(")]").next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_derivable_traits() {
+        let schema = json!({
+            "definitions": {
+                "Counts": {
+                    "type": "object",
+                    "additionalProperties": { "type": "integer" }
+                },
+                "Kind": {
+                    "type": "string",
+                    "enum": ["circle", "square"]
+                },
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "label": { "type": "string" },
+                        "children": {
+                            "type": "array",
+                            "items": { "$ref": "#/definitions/Node" }
+                        },
+                        "parent": { "$ref": "#/definitions/Node" }
+                    }
+                },
+                "Point": {
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "number" },
+                        "y": { "type": "number" }
+                    },
+                    "required": ["x", "y"]
+                },
+                "Shape": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/Node" },
+                        { "$ref": "#/definitions/Point" }
+                    ]
+                }
+            }
+        });
+
+        let base = "Serialize,Deserialize,Debug,Clone";
+        let comparisons = "PartialOrd,Ord,PartialEq,Eq,Hash";
+        assert_eq!(
+            derives(schema.clone(), &TypeSpaceSettings::default()),
+            [
+                format!("{},PartialEq,Eq", base),
+                format!("{},Copy,{}", base, comparisons),
+                format!("{},{}", base, comparisons),
+                format!("{},Copy,PartialOrd,PartialEq", base),
+                format!("{},PartialOrd,PartialEq", base),
+            ]
+        );
+        assert_eq!(
+            derives(
+                schema.clone(),
+                TypeSpaceSettings::default().with_map_type(MapType::BTreeMap)
+            )[0],
+            format!("{},{}", base, comparisons),
+        );
+        assert_eq!(
+            derives(
+                schema,
+                TypeSpaceSettings::default().with_minimal_derives(true)
+            ),
+            [
+                base.to_string(),
+                format!("{},{}", base, comparisons),
+                base.to_string(),
+                base.to_string(),
+                base.to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2021 Oxide Computer Company
+
+use serde_json::{Map, Value};
+
+/// Whether a document declares itself a draft-04 schema.
+pub(crate) fn is_draft04(document: &Value) -> bool {
+    match document.get("$schema") {
+        Some(Value::String(schema)) => {
+            let schema = schema.trim_end_matches('#');
+            schema == "http://json-schema.org/draft-04/schema"
+                || schema == "https://json-schema.org/draft-04/schema"
+        }
+        _ => false,
+    }
+}
+
+/// Rewrite draft-04 keywords in the forms that later drafts (and we) use:
+/// - boolean `exclusiveMinimum` and `exclusiveMaximum` modify `minimum` and
+///   `maximum` rather than being bounds themselves
+/// - `id` rather than `$id`
+/// - `required` may be a boolean within a property's schema rather than a
+///   list of the object's properties
+/// - integer `enum` values may be written as numbers such as `1.0`
+pub(crate) fn normalize(value: &mut Value) {
+    match value {
+        Value::Object(schema) => {
+            normalize_schema(schema);
+            for (key, child) in schema.iter_mut() {
+                match (key.as_str(), child) {
+                    // These hold values rather than schemas.
+                    ("const" | "default" | "enum" | "examples", _) => {}
+                    // These map names to schemas.
+                    (
+                        "definitions" | "dependencies" | "patternProperties" | "properties",
+                        Value::Object(schemas),
+                    ) => schemas.values_mut().for_each(normalize),
+                    (_, child) => normalize(child),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(normalize),
+        _ => {}
+    }
+}
+
+fn normalize_schema(schema: &mut Map<String, Value>) {
+    for (exclusive, bound) in [
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        match schema.get(exclusive) {
+            Some(Value::Bool(true)) => match schema.remove(bound) {
+                Some(value) => {
+                    schema.insert(exclusive.to_string(), value);
+                }
+                None => {
+                    schema.remove(exclusive);
+                }
+            },
+            Some(Value::Bool(false)) => {
+                schema.remove(exclusive);
+            }
+            _ => {}
+        }
+    }
+
+    if !schema.contains_key("$id") {
+        if let Some(Value::String(_)) = schema.get("id") {
+            let id = schema.remove("id").unwrap();
+            schema.insert("$id".to_string(), id);
+        }
+    }
+
+    let mut required = Vec::new();
+    if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+        for (name, property) in properties.iter_mut() {
+            if let Value::Object(property) = property {
+                if let Some(Value::Bool(is_required)) = property.get("required") {
+                    if *is_required {
+                        required.push(name.clone());
+                    }
+                    property.remove("required");
+                }
+            }
+        }
+    }
+    if !required.is_empty() {
+        let list = schema
+            .entry("required")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(list) = list {
+            for name in required {
+                if !list.contains(&Value::String(name.clone())) {
+                    list.push(Value::String(name));
+                }
+            }
+        }
+    }
+
+    if schema.get("type") == Some(&Value::String("integer".to_string())) {
+        if let Some(Value::Array(values)) = schema.get_mut("enum") {
+            for value in values {
+                if let Some(n) = value.as_f64().filter(|n| n.fract() == 0.0) {
+                    if !value.is_i64() && !value.is_u64() {
+                        *value = Value::from(n as i64);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{is_draft04, normalize};
+
+    #[test]
+    fn test_normalize() {
+        let mut document = json!({
+            "$schema": "http://json-schema.org/draft-04/schema#",
+            "id": "http://example.com/sensor.json",
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "required": true },
+                "note": { "type": "string", "required": false },
+                "channel": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "exclusiveMinimum": false,
+                    "maximum": 256,
+                    "exclusiveMaximum": true
+                },
+                "level": { "type": "integer", "enum": [1.0, 2, 3.0] }
+            },
+            "definitions": {
+                "Reading": {
+                    "type": "number",
+                    "minimum": 0,
+                    "exclusiveMinimum": true,
+                    "default": { "id": "not an id", "exclusiveMaximum": true }
+                }
+            }
+        });
+        assert!(is_draft04(&document));
+
+        normalize(&mut document);
+        assert_eq!(
+            document,
+            json!({
+                "$schema": "http://json-schema.org/draft-04/schema#",
+                "$id": "http://example.com/sensor.json",
+                "type": "object",
+                "required": ["id"],
+                "properties": {
+                    "id": { "type": "string" },
+                    "note": { "type": "string" },
+                    "channel": { "type": "integer", "minimum": 0, "exclusiveMaximum": 256 },
+                    "level": { "type": "integer", "enum": [1, 2, 3] }
+                },
+                "definitions": {
+                    "Reading": {
+                        "type": "number",
+                        "exclusiveMinimum": 0,
+                        "default": { "id": "not an id", "exclusiveMaximum": true }
+                    }
+                }
+            })
+        );
+
+        assert!(!is_draft04(&json!({
+            "$schema": "http://json-schema.org/draft-07/schema#"
+        })));
+    }
+}
@@ -3,17 +3,24 @@
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use convert_case::{Case, Casing};
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::{Ident, Literal, TokenStream};
 use quote::{format_ident, quote};
 use schemars::schema::{
     ArrayValidation, InstanceType, Metadata, ObjectValidation, Schema, SchemaObject, SingleOrVec,
+    SubschemaValidation,
 };
 
 use crate::{
     structs::output_struct_property,
-    type_entry::{EnumTagType, TypeEntryEnum, Variant, VariantDetails},
-    util::{constant_string_value, get_type_name, metadata_description, recase, schema_is_named},
-    Name, Result, TypeEntry, TypeSpace,
+    type_entry::{
+        deprecated_attr, EnumTagType, SerdeNaming, StructProperty, TypeEntryAlias, TypeEntryEnum,
+        Variant, VariantDetails,
+    },
+    util::{
+        constant_string_value, enum_descriptions, get_type_name, metadata_description,
+        non_null_subschemas, recase, sanitize, schema_is_named, string_format, unraw,
+    },
+    Error, Name, Result, SchemaLocation, TypeEntry, TypeId, TypeSpace, Warning,
 };
 
 impl TypeSpace {
@@ -28,25 +35,38 @@ impl TypeSpace {
         }
         // Let's be as general as possible and consider the possibility that
         // more than one subschema is the simple null.
-        let non_nulls = subschemas
-            .iter()
-            .filter(|schema| {
-                !matches!(schema, Schema::Object(SchemaObject {
-                instance_type: Some(SingleOrVec::Single(single)),
-                ..
-            }) if single.as_ref() == &InstanceType::Null)
-            })
-            .collect::<Vec<_>>();
+        let non_nulls = non_null_subschemas(subschemas)?;
+
+        // Several other subschemas become an option of the enum of them.
+        let non_null = match non_nulls.as_slice() {
+            [non_null] => non_null.clone(),
+            _ => Schema::Object(SchemaObject {
+                subschemas: Some(Box::new(SubschemaValidation {
+                    one_of: Some(non_nulls),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+        };
 
-        if non_nulls.len() != 1 {
-            return None;
-        }
+        let (type_entry, _) = self.convert_option(type_name, metadata, &non_null).ok()?;
 
-        let non_null = non_nulls.into_iter().next()?;
+        Some(type_entry)
+    }
 
-        let (type_entry, _) = self.convert_option(type_name, metadata, non_null).ok()?;
+    /// A one of an IPv4 address and an IPv6 address is simply an IP address.
+    pub(crate) fn maybe_ip_addr(&self, subschemas: &[Schema]) -> Option<TypeEntry> {
+        let mut formats = subschemas
+            .iter()
+            .map(string_format)
+            .collect::<Option<Vec<_>>>()?;
+        formats.sort_unstable();
 
-        Some(type_entry)
+        if formats == ["ipv4", "ipv6"] {
+            Some(TypeEntry::new_builtin("std::net::IpAddr"))
+        } else {
+            None
+        }
     }
 
     pub(crate) fn maybe_externally_tagged_enum(
@@ -73,13 +93,10 @@ impl TypeSpace {
             .iter()
             .map(|schema| -> Option<Vec<ProtoVariant<'_>>> {
                 match schema {
-                    // It shouldn't be possible to encounter the "match
-                    // anything" schema here.
-                    Schema::Bool(true) => unreachable!(),
-                    // It would be odd to see the "match nothing" schema here.
-                    // Let's abort for now, but we could implement this as a
-                    // variant that we'd never use... I guess.
-                    Schema::Bool(false) => todo!(),
+                    // `convert_one_of` leaves the "match nothing" schema out
+                    // and gives the "match anything" schema an untagged
+                    // variant.
+                    Schema::Bool(_) => None,
 
                     // Strings must be simple enumerations.
                     Schema::Object(SchemaObject {
@@ -94,17 +111,21 @@ impl TypeSpace {
                         array: None,
                         object: None,
                         reference: None,
-                        extensions: _,
+                        extensions,
                     }) if single.as_ref() == &InstanceType::String => {
+                        // Each value may have its own description.
+                        let descriptions = enum_descriptions("", extensions, values).ok()?;
                         // Confirm that all values are, in fact, simple strings.
                         // Simple strings become simple variants. If any is not
                         // a string, we'll end up returning None
                         values
                             .iter()
-                            .map(|value| {
+                            .zip(descriptions)
+                            .map(|(value, description)| {
                                 value.as_str().map(|variant_name| ProtoVariant::Simple {
                                     name: variant_name,
-                                    description: metadata_description(metadata),
+                                    description: description
+                                        .or_else(|| metadata_description(metadata)),
                                 })
                             })
                             .collect()
@@ -198,6 +219,7 @@ impl TypeSpace {
                         name,
                         rename,
                         description,
+                        deprecated: false,
                         details: VariantDetails::Simple,
                     })
                 }
@@ -223,6 +245,7 @@ impl TypeSpace {
                         name,
                         rename,
                         description,
+                        deprecated: false,
                         details,
                     })
                 }
@@ -370,6 +393,17 @@ impl TypeSpace {
         metadata: &Option<Box<Metadata>>,
         subschemas: &[Schema],
     ) -> Option<TypeEntry> {
+        // The variants' documentation comes from the subschemas as they are.
+        let descriptions = subschemas
+            .iter()
+            .map(|schema| self.variant_description(schema))
+            .collect::<Vec<_>>();
+        // Subschemas may reference the definitions of objects.
+        let (subschemas, definition_names): (Vec<_>, Vec<_>) = subschemas
+            .iter()
+            .map(|schema| self.resolve_object(schema))
+            .unzip();
+
         // All subschemas must be objects and all objects must have a *fixed-value*
         // required property in common. To detect this, we look at all such
         // properties along with the specific values.
@@ -381,6 +415,7 @@ impl TypeSpace {
                     validation
                         .properties
                         .iter()
+                        .filter(|(prop_name, _)| validation.required.contains(*prop_name))
                         .filter_map(|(prop_name, prop_type)| {
                             constant_string_value(prop_type).map(|value| {
                                 // Tuple with the name and a set with a single value
@@ -417,10 +452,13 @@ impl TypeSpace {
         constant_value_properties.sort();
         let tag = constant_value_properties.first()?;
 
+        let enum_name = get_type_name(&type_name, metadata, Case::Pascal);
         let mut deny_unknown_fields = false;
         let variants = subschemas
             .iter()
-            .map(|schema| {
+            .zip(definition_names)
+            .zip(descriptions)
+            .map(|((schema, definition_name), description)| {
                 // We've already validated this; we just need to pluck out the
                 // pieces we need to construct the variant.
                 if let Schema::Object(SchemaObject {
@@ -435,7 +473,16 @@ impl TypeSpace {
                         None => {}
                         _ => unreachable!(),
                     }
-                    Ok(self.internal_variant(validation, tag)?)
+                    let variant = self.internal_variant(
+                        enum_name.as_deref(),
+                        definition_name,
+                        validation,
+                        tag,
+                    )?;
+                    Ok(Variant {
+                        description,
+                        ..variant
+                    })
                 } else {
                     unreachable!();
                 }
@@ -452,7 +499,47 @@ impl TypeSpace {
         ))
     }
 
-    fn internal_variant(&mut self, validation: &ObjectValidation, tag: &str) -> Result<Variant> {
+    /// The documentation of the variant for a subschema: its description, or
+    /// else its title, followed by that of the definition it references.
+    fn variant_description(&self, schema: &Schema) -> Option<String> {
+        let own = match schema {
+            Schema::Object(SchemaObject { metadata, .. }) => metadata_description(metadata),
+            Schema::Bool(_) => None,
+        };
+        let referenced = match reference_key(schema).and_then(|key| self.definitions.get(key)) {
+            Some(Schema::Object(SchemaObject { metadata, .. })) => metadata_description(metadata),
+            _ => None,
+        };
+        match (own, referenced) {
+            (Some(own), Some(referenced)) if own != referenced => {
+                Some(format!("{}\n\n{}", own, referenced))
+            }
+            (own, referenced) => own.or(referenced),
+        }
+    }
+
+    /// If the schema is a reference to the definition of an object, return
+    /// that definition along with the name of its type; otherwise return the
+    /// schema.
+    fn resolve_object(&self, schema: &Schema) -> (Schema, Option<String>) {
+        if let Some(key) = reference_key(schema) {
+            if let Some(resolved) = self.definitions.get(key) {
+                if get_object(resolved).is_some() {
+                    let name = get_type_name(&Name::Required(key.to_string()), &None, Case::Pascal);
+                    return (resolved.clone(), name);
+                }
+            }
+        }
+        (schema.clone(), None)
+    }
+
+    fn internal_variant(
+        &mut self,
+        enum_name: Option<&str>,
+        definition_name: Option<String>,
+        validation: &ObjectValidation,
+        tag: &str,
+    ) -> Result<Variant> {
         if validation.properties.len() == 1 {
             let (tag_name, schema) = validation.properties.iter().next().unwrap();
             let variant_name = constant_string_value(schema).unwrap();
@@ -466,6 +553,7 @@ impl TypeSpace {
                 name,
                 rename,
                 description: None,
+                deprecated: false,
                 details: VariantDetails::Simple,
             };
             Ok(variant)
@@ -479,23 +567,179 @@ impl TypeSpace {
             new_validation.properties.remove(tag);
             new_validation.required.remove(tag);
 
-            let (properties, _) = self.struct_members(None, &new_validation)?;
+            // Name the types of properties after the variant or, if it's
+            // from a definition, as that definition does so that the types
+            // are shared.
+            let variant_type_name = definition_name
+                .or_else(|| enum_name.map(|enum_name| format!("{}{}", enum_name, name)));
+            let (properties, _) = self.struct_members(variant_type_name, &new_validation)?;
             let variant = Variant {
                 name,
                 rename,
                 description: None,
+                deprecated: false,
                 details: VariantDetails::Struct(properties),
             };
             Ok(variant)
         }
     }
 
+    /// Produce an internally tagged enum for a `oneOf` with an OpenAPI
+    /// `discriminator`. Its `propertyName` is the tag and its `mapping`
+    /// associates values of the tag with the subschemas they select; a
+    /// subschema that no mapping selects is selected by its constant value for
+    /// the tag or else by the name of the definition it references.
+    pub(crate) fn discriminated_enum(
+        &mut self,
+        type_name: Name,
+        metadata: &Option<Box<schemars::schema::Metadata>>,
+        subschemas: &[Schema],
+        discriminator: &serde_json::Value,
+    ) -> Result<TypeEntry> {
+        let enum_name = get_type_name(&type_name, metadata, Case::Pascal);
+        let invalid = |reason: String| {
+            Error::InvalidSchema(
+                enum_name
+                    .clone()
+                    .unwrap_or_else(|| "an anonymous type".to_string()),
+                reason,
+            )
+        };
+
+        let tag = discriminator
+            .get("propertyName")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| invalid("the discriminator has no propertyName".to_string()))?;
+        let mapping = match discriminator.get("mapping") {
+            None => Vec::new(),
+            Some(serde_json::Value::Object(mapping)) => mapping
+                .iter()
+                .map(|(value, target)| match target.as_str() {
+                    Some(target) => Ok((value.clone(), target.to_string())),
+                    None => Err(invalid(format!(
+                        "the discriminator mapping for {:?} is not a string",
+                        value
+                    ))),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => {
+                return Err(invalid(
+                    "the discriminator mapping is not an object".to_string(),
+                ))
+            }
+        };
+
+        // Each mapping must select one of the subschemas.
+        if let Some((value, target)) = mapping.iter().find(|(_, target)| {
+            !subschemas
+                .iter()
+                .any(|schema| reference_matches(schema, target))
+        }) {
+            return Err(invalid(format!(
+                "the discriminator mapping for {:?} refers to {}, which is not in the oneOf",
+                value, target
+            )));
+        }
+
+        let mut deny_unknown_fields = false;
+        let mut variants = Vec::new();
+        for (index, schema) in subschemas.iter().enumerate() {
+            let description = self.variant_description(schema);
+            let (resolved, definition_name) = self.resolve_object(schema);
+            let mut validation = match get_object(&resolved) {
+                Some((_, validation)) => validation.clone(),
+                None => {
+                    return Err(invalid(format!(
+                        "the subschemas of a oneOf with a discriminator must be objects, \
+                         but one is {}",
+                        serde_json::to_string(schema).unwrap()
+                    )))
+                }
+            };
+
+            let mut values = mapping
+                .iter()
+                .filter(|(_, target)| reference_matches(schema, target))
+                .map(|(value, _)| value.clone())
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                let value = validation
+                    .properties
+                    .get(tag)
+                    .and_then(constant_string_value)
+                    .or_else(|| reference_key(schema).map(str::to_string))
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "a subschema of the oneOf has no value for the discriminator {:?}",
+                            tag
+                        ))
+                    })?;
+                values.push(value);
+            }
+
+            // The tag isn't part of the data for the variant.
+            validation.properties.remove(tag);
+            validation.required.remove(tag);
+            if let Some(Schema::Bool(false)) = validation.additional_properties.as_deref() {
+                deny_unknown_fields = true;
+            }
+
+            for value in values {
+                let (name, rename) = recase(value, Case::Pascal);
+                let details = if validation.properties.is_empty() {
+                    VariantDetails::Simple
+                } else {
+                    // As with other internally tagged enums, name the types of
+                    // properties as their definition does so they're shared.
+                    let variant_type_name = definition_name.clone().or_else(|| {
+                        enum_name
+                            .as_ref()
+                            .map(|enum_name| format!("{}{}", enum_name, name))
+                    });
+                    let (properties, _) = self
+                        .within(["oneOf".into(), index.into()], |type_space| {
+                            type_space.struct_members(variant_type_name, &validation)
+                        })?;
+                    VariantDetails::Struct(properties)
+                };
+                variants.push(Variant {
+                    name,
+                    rename,
+                    description: description.clone(),
+                    deprecated: false,
+                    details,
+                });
+            }
+        }
+
+        Ok(TypeEntryEnum::from_metadata(
+            type_name,
+            metadata,
+            EnumTagType::Internal {
+                tag: tag.to_string(),
+            },
+            variants,
+            deny_unknown_fields,
+        ))
+    }
+
     pub(crate) fn maybe_adjacently_tagged_enum(
         &mut self,
         type_name: Name,
         metadata: &Option<Box<schemars::schema::Metadata>>,
         subschemas: &[Schema],
     ) -> Option<TypeEntry> {
+        // The variants' documentation comes from the subschemas as they are.
+        let descriptions = subschemas
+            .iter()
+            .map(|schema| self.variant_description(schema))
+            .collect::<Vec<_>>();
+        // Subschemas may reference the definitions of objects.
+        let (subschemas, definition_names): (Vec<_>, Vec<_>) = subschemas
+            .iter()
+            .map(|schema| self.resolve_object(schema))
+            .unzip();
+
         // All subschemas need to be objects with at most two properties: a
         // constant, required property (the tag) and an optional property for
         // content. Naturally, those two properties need to be the same for each
@@ -515,8 +759,8 @@ impl TypeSpace {
                         .collect::<BTreeSet<_>>();
                     let properties = validation
                         .properties
-                        .iter()
-                        .map(|(prop_name, _)| prop_name.clone())
+                        .keys()
+                        .cloned()
                         .collect::<BTreeSet<_>>();
 
                     Some((constants, properties))
@@ -545,14 +789,28 @@ impl TypeSpace {
             return None;
         }
 
-        let content = content_props.difference(&tag_props).cloned().next()?;
+        let content = content_props.difference(&tag_props).next().cloned()?;
         let tag = tag_props.into_iter().next()?;
 
+        // Each subschema needs its own value for the tag.
+        let tag_values = subschemas
+            .iter()
+            .filter_map(|schema| {
+                let (_, validation) = get_object(schema)?;
+                constant_string_value(validation.properties.get(&tag)?)
+            })
+            .collect::<BTreeSet<_>>();
+        if tag_values.len() != subschemas.len() {
+            return None;
+        }
+
         let mut deny_unknown_fields = false;
 
         let variants = subschemas
             .iter()
-            .map(|schema| {
+            .zip(definition_names)
+            .zip(descriptions)
+            .map(|((schema, definition_name), description)| {
                 // We've already validated this; we just need to pluck out the
                 // pieces we need to construct the variant.
                 if let Schema::Object(SchemaObject {
@@ -560,9 +818,25 @@ impl TypeSpace {
                     ..
                 }) = schema
                 {
-                    let (variant, deny) = self.adjacent_variant(validation, &tag, &content)?;
+                    // Objects that allow only the tag and content mean that
+                    // the enum denies unknown fields.
+                    if let Some(Schema::Bool(false)) =
+                        validation.additional_properties.as_ref().map(Box::as_ref)
+                    {
+                        deny_unknown_fields = true;
+                    }
+                    let (variant, deny) = self.adjacent_variant(
+                        type_name.clone(),
+                        definition_name,
+                        validation,
+                        &tag,
+                        &content,
+                    )?;
                     deny_unknown_fields |= deny;
-                    Ok(variant)
+                    Ok(Variant {
+                        description,
+                        ..variant
+                    })
                 } else {
                     unreachable!();
                 }
@@ -581,6 +855,8 @@ impl TypeSpace {
 
     fn adjacent_variant(
         &mut self,
+        type_name: Name,
+        definition_name: Option<String>,
         validation: &ObjectValidation,
         tag: &str,
         content: &str,
@@ -598,6 +874,7 @@ impl TypeSpace {
                 name,
                 rename,
                 description: None,
+                deprecated: false,
                 details: VariantDetails::Simple,
             };
             Ok((variant, false))
@@ -606,13 +883,24 @@ impl TypeSpace {
             let variant_name = constant_string_value(tag_schema).unwrap();
             let (name, rename) = recase(variant_name, Case::Pascal);
 
+            // Name the content type after the variant or, if it's from a
+            // definition, as that definition names its property so that the
+            // types are shared.
             let content_schema = validation.properties.get(content).unwrap();
-            let (details, deny) = self.external_variant(Name::Unknown, &name, content_schema)?;
+            let (details, deny) = match definition_name {
+                Some(definition_name) => self.external_variant(
+                    Name::Suggested(definition_name),
+                    content,
+                    content_schema,
+                )?,
+                None => self.external_variant(type_name, &name, content_schema)?,
+            };
 
             let variant = Variant {
                 name,
                 rename,
                 description: None,
+                deprecated: false,
                 details,
             };
             Ok((variant, deny))
@@ -640,7 +928,10 @@ impl TypeSpace {
     /// }
     /// ```
     ///
-    /// We even do a step better by eliminating common prefixes:
+    /// The titles of the subschemas, where they have them, take precedence
+    /// over the names of the types they refer to. We even do a step better
+    /// by eliminating common prefixes, so long as the names that remain are
+    /// distinct identifiers:
     ///
     /// ```compile_fail
     /// enum MyEnum {
@@ -656,9 +947,6 @@ impl TypeSpace {
     ) -> Result<TypeEntry> {
         let tmp_type_name = get_type_name(&type_name, metadata, Case::Pascal);
 
-        let mut names_from_variants = true;
-        let mut common_prefix = None;
-
         let mut deny_unknown_fields = false;
 
         // Gather the variant details along with an Option of its "good" name.
@@ -671,46 +959,57 @@ impl TypeSpace {
                     Some(name) => Name::Suggested(name.clone()),
                     None => Name::Unknown,
                 };
-                let (details, deny) =
-                    self.external_variant(sub_type_name, &variant_name, schema)?;
+                let (details, deny) = self.within([idx.into()], |type_space| {
+                    type_space.external_variant(sub_type_name, &variant_name, schema)
+                })?;
                 deny_unknown_fields |= deny;
-                let good_name = schema_is_named(schema);
-                match (&good_name, common_prefix.as_ref()) {
-                    (None, _) => {
-                        names_from_variants = false;
-                    }
-                    (Some(name), None) => {
-                        common_prefix = Some(name.clone());
-                    }
-                    (Some(name), Some(prefix)) => {
-                        common_prefix = Some(get_common_prefix(name, prefix));
-                    }
-                }
+                let good_name = match schema {
+                    Schema::Object(SchemaObject {
+                        metadata: Some(metadata),
+                        ..
+                    }) if metadata.title.is_some() => metadata
+                        .title
+                        .as_deref()
+                        .map(|title| sanitize(title, Case::Pascal)),
+                    _ => schema_is_named(schema),
+                };
+                let description = self.variant_description(schema);
 
-                Ok((details, good_name))
+                Ok((details, good_name, description))
             })
             .collect::<Result<Vec<_>>>()?;
 
-        let common_prefix_index = match &common_prefix {
-            Some(prefix) => prefix.len(),
-            None => 0,
+        // Variants of the very same types as earlier ones would never
+        // deserialize, as serde tries them in order, so they go.
+        let mut distinct = Vec::<(VariantDetails, Option<String>, Option<String>)>::new();
+        for (details, good_name, description) in variant_details {
+            if !distinct.iter().any(|(earlier, _, _)| *earlier == details) {
+                distinct.push((details, good_name, description));
+            }
+        }
+        let variant_details = distinct;
+
+        // The variants take their good names only if they all have them.
+        let names = match variant_details
+            .iter()
+            .map(|(_, good_name, _)| good_name.clone())
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(names) => without_common_prefix(names),
+            None => (0..variant_details.len())
+                .map(|idx| format!("Variant{}", idx))
+                .collect(),
         };
 
         let variants = variant_details
             .into_iter()
-            .enumerate()
-            .map(|(idx, (details, good_name))| {
-                let name = if names_from_variants {
-                    (&good_name.unwrap()[common_prefix_index..]).to_string()
-                } else {
-                    format!("Variant{}", idx)
-                };
-                Variant {
-                    name,
-                    rename: None,
-                    description: None,
-                    details,
-                }
+            .zip(names)
+            .map(|((details, _, description), name)| Variant {
+                name,
+                rename: None,
+                description,
+                deprecated: false,
+                details,
             })
             .collect();
 
@@ -722,6 +1021,154 @@ impl TypeSpace {
             deny_unknown_fields,
         ))
     }
+
+    /// Warn of the variants of untagged enums that would never deserialize,
+    /// as serde tries them in order and an earlier one has the same shape,
+    /// e.g. structs of other names but the same properties, once the types
+    /// they refer to are all converted. (`untagged_enum` drops those of the
+    /// very same types as earlier ones.)
+    pub(crate) fn warn_ambiguous_variants(&mut self) {
+        let definitions = self
+            .ref_to_id
+            .iter()
+            .map(|(ref_name, type_id)| (type_id, ref_name))
+            .collect::<BTreeMap<_, _>>();
+        let mut warnings = Vec::new();
+        for (type_id, type_entry) in &self.id_to_entry {
+            let (name, variants) = match type_entry {
+                TypeEntry::Enum(TypeEntryEnum {
+                    name,
+                    tag_type: EnumTagType::Untagged,
+                    variants,
+                    ..
+                }) => (name, variants),
+                _ => continue,
+            };
+            for (index, variant) in variants.iter().enumerate() {
+                let earlier = variants[..index]
+                    .iter()
+                    .find(|earlier| self.same_variant_shape(&earlier.details, &variant.details));
+                if let Some(earlier) = earlier {
+                    let path = match definitions.get(type_id) {
+                        Some(ref_name) => vec!["definitions".into(), ref_name.as_str().into()],
+                        None => Vec::new(),
+                    };
+                    warnings.push(Warning {
+                        location: SchemaLocation { file: None, path },
+                        keyword: "oneOf".to_string(),
+                        message: format!(
+                            "variant {} of {} has the shape of {} so it never deserializes; a \
+                             discriminator property would tell them apart",
+                            variant.name, name, earlier.name,
+                        ),
+                    });
+                }
+            }
+        }
+        for warning in warnings {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+
+    fn same_variant_shape(&self, a: &VariantDetails, b: &VariantDetails) -> bool {
+        let mut seen = BTreeSet::new();
+        match (a, b) {
+            (VariantDetails::Simple, VariantDetails::Simple) => true,
+            (VariantDetails::Tuple(a), VariantDetails::Tuple(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| self.same_shape(a, b, &mut seen))
+            }
+            (VariantDetails::Struct(a), VariantDetails::Struct(b)) => {
+                self.same_properties(a, b, &mut seen)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether values of the two types (de)serialize alike: they're the same
+    /// type, or structs, newtypes, and the like of types that are. `seen`
+    /// holds the pairs we're already comparing, which are alike unless
+    /// something else about them differs, as for recursive types.
+    fn same_shape(&self, a: &TypeId, b: &TypeId, seen: &mut BTreeSet<(TypeId, TypeId)>) -> bool {
+        if a == b || !seen.insert((a.clone(), b.clone())) {
+            return true;
+        }
+        match (self.id_to_entry.get(a), self.id_to_entry.get(b)) {
+            // Boxes and aliases (de)serialize as what they hold.
+            (
+                Some(
+                    TypeEntry::Box(a)
+                    | TypeEntry::Reference(a)
+                    | TypeEntry::Alias(TypeEntryAlias { type_id: a, .. }),
+                ),
+                _,
+            ) => self.same_shape(a, b, seen),
+            (
+                _,
+                Some(
+                    TypeEntry::Box(b)
+                    | TypeEntry::Reference(b)
+                    | TypeEntry::Alias(TypeEntryAlias { type_id: b, .. }),
+                ),
+            ) => self.same_shape(a, b, seen),
+            (Some(TypeEntry::Struct(a)), Some(TypeEntry::Struct(b))) => {
+                a.deny_unknown_fields == b.deny_unknown_fields
+                    && self.same_properties(&a.properties, &b.properties, seen)
+            }
+            (Some(TypeEntry::Newtype(a)), Some(TypeEntry::Newtype(b))) => {
+                a.constraints == b.constraints && self.same_shape(&a.type_id, &b.type_id, seen)
+            }
+            (Some(TypeEntry::Option(a)), Some(TypeEntry::Option(b)))
+            | (Some(TypeEntry::Array(a)), Some(TypeEntry::Array(b)))
+            | (Some(TypeEntry::Set(a)), Some(TypeEntry::Set(b))) => self.same_shape(a, b, seen),
+            (Some(TypeEntry::FixedArray(a, a_len)), Some(TypeEntry::FixedArray(b, b_len))) => {
+                a_len == b_len && self.same_shape(a, b, seen)
+            }
+            (Some(TypeEntry::Map(a_key, a_value)), Some(TypeEntry::Map(b_key, b_value))) => {
+                self.same_shape(a_key, b_key, seen) && self.same_shape(a_value, b_value, seen)
+            }
+            (Some(TypeEntry::Tuple(a)), Some(TypeEntry::Tuple(b))) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.same_shape(a, b, seen))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the properties are alike by their serialized names, how
+    /// they're serialized, and their types.
+    fn same_properties(
+        &self,
+        a: &[StructProperty],
+        b: &[StructProperty],
+        seen: &mut BTreeSet<(TypeId, TypeId)>,
+    ) -> bool {
+        let by_name = |properties: &[StructProperty]| {
+            let mut properties = properties
+                .iter()
+                .map(|property| {
+                    let name = match &property.serde_naming {
+                        SerdeNaming::Rename(name) => Some(name.clone()),
+                        SerdeNaming::None => Some(property.name.clone()),
+                        SerdeNaming::Flatten => None,
+                    };
+                    (name, property.serde_rules.clone(), property.type_id.clone())
+                })
+                .collect::<Vec<_>>();
+            properties.sort();
+            properties
+        };
+        let (a, b) = (by_name(a), by_name(b));
+        a.len() == b.len()
+            && a.iter()
+                .zip(&b)
+                .all(|((a_name, a_rules, a_id), (b_name, b_rules, b_id))| {
+                    a_name == b_name && a_rules == b_rules && self.same_shape(a_id, b_id, seen)
+                })
+    }
 }
 
 /// Internally and adjacently tagged enums expect their subschemas to be
@@ -731,7 +1178,7 @@ fn get_object(schema: &Schema) -> Option<(Option<&Metadata>, &ObjectValidation)>
     match schema {
         Schema::Object(SchemaObject {
             metadata,
-            instance_type: Some(SingleOrVec::Single(single)),
+            instance_type,
             format: None,
             enum_values: None,
             const_value: None,
@@ -742,7 +1189,9 @@ fn get_object(schema: &Schema) -> Option<(Option<&Metadata>, &ObjectValidation)>
             object: Some(validation),
             reference: None,
             extensions: _,
-        }) if single.as_ref() == &InstanceType::Object
+        }) if instance_type
+            .as_ref()
+            .is_none_or(|it| it == &SingleOrVec::from(InstanceType::Object))
             && schema_none_or_false(&validation.additional_properties)
             && validation.max_properties.is_none()
             && validation.min_properties.is_none()
@@ -757,6 +1206,34 @@ fn get_object(schema: &Schema) -> Option<(Option<&Metadata>, &ObjectValidation)>
     }
 }
 
+/// The name of the definition that a schema references, if it's a reference.
+fn reference_key(schema: &Schema) -> Option<&str> {
+    match schema {
+        Schema::Object(SchemaObject {
+            reference: Some(reference),
+            ..
+        }) => Some(match reference.rfind('/') {
+            Some(idx) => &reference[idx + 1..],
+            None => reference,
+        }),
+        _ => None,
+    }
+}
+
+/// Whether a schema references the target of a discriminator mapping, which
+/// may be either a reference or just the name of a definition.
+fn reference_matches(schema: &Schema, target: &str) -> bool {
+    match schema {
+        Schema::Object(SchemaObject {
+            reference: Some(reference),
+            ..
+        }) => {
+            reference == target || (!target.contains('/') && reference_key(schema) == Some(target))
+        }
+        _ => false,
+    }
+}
+
 // We infer from a Some(Schema::Bool(false)) or None value that either nothing
 // or nothing of importance is in the additional properties.
 fn schema_none_or_false(additional_properties: &Option<Box<Schema>>) -> bool {
@@ -766,6 +1243,31 @@ fn schema_none_or_false(additional_properties: &Option<Box<Schema>>) -> bool {
     )
 }
 
+/// The names without the prefix that they all share, if what remains of each
+/// is an identifier and they stay distinct, e.g. `Create` and `Attach` for
+/// `DiskCreate` and `DiskAttach`; otherwise the names as they are.
+fn without_common_prefix(names: Vec<String>) -> Vec<String> {
+    let prefix = match names.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.clone(), |prefix, name| {
+            get_common_prefix(name, &prefix)
+        }),
+        None => return names,
+    };
+    let stripped = names
+        .iter()
+        .map(|name| {
+            let rest = name.strip_prefix(&prefix)?;
+            (sanitize(rest, Case::Pascal) == rest).then(|| rest.to_string())
+        })
+        .collect::<Option<Vec<_>>>();
+    match stripped {
+        Some(stripped) if stripped.iter().collect::<BTreeSet<_>>().len() == stripped.len() => {
+            stripped
+        }
+        _ => names,
+    }
+}
+
 /// Get the string that represents the common prefix, considering only
 /// case-relevant boundaries.
 fn get_common_prefix(name: &str, prefix: &str) -> String {
@@ -779,46 +1281,87 @@ fn get_common_prefix(name: &str, prefix: &str) -> String {
         .to_case(Case::Pascal)
 }
 
-pub(crate) fn output_variant(variant: &Variant, type_space: &TypeSpace) -> TokenStream {
+pub(crate) fn output_variant(
+    type_name: &str,
+    variant: &Variant,
+    type_space: &TypeSpace,
+) -> TokenStream {
     let name = format_ident!("{}", variant.name);
     let doc = match &variant.description {
         Some(s) => quote! {#[doc = #s]},
         None => quote! {},
     };
+    let deprecated = deprecated_attr(variant.deprecated, &variant.description);
     let rename = match &variant.rename {
         Some(s) => quote! { #[serde(rename = #s)]},
         None => quote! {},
     };
     match &variant.details {
         VariantDetails::Simple => quote! {
+            #doc
+            #deprecated
+            #rename
+            #name,
+        },
+
+        VariantDetails::Unknown => quote! {
             #doc
             #rename
+            #[serde(other)]
             #name,
         },
 
+        VariantDetails::Other(type_id) => {
+            let type_name = type_space
+                .id_to_entry
+                .get(type_id)
+                .unwrap()
+                .type_ident(type_space, false);
+            quote! {
+                #doc
+                #[serde(untagged)]
+                #name(#type_name),
+            }
+        }
+
+        VariantDetails::Integer(value) => {
+            let value = Literal::i64_unsuffixed(*value);
+            quote! {
+                #doc
+                #deprecated
+                #name = #value,
+            }
+        }
+
         VariantDetails::Tuple(tuple) => {
             let types = tuple
                 .iter()
                 .map(|type_id| {
                     let item_type = type_space.id_to_entry.get(type_id).unwrap();
                     let type_name = item_type.type_ident(type_space, false);
-                    quote! { #type_name }
+                    let serde = item_type
+                        .serde_with(type_space)
+                        .map(|module| quote! { #[serde(with = #module)] });
+                    quote! { #serde #type_name }
                 })
                 .collect::<Vec<_>>();
             quote! {
                 #doc
+                #deprecated
                 #rename
                 #name(#(#types),*),
             }
         }
 
         VariantDetails::Struct(props) => {
+            let owner = format!("{}{}", type_name, variant.name);
             let properties = props
                 .iter()
-                .map(|prop| output_struct_property(prop, type_space, false))
+                .map(|prop| output_struct_property(prop, &owner, type_space, false))
                 .collect::<Vec<_>>();
             quote! {
                 #doc
+                #deprecated
                 #rename
                 #name {
                     #(#properties)*
@@ -828,32 +1371,120 @@ pub(crate) fn output_variant(variant: &Variant, type_space: &TypeSpace) -> Token
     }
 }
 
+/// A `Debug` impl for an enum with struct variants of redacted properties,
+/// as `structs::redacted_debug` gives for structs; `None` if there are no
+/// such properties.
+pub(crate) fn redacted_enum_debug(type_name: &Ident, variants: &[Variant]) -> Option<TokenStream> {
+    let redacted = variants.iter().any(|variant| {
+        matches!(
+            &variant.details,
+            VariantDetails::Struct(properties) if properties.iter().any(|prop| prop.redacted)
+        )
+    });
+    if !redacted {
+        return None;
+    }
+    let arms = variants.iter().map(|variant| {
+        let name = format_ident!("{}", variant.name);
+        let label = variant.name.as_str();
+        match &variant.details {
+            VariantDetails::Simple | VariantDetails::Integer(_) | VariantDetails::Unknown => {
+                quote! { Self::#name => f.write_str(#label) }
+            }
+            VariantDetails::Other(_) => quote! {
+                Self::#name(value) => f.debug_tuple(#label).field(value).finish()
+            },
+            VariantDetails::Tuple(items) => {
+                let bindings = (0..items.len())
+                    .map(|index| format_ident!("item_{}", index))
+                    .collect::<Vec<_>>();
+                quote! {
+                    Self::#name(#(#bindings),*) => f.debug_tuple(#label)
+                        #(.field(#bindings))*
+                        .finish()
+                }
+            }
+            VariantDetails::Struct(properties) => {
+                let (bindings, fields): (Vec<_>, Vec<_>) = properties
+                    .iter()
+                    .map(|prop| {
+                        let name = format_ident!("{}", prop.name);
+                        let prop_label = unraw(&prop.name);
+                        if prop.redacted {
+                            (quote! { #name: _ }, quote! { .field(#prop_label, &"***") })
+                        } else {
+                            (quote! { #name }, quote! { .field(#prop_label, #name) })
+                        }
+                    })
+                    .unzip();
+                quote! {
+                    Self::#name { #(#bindings),* } => f.debug_struct(#label)
+                        #(#fields)*
+                        .finish()
+                }
+            }
+        }
+    });
+    Some(quote! {
+        impl std::fmt::Debug for #type_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
+
 pub(crate) fn enum_impl(type_name: &Ident, variants: &[Variant]) -> TokenStream {
     let maybe_simple_variants = variants
         .iter()
-        .map(|variant| {
-            if let VariantDetails::Simple = variant.details {
-                Some(variant)
-            } else {
-                None
-            }
+        .map(|variant| match variant.details {
+            VariantDetails::Simple
+            | VariantDetails::Integer(_)
+            | VariantDetails::Unknown
+            | VariantDetails::Other(_) => Some(variant),
+            _ => None,
         })
         .collect::<Option<Vec<_>>>();
 
     match maybe_simple_variants {
+        // An enum without variants has no values to display.
+        Some(simple_variants) if simple_variants.is_empty() => quote! {},
         Some(simple_variants) => {
             let match_variants = simple_variants.iter().map(|variant| {
                 let variant_name = format_ident!("{}", variant.name);
-                let variant_str = match &variant.rename {
-                    Some(s) => s,
-                    None => &variant.name,
+                let variant_str = match (&variant.details, &variant.rename) {
+                    (VariantDetails::Other(_), _) => {
+                        return quote! { #type_name::#variant_name(value) => value.clone() }
+                    }
+                    (VariantDetails::Integer(value), _) => value.to_string(),
+                    (_, Some(s)) => s.clone(),
+                    (_, None) => variant.name.clone(),
                 };
                 quote! {
                     #type_name::#variant_name => #variant_str.to_string()
                 }
             });
 
+            // The catch-all variants aren't values of the schema.
+            let variant_values = simple_variants.iter().filter(|variant| {
+                matches!(
+                    variant.details,
+                    VariantDetails::Simple | VariantDetails::Integer(_)
+                )
+            });
+            let variant_values = variant_values.map(|variant| {
+                let variant_name = format_ident!("{}", variant.name);
+                quote! { #type_name::#variant_name }
+            });
+
             quote! {
+                impl #type_name {
+                    /// Every variant, in the order of the schema's values.
+                    pub const VARIANTS: &[Self] = &[#(#variant_values),*];
+                }
+
                 impl ToString for #type_name {
                     fn to_string(&self) -> String {
                         match self {
@@ -882,7 +1513,7 @@ mod tests {
     use serde::Serialize;
 
     use crate::{
-        test_util::{validate_output, validate_output_for_untagged_enm},
+        test_util::{assert_output, validate_output, validate_output_for_untagged_enm},
         type_entry::{EnumTagType, TypeEntryEnum, Variant, VariantDetails},
         Name, TypeEntry, TypeId, TypeSpace,
     };
@@ -1063,12 +1694,14 @@ mod tests {
                 tag_type: EnumTagType::Untagged,
                 variants,
                 deny_unknown_fields: _,
+                deprecated: false,
+                default: None,
             }) => {
                 assert_eq!(name, "UntaggedEnum");
                 assert_eq!(variants.len(), 5);
 
                 assert!(matches!(
-                    variants.get(0).unwrap(),
+                    variants.first().unwrap(),
                     Variant {
                         details: VariantDetails::Simple,
                         ..
@@ -1107,9 +1740,25 @@ mod tests {
             _ => panic!(),
         }
     }
+    // A unit variant of an untagged enum is null, which would make the enum
+    // optional.
+    #[allow(dead_code)]
+    #[derive(Serialize, JsonSchema, Schema)]
+    #[serde(untagged, deny_unknown_fields)]
+    enum UntaggedEnumWithoutUnit {
+        #[serde(rename_all = "camelCase")]
+        Bravo {
+            bee_bee: String,
+            foo_foo: String,
+        },
+        Charlie(Vec<u32>),
+        Delta(u32, String),
+        Echo(Option<String>, String, String),
+    }
+
     #[test]
     fn test_untagged_enum_output() {
-        validate_output_for_untagged_enm::<UntaggedEnum>();
+        validate_output_for_untagged_enm::<UntaggedEnumWithoutUnit>();
     }
 
     #[test]
@@ -1122,6 +1771,12 @@ mod tests {
             .convert_one_of(Name::Required("Xyz".to_string()), &None, &subschemas)
             .unwrap();
 
+        // The unit variant is null so the enum of the others is optional.
+        let ty = match ty {
+            TypeEntry::Option(id) => type_space.id_to_entry.get(&id).unwrap().clone(),
+            _ => panic!("{:?}", ty),
+        };
+
         // This confirms in particular that the tag type is untagged and
         // therefore that the other enum tagging regimes did not match.
         assert!(matches!(
@@ -1258,7 +1913,7 @@ mod tests {
     }
 
     #[test]
-    fn test_simple_untagged_enum() {
+    fn test_internally_tagged_refs() {
         let schema_json = r##"
         {
             "definitions": {
@@ -1327,23 +1982,121 @@ mod tests {
         let mut type_space = TypeSpace::default();
         type_space.add_ref_types(schema.definitions).unwrap();
 
+        // Each subschema requires a `status` of its own, which is the tag.
         let type_id = type_space.ref_to_id.get("workflow-step").unwrap();
         let type_entry = type_space.id_to_entry.get(type_id).unwrap();
+        let actual = type_entry.output(&type_space);
+        let expected = quote! {
+            #[doc = "Workflow Step"]
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+            #[serde(tag = "status", rename_all = "snake_case", deny_unknown_fields)]
+            pub enum WorkflowStep {
+                #[doc = "Workflow Step (In Progress)"]
+                InProgress {
+                    completed_at: (),
+                    conclusion: (),
+                    name: String,
+                    number: i64,
+                    started_at: String,
+                },
+                #[doc = "Workflow Step (Completed)"]
+                Completed {
+                    completed_at: String,
+                    conclusion: WorkflowStepCompletedConclusion,
+                    name: String,
+                    number: i64,
+                    started_at: String,
+                },
+            }
+        };
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
 
-        match &type_entry {
-            TypeEntry::Enum(TypeEntryEnum {
-                tag_type,
-                variants,
-                deny_unknown_fields: _,
-                ..
-            }) => {
-                assert_eq!(tag_type, &EnumTagType::Untagged);
-                //assert_eq!(deny_unknown_fields, &true);
-                for variant in variants {
-                    match &variant.details {
-                        VariantDetails::Tuple(items) if items.len() == 1 => {
-                            let variant_type =
-                                type_space.id_to_entry.get(items.first().unwrap()).unwrap();
+    #[test]
+    fn test_simple_untagged_enum() {
+        let schema_json = r##"
+        {
+            "definitions": {
+                "workflow-step-completed": {
+                    "$schema": "http://json-schema.org/draft-07/schema",
+                    "required": [
+                        "name",
+                        "conclusion",
+                        "number",
+                        "started_at",
+                        "completed_at"
+                    ],
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "conclusion": {
+                            "type": "string",
+                            "enum": ["failure", "skipped", "success"]
+                        },
+                        "number": { "type": "integer" },
+                        "started_at": { "type": "string" },
+                        "completed_at": { "type": "string" }
+                    },
+                    "additionalProperties": false,
+                    "title": "Workflow Step (Completed)"
+                },
+                "workflow-step-in_progress": {
+                    "$schema": "http://json-schema.org/draft-07/schema",
+                    "required": [
+                        "name",
+                        "conclusion",
+                        "number",
+                        "started_at",
+                        "completed_at"
+                    ],
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "conclusion": { "type": "null" },
+                        "number": { "type": "integer" },
+                        "started_at": { "type": "string" },
+                        "completed_at": { "type": "null" }
+                    },
+                    "additionalProperties": false,
+                    "title": "Workflow Step (In Progress)"
+                },
+                "workflow-step": {
+                    "$schema": "http://json-schema.org/draft-07/schema",
+                    "type": "object",
+                    "oneOf": [
+                        { "$ref": "#/definitions/workflow-step-in_progress" },
+                        { "$ref": "#/definitions/workflow-step-completed" }
+                    ],
+                    "title": "Workflow Step"
+                }
+            }
+        }
+        "##;
+
+        let schema: RootSchema = serde_json::from_str(schema_json).unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+
+        // Without a property of constant value in common, the subschemas are
+        // told apart by their data.
+        let type_id = type_space.ref_to_id.get("workflow-step").unwrap();
+        let type_entry = type_space.id_to_entry.get(type_id).unwrap();
+
+        match &type_entry {
+            TypeEntry::Enum(TypeEntryEnum {
+                tag_type,
+                variants,
+                deny_unknown_fields: _,
+                ..
+            }) => {
+                assert_eq!(tag_type, &EnumTagType::Untagged);
+                //assert_eq!(deny_unknown_fields, &true);
+                for variant in variants {
+                    match &variant.details {
+                        VariantDetails::Tuple(items) if items.len() == 1 => {
+                            let variant_type =
+                                type_space.id_to_entry.get(items.first().unwrap()).unwrap();
                             assert!(variant_type.name().unwrap().ends_with(&variant.name));
                         }
                         _ => panic!("{:#?}", type_entry),
@@ -1380,7 +2133,7 @@ mod tests {
             .unwrap();
         let actual = type_entry.output(&type_space);
         let expected = quote! {
-            #[derive(Serialize, Deserialize, Debug, Clone)]
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
             pub enum ResultX {
                 Ok(u32),
                 Err(String),
@@ -1388,4 +2141,494 @@ mod tests {
         };
         assert_eq!(actual.to_string(), expected.to_string());
     }
+
+    #[test]
+    fn test_internally_tagged_const() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Square": {
+                    "type": "object",
+                    "required": ["kind", "side"],
+                    "properties": {
+                        "kind": { "const": "square" },
+                        "side": { "type": "number" }
+                    }
+                },
+                "Shape": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["kind", "radius"],
+                            "properties": {
+                                "kind": { "type": "string", "const": "circle" },
+                                "radius": { "type": "number" }
+                            }
+                        },
+                        { "$ref": "#/definitions/Square" }
+                    ]
+                },
+                "Loose": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "properties": { "kind": { "const": "a" } }
+                        },
+                        {
+                            "type": "object",
+                            "properties": { "kind": { "const": "b" } }
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The tag must be required in each subschema.
+        assert_output(&type_space, "enums/internally_tagged_const");
+    }
+
+    #[test]
+    fn test_adjacently_tagged_const() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Image": {
+                    "type": "object",
+                    "required": ["t", "c"],
+                    "properties": {
+                        "t": { "const": "image" },
+                        "c": {
+                            "type": "object",
+                            "required": ["width"],
+                            "properties": {
+                                "width": { "type": "integer", "minimum": 0 }
+                            }
+                        }
+                    }
+                },
+                "Message": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["t"],
+                            "properties": { "t": { "const": "ping" } }
+                        },
+                        {
+                            "type": "object",
+                            "required": ["t", "c"],
+                            "properties": {
+                                "t": { "type": "string", "const": "text" },
+                                "c": { "type": "string" }
+                            }
+                        },
+                        { "$ref": "#/definitions/Image" }
+                    ]
+                },
+                "Same": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["t", "c"],
+                            "properties": {
+                                "t": { "const": "a" },
+                                "c": { "type": "string" }
+                            }
+                        },
+                        {
+                            "type": "object",
+                            "required": ["t", "c"],
+                            "properties": {
+                                "t": { "const": "a" },
+                                "c": { "type": "boolean" }
+                            }
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Each subschema needs a distinct tag value.
+        assert_output(&type_space, "enums/adjacently_tagged_const");
+    }
+
+    #[test]
+    fn test_discriminator() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Dog": {
+                    "type": "object",
+                    "required": ["petType", "bark"],
+                    "properties": {
+                        "petType": { "type": "string" },
+                        "bark": { "type": "boolean" }
+                    }
+                },
+                "Cat": {
+                    "type": "object",
+                    "required": ["petType"],
+                    "properties": {
+                        "petType": { "type": "string" },
+                        "lives": { "type": "integer", "format": "uint8" }
+                    }
+                },
+                "Fish": {
+                    "type": "object",
+                    "properties": {
+                        "petType": { "type": "string" }
+                    }
+                },
+                "Pet": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/Dog" },
+                        { "$ref": "#/definitions/Cat" },
+                        { "$ref": "#/definitions/Fish" }
+                    ],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {
+                            "dog": "#/definitions/Dog",
+                            "cat": "Cat"
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "enums/discriminator");
+    }
+
+    #[test]
+    fn test_discriminator_unknown_mapping() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Dog": {
+                    "type": "object",
+                    "properties": { "petType": { "type": "string" } }
+                },
+                "Pet": {
+                    "oneOf": [{ "$ref": "#/definitions/Dog" }],
+                    "discriminator": {
+                        "propertyName": "petType",
+                        "mapping": {
+                            "dog": "#/definitions/Dog",
+                            "cat": "#/definitions/Cat"
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        let err = type_space.add_ref_types(schema.definitions).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "definitions.Pet: invalid schema for Pet: the discriminator mapping for \"cat\" refers to \
+             #/definitions/Cat, which is not in the oneOf"
+        );
+    }
+
+    #[test]
+    fn test_null_branch() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": { "id": { "type": "string" } }
+                },
+                "Holder": {
+                    "type": "object",
+                    "required": ["required", "several"],
+                    "properties": {
+                        "required": {
+                            "oneOf": [
+                                { "type": "null" },
+                                { "$ref": "#/definitions/Thing" }
+                            ]
+                        },
+                        "optional": {
+                            "anyOf": [
+                                { "$ref": "#/definitions/Thing" },
+                                { "type": "null" }
+                            ]
+                        },
+                        "several": {
+                            "oneOf": [
+                                { "type": "null" },
+                                { "type": "string" },
+                                { "type": "boolean" }
+                            ]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "enums/null_branch");
+    }
+
+    #[test]
+    fn test_enum_variants() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Color": {
+                    "type": "string",
+                    "enum": ["red", "green", "blue"]
+                },
+                "Shape": {
+                    "oneOf": [
+                        { "type": "string", "enum": ["none"] },
+                        {
+                            "type": "object",
+                            "required": ["circle"],
+                            "properties": { "circle": { "type": "number" } },
+                            "additionalProperties": false
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "enums/enum_variants");
+    }
+
+    #[test]
+    fn test_ambiguous_untagged_variants() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Named": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": { "name": { "type": "string" } }
+                        },
+                        {
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": { "name": { "type": "string" } }
+                        },
+                        { "type": "integer" }
+                    ]
+                },
+                "Owner": {
+                    "oneOf": [
+                        {
+                            "title": "Person",
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": { "name": { "type": "string" } }
+                        },
+                        { "$ref": "#/definitions/Pet" }
+                    ]
+                },
+                "Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": { "name": { "type": "string" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Variants of the same types are one.
+        assert_output(&type_space, "enums/ambiguous_untagged_variants");
+
+        // Those of the same shape remain, with a warning.
+        let warnings = type_space
+            .warnings()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                "definitions.Owner: variant Pet of Owner has the shape of Person so it never \
+              deserializes; a discriminator property would tell them apart"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untagged_variant_names() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Disk": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": { "id": { "type": "string" } }
+                },
+                "DiskAttach": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": { "name": { "type": "string" } }
+                },
+                "DiskCreate": {
+                    "type": "object",
+                    "required": ["size"],
+                    "properties": { "size": { "type": "integer" } }
+                },
+                "DiskRequest": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/DiskCreate" },
+                        { "$ref": "#/definitions/DiskAttach" }
+                    ]
+                },
+                "TitledRequest": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/DiskCreate", "title": "new disk" },
+                        { "$ref": "#/definitions/DiskAttach", "title": "existing disk" }
+                    ]
+                },
+                "AnyDisk": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/Disk" },
+                        { "$ref": "#/definitions/DiskAttach" }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The common prefix goes.
+        // Titles come first, and their common suffix stays.
+        // Without the prefix one name would be empty.
+        assert_output(&type_space, "enums/untagged_variant_names");
+    }
+
+    #[test]
+    fn test_variant_descriptions() {
+        let schema = serde_json::from_value::<RootSchema>(serde_json::json!({
+            "definitions": {
+                "Circle": {
+                    "type": "object",
+                    "description": "A round shape.",
+                    "required": ["kind", "radius"],
+                    "properties": {
+                        "kind": { "const": "circle" },
+                        "radius": { "type": "number" }
+                    }
+                },
+                "Square": {
+                    "type": "object",
+                    "required": ["kind", "side"],
+                    "properties": {
+                        "kind": { "const": "square" },
+                        "side": { "type": "number" }
+                    }
+                },
+                "Shape": {
+                    "oneOf": [
+                        { "$ref": "#/definitions/Circle", "description": "A circle." },
+                        { "$ref": "#/definitions/Square", "description": "A square." }
+                    ]
+                },
+                "Input": {
+                    "oneOf": [
+                        { "type": "string", "description": "A path." },
+                        { "type": "integer", "description": "A descriptor." }
+                    ]
+                },
+                "Level": {
+                    "type": "string",
+                    "enum": ["low", "high"],
+                    "x-enum-descriptions": ["Quiet.", ""]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The subschema's description comes before its definition's.
+        assert_output(&type_space, "enums/variant_descriptions");
+    }
+
+    #[test]
+    fn test_bool_subschemas() {
+        let subschemas =
+            |value| serde_json::from_value::<Vec<schemars::schema::Schema>>(value).unwrap();
+
+        // Anything else becomes a variant after those of the other types.
+        let mut type_space = TypeSpace::default();
+        let (type_entry, _) = type_space
+            .convert_one_of(
+                Name::Required("Anything".to_string()),
+                &None,
+                &subschemas(serde_json::json!([true, { "type": "string" }])),
+            )
+            .unwrap();
+        let actual = type_entry.output(&type_space);
+        let expected = quote! {
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+            #[serde(untagged)]
+            pub enum Anything {
+                Variant0(String),
+                Variant1(serde_json::Value),
+            }
+        };
+        assert_eq!(actual.to_string(), expected.to_string());
+
+        // Nothing matches the false schema, so it adds no variant.
+        let mut type_space = TypeSpace::default();
+        let (type_entry, _) = type_space
+            .convert_one_of(
+                Name::Required("Count".to_string()),
+                &None,
+                &subschemas(serde_json::json!([false, { "type": "integer", "minimum": 0 }, {"type": "string"}])),
+            )
+            .unwrap();
+        let actual = type_entry.output(&type_space);
+        let expected = quote! {
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+            #[serde(untagged)]
+            pub enum Count {
+                Variant0(u64),
+                Variant1(String),
+            }
+        };
+        assert_eq!(actual.to_string(), expected.to_string());
+
+        // In an anyOf, the true schema makes the others moot while the false
+        // schema adds nothing.
+        for (schema, type_name) in [
+            (
+                serde_json::json!({ "anyOf": [true, { "type": "string" }] }),
+                "serde_json::Value",
+            ),
+            (
+                serde_json::json!({ "anyOf": [false, { "type": "string" }] }),
+                "String",
+            ),
+            (serde_json::json!({ "oneOf": [false, false] }), "Never"),
+        ] {
+            let schema = serde_json::from_value::<SchemaObject>(schema).unwrap();
+            let mut type_space = TypeSpace::default();
+            let (type_entry, _) = type_space
+                .convert_schema_object(Name::Unknown, &schema)
+                .unwrap();
+            assert_eq!(
+                type_entry.type_name(&type_space).replace(' ', ""),
+                type_name
+            );
+        }
+    }
 }
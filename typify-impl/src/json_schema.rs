@@ -0,0 +1,138 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use schemars::schema::Schema;
+use serde_json::Value;
+
+use crate::{type_entry::TypeEntry, TypeId, TypeSpace};
+
+const DEFINITIONS: &str = "#/definitions/";
+
+impl TypeSpace {
+    /// Implementations of `schemars::JsonSchema` that give the schemas of
+    /// the definitions and root schemas that types came from, if the
+    /// settings ask for them.
+    pub(crate) fn json_schema_impls(&self) -> TokenStream {
+        if !cfg!(feature = "schemars") || !self.settings.json_schema_impls {
+            return TokenStream::new();
+        }
+
+        // The schemas of named types by their ids, with the names that they
+        // have among definitions. Root types may be the types of definitions
+        // too, e.g. if the root schema is a reference.
+        let reachable = self.reachable_types();
+        let mut sources = BTreeMap::new();
+        for (ref_name, type_id) in &self.ref_to_id {
+            if let Some(schema) = self.definitions.get(ref_name) {
+                if reachable.contains(type_id) && self.is_named(type_id) {
+                    sources
+                        .entry(type_id)
+                        .or_insert_with(|| (ref_name.clone(), schema));
+                }
+            }
+        }
+        for (type_id, schema) in &self.root_schemas {
+            if reachable.contains(type_id) && self.is_named(type_id) {
+                sources
+                    .entry(type_id)
+                    .or_insert_with(|| (self.get_type_for_id(type_id).name(), schema));
+            }
+        }
+        let implemented = sources
+            .iter()
+            .filter(|(type_id, (ref_name, _))| self.ref_to_id.get(ref_name) == Some(type_id))
+            .map(|(type_id, (ref_name, _))| (ref_name.as_str(), *type_id))
+            .collect::<BTreeMap<_, _>>();
+
+        let impls = sources.iter().map(|(type_id, (name, schema))| {
+            let type_ident = self.id_to_entry[type_id].type_ident(self, false);
+
+            // The definitions the schema refers to: the types that implement
+            // `JsonSchema` add their own, and we add the others, such as
+            // those of replaced types, as they are.
+            let mut subschemas = Vec::new();
+            let mut definitions = Vec::new();
+            let mut pending = references(schema);
+            let mut seen = BTreeSet::new();
+            while let Some(ref_name) = pending.pop() {
+                if !seen.insert(ref_name.clone()) {
+                    continue;
+                }
+                if let Some(ref_type_id) = implemented.get(ref_name.as_str()) {
+                    let ref_ident = self.id_to_entry[*ref_type_id].type_ident(self, false);
+                    subschemas.push(quote! { gen.subschema_for::<#ref_ident>(); });
+                } else if let Some(definition) = self.definitions.get(&ref_name) {
+                    pending.extend(references(definition));
+                    let json = schema_literal(definition);
+                    definitions.push(quote! {
+                        gen.definitions_mut()
+                            .entry(#ref_name.to_string())
+                            .or_insert_with(|| serde_json::from_str(#json).unwrap());
+                    });
+                }
+            }
+
+            let json = schema_literal(schema);
+            let gen = if subschemas.is_empty() && definitions.is_empty() {
+                quote! { _ }
+            } else {
+                quote! { gen }
+            };
+            quote! {
+                impl schemars::JsonSchema for #type_ident {
+                    fn schema_name() -> String {
+                        #name.to_string()
+                    }
+
+                    fn json_schema(
+                        #gen: &mut schemars::gen::SchemaGenerator,
+                    ) -> schemars::schema::Schema {
+                        #(#subschemas)*
+                        #(#definitions)*
+                        serde_json::from_str(#json).unwrap()
+                    }
+                }
+            }
+        });
+
+        quote! { #(#impls)* }
+    }
+
+    pub(crate) fn is_named(&self, type_id: &TypeId) -> bool {
+        matches!(
+            self.id_to_entry.get(type_id),
+            Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_))
+        )
+    }
+}
+
+/// The names of the definitions that a schema refers to, in order.
+fn references(schema: &Schema) -> Vec<String> {
+    fn walk(value: &Value, names: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    if let Some(name) = reference.strip_prefix(DEFINITIONS) {
+                        names.push(name.to_string());
+                    }
+                }
+                object.values().for_each(|value| walk(value, names));
+            }
+            Value::Array(items) => items.iter().for_each(|value| walk(value, names)),
+            _ => {}
+        }
+    }
+
+    let mut names = Vec::new();
+    walk(&serde_json::to_value(schema).unwrap(), &mut names);
+    // We take them from the end.
+    names.reverse();
+    names
+}
+
+fn schema_literal(schema: &Schema) -> Literal {
+    Literal::string(&serde_json::to_string(schema).unwrap())
+}
@@ -1,42 +1,271 @@
 // Copyright 2021 Oxide Computer Company
 
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
 
+use dedup::InlineType;
 use proc_macro2::TokenStream;
-use quote::quote;
-use rustfmt_wrapper::rustfmt;
-use schemars::schema::{Metadata, Schema};
+use quote::{format_ident, quote, ToTokens};
+use schemars::schema::{Metadata, RootSchema, Schema, SchemaObject};
 use thiserror::Error;
-use type_entry::{TypeEntry, TypeEntryNewtype};
+use type_entry::{
+    StructProperty, TypeEntry, TypeEntryAlias, TypeEntryEnum, TypeEntryNewtype, TypeEntryStruct,
+    VariantDetails,
+};
+use util::metadata_title;
 
 #[cfg(test)]
 mod test_util;
 
+mod arbitrary;
+mod bundle;
+mod case;
 mod convert;
+mod cycles;
+mod dedup;
+mod defaults;
+mod dependent_required;
+mod depth;
+mod derives;
+mod draft04;
 mod enums;
+mod json_schema;
+mod merge;
+mod modules;
+mod named_simple;
+mod no_std;
+mod output;
+mod prune;
+mod roles;
 mod structs;
 mod type_entry;
 mod util;
+#[cfg(feature = "serde_yaml")]
+mod yaml;
+
+pub use output::GeneratedFile;
+pub use util::path_type_name;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("unexpected value type")]
     BadValue(String, serde_json::Value),
+    #[error("couldn't load {0}: {1}")]
+    Document(String, String),
+    #[error("invalid default {1} for {0}: {2}")]
+    InvalidDefault(String, String, String),
+    #[error("invalid pattern {1:?} for {0}: {2}")]
+    InvalidPattern(String, String, String),
+    #[error("invalid schema for {0}: {1}")]
+    InvalidSchema(String, String),
+    #[error("patch for {0}, which isn't a type")]
+    UnknownPatch(String),
+    #[error("root {0}, which isn't a type")]
+    UnknownRoot(String),
+    /// A setting that the generated code can't use, e.g. a derive that isn't
+    /// a path, and why.
+    #[error("invalid {0}: {1}")]
+    InvalidSetting(String, String),
+    #[error("unsupported schema: {0}")]
+    Unsupported(String),
+    /// A subschema nested more deeply than the settings permit, as
+    /// `TypeSpaceSettings::with_recursion_limit` describes.
+    #[error("subschemas nested more than {depth} levels deep")]
+    RecursionLimit { depth: usize },
+    /// A `$ref` to a definition or subschema that doesn't exist, and why.
+    #[error("unresolved reference {0}: {1}")]
+    UnresolvedRef(String, String),
+    /// A keyword that the generated types can't represent, and why, as a
+    /// [Warning] would report, with `with_deny_lossy(true)`.
+    #[error("lossy conversion: {1}")]
+    Lossy(String, String),
+    /// `rustfmt` failed to format the code, or isn't installed, as
+    /// `TypeSpaceSettings::with_strict_formatting` describes.
+    #[error("couldn't format the code: {0}")]
+    Format(String),
+    /// Something that the generated code can only do with `std`, with
+    /// `TypeSpaceSettings::with_no_std(true)`.
+    #[error("{0} requires std")]
+    RequiresStd(String),
+    #[error("couldn't write {0}: {1}")]
+    Write(String, String),
     #[error("unknown")]
     Unknown,
+    /// Another error along with where in the schema it occurred.
+    #[error("{location}: {error}")]
+    At {
+        location: SchemaLocation,
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Where in the schema the error occurred, if we know.
+    pub fn location(&self) -> Option<&SchemaLocation> {
+        match self {
+            Error::At { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The error without its location.
+    pub fn reason(&self) -> &Error {
+        match self {
+            Error::At { error, .. } => error.reason(),
+            error => error,
+        }
+    }
+
+    /// The error within the subschema at the given path, relative to the
+    /// path of the error so far.
+    pub(crate) fn within<const N: usize>(self, segments: [PathSegment; N]) -> Self {
+        let (mut location, error) = match self {
+            Error::At { location, error } => (location, error),
+            error => (SchemaLocation::default(), Box::new(error)),
+        };
+        location.path.splice(0..0, segments);
+        Error::At { location, error }
+    }
+
+    /// The error within the given file, unless we already know its file.
+    pub(crate) fn in_file(self, file: &Path) -> Self {
+        match self.within([]) {
+            Error::At {
+                mut location,
+                error,
+            } => {
+                location.file.get_or_insert_with(|| file.to_path_buf());
+                Error::At { location, error }
+            }
+            _ => unreachable!(),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A location within a schema document: the file, if the schema came from
+/// one, and the path of keys and indices from the root of the document to
+/// the subschema, e.g. `definitions.Widget.properties.size.allOf[1]`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SchemaLocation {
+    pub file: Option<PathBuf>,
+    pub path: Vec<PathSegment>,
+}
+
+/// A step along the path to a subschema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
+impl From<&String> for PathSegment {
+    fn from(key: &String) -> Self {
+        PathSegment::Key(key.clone())
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+impl std::fmt::Display for SchemaLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        if self.path.is_empty() {
+            return write!(f, "the root schema");
+        }
+        for (index, segment) in self.path.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if index == 0 => write!(f, "{}", key)?,
+                PathSegment::Key(key) => write!(f, ".{}", key)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A keyword of a schema that the generated types don't represent, e.g. a
+/// `pattern` on a string without string validation, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub location: SchemaLocation,
+    pub keyword: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
 /// Representation of a type which may have a definition or may be built-in.
 #[derive(Debug, Clone)]
 pub struct Type<'a> {
     type_space: &'a TypeSpace,
+    type_id: TypeId,
     type_entry: &'a TypeEntry,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
-struct TypeId(u64);
+/// The identifier of a type within a [TypeSpace]. A type keeps its
+/// identifier for the life of the [TypeSpace].
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Hash)]
+pub struct TypeId(u64);
+
+/// The structure of a [Type] as given by [Type::details]. The types of its
+/// members are given by their [TypeId]s, which [TypeSpace::get] resolves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDetails<'a> {
+    /// A struct with the given fields, by their Rust names.
+    Struct {
+        properties: Vec<(String, TypeId)>,
+    },
+    /// An enum with the given variants, by their Rust names.
+    Enum {
+        variants: Vec<(String, TypeVariant)>,
+    },
+    /// A struct with a single unnamed field of the given type.
+    Newtype(TypeId),
+    /// A type alias of the given type.
+    Alias(TypeId),
+    Option(TypeId),
+    Vec(TypeId),
+    /// An array of exactly the given number of items.
+    Array(TypeId, usize),
+    Map(TypeId, TypeId),
+    Set(TypeId),
+    Box(TypeId),
+    Tuple(Vec<TypeId>),
+    Unit,
+    /// An existing type such as `String`, `u32`, or `uuid::Uuid`.
+    Builtin(&'a str),
+    /// An existing type with the given type parameters.
+    Generic(&'a str, Vec<TypeId>),
+}
+
+/// The data a variant of an enum holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeVariant {
+    Unit,
+    Tuple(Vec<TypeId>),
+    /// Fields, by their Rust names, and their types.
+    Struct(Vec<(String, TypeId)>),
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Name {
@@ -54,6 +283,844 @@ impl Name {
     }
 }
 
+/// Settings that alter type generation.
+#[derive(Debug, Default, Clone)]
+pub struct TypeSpaceSettings {
+    all_of_strategy: AllOfStrategy,
+    arbitrary_impls: bool,
+    array_validation: bool,
+    capture_extra_properties: bool,
+    conversion: Vec<(SchemaObject, TypeReplacement)>,
+    conversion_error: Option<String>,
+    crates: BTreeMap<String, String>,
+    datetime_crate: Option<DateTimeCrate>,
+    decimal_type: Option<DecimalImpl>,
+    deny_lossy: bool,
+    derives: Vec<String>,
+    draft04: bool,
+    distinct_inline_types: bool,
+    explicit_nulls: bool,
+    extra_properties_name: Option<String>,
+    field_accessors: bool,
+    field_visibility: Option<String>,
+    ignore_nullable: bool,
+    json_schema_impls: bool,
+    map_type: Option<MapType>,
+    minimal_derives: bool,
+    module_split: Option<ModuleSplit>,
+    named_simple_types: Option<NamedSimpleTypes>,
+    no_std: bool,
+    non_exhaustive: Option<NonExhaustive>,
+    nonzero_integers: bool,
+    number_validation: bool,
+    object_validation: bool,
+    patch: BTreeMap<String, TypePatch>,
+    pattern_engine: Option<PatternEngine>,
+    raw_encoded_strings: bool,
+    raw_json_strings: bool,
+    read_write_only: Option<ReadWriteOnly>,
+    recursion_limit: Option<usize>,
+    redacted_debug: bool,
+    redacted_formats: BTreeSet<String>,
+    redacted_properties: BTreeSet<String>,
+    replace: BTreeMap<String, TypeReplacement>,
+    roots: Vec<String>,
+    skip_serializing_defaults: bool,
+    skip_serializing_empty: Option<bool>,
+    skip_serializing_none: Option<SkipSerializingNone>,
+    strict_formatting: bool,
+    string_validation: bool,
+    struct_builder: bool,
+    type_prefix: Option<String>,
+    type_suffix: Option<String>,
+    type_visibility: Option<String>,
+    unknown_values: Option<UnknownValues>,
+    wrap_module: Option<(String, String)>,
+}
+
+/// An existing type used in place of a generated one, and the traits it
+/// implements.
+#[derive(Debug, Clone)]
+struct TypeReplacement {
+    replace_type: String,
+    impls: Vec<String>,
+}
+
+/// Changes to a particular generated type.
+#[derive(Debug, Default, Clone)]
+pub struct TypePatch {
+    rename: Option<String>,
+    derives: Vec<String>,
+    docs: Vec<String>,
+    non_exhaustive: Option<bool>,
+    unknown_values: Option<UnknownValues>,
+}
+
+impl TypePatch {
+    /// Name the type `rename` rather than the name typify would choose. Its
+    /// serialized name is unchanged. A name that isn't an identifier makes
+    /// adding types fail with `Error::InvalidSetting`.
+    pub fn with_rename<S: ToString>(&mut self, rename: S) -> &mut Self {
+        self.rename = Some(rename.to_string());
+        self
+    }
+
+    /// Derive the given trait, e.g. `Hash`, for the type in addition to the
+    /// derives for all types.
+    pub fn with_derive<S: ToString>(&mut self, derive: S) -> &mut Self {
+        self.derives.push(derive.to_string());
+        self
+    }
+
+    /// Append the paragraph to the type's documentation, e.g. to say how
+    /// it's used or add something the schema's description lacks.
+    pub fn with_doc<S: ToString>(&mut self, paragraph: S) -> &mut Self {
+        self.docs.push(paragraph.to_string());
+        self
+    }
+
+    /// Handle the values of the type, a string enum, that its schema doesn't
+    /// list as the given mode prescribes rather than as the mode for all
+    /// types does.
+    pub fn with_unknown_values(&mut self, unknown_values: UnknownValues) -> &mut Self {
+        self.unknown_values = Some(unknown_values);
+        self
+    }
+
+    /// Mark the type, an enum or struct, `#[non_exhaustive]` or not
+    /// regardless of the setting for all types.
+    pub fn with_non_exhaustive(&mut self, non_exhaustive: bool) -> &mut Self {
+        self.non_exhaustive = Some(non_exhaustive);
+        self
+    }
+}
+
+/// Ways to represent an `allOf` of named types and an object that extends
+/// them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AllOfStrategy {
+    /// A single struct with the properties of each subschema, the default.
+    #[default]
+    Merge,
+    /// A struct with the properties of the extending object and a
+    /// `#[serde(flatten)]` member for each named type. The properties of the
+    /// named types and the extending object must be distinct.
+    Flatten,
+}
+
+/// Crates that may be used to represent strings with the `date-time`,
+/// `date`, and `time` formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeCrate {
+    /// `chrono::DateTime<chrono::offset::Utc>`, `chrono::NaiveDate`, and
+    /// `chrono::NaiveTime`. The generated code requires the `chrono` crate
+    /// with its `serde` feature enabled.
+    Chrono,
+    /// `time::OffsetDateTime`, `time::Date`, and `time::Time`. These are
+    /// (de)serialized with `#[serde(with = ...)]` attributes so the values
+    /// use RFC 3339 formats; the generated code requires the `time` crate
+    /// with its `serde-well-known`, `macros`, `formatting`, and `parsing`
+    /// features enabled. Arrays, sets, and maps of the types, and `Option`s of
+    /// those, use the modules of a generated `nested_serde` module; values
+    /// nested more deeply, as in `Vec<Vec<time::Date>>` or
+    /// `Vec<Option<time::Date>>`, use the `time` crate's default formats
+    /// instead, with a warning.
+    Time,
+}
+
+/// Types that may be used to represent numbers with the `decimal` format or
+/// with a fractional `multipleOf` such as `0.01`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalImpl {
+    /// `serde_json::Number`. This only preserves precision if the
+    /// `serde_json` crate's `arbitrary_precision` feature is enabled.
+    SerdeJsonNumber,
+    /// `rust_decimal::Decimal` serialized as a string. Requires the
+    /// `rust_decimal` cargo feature; the generated code requires the
+    /// `rust_decimal` crate with its `serde` feature enabled.
+    RustDecimal,
+    /// `rust_decimal::Decimal` serialized as a number with
+    /// `rust_decimal::serde::float`. Requires the `rust_decimal` cargo
+    /// feature; the generated code requires the `rust_decimal` crate with its
+    /// `serde-with-float` feature enabled.
+    RustDecimalFloat,
+}
+
+/// Types that may be used to represent maps, i.e. objects with no properties
+/// and an `additionalProperties` schema, and the additional properties of
+/// other objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapType {
+    /// `std::collections::HashMap`, the default.
+    HashMap,
+    /// `std::collections::BTreeMap` whose entries are ordered (and
+    /// serialized) by key.
+    BTreeMap,
+    /// `indexmap::IndexMap` whose entries are kept (and serialized) in the
+    /// order in which they were inserted or deserialized. Requires the
+    /// `indexmap` cargo feature; the generated code requires the `indexmap`
+    /// crate with its `serde` (`serde-1` before 2.0) feature enabled.
+    IndexMap,
+}
+
+/// Ways to handle properties that schemas mark `readOnly`, i.e. present in
+/// responses but absent in requests, or `writeOnly`, i.e. present in requests
+/// but absent in responses, as OpenAPI documents do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadWriteOnly {
+    /// Treat them like other properties, the default.
+    Ignore,
+    /// Make them optional so that the same type serves both requests and
+    /// responses.
+    Optional,
+    /// Split each struct with such properties into a `{Name}Request` without
+    /// the `readOnly` properties and a `{Name}Response` without the
+    /// `writeOnly` properties, with `From` conversions between them where
+    /// the properties one lacks are optional. Types that contain split types
+    /// are split in turn so that requests contain request types throughout.
+    Split,
+}
+
+/// Crates with which the generated code checks strings against patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternEngine {
+    /// `regress`, which supports the ECMA-262 regular expressions that JSON
+    /// Schema specifies, including lookaround and backreferences; the default.
+    Regress,
+    /// `regex`, which is faster but lacks lookaround and backreferences;
+    /// patterns that use them are an error.
+    Regex,
+}
+
+/// When to omit the optional properties that are `None` from the serialized
+/// form rather than serialize them as null.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipSerializingNone {
+    /// Always omit them, the default.
+    Always,
+    /// Never omit them, i.e. serialize them as null.
+    Never,
+    /// Omit them unless their schemas permit null, e.g. with a `type` of
+    /// `["string", "null"]`, in which case null means something that
+    /// absence doesn't.
+    UnlessNullable,
+}
+
+/// Ways to handle the values of string enums that their schemas don't list,
+/// e.g. values that a server adds to its schema after a client is generated
+/// from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownValues {
+    /// Fail to deserialize them, the default.
+    Reject,
+    /// Deserialize them as a unit `Unknown` variant with `#[serde(other)]`.
+    /// The variant serializes as `"Unknown"` so the value itself is lost.
+    Unknown,
+    /// Deserialize them as an `Other(String)` variant that holds the value
+    /// and serializes as it.
+    Other,
+}
+
+/// How to generate the types of definitions whose schemas aren't objects or
+/// enums, e.g. `{"type": "array", "items": {"$ref": "#/definitions/Widget"}}`
+/// or `{"type": "string"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedSimpleTypes {
+    /// A newtype, e.g. `pub struct WidgetList(pub Vec<Widget>);`, the
+    /// default.
+    Newtype,
+    /// A type alias, e.g. `pub type WidgetList = Vec<Widget>;`.
+    Alias,
+    /// No type of its own: references to the definition use its type, e.g.
+    /// `Vec<Widget>`, in its place.
+    Inline,
+}
+
+/// The generated types to mark `#[non_exhaustive]` so that adding a value or
+/// property to their schemas isn't a breaking change for other crates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonExhaustive {
+    /// Enums, which other crates must then match with a wildcard arm.
+    Enums,
+    /// Enums and structs. Other crates can't construct non-exhaustive
+    /// structs with literals so each struct has a builder as
+    /// `TypeSpaceSettings::with_struct_builder` describes.
+    EnumsAndStructs,
+}
+
+/// Ways to group the generated types into modules, e.g. so that tools cope
+/// with the code for a large document. The types of definitions and root
+/// schemas that the split doesn't place go in a module named `other`, and
+/// other types, such as those of inline subschemas, go with the first type
+/// that refers to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleSplit {
+    /// By the schema document each came from, in a module named for its
+    /// file, e.g. `pets` for `pets.json`.
+    Document,
+    /// By the longest of the prefixes with which the name of each's
+    /// definition begins, in a module named for it, e.g. `issue` for the
+    /// prefix `Issue`.
+    Prefix(Vec<String>),
+    /// By the modules that the mapping gives for the names of types.
+    Mapping(BTreeMap<String, String>),
+}
+
+impl TypeSpaceSettings {
+    fn recursion_limit(&self) -> usize {
+        self.recursion_limit
+            .unwrap_or(depth::DEFAULT_RECURSION_LIMIT)
+    }
+
+    /// The name of the type generated for the given name from the schema:
+    /// as its patch renames it or else with the prefix and suffix.
+    pub(crate) fn type_name(&self, name: &str) -> String {
+        match self.patch.get(name).and_then(|patch| patch.rename.as_ref()) {
+            Some(rename) => rename.clone(),
+            None => format!(
+                "{}{}{}",
+                self.type_prefix.as_deref().unwrap_or_default(),
+                name,
+                self.type_suffix.as_deref().unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// The patch for the generated type of the given name, if any.
+    pub(crate) fn patch_for(&self, name: &str) -> Option<&TypePatch> {
+        self.patch
+            .iter()
+            .find(|(type_name, _)| self.type_name(type_name) == name)
+            .map(|(_, patch)| patch)
+    }
+
+    /// Whether `Debug` output leaves out the value of a property, given its
+    /// name and `format` in the schema and whether it's `writeOnly`.
+    pub(crate) fn is_redacted(
+        &self,
+        name: &str,
+        format: Option<&str>,
+        access: type_entry::PropertyAccess,
+    ) -> bool {
+        self.redacted_properties.contains(name)
+            || format.is_some_and(|format| self.redacted_formats.contains(format))
+            || (self.redacted_debug
+                && (format == Some("password") || access == type_entry::PropertyAccess::WriteOnly))
+    }
+
+    /// Represent each `allOf` of named types and an object that extends them
+    /// as the given strategy prescribes. Other `allOf` constructions are
+    /// merged where possible regardless.
+    pub fn with_all_of_strategy(&mut self, all_of_strategy: AllOfStrategy) -> &mut Self {
+        self.all_of_strategy = all_of_strategy;
+        self
+    }
+
+    /// Generate newtypes for arrays with a `minItems` or `maxItems` that
+    /// validate their lengths on construction and deserialization. This
+    /// excludes arrays with equal `minItems` and `maxItems` which are
+    /// fixed-size arrays regardless. By default these constraints are ignored
+    /// and such arrays are simply `Vec`s.
+    pub fn with_array_validation(&mut self, array_validation: bool) -> &mut Self {
+        self.array_validation = array_validation;
+        self
+    }
+
+    /// Capture the unknown properties of objects that have properties and
+    /// also explicitly permit any additional properties (with
+    /// `"additionalProperties": true` or `{}`) in a flattened
+    /// `HashMap<String, serde_json::Value>` member so that they survive a
+    /// round trip. Objects with an `additionalProperties` schema always have
+    /// such a map with values of the given type. By default, permitted
+    /// additional properties are discarded.
+    pub fn with_capture_extra_properties(&mut self, capture_extra_properties: bool) -> &mut Self {
+        self.capture_extra_properties = capture_extra_properties;
+        self
+    }
+
+    /// Represent date and time formatted strings with types from the given
+    /// crate. By default these are simply `String`s.
+    pub fn with_datetime_crate(&mut self, datetime_crate: DateTimeCrate) -> &mut Self {
+        self.datetime_crate = Some(datetime_crate);
+        self
+    }
+
+    /// Represent decimal numbers, i.e. those with the `decimal` format or a
+    /// fractional `multipleOf`, with the given type. By default these are
+    /// simply `f64`s, as they are too if a `rust_decimal` type is selected
+    /// without the `rust_decimal` cargo feature enabled; either way the
+    /// conversion gives a warning of the fallback for the `decimal` format,
+    /// as it does for a fractional `multipleOf` with an unavailable type.
+    pub fn with_decimal_type(&mut self, decimal_type: DecimalImpl) -> &mut Self {
+        self.decimal_type = Some(decimal_type);
+        self
+    }
+
+    /// Name the flattened map member that holds the additional properties of
+    /// a struct `name` rather than `extra`. If that's also the name of one of
+    /// the struct's properties we append a number to make it unique.
+    pub fn with_extra_properties_name<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.extra_properties_name = Some(name.to_string());
+        self
+    }
+
+    /// Derive the given trait, e.g. `PartialEq` or `schemars::JsonSchema`,
+    /// for every generated type in addition to `Serialize`, `Deserialize`,
+    /// `Debug`, and `Clone`. Traits from other crates should be given by
+    /// their full paths; one that isn't a path makes adding types fail with
+    /// `Error::InvalidSetting`.
+    pub fn with_derive<S: ToString>(&mut self, derive: S) -> &mut Self {
+        self.derives.push(derive.to_string());
+        self
+    }
+
+    /// Fail with an [Error::Lossy] where a conversion would otherwise give a
+    /// [Warning], i.e. where the generated types wouldn't represent part of a
+    /// schema, rather than generating them anyway.
+    pub fn with_deny_lossy(&mut self, deny_lossy: bool) -> &mut Self {
+        self.deny_lossy = deny_lossy;
+        self
+    }
+
+    /// Generate a type for each inline subschema, e.g. of a property, even if
+    /// it's identical to another but for its title and description. By
+    /// default such subschemas share a type, named for the title of one of
+    /// them if any has one.
+    pub fn with_distinct_inline_types(&mut self, distinct_inline_types: bool) -> &mut Self {
+        self.distinct_inline_types = distinct_inline_types;
+        self
+    }
+
+    /// Treat schema documents as draft-04 ones, e.g. with boolean
+    /// `exclusiveMinimum`s and `id`s rather than `$id`s, even if their
+    /// `$schema` doesn't say so. Documents whose `$schema` is draft-04 are
+    /// always treated as such.
+    pub fn with_draft04(&mut self, draft04: bool) -> &mut Self {
+        self.draft04 = draft04;
+        self
+    }
+
+    /// Distinguish null from absence for properties that are optional and
+    /// nullable, e.g. for JSON Merge Patch requests in which null clears a
+    /// value and absence leaves it alone: their fields are `None` when
+    /// they're absent, `Some(None)` when they're null and `Some(Some(value))`
+    /// otherwise, and serialize as such. Other properties are unaffected.
+    pub fn with_explicit_nulls(&mut self, explicit_nulls: bool) -> &mut Self {
+        self.explicit_nulls = explicit_nulls;
+        self
+    }
+
+    /// Ignore the OpenAPI 3.0 `nullable` keyword, e.g. for schemas with a
+    /// vendor extension of that name. By default schemas with
+    /// `"nullable": true` become `Option`s.
+    pub fn with_ignore_nullable(&mut self, ignore_nullable: bool) -> &mut Self {
+        self.ignore_nullable = ignore_nullable;
+        self
+    }
+
+    /// Implement `schemars::JsonSchema` for the types of definitions and root
+    /// schemas so that `schemars::schema_for!` gives the schemas they came
+    /// from, with the definitions they refer to. The generated code then
+    /// requires the `schemars` and `serde_json` crates, and the settings must
+    /// not also derive `JsonSchema`. By default, as without the `schemars`
+    /// cargo feature enabled, there are no such implementations.
+    pub fn with_json_schema_impls(&mut self, json_schema_impls: bool) -> &mut Self {
+        self.json_schema_impls = json_schema_impls;
+        self
+    }
+
+    /// Implement `proptest::arbitrary::Arbitrary` for each named type, with
+    /// strategies that generate valid values for the types' bounds, lengths
+    /// and enumerated values, and that filter values of other constraints
+    /// such as patterns. Types that contain themselves stop recursing after a
+    /// few levels. Built-in types from other crates get strategies of their
+    /// own, but replaced types and `x-rust-type` types must implement
+    /// `Arbitrary`. The generated code then requires the `proptest` crate.
+    /// By default, as without the `proptest` cargo feature enabled, there
+    /// are no such implementations.
+    pub fn with_arbitrary_impls(&mut self, arbitrary_impls: bool) -> &mut Self {
+        self.arbitrary_impls = arbitrary_impls;
+        self
+    }
+
+    /// Show `***` rather than the values of properties with `format:
+    /// password` or `writeOnly: true` in the `Debug` output of their structs
+    /// and enums, whose `Debug` impls are then written out rather than
+    /// derived. By default all values are shown, other than those of the
+    /// properties that `with_redacted_property` and `with_redacted_format`
+    /// name.
+    pub fn with_redacted_debug(&mut self, redacted_debug: bool) -> &mut Self {
+        self.redacted_debug = redacted_debug;
+        self
+    }
+
+    /// Redact the values of properties of the given name, as it is in
+    /// schemas, in `Debug` output as `with_redacted_debug` does.
+    pub fn with_redacted_property<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.redacted_properties.insert(name.to_string());
+        self
+    }
+
+    /// Redact the values of properties whose schemas have the given
+    /// `format`, e.g. `"secret"`, in `Debug` output as `with_redacted_debug`
+    /// does.
+    pub fn with_redacted_format<S: ToString>(&mut self, format: S) -> &mut Self {
+        self.redacted_formats.insert(format.to_string());
+        self
+    }
+
+    /// Represent maps with the given type. By default these are `HashMap`s as
+    /// they are if `MapType::IndexMap` is selected without the `indexmap`
+    /// cargo feature enabled.
+    pub fn with_map_type(&mut self, map_type: MapType) -> &mut Self {
+        self.map_type = Some(map_type);
+        self
+    }
+
+    /// Derive `PartialEq`, `Eq`, `PartialOrd`, `Ord`, and `Hash` only where
+    /// the generated code requires them, i.e. for enums of unit variants and
+    /// for string types that may be map keys, and never derive `Copy`. By
+    /// default we derive each of these for every type whose members all
+    /// implement the trait.
+    pub fn with_minimal_derives(&mut self, minimal_derives: bool) -> &mut Self {
+        self.minimal_derives = minimal_derives;
+        self
+    }
+
+    /// Generate the types of definitions whose schemas are arrays, maps,
+    /// primitives, or the like as the given mode prescribes. Those with
+    /// constraints to check or defaults remain newtypes, as do those that
+    /// refer to themselves through other such definitions, which a type alias
+    /// can't. By default each is a newtype.
+    pub fn with_named_simple_types(&mut self, named_simple_types: NamedSimpleTypes) -> &mut Self {
+        self.named_simple_types = Some(named_simple_types);
+        self
+    }
+
+    /// Mark the given generated types `#[non_exhaustive]`. Patches may
+    /// override this for particular types. By default none are.
+    pub fn with_non_exhaustive(&mut self, non_exhaustive: NonExhaustive) -> &mut Self {
+        self.non_exhaustive = Some(non_exhaustive);
+        self
+    }
+
+    /// Represent unsigned integers whose bounds exclude zero, e.g. with
+    /// `"minimum": 1` or `"exclusiveMinimum": 0`, as `std::num::NonZeroU8`,
+    /// `NonZeroU16`, etc. Integers without a format or with an unsigned one
+    /// qualify; those with a signed format such as `int32` keep their signed
+    /// types. By default these are the plain unsigned types.
+    pub fn with_nonzero_integers(&mut self, nonzero_integers: bool) -> &mut Self {
+        self.nonzero_integers = nonzero_integers;
+        self
+    }
+
+    /// Generate newtypes for integers and numbers with bounds or a
+    /// `multipleOf` that validate their values on construction and
+    /// deserialization. For integers we only check the bounds that the
+    /// selected integer type does not already enforce. The `multipleOf`
+    /// check for floating-point numbers is best-effort due to the inexact
+    /// nature of floating-point arithmetic. By default these constraints are
+    /// ignored and such values are plain integer or floating-point types.
+    pub fn with_number_validation(&mut self, number_validation: bool) -> &mut Self {
+        self.number_validation = number_validation;
+        self
+    }
+
+    /// Check the `dependentRequired` rules of objects, i.e. the properties
+    /// that must be present along with others, with a `validate` method on
+    /// their structs that deserialization and builders call. Optional arrays
+    /// and maps that are empty count as absent. `dependentRequired` is
+    /// enforced only with this setting: by default the rules are ignored,
+    /// with a warning for each object that has them.
+    pub fn with_object_validation(&mut self, object_validation: bool) -> &mut Self {
+        self.object_validation = object_validation;
+        self
+    }
+
+    /// Change the type generated for the given type name as the patch
+    /// describes.
+    pub fn with_patch<S: ToString>(&mut self, type_name: S, type_patch: &TypePatch) -> &mut Self {
+        self.patch.insert(type_name.to_string(), type_patch.clone());
+        self
+    }
+
+    /// Use the existing type `conversion_type`, e.g. `crate::Money`, for
+    /// every schema with the properties of `schema`, e.g. `{ "type":
+    /// "string", "format": "money" }`, other than its metadata. `impls` are
+    /// the traits the type implements, as for `with_replacement`. The first
+    /// matching conversion applies.
+    pub fn with_conversion<S, I>(
+        &mut self,
+        schema: SchemaObject,
+        conversion_type: S,
+        impls: I,
+    ) -> &mut Self
+    where
+        S: ToString,
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.conversion.push((
+            schema,
+            TypeReplacement {
+                replace_type: conversion_type.to_string(),
+                impls: impls.into_iter().map(|imp| imp.to_string()).collect(),
+            },
+        ));
+        self
+    }
+
+    /// Use the types that schemas name with an `x-rust-type` extension from
+    /// the crate `crate_name` if the extension's version is compatible with
+    /// `version`, e.g. `0.4`. Types from the standard library are always used.
+    pub fn with_crate<C: ToString, V: ToString>(&mut self, crate_name: C, version: V) -> &mut Self {
+        self.crates
+            .insert(crate_name.to_string(), version.to_string());
+        self
+    }
+
+    /// Use the existing type `conversion_error`, e.g.
+    /// `crate::error::Error`, as the error of the generated `TryFrom` impls
+    /// and builders rather than a generated `error::ConversionError`. The
+    /// type must implement `From<&'static str>`, `From<String>`, `Display`,
+    /// and `Debug`.
+    pub fn with_conversion_error<S: ToString>(&mut self, conversion_error: S) -> &mut Self {
+        self.conversion_error = Some(conversion_error.to_string());
+        self
+    }
+
+    /// Use the existing type `replace_type`, e.g. `uuid::Uuid`, for the
+    /// definition named `type_name` rather than generating a type for it.
+    /// `impls` are the traits the type implements, e.g. `["Display",
+    /// "FromStr"]`; types that contain it derive `PartialEq`, `Eq`,
+    /// `PartialOrd`, `Ord`, `Hash`, and `Copy` only if it implements them.
+    pub fn with_replacement<TS, RS, I>(
+        &mut self,
+        type_name: TS,
+        replace_type: RS,
+        impls: I,
+    ) -> &mut Self
+    where
+        TS: ToString,
+        RS: ToString,
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.replace.insert(
+            type_name.to_string(),
+            TypeReplacement {
+                replace_type: replace_type.to_string(),
+                impls: impls.into_iter().map(|imp| imp.to_string()).collect(),
+            },
+        );
+        self
+    }
+
+    /// Generate only the types of the given definitions, by their names in
+    /// the schemas (e.g. `Instance` for `#/definitions/Instance`), and the
+    /// types they're made of. Each root must name a type once the schemas
+    /// are added. By default every type is generated.
+    /// [`TypeSpace::pruned_types`] names the types left out.
+    pub fn with_roots<I>(&mut self, roots: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.roots
+            .extend(roots.into_iter().map(|root| root.to_string()));
+        self
+    }
+
+    /// Generate newtypes for strings with a `pattern`, `minLength`, or
+    /// `maxLength` that validate their values on construction and
+    /// deserialization. Patterns require the crate of the configured pattern
+    /// engine, by default `regress`, and the `once_cell` crate in the
+    /// generated code. By default these constraints are ignored and such
+    /// strings are simply `String`s.
+    pub fn with_string_validation(&mut self, string_validation: bool) -> &mut Self {
+        self.string_validation = string_validation;
+        self
+    }
+
+    /// Generate a builder for each struct, e.g.
+    /// `Thing::builder().name("x").size(3).try_into()`. Setters take any value
+    /// that converts to the property's type; properties without a value are
+    /// their defaults, and converting the builder fails if a required
+    /// property has none.
+    pub fn with_struct_builder(&mut self, struct_builder: bool) -> &mut Self {
+        self.struct_builder = struct_builder;
+        self
+    }
+
+    /// Omit properties with schema defaults from the serialized form when
+    /// they have their default values, for types that implement `PartialEq`.
+    /// By default they're always serialized.
+    pub fn with_skip_serializing_defaults(&mut self, skip_serializing_defaults: bool) -> &mut Self {
+        self.skip_serializing_defaults = skip_serializing_defaults;
+        self
+    }
+
+    /// Check patterns in the generated code with the given crate, which the
+    /// code then requires along with `once_cell`. By default it's `regress`.
+    /// Either way patterns that the crate can't compile are an error.
+    pub fn with_pattern_engine(&mut self, pattern_engine: PatternEngine) -> &mut Self {
+        self.pattern_engine = Some(pattern_engine);
+        self
+    }
+
+    /// Omit optional array and map properties from the serialized form when
+    /// they're empty, the default. Either way they're empty when absent.
+    pub fn with_skip_serializing_empty(&mut self, skip_serializing_empty: bool) -> &mut Self {
+        self.skip_serializing_empty = Some(skip_serializing_empty);
+        self
+    }
+
+    /// Omit optional properties that are `None` from the serialized form as
+    /// the given mode prescribes. By default they're always omitted. Either
+    /// way they're `None` when absent. This doesn't apply to the properties
+    /// that `with_explicit_nulls(true)` lets tell null from absence.
+    pub fn with_skip_serializing_none(
+        &mut self,
+        skip_serializing_none: SkipSerializingNone,
+    ) -> &mut Self {
+        self.skip_serializing_none = Some(skip_serializing_none);
+        self
+    }
+
+    /// Keep strings of binary data, i.e. with a `contentEncoding` of `base64`
+    /// or `base16` or with the `byte` format, as `String`s of the encoded
+    /// data. By default they're `Vec<u8>`s that are encoded when serialized
+    /// and decoded when deserialized.
+    pub fn with_raw_encoded_strings(&mut self, raw_encoded_strings: bool) -> &mut Self {
+        self.raw_encoded_strings = raw_encoded_strings;
+        self
+    }
+
+    /// Keep strings of JSON, i.e. with a `contentMediaType` of
+    /// `application/json`, as `String`s, e.g. to parse them only when
+    /// they're needed. By default they're newtypes of the documents' types,
+    /// i.e. those of their `contentSchema`s or `serde_json::Value`, which are
+    /// parsed when they're deserialized.
+    pub fn with_raw_json_strings(&mut self, raw_json_strings: bool) -> &mut Self {
+        self.raw_json_strings = raw_json_strings;
+        self
+    }
+
+    /// Handle `readOnly` and `writeOnly` properties as the given mode
+    /// prescribes. By default they're treated like other properties.
+    pub fn with_read_write_only(&mut self, read_write_only: ReadWriteOnly) -> &mut Self {
+        self.read_write_only = Some(read_write_only);
+        self
+    }
+
+    /// Fail with `Error::RecursionLimit` for schemas whose subschemas are
+    /// nested more than `recursion_limit` levels deep rather than risk
+    /// overflowing the stack, as conversion recurses through each level.
+    /// References don't count, as a definition may refer to itself. By
+    /// default the limit is 64 levels: far more than schemas usually nest,
+    /// yet far fewer than would exhaust the 2 MiB stack of a spawned thread,
+    /// even in a debug build.
+    pub fn with_recursion_limit(&mut self, recursion_limit: usize) -> &mut Self {
+        self.recursion_limit = Some(recursion_limit);
+        self
+    }
+
+    /// Fail with `Error::Format` in `TypeSpace::to_string_pretty` and
+    /// `TypeSpace::write_to_file` if `rustfmt` isn't installed, rather than
+    /// formatting the code with `prettyplease`.
+    pub fn with_strict_formatting(&mut self, strict_formatting: bool) -> &mut Self {
+        self.strict_formatting = strict_formatting;
+        self
+    }
+
+    /// Declare the generated types with the given visibility, e.g.
+    /// `"pub(crate)"`, rather than `pub`. One that isn't a visibility makes
+    /// adding types fail with `Error::InvalidSetting`.
+    pub fn with_type_visibility<S: ToString>(&mut self, visibility: S) -> &mut Self {
+        self.type_visibility = Some(visibility.to_string());
+        self
+    }
+
+    /// Declare the fields of generated structs and newtypes with the given
+    /// visibility, e.g. `"pub(crate)"` or `""` for private fields, rather
+    /// than `pub`. Builders and conversions still work as they're in the
+    /// same module; validated newtypes' fields are always private.
+    pub fn with_field_visibility<S: ToString>(&mut self, visibility: S) -> &mut Self {
+        self.field_visibility = Some(visibility.to_string());
+        self
+    }
+
+    /// Generate a method for each field of a struct that returns a reference
+    /// to it, e.g. `fn name(&self) -> &String`, for use with private fields.
+    pub fn with_field_accessors(&mut self, field_accessors: bool) -> &mut Self {
+        self.field_accessors = field_accessors;
+        self
+    }
+
+    /// Begin the name of every generated type with the prefix, e.g. `Api` for
+    /// `ApiConfig` and `ApiError`, to avoid collisions with other types of
+    /// the crate. It doesn't apply to replaced types, to types a patch
+    /// renames, or to serialized names. A prefix that doesn't make
+    /// identifiers of the names makes adding types fail with
+    /// `Error::InvalidSetting`, as does such a suffix.
+    pub fn with_type_prefix<S: ToString>(&mut self, type_prefix: S) -> &mut Self {
+        self.type_prefix = Some(type_prefix.to_string());
+        self
+    }
+
+    /// End the name of every generated type with the suffix, as
+    /// `with_type_prefix` begins it.
+    pub fn with_type_suffix<S: ToString>(&mut self, type_suffix: S) -> &mut Self {
+        self.type_suffix = Some(type_suffix.to_string());
+        self
+    }
+
+    /// Generate code for `no_std` crates with `alloc`, which must declare
+    /// `extern crate alloc;`: `std` paths are those in `core` and `alloc`
+    /// instead, the code and each of its modules `use` `String`, `Vec` and
+    /// the rest of the standard prelude from `alloc`, and maps are
+    /// `BTreeMap`s whatever `with_map_type` says. IP addresses are those of
+    /// `core::net`, which needs Rust 1.77, and `impl`s of `std::error::Error`
+    /// those of `core::error::Error`, which needs Rust 1.81. Checking
+    /// patterns and the `multipleOf` of numbers needs `std`, so such
+    /// constraints are an error. The `schemars` and `proptest` impls need
+    /// `std` regardless.
+    pub fn with_no_std(&mut self, no_std: bool) -> &mut Self {
+        self.no_std = no_std;
+        self
+    }
+
+    /// Wrap the output of `TypeSpace::to_stream` in a module of the given
+    /// name and visibility, e.g. `pub mod types { ... }` for `"types"` and
+    /// `"pub"`. The module uses serde's derive macros itself. A name that
+    /// isn't an identifier makes adding types fail with
+    /// `Error::InvalidSetting`.
+    pub fn with_module<N: ToString, V: ToString>(&mut self, name: N, visibility: V) -> &mut Self {
+        self.wrap_module = Some((name.to_string(), visibility.to_string()));
+        self
+    }
+
+    /// Group the generated types into modules as the given split says, each
+    /// `use`-ing the types of the others that it refers to. `to_stream`
+    /// gives the modules nested in the code, and `to_module_streams` gives
+    /// them apart for writing to separate files, along with an index that
+    /// declares them. The index re-exports their types so that they may be
+    /// used as they would be without the split and so they must not be
+    /// private.
+    pub fn with_module_split(&mut self, module_split: ModuleSplit) -> &mut Self {
+        self.module_split = Some(module_split);
+        self
+    }
+
+    /// Handle the values of string enums that their schemas don't list as
+    /// the given mode prescribes. Patches may override this for particular
+    /// types. By default such values fail to deserialize.
+    pub fn with_unknown_values(&mut self, unknown_values: UnknownValues) -> &mut Self {
+        self.unknown_values = Some(unknown_values);
+        self
+    }
+}
+
 /// A collection of types.
 #[derive(Debug)]
 pub struct TypeSpace {
@@ -64,6 +1131,13 @@ pub struct TypeSpace {
     // need this by keeping a single Map of referenced types whose value was an
     // enum of a "raw" or a "converted" schema.
     definitions: BTreeMap<String, Schema>,
+    // The documents that definitions came from, to say where a conflicting
+    // definition of the same name was.
+    definition_files: BTreeMap<String, PathBuf>,
+    // The documents that `add_schema_files` loaded, in order.
+    schema_files: Vec<PathBuf>,
+    // The documents of the root types that `add_schema_files` added.
+    root_files: BTreeMap<TypeId, PathBuf>,
 
     // TODO needs an API
     pub(crate) id_to_entry: BTreeMap<TypeId, TypeEntry>,
@@ -71,26 +1145,67 @@ pub struct TypeSpace {
 
     name_to_id: BTreeMap<String, TypeId>,
     ref_to_id: BTreeMap<String, TypeId>,
+    // The schemas of the root types, for their `JsonSchema` impls.
+    root_schemas: BTreeMap<TypeId, Schema>,
+
+    // The request types of types split by `readOnly` and `writeOnly`
+    // properties, by the ids of the response types.
+    request_types: BTreeMap<TypeId, TypeId>,
+
+    // The types of inline subschemas by `dedup::inline_key`.
+    inline_types: BTreeMap<String, InlineType>,
 
     uses_chrono: bool,
+    uses_indexmap: bool,
+    uses_regex: bool,
+    uses_regress: bool,
+    uses_rust_decimal: bool,
+    uses_time: bool,
     uses_uuid: bool,
     uses_serde_json: bool,
     pub(crate) type_mod: Option<String>,
+
+    settings: TypeSpaceSettings,
+
+    // Non-fatal issues encountered during conversion such as constraints
+    // that we could not represent.
+    warnings: Vec<Warning>,
 }
 
 impl Default for TypeSpace {
     fn default() -> Self {
+        Self::new(&TypeSpaceSettings::default())
+    }
+}
+
+impl TypeSpace {
+    /// Create a new [TypeSpace] whose types are generated according to the
+    /// given settings.
+    pub fn new(settings: &TypeSpaceSettings) -> Self {
         Self {
             next_id: 1,
             definitions: BTreeMap::new(),
+            definition_files: BTreeMap::new(),
+            schema_files: Vec::new(),
+            root_files: BTreeMap::new(),
             id_to_entry: BTreeMap::new(),
             name_to_id: BTreeMap::new(),
             ref_to_id: BTreeMap::new(),
+            root_schemas: BTreeMap::new(),
+            request_types: BTreeMap::new(),
+            inline_types: BTreeMap::new(),
             type_to_id: BTreeMap::new(),
             uses_chrono: false,
+            uses_indexmap: false,
+            uses_regex: false,
+            uses_regress: false,
+            uses_rust_decimal: false,
+            uses_time: false,
             uses_uuid: false,
             uses_serde_json: false,
             type_mod: None,
+            settings: settings.clone(),
+            warnings: Vec::new(),
         }
     }
 }
@@ -99,9 +1214,13 @@ impl TypeSpace {
     /// Add a collection of types that will be used as references. Regardless
     /// of how these types are defined--*de novo* or built-in--these types will
     /// appear in the final output in some form. This method may be called
-    /// multiple times, but collections of references must be self-contained;
-    /// in other words, a type in one invocation may not refer to a type in
-    /// another invocation.
+    /// multiple times; a type may refer to the types of earlier invocations
+    /// but not of later ones. A definition identical to one added before is
+    /// the same type, while a different definition of the same name is an
+    /// error.
+    ///
+    /// Each patch in the settings must name one of these types (or one that
+    /// was added before).
     // TODO on an error the TypeSpace is in a weird state; we, perhaps, create
     // a child TypeSpace and then merge it in once all conversions hae
     // succeeded.
@@ -110,99 +1229,543 @@ impl TypeSpace {
         I: IntoIterator<Item = (S, Schema)>,
         S: AsRef<str>,
     {
-        // Gather up all types to make things a little more convenient.
-        let definitions = type_defs
+        self.add_definitions(type_defs)?;
+        self.check_patches()?;
+        self.check_lossy()
+    }
+
+    /// Add the types of definitions as `add_ref_types` does, without checking
+    /// the patches, e.g. as the types of root schemas may be yet to come.
+    fn add_definitions<I, S>(&mut self, type_defs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (S, Schema)>,
+        S: AsRef<str>,
+    {
+        self.check_settings()?;
+        let type_defs = type_defs
             .into_iter()
             .map(|(name, schema)| (name.as_ref().to_string(), schema))
-            .collect::<Vec<(String, Schema)>>();
+            .collect::<Vec<_>>();
+        let limit = self.settings.recursion_limit();
+        if let Some(e) = type_defs.iter().find_map(|(name, schema)| {
+            depth::check(schema, vec!["definitions".into(), name.into()], limit).err()
+        }) {
+            type_defs
+                .into_iter()
+                .for_each(|(_, schema)| depth::dismantle(schema));
+            return Err(e);
+        }
+
+        // Gather up all types to make things a little more convenient.
+        // Definitions we already have, e.g. from another document that
+        // shares them, are used as they are if they're identical.
+        let mut definitions = Vec::new();
+        for (name, schema) in type_defs {
+            match self.definitions.get(&name) {
+                None => definitions.push((name, schema)),
+                Some(existing) if existing == &schema => {}
+                Some(_) => {
+                    let existing = match self.definition_files.get(&name) {
+                        Some(file) => file.display().to_string(),
+                        None => "a schema added before".to_string(),
+                    };
+                    return Err(Error::InvalidSchema(
+                        name.clone(),
+                        format!(
+                            "it differs from the definition of the same name from {}",
+                            existing
+                        ),
+                    )
+                    .within(["definitions".into(), (&name).into()]));
+                }
+            }
+        }
+
+        let type_names = self.definition_type_names(&definitions)?;
 
         // Assign IDs to reference types before actually converting them. We'll
         // need these in the case of forward (or circular) references.
         let base_id = self.next_id;
         self.next_id += definitions.len() as u64;
 
-        for (index, (ref_name, _)) in definitions.iter().enumerate() {
+        // Record the schemas too so that we may resolve references (e.g. to
+        // merge allOf subschemas) during conversion. We keep just this copy
+        // of each, cloning it only while we convert it.
+        // Their names are reserved so that no other type takes them first.
+        let mut ref_names = Vec::with_capacity(definitions.len());
+        for (index, ((ref_name, schema), type_name)) in
+            definitions.into_iter().zip(&type_names).enumerate()
+        {
             self.ref_to_id
                 .insert(ref_name.to_string(), TypeId(base_id + index as u64));
+            self.definitions.insert(ref_name.to_string(), schema);
+
+            if !self
+                .settings
+                .replace
+                .contains_key(definition_type_name(&ref_name))
+            {
+                self.name_to_id.insert(
+                    self.settings
+                        .type_name(&util::sanitize(type_name, convert_case::Case::Pascal)),
+                    TypeId(base_id + index as u64),
+                );
+            }
+            ref_names.push(ref_name);
         }
 
         // Convert all types; note that we use the type assigned from the
         // previous step because each type may create additional types.
-        for (index, (ref_name, schema)) in definitions.into_iter().enumerate() {
-            let type_name = match ref_name.rfind('/') {
-                Some(idx) => &ref_name[idx..],
-                None => &ref_name,
-            };
+        let mut simple = Vec::new();
+        for (index, (ref_name, type_name)) in ref_names.into_iter().zip(&type_names).enumerate() {
+            let type_name = type_name.as_str();
+
+            // Replaced types are used as they are.
+            if let Some(replacement) = self.settings.replace.get(definition_type_name(&ref_name)) {
+                self.id_to_entry.insert(
+                    TypeId(base_id + index as u64),
+                    TypeEntry::BuiltIn(replacement.replace_type.clone()),
+                );
+                continue;
+            }
 
+            let schema = self.definitions[&ref_name].clone();
             let (type_entry, metadata) =
-                self.convert_schema(Name::Required(type_name.to_string()), &schema)?;
+                self.within(["definitions".into(), (&ref_name).into()], |type_space| {
+                    type_space.convert_schema(Name::Required(type_name.to_string()), &schema)
+                })?;
+            let type_id = TypeId(base_id + index as u64);
             let type_entry = match type_entry {
-                // This is effectively a forward declaration so we can discard
-                // the TypeEntry without assigning it. We'd see this if there
-                // were a cycle in the type graph.
-                TypeEntry::Reference(type_id) => TypeEntryNewtype::from_metadata(
-                    Name::Required(type_name.to_string()),
-                    metadata,
-                    type_id,
-                ),
-
                 // The types that are already named are good to go.
                 TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_) => type_entry,
 
                 // For types that don't have names, this is effectively a type
-                // alias which we treat as a newtype (though we could probably
-                // handle it as a type alias).
-                _ => TypeEntryNewtype::from_metadata(
-                    Name::Required(type_name.to_string()),
-                    metadata,
-                    self.assign_type(type_entry),
-                ),
+                // alias which is a newtype until `simplify_named_types`
+                // handles it as the settings say. A reference is effectively
+                // a forward declaration so we can discard the TypeEntry
+                // without assigning it. We'd see this if there were a cycle
+                // in the type graph.
+                _ => {
+                    let inner_id = match type_entry {
+                        TypeEntry::Reference(type_id) => type_id,
+                        _ => self.assign_type(type_entry),
+                    };
+                    let type_entry = TypeEntryNewtype::from_metadata(
+                        Name::Required(type_name.to_string()),
+                        metadata,
+                        inner_id,
+                    );
+                    if type_entry.default().is_none() {
+                        simple.push(type_id.clone());
+                    }
+                    type_entry
+                }
             };
-            self.definitions.insert(ref_name, schema);
-            self.id_to_entry
-                .insert(TypeId(base_id + index as u64), type_entry);
+            let type_entry = self.apply_patch(type_entry);
+            self.id_to_entry.insert(type_id, type_entry);
         }
-        Ok(())
+
+        self.simplify_named_types(simple);
+        self.split_read_write_only();
+        self.break_cycles();
+        self.warn_ambiguous_variants();
+        self.check_defaults()
     }
 
-    /// Add a new type and return a type identifier that may be used in
-    /// function signatures or embedded within other types.
-    pub fn add_type(&mut self, schema: &Schema) -> Result<Type> {
-        let (type_entry, _) = self.convert_schema(Name::Unknown, schema)?;
+    /// The names of the types of definitions. Definitions whose names
+    /// sanitize to the same type name, such as `HTTPServer` and
+    /// `HttpServer` or `foo.bar` and `foo_bar`, or to that of a type we
+    /// already have, would define it twice. Of those, the first spelled as
+    /// the type is, or else the first, keeps the name, and the others append
+    /// the words of `spelling_suffixes`, e.g. `HttpServerUpper` and
+    /// `FooBarUnderscore`, so that each gets the same name every time. If
+    /// even those are taken it's an error.
+    fn definition_type_names(&self, definitions: &[(String, Schema)]) -> Result<Vec<String>> {
+        let mut names = definitions
+            .iter()
+            .map(|(ref_name, _)| definition_type_name(ref_name).to_string())
+            .collect::<Vec<_>>();
+        let sanitized = |name: &str| util::sanitize(name, convert_case::Case::Pascal);
+        let type_name = |name: &str| self.settings.type_name(&sanitized(name));
 
-        let type_id = self.assign_type(type_entry);
-        let type_entry = self.id_to_entry.get(&type_id).unwrap();
-        Ok(Type {
-            type_space: self,
-            type_entry,
-        })
+        let mut order = (0..names.len())
+            .filter(|&index| !self.settings.replace.contains_key(&names[index]))
+            .collect::<Vec<_>>();
+        order.sort_by_key(|&index| sanitized(&names[index]) != names[index]);
+
+        let mut taken = self.name_to_id.keys().cloned().collect::<BTreeSet<_>>();
+        for index in order {
+            if taken.insert(type_name(&names[index])) {
+                continue;
+            }
+            let name = names[index].trim_start_matches('/');
+            let renamed = util::spelling_suffixes(name)
+                .into_iter()
+                .map(|suffix| format!("{}{}", sanitized(name), suffix))
+                .find(|renamed| !taken.contains(&type_name(renamed)))
+                .ok_or_else(|| {
+                    let ref_name = &definitions[index].0;
+                    Error::InvalidSchema(
+                        ref_name.clone(),
+                        format!(
+                            "another type is named {} as its type would be; rename one of them",
+                            type_name(name)
+                        ),
+                    )
+                    .within(["definitions".into(), ref_name.into()])
+                })?;
+            taken.insert(type_name(&renamed));
+            names[index] = renamed;
+        }
+        Ok(names)
     }
 
-    /// Add a new type with a name hint and return a the components necessary
-    /// to use the type for various components of a function signature.
-    pub fn add_type_with_name(
-        &mut self,
-        schema: &Schema,
-        name_hint: Option<String>,
-    ) -> Result<Type> {
-        let name = match name_hint {
-            Some(s) => Name::Suggested(s),
+    /// Add the types defined by the JSON Schema document at `path`: its
+    /// definitions and, if it has a title, its root schema. References to
+    /// other documents, such as `common.json#/definitions/Tag`, are resolved
+    /// relative to the referring document and the definitions they refer to
+    /// are added as well. Returns the paths of all the documents loaded, e.g.
+    /// to track them as dependencies.
+    pub fn add_schema_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<PathBuf>> {
+        self.add_schema_files(&[path])
+    }
+
+    /// Add the types defined by the JSON Schema documents at `paths` as
+    /// `add_schema_file` does. A reference to a definition that isn't in the
+    /// referring document, such as `#/definitions/Thing`, refers to the
+    /// definition of that name in one of the other documents or in those
+    /// added before. Identical definitions of the same name, in these
+    /// documents or those added before, are one type; different ones are an
+    /// error that names both documents.
+    pub fn add_schema_files<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<Vec<PathBuf>> {
+        let known = self.definitions.keys().cloned().collect();
+        let mut bundle = bundle::load(paths, self.settings.draft04, &known)?;
+        let mark = self.warnings.len();
+        self.add_definitions(std::mem::take(&mut bundle.definitions))
+            .map_err(|e| bundle.locate(e))?;
+        for warning in &mut self.warnings[mark..] {
+            bundle.locate_path(&mut warning.location);
+        }
+        self.check_lossy()?;
+        for (name, location) in &bundle.locations {
+            if let Some(file) = &location.file {
+                self.definition_files
+                    .entry(name.clone())
+                    .or_insert_with(|| file.clone());
+            }
+        }
+
+        // Only convert the top-level types that have names.
+        for (schema, root) in bundle.schemas.iter().zip(&bundle.roots) {
+            if let Schema::Object(object) = schema {
+                if (|| object.metadata.as_ref()?.title.as_ref())().is_some() {
+                    let mark = self.warnings.len();
+                    let type_id = self
+                        .add_type_id(Name::Unknown, schema)
+                        .map_err(|e| e.in_file(root))?;
+                    self.root_files.insert(type_id.clone(), root.clone());
+                    self.root_schemas.insert(type_id, schema.clone());
+                    for warning in &mut self.warnings[mark..] {
+                        warning.location.file.get_or_insert_with(|| root.clone());
+                    }
+                }
+            }
+        }
+        self.check_patches()?;
+        for path in &bundle.paths {
+            if !self.schema_files.contains(path) {
+                self.schema_files.push(path.clone());
+            }
+        }
+        Ok(bundle.paths)
+    }
+
+    /// Add the types defined by a JSON Schema document: its definitions
+    /// (from `definitions` or `$defs`) and, if it has a title, its root
+    /// schema whose type is returned. References to subschemas other than
+    /// definitions, such as `#/properties/config`, are resolved by adding
+    /// those subschemas as definitions.
+    pub fn add_root_schema(&mut self, schema: RootSchema) -> Result<Option<Type<'_>>> {
+        // Check the depth first as resolving references walks the schema.
+        let limit = self.settings.recursion_limit();
+        let checked = depth::check_object(&schema.schema, Vec::new(), limit).and_then(|()| {
+            schema
+                .definitions
+                .iter()
+                .try_for_each(|(name, definition)| {
+                    depth::check(definition, vec!["definitions".into(), name.into()], limit)
+                })
+        });
+        if let Err(e) = checked {
+            depth::dismantle(Schema::Object(schema.schema));
+            schema
+                .definitions
+                .into_iter()
+                .for_each(|(_, definition)| depth::dismantle(definition));
+            return Err(e);
+        }
+
+        let RootSchema {
+            schema,
+            definitions,
+            ..
+        } = bundle::resolve(
+            schema,
+            self.settings.draft04,
+            &self.definitions.keys().cloned().collect(),
+        )?;
+        self.add_definitions(definitions)?;
+
+        // Only convert the top-level type if it has a name
+        let type_id = if (|| schema.metadata.as_ref()?.title.as_ref())().is_some() {
+            let schema = Schema::Object(schema);
+            let type_id = self.add_type_id(Name::Unknown, &schema)?;
+            self.root_schemas.insert(type_id.clone(), schema);
+            Some(type_id)
+        } else {
+            None
+        };
+        self.check_patches()?;
+        self.check_lossy()?;
+        let type_space = &*self;
+        Ok(type_id.map(move |type_id| type_space.get_type_for_id(&type_id)))
+    }
+
+    /// Add the types of a schema written in YAML, as with
+    /// [`TypeSpace::add_root_schema`].
+    #[cfg(feature = "serde_yaml")]
+    pub fn add_yaml_schema(&mut self, yaml: &str) -> Result<Option<Type<'_>>> {
+        let invalid = |reason| Error::Document("the YAML schema".to_string(), reason);
+        let value = yaml::from_str(yaml).map_err(invalid)?;
+        let schema =
+            serde_json::from_value::<RootSchema>(value).map_err(|e| invalid(e.to_string()))?;
+        self.add_root_schema(schema)
+    }
+
+    /// Add a new type and return a type identifier that may be used in
+    /// function signatures or embedded within other types. The definitions
+    /// that the schema refers to must have been added already, e.g. with
+    /// [`TypeSpace::add_ref_types`]; a reference to another is an error.
+    pub fn add_type(&mut self, schema: &Schema) -> Result<Type<'_>> {
+        let type_id = self.add_type_id(Name::Unknown, schema)?;
+        Ok(self.get_type_for_id(&type_id))
+    }
+
+    /// Add a new type as [`TypeSpace::add_type`] does, naming it for the hint
+    /// if its schema has no title, and return the components necessary to
+    /// use the type for various components of a function signature.
+    pub fn add_type_with_name(
+        &mut self,
+        schema: &Schema,
+        name_hint: Option<String>,
+    ) -> Result<Type<'_>> {
+        let name = match name_hint {
+            Some(s) => Name::Suggested(s),
             None => Name::Unknown,
         };
+        let type_id = self.add_type_id(name, schema)?;
+        Ok(self.get_type_for_id(&type_id))
+    }
+
+    fn add_type_id(&mut self, name: Name, schema: &Schema) -> Result<TypeId> {
+        self.check_settings()?;
+        depth::check(schema, Vec::new(), self.settings.recursion_limit())?;
         let (type_entry, _) = self.convert_schema(name, schema)?;
 
         let type_id = self.assign_type(type_entry);
-        let type_entry = self.id_to_entry.get(&type_id).unwrap();
-        Ok(Type {
+        self.split_read_write_only();
+        self.warn_ambiguous_variants();
+        self.check_defaults()?;
+        self.check_lossy()?;
+        Ok(type_id)
+    }
+
+    fn get_type_for_id(&self, type_id: &TypeId) -> Type<'_> {
+        self.get(type_id).unwrap()
+    }
+
+    /// The type with the given identifier, e.g. that of a member of another
+    /// type's [Type::details], or `None` if this [TypeSpace] has no such type.
+    pub fn get(&self, type_id: &TypeId) -> Option<Type<'_>> {
+        Some(Type {
             type_space: self,
-            type_entry,
+            type_id: type_id.clone(),
+            type_entry: self.id_to_entry.get(type_id)?,
         })
     }
 
+    /// The type of the definition that a `$ref` such as
+    /// `#/definitions/Instance` refers to, or of the definition of the given
+    /// name, whatever the type's name is after any patch, prefix, or suffix.
+    /// For a replaced definition this is the replacement, whose
+    /// [Type::ident] is its path.
+    pub fn get_type_by_ref(&self, reference: &str) -> Option<Type<'_>> {
+        let key = match reference.rfind('/') {
+            Some(idx) => &reference[idx + 1..],
+            None => reference,
+        };
+        self.get(self.ref_to_id.get(key)?)
+    }
+
+    /// The type with the given Rust name, e.g. `ApiInstance` with a prefix of
+    /// `Api`, or else the type of the definition with the given name.
+    pub fn get_type_by_name(&self, name: &str) -> Option<Type<'_>> {
+        match self.name_to_id.get(name) {
+            Some(type_id) => self.get(type_id),
+            None => self.get_type_by_ref(name),
+        }
+    }
+
+    /// Make sure that the settings given as strings are valid code, e.g. that
+    /// each derive is a path, before any code is generated with them.
+    fn check_settings(&self) -> Result<()> {
+        let settings = &self.settings;
+        let patch_derives = settings.patch.values().flat_map(|patch| &patch.derives);
+        for derive in settings.derives.iter().chain(patch_derives) {
+            syn::parse_str::<syn::Path>(derive).map_err(|e| {
+                Error::InvalidSetting(format!("derive {:?}", derive), e.to_string())
+            })?;
+        }
+        let visibilities = [
+            settings.type_visibility.as_ref(),
+            settings.field_visibility.as_ref(),
+            settings
+                .wrap_module
+                .as_ref()
+                .map(|(_, visibility)| visibility),
+        ];
+        for visibility in visibilities.iter().flatten() {
+            syn::parse_str::<syn::Visibility>(visibility).map_err(|e| {
+                Error::InvalidSetting(format!("visibility {:?}", visibility), e.to_string())
+            })?;
+        }
+        // The prefix and suffix must make identifiers of the names they join.
+        let check_ident = |setting: &str, value: &str, ident: String| {
+            syn::parse_str::<syn::Ident>(&ident).map_err(|_| {
+                Error::InvalidSetting(
+                    format!("{} {:?}", setting, value),
+                    "the names of types and modules must be identifiers".to_string(),
+                )
+            })
+        };
+        if let Some(prefix) = &settings.type_prefix {
+            check_ident("type prefix", prefix, format!("{}Type", prefix))?;
+        }
+        if let Some(suffix) = &settings.type_suffix {
+            check_ident("type suffix", suffix, format!("Type{}", suffix))?;
+        }
+        if let Some((name, _)) = &settings.wrap_module {
+            check_ident("module name", name, name.clone())?;
+        }
+        let renames = settings
+            .patch
+            .values()
+            .filter_map(|patch| patch.rename.as_ref());
+        for rename in renames {
+            check_ident("rename", rename, rename.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Make sure that each patch and root names a type, e.g. so that a
+    /// misspelled name isn't silently ignored.
+    fn check_patches(&self) -> Result<()> {
+        for type_name in self.settings.patch.keys() {
+            let name = self.settings.type_name(type_name);
+            if !self
+                .id_to_entry
+                .values()
+                .any(|type_entry| type_entry.name() == Some(&name))
+            {
+                return Err(Error::UnknownPatch(type_name.clone()));
+            }
+        }
+        for root in &self.settings.roots {
+            if self.get_type_by_name(root).is_none() {
+                return Err(Error::UnknownRoot(root.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Non-fatal issues encountered while converting schemas, such as bounds
+    /// that could not be represented by the generated types, along with
+    /// where they are.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Record that the generated types won't represent the keyword of the
+    /// schema being converted. A schema converted again, e.g. in another
+    /// form, gives the warning once.
+    pub(crate) fn warn<K: ToString, M: ToString>(&mut self, keyword: K, message: M) {
+        let warning = Warning {
+            location: SchemaLocation::default(),
+            keyword: keyword.to_string(),
+            message: message.to_string(),
+        };
+        if self.warnings.last() != Some(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// The result of `f`, converting the subschema at the path relative to
+    /// the schema being converted, with its error and the warnings it gives
+    /// located within that subschema.
+    pub(crate) fn within<T, F, const N: usize>(
+        &mut self,
+        segments: [PathSegment; N],
+        f: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let mark = self.warnings.len();
+        let result = f(self);
+        for warning in &mut self.warnings[mark..] {
+            warning.location.path.splice(0..0, segments.iter().cloned());
+        }
+        result.map_err(|e| e.within(segments))
+    }
+
+    /// With `with_deny_lossy(true)`, the first warning as an error.
+    fn check_lossy(&self) -> Result<()> {
+        match self.warnings.first() {
+            Some(warning) if self.settings.deny_lossy => Err(Error::At {
+                location: warning.location.clone(),
+                error: Box::new(Error::Lossy(
+                    warning.keyword.clone(),
+                    warning.message.clone(),
+                )),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     pub fn uses_chrono(&self) -> bool {
         self.uses_chrono
     }
 
+    pub fn uses_indexmap(&self) -> bool {
+        self.uses_indexmap
+    }
+
+    pub fn uses_regex(&self) -> bool {
+        self.uses_regex
+    }
+
+    pub fn uses_regress(&self) -> bool {
+        self.uses_regress
+    }
+
+    pub fn uses_rust_decimal(&self) -> bool {
+        self.uses_rust_decimal
+    }
+
+    pub fn uses_time(&self) -> bool {
+        self.uses_time
+    }
+
     pub fn uses_uuid(&self) -> bool {
         self.uses_uuid
     }
@@ -217,22 +1780,637 @@ impl TypeSpace {
     }
 
     /// Iterate over all types including those defined in this [TypeSpace] and
-    /// those referred to by those types.
-    pub fn iter_types(&self) -> impl Iterator<Item = Type> {
-        self.id_to_entry.values().map(move |type_entry| Type {
+    /// those referred to by those types. The order is the same for the same
+    /// schemas and settings: first the types of definitions in the order they
+    /// were added, then the other named types ordered by name, then the rest.
+    pub fn iter_types(&self) -> impl Iterator<Item = Type<'_>> {
+        let definitions = self.ref_to_id.values().collect::<BTreeSet<_>>();
+        let mut entries = self.id_to_entry.iter().collect::<Vec<_>>();
+        entries.sort_by_cached_key(|(type_id, type_entry)| {
+            if definitions.contains(type_id) {
+                (0, None, (*type_id).clone())
+            } else {
+                let name = type_entry.name().cloned();
+                (if name.is_some() { 1 } else { 2 }, name, (*type_id).clone())
+            }
+        });
+        entries.into_iter().map(move |(type_id, type_entry)| Type {
             type_space: self,
+            type_id: type_id.clone(),
             type_entry,
         })
     }
 
     pub fn to_stream(&self) -> TokenStream {
-        let type_defs = self.iter_types().map(|t| t.definition());
+        let output = match &self.settings.module_split {
+            Some(split) => self.to_split_stream(split),
+            None => {
+                let reachable = self.reachable_types();
+                let type_defs = self
+                    .iter_types()
+                    .filter(|t| reachable.contains(&t.type_id))
+                    .map(|t| t.definition());
+                let time_serde = self.time_serde();
+                let content_encoding = self.content_encoding();
+                let nested_serde = self.nested_serde();
+                let double_option = self.double_option();
+                let defaults = self.defaults_module();
+                let json_schema_impls = self.json_schema_impls();
+                let error = self.error_module();
+
+                quote! {
+                    #(#type_defs)*
+                    #json_schema_impls
+                    #error
+                    #time_serde
+                    #content_encoding
+                    #nested_serde
+                    #double_option
+                    #defaults
+                }
+            }
+        };
+
+        let output = match &self.settings.wrap_module {
+            Some((name, visibility)) => {
+                let name = format_ident!("{}", name);
+                let visibility = self::visibility(Some(visibility));
+                quote! {
+                    #visibility mod #name {
+                        use serde::{Deserialize, Serialize};
+
+                        #output
+                    }
+                }
+            }
+            None => output,
+        };
+        self.no_std_code(output, self.settings.wrap_module.is_none())
+    }
+
+    /// The error type of the generated conversions.
+    pub(crate) fn conversion_error(&self) -> TokenStream {
+        match &self.settings.conversion_error {
+            Some(path) => syn::parse_str::<syn::TypePath>(path)
+                .unwrap()
+                .into_token_stream(),
+            None => quote! { error::ConversionError },
+        }
+    }
+
+    /// The module of the generated error type if any of the types convert
+    /// with it and the settings don't replace it.
+    pub(crate) fn error_module(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let used = self.id_to_entry.iter().any(|(type_id, type_entry)| {
+            reachable.contains(type_id) && type_entry.uses_conversion_error(self)
+        });
+        if !used || self.settings.conversion_error.is_some() {
+            return quote! {};
+        }
+
+        quote! {
+            /// Error types for the generated conversions.
+            pub mod error {
+                /// The error from converting a value to a generated type,
+                /// e.g. one that doesn't satisfy the type's constraints.
+                pub struct ConversionError(std::borrow::Cow<'static, str>);
+
+                impl std::error::Error for ConversionError {}
+
+                impl std::fmt::Display for ConversionError {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        std::fmt::Display::fmt(&self.0, f)
+                    }
+                }
+
+                impl std::fmt::Debug for ConversionError {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        std::fmt::Debug::fmt(&self.0, f)
+                    }
+                }
+
+                impl From<&'static str> for ConversionError {
+                    fn from(value: &'static str) -> Self {
+                        Self(value.into())
+                    }
+                }
+
+                impl From<String> for ConversionError {
+                    fn from(value: String) -> Self {
+                        Self(value.into())
+                    }
+                }
+            }
+        }
+    }
+
+    /// The `time` crate has no built-in serde module for RFC 3339 dates and
+    /// times (only for date-times) so we generate the ones we need.
+    pub(crate) fn time_serde(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let uses = |name: &str| {
+            self.id_to_entry.iter().any(|(type_id, type_entry)| {
+                reachable.contains(type_id)
+                    && matches!(type_entry, TypeEntry::BuiltInWith(type_name, _, _) if type_name == name)
+            })
+        };
+
+        let date = uses("time::Date").then(|| {
+            quote! {
+                ::time::serde::format_description!(pub date, Date, "[year]-[month]-[day]");
+            }
+        });
+        let time = uses("time::Time").then(|| {
+            quote! {
+                ::time::serde::format_description!(
+                    pub time,
+                    Time,
+                    "[hour]:[minute]:[second][optional [.[subsecond]]]"
+                );
+            }
+        });
+
+        if date.is_none() && time.is_none() {
+            quote! {}
+        } else {
+            quote! {
+                /// Serde helpers for `time` types without well-known formats.
+                pub mod time_serde {
+                    #date
+                    #time
+                }
+            }
+        }
+    }
+
+    /// The serde helpers for strings of binary data, which encode bytes as
+    /// the schemas' `contentEncoding`s say, for the encodings in use.
+    pub(crate) fn content_encoding(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let uses = |module: &str| {
+            self.id_to_entry.iter().any(|(type_id, type_entry)| {
+                reachable.contains(type_id)
+                    && matches!(type_entry, TypeEntry::BuiltInWith(_, with, _) if with == module)
+            })
+        };
+
+        let base64 = uses("content_encoding::base64").then(|| {
+            let alphabet = proc_macro2::Literal::byte_string(
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+            );
+            let serde_fns = encoding_serde_fns("base64");
+            quote! {
+                /// Base64 with the standard alphabet and padding, which may
+                /// be omitted when decoding.
+                pub mod base64 {
+                    const ALPHABET: &[u8; 64] = #alphabet;
+
+                    pub fn encode(bytes: &[u8]) -> String {
+                        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+                        for chunk in bytes.chunks(3) {
+                            let n = chunk
+                                .iter()
+                                .enumerate()
+                                .fold(0u32, |n, (i, byte)| n | ((*byte as u32) << (16 - 8 * i)));
+                            for i in 0..4 {
+                                if i <= chunk.len() {
+                                    encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+                                } else {
+                                    encoded.push('=');
+                                }
+                            }
+                        }
+                        encoded
+                    }
+
+                    pub fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+                        let digits = encoded.trim_end_matches('=');
+                        if encoded.len() - digits.len() > 2 || digits.len() % 4 == 1 {
+                            return Err("invalid length".to_string());
+                        }
+                        let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+                        for chunk in digits.as_bytes().chunks(4) {
+                            let mut n = 0u32;
+                            for (i, digit) in chunk.iter().enumerate() {
+                                let value = ALPHABET
+                                    .iter()
+                                    .position(|c| c == digit)
+                                    .ok_or_else(|| format!("invalid character {:?}", *digit as char))?;
+                                n |= (value as u32) << (18 - 6 * i);
+                            }
+                            for i in 0..chunk.len() - 1 {
+                                bytes.push((n >> (16 - 8 * i)) as u8);
+                            }
+                        }
+                        Ok(bytes)
+                    }
+
+                    #serde_fns
+                }
+            }
+        });
+        let base16 = uses("content_encoding::base16").then(|| {
+            let serde_fns = encoding_serde_fns("base16");
+            quote! {
+                /// Base16, i.e. hexadecimal, encoded in lowercase and decoded
+                /// in either case.
+                pub mod base16 {
+                    pub fn encode(bytes: &[u8]) -> String {
+                        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+                    }
+
+                    pub fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+                        if encoded.len() % 2 == 1 {
+                            return Err("invalid length".to_string());
+                        }
+                        encoded
+                            .as_bytes()
+                            .chunks(2)
+                            .map(|pair| match std::str::from_utf8(pair) {
+                                Ok(digits) if pair.iter().all(u8::is_ascii_hexdigit) => {
+                                    Ok(u8::from_str_radix(digits, 16).unwrap())
+                                }
+                                _ => Err(format!(
+                                    "invalid digits {:?}",
+                                    String::from_utf8_lossy(pair)
+                                )),
+                            })
+                            .collect()
+                    }
+
+                    #serde_fns
+                }
+            }
+        });
+
+        if base64.is_none() && base16.is_none() {
+            quote! {}
+        } else {
+            quote! {
+                /// Serde helpers for strings of binary data.
+                pub mod content_encoding {
+                    #base64
+                    #base16
+                }
+            }
+        }
+    }
+
+    /// The serde helpers for arrays, sets, and maps of values of built-in
+    /// types with serde modules, such as `Vec<time::OffsetDateTime>`, and
+    /// `Option`s of those, which (de)serialize each value with its module:
+    /// one module for each module of the values, holding `seq`, `map`,
+    /// `option_seq`, and `option_map`.
+    pub(crate) fn nested_serde(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let values = self
+            .id_to_entry
+            .iter()
+            .filter(|(type_id, _)| reachable.contains(type_id))
+            .filter_map(|(_, type_entry)| type_entry.nested_with(self))
+            .map(|(_, type_name, module)| (module, type_name))
+            .collect::<BTreeMap<_, _>>();
+        if values.is_empty() {
+            return quote! {};
+        }
+
+        let modules = values.into_iter().map(|(module, type_name)| {
+            let name = format_ident!("{}", type_entry::nested_module_name(module));
+            let doc = format!("Collections of values that `{}` (de)serializes.", module);
+            // The modules of the generated code are siblings of this one.
+            let module = if module.starts_with("time_serde::")
+                || module.starts_with("content_encoding::")
+            {
+                syn::parse_str::<syn::Path>(&format!("super::super::{}", module))
+            } else {
+                syn::parse_str::<syn::Path>(&format!("::{}", module))
+            }
+            .unwrap();
+            let type_name = syn::parse_str::<syn::Type>(type_name).unwrap();
+            quote! {
+                #[doc = #doc]
+                pub mod #name {
+                    type Value = #type_name;
+
+                    struct Ref<'a>(&'a Value);
+
+                    impl serde::Serialize for Ref<'_> {
+                        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: serde::Serializer,
+                        {
+                            #module::serialize(self.0, serializer)
+                        }
+                    }
+
+                    struct Owned(Value);
+
+                    impl<'de> serde::Deserialize<'de> for Owned {
+                        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                        where
+                            D: serde::Deserializer<'de>,
+                        {
+                            #module::deserialize(deserializer).map(Owned)
+                        }
+                    }
+
+                    struct SeqRef<'a, C>(&'a C);
+
+                    impl<'a, C> serde::Serialize for SeqRef<'a, C>
+                    where
+                        &'a C: IntoIterator<Item = &'a Value>,
+                    {
+                        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: serde::Serializer,
+                        {
+                            serializer.collect_seq(self.0.into_iter().map(Ref))
+                        }
+                    }
+
+                    struct SeqOwned<C>(C);
+
+                    impl<'de, C> serde::Deserialize<'de> for SeqOwned<C>
+                    where
+                        C: core::iter::FromIterator<Value>,
+                    {
+                        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                        where
+                            D: serde::Deserializer<'de>,
+                        {
+                            struct Visitor<C>(core::marker::PhantomData<C>);
+
+                            impl<'de, C> serde::de::Visitor<'de> for Visitor<C>
+                            where
+                                C: core::iter::FromIterator<Value>,
+                            {
+                                type Value = C;
+
+                                fn expecting(
+                                    &self,
+                                    formatter: &mut core::fmt::Formatter,
+                                ) -> core::fmt::Result {
+                                    formatter.write_str("a sequence")
+                                }
+
+                                fn visit_seq<A>(self, mut seq: A) -> Result<C, A::Error>
+                                where
+                                    A: serde::de::SeqAccess<'de>,
+                                {
+                                    core::iter::from_fn(|| seq.next_element::<Owned>().transpose())
+                                        .map(|value| value.map(|Owned(value)| value))
+                                        .collect()
+                                }
+                            }
+
+                            deserializer
+                                .deserialize_seq(Visitor(core::marker::PhantomData))
+                                .map(SeqOwned)
+                        }
+                    }
+
+                    struct MapRef<'a, C, K>(&'a C, core::marker::PhantomData<K>);
+
+                    impl<'a, C, K> serde::Serialize for MapRef<'a, C, K>
+                    where
+                        &'a C: IntoIterator<Item = (&'a K, &'a Value)>,
+                        K: serde::Serialize + 'a,
+                    {
+                        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            S: serde::Serializer,
+                        {
+                            serializer
+                                .collect_map(self.0.into_iter().map(|(key, value)| (key, Ref(value))))
+                        }
+                    }
+
+                    struct MapOwned<C, K>(C, core::marker::PhantomData<K>);
+
+                    impl<'de, C, K> serde::Deserialize<'de> for MapOwned<C, K>
+                    where
+                        C: core::iter::FromIterator<(K, Value)>,
+                        K: serde::Deserialize<'de>,
+                    {
+                        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                        where
+                            D: serde::Deserializer<'de>,
+                        {
+                            struct Visitor<C, K>(core::marker::PhantomData<(C, K)>);
+
+                            impl<'de, C, K> serde::de::Visitor<'de> for Visitor<C, K>
+                            where
+                                C: core::iter::FromIterator<(K, Value)>,
+                                K: serde::Deserialize<'de>,
+                            {
+                                type Value = C;
+
+                                fn expecting(
+                                    &self,
+                                    formatter: &mut core::fmt::Formatter,
+                                ) -> core::fmt::Result {
+                                    formatter.write_str("a map")
+                                }
+
+                                fn visit_map<A>(self, mut map: A) -> Result<C, A::Error>
+                                where
+                                    A: serde::de::MapAccess<'de>,
+                                {
+                                    core::iter::from_fn(|| map.next_entry::<K, Owned>().transpose())
+                                        .map(|entry| entry.map(|(key, Owned(value))| (key, value)))
+                                        .collect()
+                                }
+                            }
+
+                            deserializer
+                                .deserialize_map(Visitor(core::marker::PhantomData))
+                                .map(|values| MapOwned(values, core::marker::PhantomData))
+                        }
+                    }
+
+                    pub mod seq {
+                        use super::{SeqOwned, SeqRef, Value};
+
+                        pub fn serialize<'a, C, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            &'a C: IntoIterator<Item = &'a Value>,
+                            S: serde::Serializer,
+                        {
+                            serde::Serialize::serialize(&SeqRef(values), serializer)
+                        }
+
+                        pub fn deserialize<'de, C, D>(deserializer: D) -> Result<C, D::Error>
+                        where
+                            C: core::iter::FromIterator<Value>,
+                            D: serde::Deserializer<'de>,
+                        {
+                            <SeqOwned<C> as serde::Deserialize>::deserialize(deserializer)
+                                .map(|SeqOwned(values)| values)
+                        }
+                    }
+
+                    pub mod map {
+                        use super::{MapOwned, MapRef, Value};
+
+                        pub fn serialize<'a, C, K, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+                        where
+                            &'a C: IntoIterator<Item = (&'a K, &'a Value)>,
+                            K: serde::Serialize + 'a,
+                            S: serde::Serializer,
+                        {
+                            serde::Serialize::serialize(
+                                &MapRef(values, core::marker::PhantomData),
+                                serializer,
+                            )
+                        }
+
+                        pub fn deserialize<'de, C, K, D>(deserializer: D) -> Result<C, D::Error>
+                        where
+                            C: core::iter::FromIterator<(K, Value)>,
+                            K: serde::Deserialize<'de>,
+                            D: serde::Deserializer<'de>,
+                        {
+                            <MapOwned<C, K> as serde::Deserialize>::deserialize(deserializer)
+                                .map(|MapOwned(values, _)| values)
+                        }
+                    }
+
+                    pub mod option_seq {
+                        use super::{SeqOwned, SeqRef, Value};
+
+                        pub fn serialize<'a, C, S>(
+                            values: &'a Option<C>,
+                            serializer: S,
+                        ) -> Result<S::Ok, S::Error>
+                        where
+                            &'a C: IntoIterator<Item = &'a Value>,
+                            S: serde::Serializer,
+                        {
+                            match values {
+                                Some(values) => serializer.serialize_some(&SeqRef(values)),
+                                None => serializer.serialize_none(),
+                            }
+                        }
+
+                        pub fn deserialize<'de, C, D>(deserializer: D) -> Result<Option<C>, D::Error>
+                        where
+                            C: core::iter::FromIterator<Value>,
+                            D: serde::Deserializer<'de>,
+                        {
+                            <Option<SeqOwned<C>> as serde::Deserialize>::deserialize(deserializer)
+                                .map(|values| values.map(|SeqOwned(values)| values))
+                        }
+                    }
+
+                    pub mod option_map {
+                        use super::{MapOwned, MapRef, Value};
+
+                        pub fn serialize<'a, C, K, S>(
+                            values: &'a Option<C>,
+                            serializer: S,
+                        ) -> Result<S::Ok, S::Error>
+                        where
+                            &'a C: IntoIterator<Item = (&'a K, &'a Value)>,
+                            K: serde::Serialize + 'a,
+                            S: serde::Serializer,
+                        {
+                            match values {
+                                Some(values) => serializer
+                                    .serialize_some(&MapRef(values, core::marker::PhantomData)),
+                                None => serializer.serialize_none(),
+                            }
+                        }
+
+                        pub fn deserialize<'de, C, K, D>(
+                            deserializer: D,
+                        ) -> Result<Option<C>, D::Error>
+                        where
+                            C: core::iter::FromIterator<(K, Value)>,
+                            K: serde::Deserialize<'de>,
+                            D: serde::Deserializer<'de>,
+                        {
+                            <Option<MapOwned<C, K>> as serde::Deserialize>::deserialize(deserializer)
+                                .map(|values| values.map(|MapOwned(values, _)| values))
+                        }
+                    }
+                }
+            }
+        });
+
+        quote! {
+            /// Serde helpers for arrays, sets, and maps of values with serde
+            /// modules of their own.
+            pub mod nested_serde {
+                #(#modules)*
+            }
+        }
+    }
+
+    /// The serde helpers for the fields of properties whose null differs
+    /// from their absence, if there are any. Absent properties take the
+    /// default and aren't serialized, so these handle just the inner option.
+    pub(crate) fn double_option(&self) -> TokenStream {
+        let reachable = self.reachable_types();
+        let explicit_null = |properties: &[StructProperty]| {
+            properties
+                .iter()
+                .any(|prop| prop.serde_rules == type_entry::SerdeRules::ExplicitNull)
+        };
+        let used = self
+            .id_to_entry
+            .iter()
+            .filter(|(type_id, _)| reachable.contains(type_id))
+            .any(|(_, type_entry)| match type_entry {
+                TypeEntry::Struct(TypeEntryStruct { properties, .. }) => explicit_null(properties),
+                TypeEntry::Enum(TypeEntryEnum { variants, .. }) => {
+                    variants.iter().any(|variant| match &variant.details {
+                        VariantDetails::Struct(properties) => explicit_null(properties),
+                        _ => false,
+                    })
+                }
+                _ => false,
+            });
+        if !used {
+            return quote! {};
+        }
 
         quote! {
-            #(#type_defs)*
+            /// Serde helpers for properties whose null differs from their
+            /// absence.
+            mod double_option {
+                pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    T: serde::Serialize,
+                    S: serde::Serializer,
+                {
+                    match value {
+                        Some(value) => serde::Serialize::serialize(value, serializer),
+                        None => serializer.serialize_none(),
+                    }
+                }
+
+                pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+                where
+                    T: serde::Deserialize<'de>,
+                    D: serde::Deserializer<'de>,
+                {
+                    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+                }
+            }
         }
     }
 
+    /// The visibility of the generated types.
+    pub(crate) fn type_visibility(&self) -> TokenStream {
+        visibility(self.settings.type_visibility.as_deref())
+    }
+
+    /// The visibility of the fields of generated structs and newtypes.
+    pub(crate) fn field_visibility(&self) -> TokenStream {
+        visibility(self.settings.field_visibility.as_deref())
+    }
+
     /// Allocated the next TypeId.
     fn assign(&mut self) -> TypeId {
         let id = TypeId(self.next_id);
@@ -245,28 +2423,36 @@ impl TypeSpace {
     /// two conflicting types of the same name), and deduplicates various
     /// flavors of built-in types.
     fn assign_type(&mut self, ty: TypeEntry) -> TypeId {
+        let ty = self.apply_patch(ty);
         if let TypeEntry::Reference(type_id) = ty {
             type_id
         } else if let Some(name) = ty.name() {
-            // If there's already a type of this name, we make sure it's
-            // identical.
-
-            // TODO there are many different choices we might make here
-            // that could differ depending on the texture of the schema.
-            // For example, a schema might use the string "Response" in a
-            // bunch of places and if that were the case we might expect
-            // them to be different and resolve that by renaming or scoping
-            // them in some way.
-            if let Some(type_id) = self.name_to_id.get(name) {
-                let existing_ty = self.id_to_entry.get(type_id).unwrap();
-                assert_eq!(existing_ty, &ty);
-                type_id.clone()
-            } else {
-                let type_id = self.assign();
-                self.name_to_id.insert(name.clone(), type_id.clone());
-                self.id_to_entry.insert(type_id.clone(), ty);
-                type_id
+            // If there's already a type of this name, we use it if it's
+            // identical. Otherwise this type takes the name with the first
+            // number after it that no different type has, so that the
+            // same schema gets the same name each time. The names of
+            // inline subschemas already avoid most such collisions by
+            // reflecting their paths.
+            let base_name = name.clone();
+            let mut ty = ty;
+            let candidates = std::iter::once(base_name.clone())
+                .chain((2..).map(|n| format!("{}{}", base_name, n)));
+            for candidate in candidates {
+                *ty.name_mut().unwrap().0 = candidate.clone();
+                match self.name_to_id.get(&candidate) {
+                    Some(type_id) if self.id_to_entry.get(type_id) == Some(&ty) => {
+                        return type_id.clone();
+                    }
+                    Some(_) => {}
+                    None => {
+                        let type_id = self.assign();
+                        self.name_to_id.insert(candidate, type_id.clone());
+                        self.id_to_entry.insert(type_id.clone(), ty);
+                        return type_id;
+                    }
+                }
             }
+            unreachable!()
         } else if let Some(type_id) = self.type_to_id.get(&ty) {
             type_id.clone()
         } else {
@@ -277,50 +2463,246 @@ impl TypeSpace {
         }
     }
 
-    /// Convert a schema to a TypeEntry and assign it a TypeId.
+    /// Apply the settings that alter a named type: rename it as its patch
+    /// or else the prefix and suffix prescribe, keeping its serialized name,
+    /// and append its patch's paragraphs to its documentation.
+    fn apply_patch(&self, mut ty: TypeEntry) -> TypeEntry {
+        let patch = ty.name().and_then(|name| self.settings.patch.get(name));
+
+        if let (Some(patch), Some(description)) = (patch, ty.description_mut()) {
+            for paragraph in &patch.docs {
+                *description = Some(match description.take() {
+                    Some(doc) => format!("{}\n\n{}", doc, paragraph),
+                    None => paragraph.clone(),
+                });
+            }
+        }
+
+        if let Some((name, rename)) = ty.name_mut() {
+            let new_name = self.settings.type_name(name);
+            if &new_name != name {
+                if rename.is_none() {
+                    *rename = Some(name.clone());
+                }
+                *name = new_name;
+            }
+        }
+        ty
+    }
+
+    /// Convert a schema to a TypeEntry and assign it a TypeId. Inline
+    /// subschemas, i.e. those with suggested names, that are identical but
+    /// for their titles and descriptions share a type unless the settings
+    /// say otherwise.
     fn id_for_schema<'a>(
         &mut self,
         type_name: Name,
         schema: &'a Schema,
     ) -> Result<(TypeId, &'a Option<Box<Metadata>>)> {
+        let inline = match (&type_name, schema) {
+            (Name::Suggested(_), Schema::Object(object))
+                if !self.settings.distinct_inline_types =>
+            {
+                Some((dedup::inline_key(object), object))
+            }
+            _ => None,
+        };
+        if let Some((key, object)) = &inline {
+            if let Some(InlineType {
+                type_id,
+                own_metadata,
+                ..
+            }) = self.inline_types.get(key).cloned()
+            {
+                // The title and description next to a reference are those of
+                // the use of the definition, such as a variant, not its own.
+                if object.reference.is_none() {
+                    self.prefer_title(key, &object.metadata);
+                    self.prefer_description(key, &object.metadata);
+                }
+                let meta = if own_metadata {
+                    &object.metadata
+                } else {
+                    &None
+                };
+                return Ok((type_id, meta));
+            }
+        }
+
+        let type_name = self.untaken_name(type_name, schema);
         let (ty, meta) = self.convert_schema(type_name, schema)?;
         let type_id = self.assign_type(ty);
+
+        // We can only reuse the type if the metadata we return for it is
+        // the subschema's own or none, which we can return for the others.
+        if let Some((key, object)) = inline {
+            let own_metadata = std::ptr::eq(meta, &object.metadata);
+            if own_metadata || meta.is_none() {
+                self.inline_types.insert(
+                    key,
+                    InlineType {
+                        type_id: type_id.clone(),
+                        titled: metadata_title(&object.metadata).is_some(),
+                        own_metadata,
+                    },
+                );
+            }
+        }
         Ok((type_id, meta))
     }
 
+    /// The name for a subschema whose title another type has already, such
+    /// as the definition it's within: more of its path, i.e. the suggested
+    /// name, or else the title and `Inner`, or the other way around if the
+    /// suggested name just repeats the title, e.g. `ConfigInner` before
+    /// `ConfigConfig` for the property `config` of `Config`. If those are
+    /// taken too, or the title isn't, the title is the name as usual and
+    /// `assign_type` numbers it if need be.
+    fn untaken_name(&self, type_name: Name, schema: &Schema) -> Name {
+        let (suggested, title) = match (&type_name, schema) {
+            (Name::Suggested(suggested), Schema::Object(SchemaObject { metadata, .. })) => {
+                match metadata_title(metadata) {
+                    Some(title) => (
+                        util::sanitize(suggested, convert_case::Case::Pascal),
+                        util::sanitize(&title, convert_case::Case::Pascal),
+                    ),
+                    None => return type_name,
+                }
+            }
+            _ => return type_name,
+        };
+        if !self.name_taken(&title) {
+            return type_name;
+        }
+        let inner = format!("{}Inner", title);
+        let names = match suggested == format!("{}{}", title, title) {
+            true => vec![inner, suggested],
+            false => vec![suggested, inner],
+        };
+        names
+            .into_iter()
+            .find(|name| *name != title && !self.name_taken(name))
+            .map(Name::Required)
+            .unwrap_or(type_name)
+    }
+
+    /// Whether a type, or a definition yet to be converted, has the name,
+    /// which is before any prefix or suffix.
+    pub(crate) fn name_taken(&self, name: &str) -> bool {
+        self.name_to_id.contains_key(&self.settings.type_name(name))
+    }
+
     /// Create an Option<T> from a pre-assigned TypeId and assign it an ID.
     fn id_to_option(&mut self, id: &TypeId) -> TypeId {
         self.assign_type(TypeEntry::Option(id.clone()))
     }
 
     // Create an Option<T> from a TypeEntry by assigning it type.
+    /// The path of the type that represents maps.
+    pub(crate) fn map_type(&self) -> &'static str {
+        match self.settings.map_type {
+            _ if self.settings.no_std => "alloc::collections::BTreeMap",
+            Some(MapType::BTreeMap) => "std::collections::BTreeMap",
+            Some(MapType::IndexMap) if cfg!(feature = "indexmap") => "indexmap::IndexMap",
+            Some(MapType::HashMap) | Some(MapType::IndexMap) | None => "std::collections::HashMap",
+        }
+    }
+
     fn type_to_option(&mut self, ty: TypeEntry) -> TypeEntry {
         TypeEntry::Option(self.assign_type(ty))
     }
 }
 
-impl ToString for TypeSpace {
-    fn to_string(&self) -> String {
-        rustfmt(self.to_stream().to_string()).unwrap()
+/// The name of the type of a definition, which is the last part of its
+/// name if it's a path.
+fn definition_type_name(ref_name: &str) -> &str {
+    match ref_name.rfind('/') {
+        Some(idx) => &ref_name[idx..],
+        None => ref_name,
     }
 }
 
-impl<'a> Type<'a> {
-    /// The name of the type as a String.
-    pub fn name(&self) -> String {
-        let Type {
-            type_space,
-            type_entry,
-        } = self;
-        type_entry.type_name(type_space)
-    }
+/// The given visibility, `pub` by default. `TypeSpace::check_settings` has
+/// made sure that it parses.
+fn visibility(visibility: Option<&str>) -> TokenStream {
+    let visibility = visibility.unwrap_or("pub");
+    syn::parse_str::<syn::Visibility>(visibility)
+        .unwrap_or_else(|e| panic!("invalid visibility {:?}: {}", visibility, e))
+        .to_token_stream()
+}
 
-    /// The identifier for the type as might be used for a function return or
-    /// defining the type of a member of a struct..
-    pub fn ident(&self) -> TokenStream {
-        let Type {
-            type_space,
-            type_entry,
+/// The serde functions of a `content_encoding` module, for bytes and for
+/// optional bytes, in terms of its `encode` and `decode`.
+fn encoding_serde_fns(encoding: &str) -> TokenStream {
+    let invalid = format!("invalid {} {{:?}}: {{}}", encoding);
+    quote! {
+        pub fn serialize<S>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_str(&encode(value))
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let encoded = <String as serde::Deserialize>::deserialize(deserializer)?;
+            decode(&encoded).map_err(|e| {
+                <D::Error as serde::de::Error>::custom(format!(#invalid, encoded, e))
+            })
+        }
+
+        pub mod option {
+            pub fn serialize<S>(value: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                match value {
+                    Some(value) => super::serialize(value, serializer),
+                    None => serializer.serialize_none(),
+                }
+            }
+
+            pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                match <Option<String> as serde::Deserialize>::deserialize(deserializer)? {
+                    Some(encoded) => super::decode(&encoded).map(Some).map_err(|e| {
+                        <D::Error as serde::de::Error>::custom(format!(#invalid, encoded, e))
+                    }),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TypeSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string_pretty().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl<'a> Type<'a> {
+    /// The name of the type as a String.
+    pub fn name(&self) -> String {
+        let Type {
+            type_space,
+            type_entry,
+            ..
+        } = self;
+        type_entry.type_name(type_space)
+    }
+
+    /// The identifier for the type as might be used for a function return or
+    /// defining the type of a member of a struct..
+    pub fn ident(&self) -> TokenStream {
+        let Type {
+            type_space,
+            type_entry,
+            ..
         } = self;
         type_entry.type_ident(type_space, true)
     }
@@ -332,6 +2714,7 @@ impl<'a> Type<'a> {
         let Type {
             type_space,
             type_entry,
+            ..
         } = self;
         type_entry.type_parameter_ident(type_space)
     }
@@ -342,6 +2725,7 @@ impl<'a> Type<'a> {
         let Type {
             type_space,
             type_entry,
+            ..
         } = self;
         type_entry.output(type_space)
     }
@@ -350,6 +2734,69 @@ impl<'a> Type<'a> {
     pub fn describe(&self) -> String {
         self.type_entry.describe()
     }
+
+    /// The identifier of the type within its [TypeSpace].
+    pub fn id(&self) -> &TypeId {
+        &self.type_id
+    }
+
+    /// The structure of the type, e.g. the fields of a struct and their
+    /// types, to inspect what was generated.
+    pub fn details(&self) -> TypeDetails<'a> {
+        let fields = |properties: &[StructProperty]| {
+            properties
+                .iter()
+                .map(|prop| (prop.name.clone(), prop.type_id.clone()))
+                .collect()
+        };
+        match self.type_entry {
+            TypeEntry::Struct(TypeEntryStruct { properties, .. }) => TypeDetails::Struct {
+                properties: fields(properties),
+            },
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => TypeDetails::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        let details = match &variant.details {
+                            VariantDetails::Simple
+                            | VariantDetails::Integer(_)
+                            | VariantDetails::Unknown => TypeVariant::Unit,
+                            VariantDetails::Other(type_id) => {
+                                TypeVariant::Tuple(vec![type_id.clone()])
+                            }
+                            VariantDetails::Tuple(types) => TypeVariant::Tuple(types.clone()),
+                            VariantDetails::Struct(properties) => {
+                                TypeVariant::Struct(fields(properties))
+                            }
+                        };
+                        (variant.name.clone(), details)
+                    })
+                    .collect(),
+            },
+            TypeEntry::Newtype(TypeEntryNewtype { type_id, .. }) => {
+                TypeDetails::Newtype(type_id.clone())
+            }
+            TypeEntry::Alias(TypeEntryAlias { type_id, .. }) => TypeDetails::Alias(type_id.clone()),
+            TypeEntry::Option(type_id) => TypeDetails::Option(type_id.clone()),
+            TypeEntry::Array(type_id) => TypeDetails::Vec(type_id.clone()),
+            TypeEntry::FixedArray(type_id, length) => TypeDetails::Array(type_id.clone(), *length),
+            TypeEntry::Map(key_id, value_id) => TypeDetails::Map(key_id.clone(), value_id.clone()),
+            TypeEntry::Set(type_id) => TypeDetails::Set(type_id.clone()),
+            TypeEntry::Box(type_id) => TypeDetails::Box(type_id.clone()),
+            TypeEntry::Tuple(types) => TypeDetails::Tuple(types.clone()),
+            TypeEntry::Unit => TypeDetails::Unit,
+            TypeEntry::BuiltIn(type_name)
+            | TypeEntry::BuiltInWith(type_name, _, _)
+            | TypeEntry::Integral(type_name)
+            | TypeEntry::Float(type_name) => TypeDetails::Builtin(type_name),
+            TypeEntry::BuiltInGeneric(type_name, parameters) => {
+                TypeDetails::Generic(type_name, parameters.clone())
+            }
+            TypeEntry::String => TypeDetails::Builtin("String"),
+            // A reference is the type it refers to.
+            TypeEntry::Reference(type_id) => self.type_space.get_type_for_id(type_id).details(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -361,9 +2808,11 @@ mod tests {
     use std::collections::HashSet;
 
     use crate::{
-        test_util::validate_output,
+        path_type_name,
+        test_util::{assert_output, assert_stream_output, validate_output},
         type_entry::{TypeEntryEnum, VariantDetails},
-        Name, TypeEntry, TypeSpace,
+        ModuleSplit, Name, NamedSimpleTypes, NonExhaustive, PathSegment, SkipSerializingNone,
+        TypeDetails, TypeEntry, TypePatch, TypeSpace, TypeSpaceSettings, TypeVariant,
     };
 
     #[allow(dead_code)]
@@ -489,7 +2938,12 @@ mod tests {
 
         let mut type_space = TypeSpace::default();
         let (te, _) = type_space
-            .convert_enum_string(Name::Required("OnTheGo".to_string()), &None, &enum_values)
+            .convert_enum_string(
+                Name::Required("OnTheGo".to_string()),
+                &None,
+                &enum_values,
+                &schemars::Map::new(),
+            )
             .unwrap();
 
         if let TypeEntry::Option(id) = &te {
@@ -520,6 +2974,7 @@ mod tests {
 
     #[test]
     fn test_alias() {
+        #[allow(dead_code)]
         #[derive(JsonSchema, Schema)]
         struct Stuff(Vec<String>);
 
@@ -532,4 +2987,1386 @@ mod tests {
 
         validate_output::<Things>();
     }
+
+    #[test]
+    fn test_add_root_schema() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Shelf",
+            "type": "array",
+            "items": { "$ref": "#/$defs/Book" },
+            "$defs": {
+                "Book": {
+                    "type": "object",
+                    "properties": { "title": { "type": "string" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        let ty = type_space.add_root_schema(schema).unwrap().unwrap();
+        assert_eq!(ty.name().replace(' ', ""), "Vec<Book>");
+        assert_output(&type_space, "lib/add_root_schema");
+
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": { "Name": { "type": "string" } }
+        }))
+        .unwrap();
+        assert!(type_space.add_root_schema(schema).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_add_type_with_name() {
+        let mut type_space = TypeSpace::default();
+        let schema = serde_json::from_value::<schemars::schema::Schema>(json!({
+            "type": "object",
+            "properties": { "name": { "$ref": "#/definitions/Name" } }
+        }))
+        .unwrap();
+
+        // The definition must come first.
+        let err = type_space
+            .add_type_with_name(&schema, Some("Author".to_string()))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("#/definitions/Name"), "{}", err);
+
+        type_space
+            .add_ref_types([(
+                "Name",
+                serde_json::from_value::<schemars::schema::Schema>(json!({ "type": "string" }))
+                    .unwrap(),
+            )])
+            .unwrap();
+        let ty = type_space
+            .add_type_with_name(&schema, Some("Author".to_string()))
+            .unwrap();
+        assert_eq!(ty.name(), "Author");
+        assert_output(&type_space, "lib/add_type_with_name");
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn test_add_yaml_schema() {
+        let mut type_space = TypeSpace::default();
+        let ty = type_space
+            .add_yaml_schema(
+                r##"
+                title: Shelf
+                type: array
+                items:
+                  $ref: "#/definitions/Book"
+                definitions:
+                  Book:
+                    type: object
+                    properties:
+                      title:
+                        type: string
+                "##,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(ty.name().replace(' ', ""), "Vec<Book>");
+
+        match type_space.add_yaml_schema("title: Shelf\ntype: [array\n") {
+            Err(crate::Error::Document(_, reason)) => {
+                assert!(reason.contains("line 3 column 1"), "{}", reason);
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_derive_replace_patch() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "required": ["id", "tags"],
+                    "properties": {
+                        "id": { "$ref": "#/definitions/Id" },
+                        "tags": { "type": "array", "items": { "$ref": "#/definitions/Tag" } }
+                    }
+                },
+                "Id": { "type": "string", "format": "uuid" },
+                "Tag": {
+                    "type": "string",
+                    "enum": ["a", "b"]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_derive("PartialEq")
+            .with_derive("schemars::JsonSchema")
+            .with_replacement("Id", "my_crate::Id", ["PartialEq", "Eq", "Hash"])
+            .with_patch(
+                "Thing",
+                TypePatch::default()
+                    .with_rename("MyThing")
+                    .with_derive("Eq"),
+            );
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Derives aren't repeated.
+        assert_output(&type_space, "lib/derive_replace_patch");
+    }
+
+    #[test]
+    fn test_patch_doc() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Error": {
+                    "description": "An error.",
+                    "type": "object",
+                    "properties": { "message": { "type": "string" } }
+                },
+                "Failure": {
+                    "type": "object",
+                    "properties": { "error": { "$ref": "#/definitions/Error" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_patch(
+            "Error",
+            TypePatch::default()
+                .with_rename("ApiError")
+                .with_doc("Returned by every endpoint."),
+        );
+        let mut type_space = TypeSpace::new(&settings);
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "lib/patch_doc");
+
+        // A patch for a type that doesn't exist is likely a typo.
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_patch("Eror", TypePatch::default().with_derive("Hash"));
+        let error = TypeSpace::new(&settings)
+            .add_ref_types(schema.definitions)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "patch for Eror, which isn't a type");
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Error": { "type": "object" }
+            }
+        }))
+        .unwrap();
+
+        let mut derive = TypeSpaceSettings::default();
+        derive.with_derive("Foo(");
+        let mut patch_derive = TypeSpaceSettings::default();
+        patch_derive.with_patch("Error", TypePatch::default().with_derive("Hash Eq"));
+
+        let mut type_visibility = TypeSpaceSettings::default();
+        type_visibility.with_type_visibility("pub(");
+        let mut field_visibility = TypeSpaceSettings::default();
+        field_visibility.with_field_visibility("public");
+        let mut module = TypeSpaceSettings::default();
+        module.with_module("types", "pub(crate");
+        let mut prefix = TypeSpaceSettings::default();
+        prefix.with_type_prefix("9-");
+        let mut suffix = TypeSpaceSettings::default();
+        suffix.with_type_suffix("::X");
+        let mut module_name = TypeSpaceSettings::default();
+        module_name.with_module("a b", "pub");
+        let mut rename = TypeSpaceSettings::default();
+        rename.with_patch("Error", TypePatch::default().with_rename("not valid"));
+
+        for (settings, setting) in [
+            (derive, "derive \"Foo(\""),
+            (patch_derive, "derive \"Hash Eq\""),
+            (type_visibility, "visibility \"pub(\""),
+            (field_visibility, "visibility \"public\""),
+            (module, "visibility \"pub(crate\""),
+            (prefix, "type prefix \"9-\""),
+            (suffix, "type suffix \"::X\""),
+            (module_name, "module name \"a b\""),
+            (rename, "rename \"not valid\""),
+        ] {
+            let error = TypeSpace::new(&settings)
+                .add_ref_types(schema.definitions.clone())
+                .unwrap_err();
+            assert!(
+                matches!(&error, crate::Error::InvalidSetting(invalid, _) if invalid == setting),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_type_prefix_and_module() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Config": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/definitions/Id" },
+                        "mode": { "type": "string", "enum": ["fast", "slow"] }
+                    }
+                },
+                "Error": { "type": "string" },
+                "Id": { "type": "string", "format": "uuid" }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_type_prefix("Api")
+            .with_replacement("Id", "my_crate::Id", ["Hash"])
+            .with_patch("Error", TypePatch::default().with_rename("Failure"))
+            .with_module("types", "pub(crate)");
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "lib/type_prefix_and_module");
+    }
+
+    #[test]
+    fn test_visibility() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string", "description": "The name." }
+                    }
+                },
+                "Id": { "type": "string" }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_type_visibility("pub(crate)")
+            .with_field_visibility("")
+            .with_field_accessors(true);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "lib/visibility");
+    }
+
+    #[test]
+    fn test_non_exhaustive() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "properties": { "tag": { "$ref": "#/definitions/Tag" } }
+                },
+                "Tag": { "type": "string", "enum": ["a", "b"] },
+                "Level": { "type": "string", "enum": ["low", "high"] }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_non_exhaustive(NonExhaustive::EnumsAndStructs)
+            .with_patch("Level", TypePatch::default().with_non_exhaustive(false));
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Other crates construct the struct with its builder.
+        assert_output(&type_space, "lib/non_exhaustive");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_non_exhaustive(NonExhaustive::Enums));
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Thing": {
+                    "type": "object",
+                    "properties": { "tag": { "$ref": "#/definitions/Tag" } }
+                },
+                "Tag": { "type": "string", "enum": ["a", "b"] }
+            }
+        }))
+        .unwrap();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "lib/non_exhaustive-enums");
+    }
+
+    #[test]
+    fn test_replacement() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "IpNet": { "type": "string" },
+                "Hosts": {
+                    "type": "object",
+                    "required": ["all", "names"],
+                    "properties": {
+                        "all": { "type": "array", "items": { "$ref": "#/definitions/IpNet" } },
+                        "default": { "$ref": "#/definitions/IpNet" },
+                        "names": {
+                            "type": "object",
+                            "additionalProperties": { "$ref": "#/definitions/IpNet" }
+                        }
+                    }
+                },
+                "Route": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["via"],
+                            "properties": { "via": { "$ref": "#/definitions/IpNet" } },
+                            "additionalProperties": false
+                        },
+                        { "type": "string", "enum": ["local"] }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_replacement("IpNet", "crate::net::IpNet", ["Display", "FromStr"]);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // We don't know that the type is comparable.
+        assert_output(&type_space, "lib/replacement");
+    }
+
+    #[test]
+    fn test_pointer_refs() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "title": "Job",
+            "type": "object",
+            "properties": {
+                "config": {
+                    "type": "object",
+                    "properties": {
+                        "retries": {
+                            "type": "object",
+                            "properties": { "count": { "type": "integer", "minimum": 0 } }
+                        }
+                    }
+                },
+                "fallback": { "$ref": "#/properties/config/properties/retries" },
+                "steps": {
+                    "type": "array",
+                    "items": { "type": "string", "format": "uuid" }
+                },
+                "first": { "$ref": "#/properties/steps/items" },
+                "other": { "$ref": "#/definitions/Other/allOf/0" }
+            },
+            "definitions": {
+                "Other": {
+                    "allOf": [{ "type": "object", "properties": { "a": { "type": "boolean" } } }]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_root_schema(schema).unwrap();
+        // The retries struct is defined once, by the name of its path.
+        if cfg!(feature = "uuid") {
+            assert_output(&type_space, "lib/pointer_refs-uuid");
+        } else {
+            assert_output(&type_space, "lib/pointer_refs");
+        }
+    }
+
+    #[test]
+    fn test_id_refs() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "$id": "https://example.com/schemas/order",
+            "title": "Order",
+            "type": "object",
+            "properties": {
+                "shipping": {
+                    "$id": "address",
+                    "type": "object",
+                    "properties": {
+                        "street": { "$anchor": "street", "type": "string" },
+                        "city": { "$ref": "#/properties/street" }
+                    }
+                },
+                "billing": { "$ref": "https://example.com/schemas/address" },
+                "street": { "$ref": "address#street" },
+                "nested": {
+                    "$id": "sub/nested",
+                    "type": "object",
+                    "properties": { "to": { "$ref": "../address" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_root_schema(schema).unwrap();
+        // The reference within the address is relative to its own id.
+        assert_output(&type_space, "lib/id_refs");
+    }
+
+    #[test]
+    fn test_error_location() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Widget": {
+                    "type": "object",
+                    "properties": {
+                        "size": {
+                            "oneOf": [
+                                { "type": "integer" },
+                                { "type": "string", "pattern": "^[a-z" }
+                            ]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_string_validation(true));
+        let err = type_space.add_root_schema(schema).err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.file, None);
+        assert_eq!(
+            location.to_string(),
+            "definitions.Widget.properties.size.oneOf[1]"
+        );
+        assert!(matches!(err.reason(), crate::Error::InvalidPattern(..)));
+
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/located.json");
+        let err = TypeSpace::default().add_schema_file(path).err().unwrap();
+        let location = err.location().unwrap();
+        assert!(location
+            .file
+            .as_ref()
+            .unwrap()
+            .ends_with("located_common.json"));
+        assert_eq!(
+            location.path,
+            [
+                PathSegment::Key("definitions".to_string()),
+                PathSegment::Key("Event".to_string()),
+                PathSegment::Key("properties".to_string()),
+                PathSegment::Key("when".to_string()),
+            ]
+        );
+        assert_eq!(
+            err.reason().to_string(),
+            "unsupported schema: the string format \"mystery\""
+        );
+    }
+
+    #[test]
+    fn test_pointer_unresolved() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "title": "Job",
+            "type": "object",
+            "properties": {
+                "config": {
+                    "type": "object",
+                    "properties": { "name": { "type": "string" } }
+                },
+                "bad": { "$ref": "#/properties/config/properties/retries/items" }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        match type_space.add_root_schema(schema) {
+            Err(crate::Error::UnresolvedRef(reference, reason)) => {
+                assert_eq!(reference, "#/properties/config/properties/retries/items");
+                assert!(
+                    reason.contains("#/properties/config/properties"),
+                    "{}",
+                    reason
+                );
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_dedup_inline_types() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Order": {
+                    "type": "object",
+                    "properties": {
+                        "buyer": {
+                            "type": "object",
+                            "description": "Who bought it.",
+                            "properties": { "id": { "type": "integer" } }
+                        },
+                        "seller": {
+                            "type": "object",
+                            "title": "party",
+                            "properties": { "id": { "type": "integer" } }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "lib/dedup_inline_types");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_distinct_inline_types(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "lib/dedup_inline_types-distinct");
+    }
+
+    #[test]
+    fn test_dedup_inline_forms() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Order": {
+                    "type": "object",
+                    "properties": {
+                        "buyer": {
+                            "type": "object",
+                            "properties": { "id": { "type": "integer" } }
+                        },
+                        "seller": {
+                            "type": ["object"],
+                            "description": "Who sold it.",
+                            "$comment": "the same as the buyer",
+                            "properties": { "id": { "type": "integer" } }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The type takes the description that the first occurrence lacks.
+        assert_output(&type_space, "lib/dedup_inline_forms");
+    }
+
+    #[test]
+    fn test_dedup_repeated_subschemas() {
+        // Definitions that each embed the same subschemas, as bundlers that
+        // inline references produce.
+        let item = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "size": {
+                    "type": "object",
+                    "properties": {
+                        "width": { "type": "number" },
+                        "height": { "type": "number" }
+                    }
+                }
+            }
+        });
+        let order = json!({
+            "type": "object",
+            "properties": {
+                "first": item,
+                "second": item,
+                "either": { "oneOf": [item, { "type": "string" }] }
+            }
+        });
+        let definitions = (0..100)
+            .map(|i| {
+                let schema = json!({
+                    "type": "object",
+                    "properties": { "a": order, "b": order }
+                });
+                (
+                    format!("Def{}", i),
+                    serde_json::from_value::<schemars::schema::Schema>(schema).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let convert = |settings: &TypeSpaceSettings| {
+            let mut type_space = TypeSpace::new(settings);
+            type_space.add_ref_types(definitions.clone()).unwrap();
+            (
+                type_space.iter_types().count(),
+                type_space.inline_types.len(),
+            )
+        };
+
+        // Each repeated subschema is converted once, into a type that the
+        // definitions share, rather than into a type for each occurrence.
+        assert_eq!(convert(&TypeSpaceSettings::default()), (113, 7));
+        assert_eq!(
+            convert(TypeSpaceSettings::default().with_distinct_inline_types(true)),
+            (2905, 0)
+        );
+    }
+
+    #[test]
+    fn test_path_type_names() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Server": {
+                    "type": "object",
+                    "required": ["aliases", "labels", "tags", "tls"],
+                    "properties": {
+                        "tls": {
+                            "type": "object",
+                            "required": ["cert"],
+                            "properties": {
+                                "cert": {
+                                    "type": "object",
+                                    "properties": { "pem": { "type": "string" } }
+                                }
+                            }
+                        },
+                        "tags": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": { "key": { "type": "string" } }
+                            }
+                        },
+                        "aliases": {
+                            "type": "array",
+                            "items": { "type": "string", "enum": ["a", "b"] }
+                        },
+                        "labels": {
+                            "type": "object",
+                            "properties": { "x": { "type": "integer" } }
+                        },
+                        "Labels": {
+                            "type": "object",
+                            "properties": { "y": { "type": "integer" } }
+                        }
+                    }
+                },
+                "ServerAliases": { "type": "string" }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The definition has the name that the items would have.
+        // Different types with the same name are numbered.
+        assert_output(&type_space, "lib/path_type_names");
+
+        assert_eq!(path_type_name(&["Server", "tls", "cert"]), "ServerTlsCert");
+    }
+
+    #[test]
+    fn test_type_details() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["name", "kind"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "kind": { "$ref": "#/definitions/Kind" },
+                        "tags": {
+                            "type": "array",
+                            "items": { "type": "string" }
+                        }
+                    }
+                },
+                "Kind": {
+                    "type": "string",
+                    "enum": ["cat", "dog"]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+
+        let pet = type_space
+            .iter_types()
+            .find(|ty| ty.name() == "Pet")
+            .unwrap();
+        let properties = match pet.details() {
+            TypeDetails::Struct { properties } => properties,
+            details => panic!("unexpected details {:?}", details),
+        };
+        let names = properties
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["kind", "name", "tags"]);
+
+        let kind = type_space.get(&properties[0].1).unwrap();
+        assert_eq!(kind.name(), "Kind");
+        assert_eq!(
+            kind.details(),
+            TypeDetails::Enum {
+                variants: vec![
+                    ("Cat".to_string(), TypeVariant::Unit),
+                    ("Dog".to_string(), TypeVariant::Unit),
+                ]
+            }
+        );
+        assert_eq!(
+            type_space.get(&properties[1].1).unwrap().details(),
+            TypeDetails::Builtin("String")
+        );
+        let tags = type_space.get(&properties[2].1).unwrap();
+        let item_id = match tags.details() {
+            TypeDetails::Vec(item_id) => item_id,
+            details => panic!("unexpected details {:?}", details),
+        };
+        assert_eq!(type_space.get(&item_id).unwrap().name(), "String");
+
+        // Identifiers are stable: the same type has the same one each time.
+        let again = type_space
+            .iter_types()
+            .find(|ty| ty.name() == "Pet")
+            .unwrap();
+        assert_eq!(again.id(), pet.id());
+        assert_eq!(type_space.get(pet.id()).unwrap().name(), "Pet");
+    }
+
+    #[test]
+    fn test_get_type_by_ref() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Instance": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/definitions/Id" },
+                        "parent": { "$ref": "#/definitions/MaybeInstance" }
+                    }
+                },
+                "MaybeInstance": {
+                    "oneOf": [
+                        { "type": "null" },
+                        { "$ref": "#/definitions/Instance" }
+                    ]
+                },
+                "Id": { "type": "string", "format": "uuid" }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_patch("Instance", TypePatch::default().with_rename("Vm"))
+            .with_replacement("Id", "uuid::Uuid", ["Hash"]);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+
+        // A renamed definition is found by its schema name and its new one.
+        let instance = type_space
+            .get_type_by_ref("#/definitions/Instance")
+            .unwrap();
+        assert_eq!(instance.name(), "Vm");
+        assert_eq!(
+            type_space.get_type_by_name("Instance").unwrap().id(),
+            instance.id()
+        );
+        assert_eq!(
+            type_space.get_type_by_name("Vm").unwrap().id(),
+            instance.id()
+        );
+
+        // A definition that is an Option of another is a newtype of it.
+        let maybe = type_space
+            .get_type_by_ref("#/definitions/MaybeInstance")
+            .unwrap();
+        assert_eq!(maybe.name(), "MaybeInstance");
+        let option_id = match maybe.details() {
+            TypeDetails::Newtype(type_id) => type_id,
+            details => panic!("unexpected details {:?}", details),
+        };
+        assert_eq!(
+            type_space.get(&option_id).unwrap().details(),
+            TypeDetails::Option(instance.id().clone())
+        );
+
+        let id = type_space.get_type_by_ref("#/definitions/Id").unwrap();
+        assert_eq!(id.ident().to_string().replace(' ', ""), "uuid::Uuid");
+
+        assert!(type_space.get_type_by_ref("#/definitions/Other").is_none());
+        assert!(type_space.get_type_by_name("Other").is_none());
+    }
+
+    #[test]
+    fn test_add_documents_separately() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/external/multi");
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_schema_files(&[dir.join("a.json"), dir.join("b.json")])
+            .unwrap();
+
+        // The identical Part is the same type and Widget, from an earlier
+        // document, resolves by its name.
+        type_space.add_schema_file(dir.join("d.json")).unwrap();
+        assert_output(&type_space, "lib/add_documents_separately");
+
+        // A different Part is an error that names both documents.
+        let err = type_space
+            .add_schema_file(dir.join("c.json"))
+            .err()
+            .unwrap();
+        assert!(err
+            .location()
+            .unwrap()
+            .file
+            .as_ref()
+            .unwrap()
+            .ends_with("c.json"));
+        let err = err.to_string();
+        assert!(err.contains("b.json"), "{}", err);
+        assert!(err.contains("definitions.Part"), "{}", err);
+    }
+
+    #[test]
+    fn test_lossy_warnings() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Account": {
+                    "type": "object",
+                    "unevaluatedProperties": false,
+                    "properties": {
+                        "handle": { "type": "string", "pattern": "^[a-z]+$" },
+                        "limits": {
+                            "type": "array",
+                            "items": { "type": "number", "multipleOf": 0.5 }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        let warnings = type_space
+            .warnings()
+            .iter()
+            .map(|warning| (warning.location.to_string(), warning.keyword.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                ("definitions.Account".to_string(), "unevaluatedProperties"),
+                (
+                    "definitions.Account.properties.handle".to_string(),
+                    "pattern"
+                ),
+                (
+                    "definitions.Account.properties.limits.items".to_string(),
+                    "multipleOf"
+                ),
+            ]
+        );
+        assert_eq!(
+            type_space.warnings()[1].to_string(),
+            "definitions.Account.properties.handle: pattern \"^[a-z]+$\" isn't checked \
+             without string validation"
+        );
+
+        let mut type_space = TypeSpace::new(TypeSpaceSettings::default().with_deny_lossy(true));
+        let err = type_space.add_ref_types(schema.definitions).err().unwrap();
+        assert_eq!(err.location().unwrap().to_string(), "definitions.Account");
+        assert!(
+            matches!(err.reason(), crate::Error::Lossy(keyword, _) if keyword == "unevaluatedProperties")
+        );
+    }
+
+    #[test]
+    fn test_roots() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Instance": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/definitions/Id" },
+                        "disks": {
+                            "type": "array",
+                            "items": { "$ref": "#/definitions/Disk" }
+                        }
+                    }
+                },
+                "Disk": {
+                    "type": "object",
+                    "properties": {
+                        "size": { "type": "integer" }
+                    }
+                },
+                "Id": {
+                    "type": "array",
+                    "items": { "$ref": "#/definitions/IdPart" }
+                },
+                "IdPart": { "type": "string" },
+                "Unused": { "type": "string" }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_roots(["Instance"])
+            .with_replacement("Id", "uuid::Uuid", ["Hash"]);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+
+        // The replaced type's definition doesn't make its parts reachable.
+        assert_eq!(type_space.pruned_types(), ["IdPart", "Unused"]);
+        assert_output(&type_space, "lib/roots");
+
+        // Without roots, nothing is left out.
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(type_space.pruned_types().is_empty());
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_roots(["Instance", "Volume"]);
+        let mut type_space = TypeSpace::new(&settings);
+        assert!(matches!(
+            type_space.add_ref_types(schema.definitions),
+            Err(crate::Error::UnknownRoot(root)) if root == "Volume"
+        ));
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        // Objects each with a property of the next, built without recursing
+        // as they're too deep for serde_json.
+        let nested = |depth: usize| {
+            let mut object = schemars::schema::SchemaObject::default();
+            for _ in 0..depth {
+                let mut parent = schemars::schema::SchemaObject {
+                    instance_type: Some(schemars::schema::InstanceType::Object.into()),
+                    ..Default::default()
+                };
+                parent.object().properties.insert(
+                    "child".to_string(),
+                    schemars::schema::Schema::Object(object),
+                );
+                object = parent;
+            }
+            object
+        };
+
+        // Too deep to convert, or even to drop by recursing, it's an error
+        // that says where.
+        let root = schemars::schema::RootSchema {
+            schema: nested(10_000),
+            ..Default::default()
+        };
+        let mut type_space = TypeSpace::default();
+        let error = type_space.add_root_schema(root).unwrap_err();
+        assert!(matches!(
+            error.reason(),
+            crate::Error::RecursionLimit { depth: 64 }
+        ));
+        let path = &error.location().unwrap().path;
+        assert_eq!(path.len(), 2 * 65);
+        assert_eq!(path[..2], ["properties".into(), "child".into()]);
+
+        let mut type_space = TypeSpace::default();
+        let error = type_space
+            .add_ref_types([("Tree", schemars::schema::Schema::Object(nested(10_000)))])
+            .unwrap_err();
+        assert_eq!(
+            error.location().unwrap().path[..2],
+            ["definitions".into(), "Tree".into()]
+        );
+
+        // The limit counts subschemas, not the references of a definition
+        // to itself.
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_recursion_limit(4);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space
+            .add_ref_types([
+                ("Shallow", schemars::schema::Schema::Object(nested(4))),
+                (
+                    "Node",
+                    serde_json::from_value(json!({
+                        "type": "object",
+                        "properties": {
+                            "next": { "$ref": "#/definitions/Node" }
+                        }
+                    }))
+                    .unwrap(),
+                ),
+            ])
+            .unwrap();
+        let error = type_space
+            .add_type(&schemars::schema::Schema::Object(nested(5)))
+            .unwrap_err();
+        assert!(matches!(
+            error.reason(),
+            crate::Error::RecursionLimit { depth: 4 }
+        ));
+    }
+
+    #[test]
+    fn test_module_split() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "IssueComment": {
+                    "type": "object",
+                    "properties": {
+                        "author": { "$ref": "#/definitions/User" },
+                        "reactions": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": { "emoji": { "type": "string" } }
+                            }
+                        }
+                    }
+                },
+                "Issue": {
+                    "type": "object",
+                    "properties": {
+                        "comments": {
+                            "type": "array",
+                            "items": { "$ref": "#/definitions/IssueComment" }
+                        }
+                    }
+                },
+                "User": { "type": "string" },
+                "Label": { "type": "string" }
+            }
+        }))
+        .unwrap();
+
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_module_split(ModuleSplit::Prefix(vec![
+            "Issue".to_string(),
+            "User".to_string(),
+        ]));
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema.clone()).unwrap();
+        let streams = type_space.to_module_streams();
+        let names = streams
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["mod", "issue", "other", "user"]);
+        // The inline type goes with the type that uses it.
+        for (name, stream) in streams {
+            assert_stream_output(stream, &format!("lib/module_split-prefix-{}", name));
+        }
+
+        // Or by the modules that a mapping gives, nested in one stream.
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_module_split(ModuleSplit::Mapping(
+            vec![("Label".to_string(), "Labels".to_string())]
+                .into_iter()
+                .collect(),
+        ));
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema).unwrap();
+        assert_output(&type_space, "lib/module_split");
+    }
+
+    #[test]
+    fn test_explicit_nulls() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Patch": {
+                    "type": "object",
+                    "required": ["id", "label"],
+                    "properties": {
+                        "id": { "type": "string" },
+                        "label": { "type": ["string", "null"] },
+                        "name": { "type": "string" },
+                        "note": { "type": ["string", "null"] }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_root_schema(schema.clone()).unwrap();
+        assert_output(&type_space, "lib/explicit_nulls");
+
+        // Only the optional, nullable property tells null from absence.
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_explicit_nulls(true);
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema).unwrap();
+        assert_output(&type_space, "lib/explicit_nulls-explicit");
+    }
+
+    #[test]
+    fn test_skip_serializing_none() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Item": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "note": { "type": ["string", "null"] },
+                        "tags": { "type": "array", "items": { "type": "string" } }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let generate = |settings: &TypeSpaceSettings, name: &str| {
+            let mut type_space = TypeSpace::new(settings);
+            type_space.add_root_schema(schema.clone()).unwrap();
+            assert_output(&type_space, name);
+        };
+
+        generate(&TypeSpaceSettings::default(), "lib/skip_serializing_none");
+        generate(
+            TypeSpaceSettings::default()
+                .with_skip_serializing_none(SkipSerializingNone::Never)
+                .with_skip_serializing_empty(false),
+            "lib/skip_serializing_none-never",
+        );
+        // Only the property whose schema permits null is serialized as null.
+        generate(
+            TypeSpaceSettings::default()
+                .with_skip_serializing_none(SkipSerializingNone::UnlessNullable),
+            "lib/skip_serializing_none-unless_nullable",
+        );
+    }
+
+    #[test]
+    fn test_conversion_error() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Code": { "type": "string", "maxLength": 4 },
+                "Level": { "type": "integer", "enum": [1, 2] },
+                "Plain": { "type": "string" }
+            }
+        }))
+        .unwrap();
+        let mut settings = TypeSpaceSettings::default();
+        settings.with_string_validation(true);
+
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema.clone()).unwrap();
+        // With serde_repr integer enums have no conversions of their own.
+        if cfg!(feature = "serde_repr") {
+            assert_output(&type_space, "lib/conversion_error-serde_repr");
+        } else {
+            assert_output(&type_space, "lib/conversion_error");
+        }
+
+        // An existing type replaces the generated one.
+        settings.with_conversion_error("crate::Error");
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema.clone()).unwrap();
+        if cfg!(feature = "serde_repr") {
+            assert_output(&type_space, "lib/conversion_error-existing-serde_repr");
+        } else {
+            assert_output(&type_space, "lib/conversion_error-existing");
+        }
+
+        // Without fallible conversions there's no error module.
+        let mut type_space = TypeSpace::new(TypeSpaceSettings::default().with_roots(["Plain"]));
+        type_space.add_root_schema(schema).unwrap();
+        assert_output(&type_space, "lib/conversion_error-infallible");
+    }
+
+    #[test]
+    fn test_no_std() {
+        let schema = serde_json::from_value::<schemars::schema::RootSchema>(json!({
+            "definitions": {
+                "Code": { "type": "string", "maxLength": 4 },
+                "Host": {
+                    "type": "object",
+                    "properties": {
+                        "address": { "type": "string", "format": "ip" },
+                        "labels": {
+                            "type": "object",
+                            "additionalProperties": { "type": "string" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_no_std(true)
+            .with_string_validation(true)
+            .with_number_validation(true);
+
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_root_schema(schema).unwrap();
+        assert_output(&type_space, "lib/no_std");
+
+        // The checks of patterns and of multiples of numbers need `std`.
+        for schema in [
+            json!({ "definitions": { "Code": { "type": "string", "pattern": "^[a-z]+$" } } }),
+            json!({ "definitions": { "Step": { "type": "number", "multipleOf": 0.5 } } }),
+        ] {
+            let schema = serde_json::from_value::<schemars::schema::RootSchema>(schema).unwrap();
+            let mut type_space = TypeSpace::new(&settings);
+            let error = type_space.add_root_schema(schema).unwrap_err();
+            assert!(
+                matches!(error.reason(), crate::Error::RequiresStd(_)),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_definition_name_collisions() {
+        let schema = json!({
+            "definitions": {
+                "HTTPServer": { "type": "object", "properties": { "port": { "type": "integer" } } },
+                "HttpServer": { "type": "object", "properties": { "host": { "type": "string" } } },
+                "foo.bar": { "type": "string", "enum": ["a", "b"] },
+                "foo_bar": { "type": "object", "properties": { "x": { "type": "string" } } }
+            }
+        });
+        let generate = || {
+            let schema = serde_json::from_value::<schemars::schema::RootSchema>(schema.clone());
+            let mut type_space = TypeSpace::default();
+            type_space.add_root_schema(schema.unwrap()).unwrap();
+            type_space
+        };
+        // Those spelled as their types keep the names, and then the first.
+        let type_space = generate();
+        assert_output(&type_space, "lib/definition_name_collisions");
+        assert_eq!(
+            generate().to_stream().to_string(),
+            type_space.to_stream().to_string()
+        );
+
+        // A definition added later doesn't take the name of one we have.
+        let definition = |name: &'static str| {
+            let schema = schema["definitions"][name].clone();
+            (
+                name,
+                serde_json::from_value::<schemars::schema::Schema>(schema).unwrap(),
+            )
+        };
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types([definition("foo_bar")]).unwrap();
+        type_space.add_ref_types([definition("foo.bar")]).unwrap();
+        assert_output(&type_space, "lib/definition_name_collisions-later");
+    }
+
+    #[test]
+    fn test_title_collisions() {
+        let generate = |definitions: serde_json::Value, name: &str| {
+            let schema = json!({ "definitions": definitions });
+            let mut type_space = TypeSpace::default();
+            type_space
+                .add_root_schema(serde_json::from_value(schema).unwrap())
+                .unwrap();
+            assert_output(&type_space, name);
+        };
+        let config = json!({
+            "type": "object",
+            "properties": {
+                "config": {
+                    "title": "Config",
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } }
+                },
+                "settings": {
+                    "title": "Settings",
+                    "type": "object",
+                    "properties": { "b": { "type": "string" } }
+                }
+            }
+        });
+        let settings = json!({ "type": "object", "properties": { "c": { "type": "string" } } });
+        let thing = json!({
+            "oneOf": [
+                {
+                    "title": "Thing",
+                    "type": "object",
+                    "required": ["x"],
+                    "properties": { "x": { "type": "string" } }
+                },
+                { "type": "string" }
+            ]
+        });
+
+        // A property titled for the definition it's within.
+        generate(
+            json!({ "Config": config, "Settings": settings, "Thing": thing }),
+            "lib/title_collisions",
+        );
+
+        // The path comes next if another type has the name with `Inner` too.
+        generate(
+            json!({ "Config": config, "ConfigInner": settings }),
+            "lib/title_collisions-inner",
+        );
+    }
+
+    #[test]
+    fn test_named_simple_types() {
+        let schema = json!({
+            "definitions": {
+                "Widget": {
+                    "type": "object",
+                    "properties": {
+                        "parts": { "$ref": "#/definitions/WidgetList" },
+                        "next": { "$ref": "#/definitions/MaybeWidget" }
+                    }
+                },
+                "WidgetList": { "type": "array", "items": { "$ref": "#/definitions/Widget" } },
+                "MaybeWidget": { "oneOf": [{ "type": "null" }, { "$ref": "#/definitions/Widget" }] },
+                "Tags": { "type": "object", "additionalProperties": { "type": "string" } },
+                "Count": { "type": "integer", "format": "int64" },
+                "Retries": { "type": "integer", "format": "int64", "default": 3 },
+                "Other": { "$ref": "#/definitions/Widget" },
+                "Loop": { "type": "array", "items": { "$ref": "#/definitions/Loop" } },
+                "Holder": {
+                    "type": "object",
+                    "required": ["count", "tags", "other", "retries", "loop"],
+                    "properties": {
+                        "count": { "$ref": "#/definitions/Count" },
+                        "tags": { "$ref": "#/definitions/Tags" },
+                        "other": { "$ref": "#/definitions/Other" },
+                        "retries": { "$ref": "#/definitions/Retries" },
+                        "loop": { "$ref": "#/definitions/Loop" }
+                    }
+                }
+            }
+        });
+        let generate = |mode, name: &str| {
+            let mut settings = TypeSpaceSettings::default();
+            settings.with_named_simple_types(mode);
+            let mut type_space = TypeSpace::new(&settings);
+            type_space
+                .add_root_schema(serde_json::from_value(schema.clone()).unwrap())
+                .unwrap();
+            assert_output(&type_space, name);
+        };
+
+        // Those with defaults and those that contain themselves stay
+        // newtypes in each mode.
+        generate(NamedSimpleTypes::Newtype, "lib/named_simple_types-newtype");
+        generate(NamedSimpleTypes::Alias, "lib/named_simple_types-alias");
+        generate(NamedSimpleTypes::Inline, "lib/named_simple_types-inline");
+    }
 }
@@ -0,0 +1,670 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeMap;
+
+use schemars::schema::{
+    ArrayValidation, InstanceType, NumberValidation, ObjectValidation, Schema, SchemaObject,
+    SingleOrVec, StringValidation, SubschemaValidation,
+};
+
+use crate::util::value_matches_type;
+
+/// Reasons why the subschemas of an `allOf` can't be merged into one schema.
+#[derive(Debug, PartialEq)]
+pub(crate) enum MergeError {
+    /// No value could satisfy all the subschemas.
+    Conflict(String),
+    /// We don't know how to combine the subschemas; the caller should fall
+    /// back to some other representation.
+    Unsupported,
+}
+
+type MergeResult<T> = std::result::Result<T, MergeError>;
+
+/// Merge the subschemas of an `allOf` into a single schema that accepts
+/// exactly the values that each of them accepts. References are resolved
+/// against `definitions` and nested `allOf` constructions are merged as well.
+/// The result carries no metadata.
+pub(crate) fn merge_all<'a, I>(
+    schemas: I,
+    definitions: &BTreeMap<String, Schema>,
+) -> MergeResult<SchemaObject>
+where
+    I: IntoIterator<Item = &'a Schema>,
+{
+    let mut parts = Vec::new();
+    for schema in schemas {
+        collect_parts(schema, definitions, &mut Vec::new(), &mut parts)?;
+    }
+
+    let mut merged = parts
+        .iter()
+        .try_fold(SchemaObject::default(), |merged, part| {
+            merge_objects(merged, part, definitions)
+        })?;
+
+    // A const value must be one of the enumerated values, if any, and is then
+    // the only one.
+    if let (Some(value), Some(values)) = (&merged.const_value, &merged.enum_values) {
+        if !values.contains(value) {
+            return Err(MergeError::Conflict(format!(
+                "const value {} isn't among the enum values",
+                value
+            )));
+        }
+        merged.enum_values = None;
+    }
+
+    infer_instance_type(&mut merged);
+
+    // Enumerated values must be of the merged type.
+    if let (Some(instance_type), Some(values)) = (&merged.instance_type, &merged.enum_values) {
+        let instance_types = match instance_type {
+            SingleOrVec::Single(single) => vec![**single],
+            SingleOrVec::Vec(multiple) => multiple.clone(),
+        };
+        let values = values
+            .iter()
+            .filter(|value| {
+                instance_types
+                    .iter()
+                    .any(|instance_type| value_matches_type(value, instance_type))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Err(MergeError::Conflict(format!(
+                "no enum values are of type {}",
+                instance_types_name(instance_type),
+            )));
+        }
+
+        // The null type has just the one value.
+        merged.enum_values = match instance_types.as_slice() {
+            [InstanceType::Null] => None,
+            _ => Some(values),
+        };
+    }
+
+    Ok(merged)
+}
+
+/// Gather the schema objects that must all be satisfied, resolving references
+/// and flattening nested `allOf` constructions. `seen` holds the references
+/// being resolved to detect cycles. Parts are borrowed rather than cloned:
+/// merging takes neither their metadata nor their `allOf`.
+fn collect_parts<'a>(
+    schema: &'a Schema,
+    definitions: &'a BTreeMap<String, Schema>,
+    seen: &mut Vec<String>,
+    parts: &mut Vec<&'a SchemaObject>,
+) -> MergeResult<()> {
+    let object = match schema {
+        Schema::Bool(true) => return Ok(()),
+        Schema::Bool(false) => {
+            return Err(MergeError::Conflict(
+                "a subschema of false matches nothing".to_string(),
+            ))
+        }
+        Schema::Object(object) => object,
+    };
+
+    match object {
+        SchemaObject {
+            metadata: _,
+            instance_type: None,
+            format: None,
+            enum_values: None,
+            const_value: None,
+            subschemas: None,
+            number: None,
+            string: None,
+            array: None,
+            object: None,
+            reference: Some(reference),
+            extensions: _,
+        } => {
+            let key = match reference.rfind('/') {
+                Some(idx) => &reference[idx + 1..],
+                None => reference,
+            };
+            if seen.iter().any(|name| name == key) {
+                return Err(MergeError::Unsupported);
+            }
+            let resolved = definitions.get(key).ok_or(MergeError::Unsupported)?;
+            seen.push(key.to_string());
+            collect_parts(resolved, definitions, seen, parts)?;
+            seen.pop();
+            Ok(())
+        }
+
+        // References alongside other constraints aren't meaningful in the
+        // drafts we support.
+        SchemaObject {
+            reference: Some(_), ..
+        } => Err(MergeError::Unsupported),
+
+        SchemaObject {
+            subschemas: Some(subschemas),
+            ..
+        } => match subschemas.as_ref() {
+            SubschemaValidation {
+                all_of: Some(all_of),
+                any_of: None,
+                one_of: None,
+                not: None,
+                if_schema: None,
+                then_schema: None,
+                else_schema: None,
+            } => {
+                for subschema in all_of {
+                    collect_parts(subschema, definitions, seen, parts)?;
+                }
+                parts.push(object);
+                Ok(())
+            }
+            _ => Err(MergeError::Unsupported),
+        },
+
+        _ => {
+            parts.push(object);
+            Ok(())
+        }
+    }
+}
+
+fn merge_objects(
+    a: SchemaObject,
+    b: &SchemaObject,
+    definitions: &BTreeMap<String, Schema>,
+) -> MergeResult<SchemaObject> {
+    let mut extensions = a.extensions;
+    for (key, value) in &b.extensions {
+        match extensions.get(key) {
+            Some(existing) if existing != value => return Err(MergeError::Unsupported),
+            _ => {
+                extensions.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    Ok(SchemaObject {
+        metadata: None,
+        instance_type: merge_instance_types(a.instance_type, &b.instance_type)?,
+        format: merge_equal(a.format, &b.format, |a, b| {
+            MergeError::Conflict(format!("format {:?} conflicts with format {:?}", a, b))
+        })?,
+        enum_values: merge_enum_values(a.enum_values, &b.enum_values)?,
+        const_value: merge_equal(a.const_value, &b.const_value, |a, b| {
+            MergeError::Conflict(format!(
+                "const value {} conflicts with const value {}",
+                a, b
+            ))
+        })?,
+        subschemas: None,
+        number: merge_boxed(a.number, &b.number, merge_number)?,
+        string: merge_boxed(a.string, &b.string, merge_string)?,
+        array: merge_boxed(a.array, &b.array, |a, b| merge_array(a, b, definitions))?,
+        object: merge_boxed(a.object, &b.object, |a, b| merge_object(a, b, definitions))?,
+        reference: None,
+        extensions,
+    })
+}
+
+/// Values that must be identical in each subschema that specifies them.
+fn merge_equal<T, F>(a: Option<T>, b: &Option<T>, conflict: F) -> MergeResult<Option<T>>
+where
+    T: Clone + PartialEq,
+    F: FnOnce(&T, &T) -> MergeError,
+{
+    match (a, b) {
+        (Some(a), Some(b)) if &a != b => Err(conflict(&a, b)),
+        (Some(a), _) => Ok(Some(a)),
+        (None, b) => Ok(b.clone()),
+    }
+}
+
+fn merge_boxed<T, F>(a: Option<Box<T>>, b: &Option<Box<T>>, merge: F) -> MergeResult<Option<Box<T>>>
+where
+    T: Clone,
+    F: FnOnce(T, &T) -> MergeResult<T>,
+{
+    match (a, b) {
+        (Some(a), Some(b)) => Ok(Some(Box::new(merge(*a, b)?))),
+        (Some(a), None) => Ok(Some(a)),
+        (None, b) => Ok(b.clone()),
+    }
+}
+
+fn instance_type_name(instance_type: &InstanceType) -> &'static str {
+    match instance_type {
+        InstanceType::Null => "null",
+        InstanceType::Boolean => "boolean",
+        InstanceType::Object => "object",
+        InstanceType::Array => "array",
+        InstanceType::Number => "number",
+        InstanceType::String => "string",
+        InstanceType::Integer => "integer",
+    }
+}
+
+fn instance_types_name(instance_types: &SingleOrVec<InstanceType>) -> String {
+    match instance_types {
+        SingleOrVec::Single(single) => instance_type_name(single).to_string(),
+        SingleOrVec::Vec(multiple) => format!(
+            "[{}]",
+            multiple
+                .iter()
+                .map(instance_type_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The types a value may have are those permitted by both subschemas; note
+/// that integers are also numbers.
+fn merge_instance_types(
+    a: Option<SingleOrVec<InstanceType>>,
+    b: &Option<SingleOrVec<InstanceType>>,
+) -> MergeResult<Option<SingleOrVec<InstanceType>>> {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (Some(a), None) => return Ok(Some(a)),
+        (None, b) => return Ok(b.clone()),
+    };
+
+    let a_types = match &a {
+        SingleOrVec::Single(single) => vec![**single],
+        SingleOrVec::Vec(multiple) => multiple.clone(),
+    };
+    let b_types = match b {
+        SingleOrVec::Single(single) => vec![**single],
+        SingleOrVec::Vec(multiple) => multiple.clone(),
+    };
+
+    let mut types = Vec::new();
+    for instance_type in a_types {
+        let other = match instance_type {
+            InstanceType::Integer => InstanceType::Number,
+            InstanceType::Number => InstanceType::Integer,
+            _ => instance_type,
+        };
+        let common = if b_types.contains(&instance_type) {
+            instance_type
+        } else if b_types.contains(&other) {
+            InstanceType::Integer
+        } else {
+            continue;
+        };
+        if !types.contains(&common) {
+            types.push(common);
+        }
+    }
+
+    match types.len() {
+        0 => Err(MergeError::Conflict(format!(
+            "type {} conflicts with type {}",
+            instance_types_name(&a),
+            instance_types_name(b),
+        ))),
+        1 => Ok(Some(SingleOrVec::from(types[0]))),
+        _ => Ok(Some(SingleOrVec::Vec(types))),
+    }
+}
+
+fn merge_enum_values(
+    a: Option<Vec<serde_json::Value>>,
+    b: &Option<Vec<serde_json::Value>>,
+) -> MergeResult<Option<Vec<serde_json::Value>>> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let values = a
+                .into_iter()
+                .filter(|value| b.contains(value))
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                Err(MergeError::Conflict(
+                    "enum values have no values in common".to_string(),
+                ))
+            } else {
+                Ok(Some(values))
+            }
+        }
+        (Some(a), None) => Ok(Some(a)),
+        (None, b) => Ok(b.clone()),
+    }
+}
+
+fn tightest<T: Copy, F: Fn(T, T) -> T>(a: Option<T>, b: Option<T>, pick: F) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(pick(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+    min_name: &str,
+    min: Option<T>,
+    max_name: &str,
+    max: Option<T>,
+    inclusive: bool,
+) -> MergeResult<()> {
+    match (min, max) {
+        (Some(min), Some(max)) if min > max || (!inclusive && min == max) => Err(
+            MergeError::Conflict(format!("{} {} exceeds {} {}", min_name, min, max_name, max)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn merge_number(a: NumberValidation, b: &NumberValidation) -> MergeResult<NumberValidation> {
+    let multiple_of = match (a.multiple_of, b.multiple_of) {
+        (Some(x), Some(y)) => {
+            let (small, large) = if x < y { (x, y) } else { (y, x) };
+            if (large / small).fract() != 0.0 {
+                return Err(MergeError::Unsupported);
+            }
+            Some(large)
+        }
+        (x, y) => x.or(y),
+    };
+
+    let merged = NumberValidation {
+        multiple_of,
+        maximum: tightest(a.maximum, b.maximum, f64::min),
+        exclusive_maximum: tightest(a.exclusive_maximum, b.exclusive_maximum, f64::min),
+        minimum: tightest(a.minimum, b.minimum, f64::max),
+        exclusive_minimum: tightest(a.exclusive_minimum, b.exclusive_minimum, f64::max),
+    };
+
+    check_range("minimum", merged.minimum, "maximum", merged.maximum, true)?;
+    check_range(
+        "minimum",
+        merged.minimum,
+        "exclusiveMaximum",
+        merged.exclusive_maximum,
+        false,
+    )?;
+    check_range(
+        "exclusiveMinimum",
+        merged.exclusive_minimum,
+        "maximum",
+        merged.maximum,
+        false,
+    )?;
+    check_range(
+        "exclusiveMinimum",
+        merged.exclusive_minimum,
+        "exclusiveMaximum",
+        merged.exclusive_maximum,
+        false,
+    )?;
+
+    Ok(merged)
+}
+
+fn merge_string(a: StringValidation, b: &StringValidation) -> MergeResult<StringValidation> {
+    // The regex crate has no lookahead with which to combine patterns.
+    let pattern = merge_equal(a.pattern, &b.pattern, |_, _| MergeError::Unsupported)?;
+
+    let merged = StringValidation {
+        max_length: tightest(a.max_length, b.max_length, u32::min),
+        min_length: tightest(a.min_length, b.min_length, u32::max),
+        pattern,
+    };
+    check_range(
+        "minLength",
+        merged.min_length,
+        "maxLength",
+        merged.max_length,
+        true,
+    )?;
+
+    Ok(merged)
+}
+
+/// Schemas that apply to the same values must be merged themselves.
+fn merge_subschema(
+    a: Box<Schema>,
+    b: &Schema,
+    definitions: &BTreeMap<String, Schema>,
+) -> MergeResult<Box<Schema>> {
+    if a.as_ref() == b {
+        Ok(a)
+    } else {
+        let merged = merge_all(vec![a.as_ref(), b], definitions)?;
+        Ok(Box::new(Schema::Object(merged)))
+    }
+}
+
+fn merge_array(
+    a: ArrayValidation,
+    b: &ArrayValidation,
+    definitions: &BTreeMap<String, Schema>,
+) -> MergeResult<ArrayValidation> {
+    let items = match (a.items, &b.items) {
+        (Some(SingleOrVec::Single(a)), Some(SingleOrVec::Single(b))) => {
+            Some(SingleOrVec::Single(merge_subschema(a, b, definitions)?))
+        }
+        (a, b) => merge_equal(a, b, |_, _| MergeError::Unsupported)?,
+    };
+
+    let merged = ArrayValidation {
+        items,
+        additional_items: merge_equal(a.additional_items, &b.additional_items, |_, _| {
+            MergeError::Unsupported
+        })?,
+        max_items: tightest(a.max_items, b.max_items, u32::min),
+        min_items: tightest(a.min_items, b.min_items, u32::max),
+        unique_items: match (a.unique_items, b.unique_items) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (x, y) => x.or(y),
+        },
+        contains: merge_equal(a.contains, &b.contains, |_, _| MergeError::Unsupported)?,
+    };
+    check_range(
+        "minItems",
+        merged.min_items,
+        "maxItems",
+        merged.max_items,
+        true,
+    )?;
+
+    Ok(merged)
+}
+
+fn merge_object(
+    a: ObjectValidation,
+    b: &ObjectValidation,
+    definitions: &BTreeMap<String, Schema>,
+) -> MergeResult<ObjectValidation> {
+    // Subschemas commonly narrow the type of an inherited property, e.g. from
+    // a oneOf of a type and null to just null. Where we can't merge the
+    // schemas of a property we take the later schema as the narrower one.
+    let mut properties = a.properties;
+    for (name, schema) in &b.properties {
+        let merged = match properties.remove(name) {
+            Some(existing) => match merge_subschema(Box::new(existing), schema, definitions) {
+                Ok(merged) => *merged,
+                Err(MergeError::Unsupported) => schema.clone(),
+                Err(conflict) => return Err(conflict),
+            },
+            None => schema.clone(),
+        };
+        properties.insert(name.clone(), merged);
+    }
+
+    let mut pattern_properties = a.pattern_properties;
+    for (pattern, schema) in &b.pattern_properties {
+        match pattern_properties.get(pattern) {
+            Some(existing) if existing != schema => return Err(MergeError::Unsupported),
+            _ => {
+                pattern_properties.insert(pattern.clone(), schema.clone());
+            }
+        }
+    }
+
+    let mut required = a.required;
+    required.extend(b.required.iter().cloned());
+
+    // The permissive schema constrains nothing and `false` excludes any
+    // other schema; we keep the properties of each subschema in the latter
+    // case as the intent is usually to close the combined object.
+    let additional_properties = match (a.additional_properties, &b.additional_properties) {
+        (Some(a), Some(b)) if a.as_ref() == b.as_ref() => Some(a),
+        (Some(a), Some(b)) if b.as_ref() == &Schema::Bool(true) => Some(a),
+        (Some(a), Some(b)) if a.as_ref() == &Schema::Bool(true) => Some(b.clone()),
+        (Some(a), Some(_)) if a.as_ref() == &Schema::Bool(false) => Some(a),
+        (Some(_), Some(b)) if b.as_ref() == &Schema::Bool(false) => Some(b.clone()),
+        (Some(_), Some(_)) => return Err(MergeError::Unsupported),
+        (a, b) => a.or_else(|| b.clone()),
+    };
+
+    let merged = ObjectValidation {
+        max_properties: tightest(a.max_properties, b.max_properties, u32::min),
+        min_properties: tightest(a.min_properties, b.min_properties, u32::max),
+        required,
+        properties,
+        pattern_properties,
+        additional_properties,
+        property_names: merge_equal(a.property_names, &b.property_names, |_, _| {
+            MergeError::Unsupported
+        })?,
+    };
+    check_range(
+        "minProperties",
+        merged.min_properties,
+        "maxProperties",
+        merged.max_properties,
+        true,
+    )?;
+
+    Ok(merged)
+}
+
+/// Subschemas often leave the type implicit in their constraints; infer it
+/// if only one kind of constraint is present and drop constraints (and
+/// formats) that are irrelevant to the type otherwise.
+fn infer_instance_type(schema: &mut SchemaObject) {
+    match &schema.instance_type {
+        None => {
+            let inferred = match (
+                &schema.number,
+                &schema.string,
+                &schema.array,
+                &schema.object,
+            ) {
+                (Some(_), None, None, None) => InstanceType::Number,
+                (None, Some(_), None, None) => InstanceType::String,
+                (None, None, Some(_), None) => InstanceType::Array,
+                (None, None, None, Some(_)) => InstanceType::Object,
+                _ => return,
+            };
+            schema.instance_type = Some(SingleOrVec::from(inferred));
+        }
+        Some(SingleOrVec::Single(single)) => match single.as_ref() {
+            InstanceType::Integer | InstanceType::Number => {
+                schema.string = None;
+                schema.array = None;
+                schema.object = None;
+            }
+            InstanceType::String => {
+                schema.number = None;
+                schema.array = None;
+                schema.object = None;
+            }
+            InstanceType::Array => {
+                schema.format = None;
+                schema.number = None;
+                schema.string = None;
+                schema.object = None;
+            }
+            InstanceType::Object => {
+                schema.format = None;
+                schema.number = None;
+                schema.string = None;
+                schema.array = None;
+            }
+            InstanceType::Boolean | InstanceType::Null => {
+                schema.format = None;
+                schema.number = None;
+                schema.string = None;
+                schema.array = None;
+                schema.object = None;
+            }
+        },
+        Some(SingleOrVec::Vec(_)) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use schemars::schema::Schema;
+    use serde_json::json;
+
+    use super::{merge_all, MergeError};
+
+    fn schemas(value: serde_json::Value) -> Vec<Schema> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_merge_bounds() {
+        let merged = merge_all(
+            &schemas(json!([
+                { "type": "number", "minimum": 0, "maximum": 100 },
+                { "type": "integer", "maximum": 10 },
+                { "enum": [1, 5, 50] },
+                { "enum": [5, 50, 500] }
+            ])),
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&merged).unwrap(),
+            json!({
+                "type": "integer",
+                "enum": [5, 50],
+                "minimum": 0.0,
+                "maximum": 10.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        for (value, reason) in [
+            (
+                json!([{ "type": "string" }, { "type": "integer" }]),
+                "type string conflicts with type integer",
+            ),
+            (
+                json!([{ "minLength": 5 }, { "maxLength": 2 }]),
+                "minLength 5 exceeds maxLength 2",
+            ),
+            (
+                json!([{ "enum": ["a"] }, { "enum": ["b"] }]),
+                "enum values have no values in common",
+            ),
+        ] {
+            assert_eq!(
+                merge_all(&schemas(value), &BTreeMap::new()),
+                Err(MergeError::Conflict(reason.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_unsupported() {
+        assert_eq!(
+            merge_all(
+                &schemas(json!([{ "pattern": "^a" }, { "pattern": "b$" }])),
+                &BTreeMap::new()
+            ),
+            Err(MergeError::Unsupported)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use convert_case::Case;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{type_entry::TypeEntry, util::sanitize, ModuleSplit, TypeId, TypeSpace};
+
+/// The module of the types that the split puts nowhere else.
+const OTHER_MODULE: &str = "other";
+
+/// The name of the index among the streams of `to_module_streams`.
+const INDEX: &str = "mod";
+
+impl TypeSpace {
+    /// The code split into modules as `TypeSpaceSettings::with_module_split`
+    /// says, for writing each to its own file: first the index, named `mod`
+    /// (e.g. for `mod.rs`), which declares the other modules, re-exports
+    /// their types, and holds what they share; then each module by name.
+    /// Without a split the index holds all the types.
+    pub fn to_module_streams(&self) -> Vec<(String, TokenStream)> {
+        let modules = match &self.settings.module_split {
+            Some(split) => self.split_modules(split),
+            None => return vec![(INDEX.to_string(), self.to_stream())],
+        };
+
+        let names = modules.keys().map(|name| format_ident!("{}", name));
+        let index = self.split_index(modules.keys());
+        let index = quote! {
+            #(pub mod #names;)*
+            #index
+        };
+        std::iter::once((INDEX.to_string(), index))
+            .chain(modules)
+            .map(|(name, stream)| (name, self.no_std_code(stream, true)))
+            .collect()
+    }
+
+    /// The split code as nested modules within one stream.
+    pub(crate) fn to_split_stream(&self, split: &ModuleSplit) -> TokenStream {
+        let modules = self.split_modules(split);
+        let index = self.split_index(modules.keys());
+        let modules = modules.into_iter().map(|(name, stream)| {
+            let name = format_ident!("{}", name);
+            quote! {
+                pub mod #name {
+                    #stream
+                }
+            }
+        });
+        quote! {
+            #(#modules)*
+            #index
+        }
+    }
+
+    /// What the index holds besides the modules: the re-exports of their
+    /// types, and the code that refers to the types of several modules.
+    fn split_index<'a>(&self, modules: impl Iterator<Item = &'a String>) -> TokenStream {
+        let names = modules.map(|name| format_ident!("{}", name));
+        let json_schema_impls = self.json_schema_impls();
+        let time_serde = self.time_serde();
+        let content_encoding = self.content_encoding();
+        let nested_serde = self.nested_serde();
+        let double_option = self.double_option();
+        let defaults = self.defaults_module();
+        let error = self.error_module();
+        quote! {
+            #(pub use #names::*;)*
+            #json_schema_impls
+            #error
+            #time_serde
+            #content_encoding
+            #nested_serde
+            #double_option
+            #defaults
+        }
+    }
+
+    /// The code of each module by name.
+    fn split_modules(&self, split: &ModuleSplit) -> BTreeMap<String, TokenStream> {
+        let module_of = self.module_of(split);
+
+        // The modules that the index holds, which the attributes of types
+        // name by their paths, e.g. `defaults::thing_retries`.
+        let mut shared = Vec::new();
+        if !self.error_module().is_empty() {
+            shared.push(format_ident!("error"));
+        }
+        if !self.time_serde().is_empty() {
+            shared.push(format_ident!("time_serde"));
+        }
+        if !self.content_encoding().is_empty() {
+            shared.push(format_ident!("content_encoding"));
+        }
+        if !self.nested_serde().is_empty() {
+            shared.push(format_ident!("nested_serde"));
+        }
+        if !self.double_option().is_empty() {
+            shared.push(format_ident!("double_option"));
+        }
+        if !self.defaults_module().is_empty() {
+            shared.push(format_ident!("defaults"));
+        }
+        let shared = (!shared.is_empty()).then(|| {
+            quote! {
+                #[allow(unused_imports)]
+                use super::{#(#shared),*};
+            }
+        });
+
+        let mut modules = BTreeMap::<_, Vec<_>>::new();
+        for ty in self.iter_types() {
+            if let Some(module) = module_of.get(&ty.type_id) {
+                modules.entry(module.clone()).or_default().push(ty);
+            }
+        }
+        modules
+            .into_iter()
+            .map(|(module, types)| {
+                // The types of other modules that these types refer to.
+                let imports = types
+                    .iter()
+                    .flat_map(|ty| self.named_references(&ty.type_id))
+                    .filter_map(|type_id| {
+                        let other = module_of.get(&type_id)?;
+                        (*other != module).then_some((other, type_id))
+                    })
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(|(other, type_id)| {
+                        let other = format_ident!("{}", other);
+                        let type_ident = self.id_to_entry[&type_id].type_ident(self, false);
+                        quote! { use super::#other::#type_ident; }
+                    });
+                let type_defs = types.iter().map(|ty| ty.definition());
+                let stream = quote! {
+                    use serde::{Deserialize, Serialize};
+                    #shared
+                    #(#imports)*
+
+                    #(#type_defs)*
+                };
+                (module, stream)
+            })
+            .collect()
+    }
+
+    /// The module of each named type to generate. The split places the
+    /// types of definitions and root schemas; the others go with the first
+    /// type that refers to them, or else in the `other` module.
+    fn module_of(&self, split: &ModuleSplit) -> BTreeMap<TypeId, String> {
+        let reachable = self.reachable_types();
+        let mut ref_names = BTreeMap::new();
+        for (ref_name, type_id) in &self.ref_to_id {
+            ref_names.entry(type_id).or_insert(ref_name);
+        }
+        let named = self
+            .iter_types()
+            .filter(|ty| {
+                reachable.contains(&ty.type_id)
+                    && (self.is_named(&ty.type_id) || matches!(ty.type_entry, TypeEntry::Alias(_)))
+            })
+            .map(|ty| ty.type_id)
+            .collect::<Vec<_>>();
+
+        let mut module_of = BTreeMap::new();
+        for type_id in &named {
+            let ref_name = ref_names.get(type_id);
+            let type_name = self.id_to_entry[type_id].name();
+            let module = match split {
+                ModuleSplit::Document => ref_name
+                    .and_then(|ref_name| self.definition_files.get(*ref_name))
+                    .or_else(|| self.root_files.get(type_id))
+                    .and_then(|path| Some(path.file_stem()?.to_string_lossy().to_string())),
+                ModuleSplit::Prefix(prefixes) => {
+                    let name = ref_name.copied().or(type_name);
+                    prefixes
+                        .iter()
+                        .filter(|prefix| name.is_some_and(|name| name.starts_with(*prefix)))
+                        .max_by_key(|prefix| prefix.len())
+                        .cloned()
+                }
+                ModuleSplit::Mapping(mapping) => {
+                    type_name.and_then(|name| mapping.get(name).cloned())
+                }
+            };
+            if let Some(module) = module {
+                module_of.insert(type_id.clone(), module_name(&module));
+            }
+        }
+
+        let mut pending = named
+            .iter()
+            .rev()
+            .filter(|type_id| module_of.contains_key(*type_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        while let Some(type_id) = pending.pop() {
+            let module = module_of[&type_id].clone();
+            for other in self.named_references(&type_id).into_iter().rev() {
+                if reachable.contains(&other) && !module_of.contains_key(&other) {
+                    module_of.insert(other.clone(), module.clone());
+                    pending.push(other);
+                }
+            }
+        }
+        for type_id in named {
+            module_of
+                .entry(type_id)
+                .or_insert_with(|| OTHER_MODULE.to_string());
+        }
+        module_of
+    }
+
+    /// The named types that the definition of a type refers to: those of its
+    /// members, within options, arrays and so on, and the type it converts
+    /// to and from if it was split by `readOnly` and `writeOnly` properties.
+    fn named_references(&self, type_id: &TypeId) -> Vec<TypeId> {
+        let mut references = Vec::new();
+        let mut pending = self
+            .id_to_entry
+            .get(type_id)
+            .map(TypeEntry::members)
+            .unwrap_or_default();
+        pending.extend(self.request_types.get(type_id).cloned());
+        pending.extend(
+            self.request_types
+                .iter()
+                .filter(|(_, request_id)| *request_id == type_id)
+                .map(|(response_id, _)| response_id.clone()),
+        );
+        pending.reverse();
+
+        let mut seen = BTreeSet::new();
+        while let Some(type_id) = pending.pop() {
+            if !seen.insert(type_id.clone()) {
+                continue;
+            }
+            match self.id_to_entry.get(&type_id) {
+                Some(
+                    TypeEntry::Enum(_)
+                    | TypeEntry::Struct(_)
+                    | TypeEntry::Newtype(_)
+                    | TypeEntry::Alias(_),
+                ) => references.push(type_id),
+                Some(
+                    TypeEntry::Option(id)
+                    | TypeEntry::Array(id)
+                    | TypeEntry::FixedArray(id, _)
+                    | TypeEntry::Set(id)
+                    | TypeEntry::Box(id)
+                    | TypeEntry::Reference(id),
+                ) => pending.push(id.clone()),
+                Some(TypeEntry::Map(key_id, value_id)) => {
+                    pending.push(value_id.clone());
+                    pending.push(key_id.clone());
+                }
+                Some(TypeEntry::Tuple(ids)) => pending.extend(ids.iter().rev().cloned()),
+                _ => {}
+            }
+        }
+        references
+    }
+}
+
+/// A module's name as an identifier, avoiding those of the modules that the
+/// index holds.
+fn module_name(name: &str) -> String {
+    match sanitize(name, Case::Snake) {
+        name if matches!(
+            name.as_str(),
+            "content_encoding"
+                | "defaults"
+                | "double_option"
+                | "error"
+                | "nested_serde"
+                | "time_serde"
+        ) =>
+        {
+            format!("{}_types", name)
+        }
+        name => name,
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    type_entry::{TypeEntry, TypeEntryAlias, TypeEntryNewtype},
+    NamedSimpleTypes, TypeId, TypeSpace,
+};
+
+impl TypeSpace {
+    /// Make the newtypes of the given definitions, whose schemas are arrays,
+    /// maps, primitives, and the like, type aliases or inline them as
+    /// `TypeSpaceSettings::with_named_simple_types` prescribes. Those that
+    /// reach themselves through other such definitions stay newtypes: an
+    /// alias can't refer to itself and inlining them would never end.
+    pub(crate) fn simplify_named_types(&mut self, type_ids: Vec<TypeId>) {
+        let mode = self
+            .settings
+            .named_simple_types
+            .unwrap_or(NamedSimpleTypes::Newtype);
+        if mode == NamedSimpleTypes::Newtype {
+            return;
+        }
+
+        // Once one of a cycle stays a newtype the others may go.
+        let mut simple = type_ids.iter().cloned().collect::<BTreeSet<_>>();
+        for type_id in &type_ids {
+            if self.reaches_itself(type_id, &simple) {
+                simple.remove(type_id);
+            }
+        }
+        let type_ids = type_ids
+            .into_iter()
+            .filter(|type_id| simple.contains(type_id))
+            .collect::<Vec<_>>();
+
+        match mode {
+            NamedSimpleTypes::Newtype => {}
+            NamedSimpleTypes::Alias => {
+                for type_id in type_ids {
+                    if let Some(TypeEntry::Newtype(newtype)) = self.id_to_entry.get(&type_id) {
+                        let TypeEntryNewtype {
+                            name,
+                            rename,
+                            description,
+                            deprecated,
+                            type_id: inner_id,
+                            ..
+                        } = newtype.clone();
+                        let alias = TypeEntry::Alias(TypeEntryAlias {
+                            name,
+                            rename,
+                            description,
+                            deprecated,
+                            type_id: inner_id,
+                        });
+                        self.id_to_entry.insert(type_id, alias);
+                    }
+                }
+            }
+            NamedSimpleTypes::Inline => self.inline_named_types(type_ids),
+        }
+    }
+
+    /// Whether the values of a type may hold those of the type itself through
+    /// unnamed types and the given simple types.
+    fn reaches_itself(&self, type_id: &TypeId, simple: &BTreeSet<TypeId>) -> bool {
+        let mut pending = self.members(type_id);
+        let mut seen = BTreeSet::new();
+        while let Some(other) = pending.pop() {
+            if other == *type_id {
+                return true;
+            }
+            if !seen.insert(other.clone()) {
+                continue;
+            }
+            match self.id_to_entry.get(&other) {
+                Some(TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_))
+                    if !simple.contains(&other) => {}
+                Some(
+                    TypeEntry::Option(id)
+                    | TypeEntry::Array(id)
+                    | TypeEntry::FixedArray(id, _)
+                    | TypeEntry::Set(id)
+                    | TypeEntry::Box(id)
+                    | TypeEntry::Reference(id),
+                ) => pending.push(id.clone()),
+                Some(TypeEntry::Map(key_id, value_id)) => {
+                    pending.push(key_id.clone());
+                    pending.push(value_id.clone());
+                }
+                Some(TypeEntry::Tuple(ids) | TypeEntry::BuiltInGeneric(_, ids)) => {
+                    pending.extend(ids.iter().cloned())
+                }
+                // The simple types themselves.
+                Some(type_entry) => pending.extend(type_entry.members()),
+                None => {}
+            }
+        }
+        false
+    }
+
+    /// Replace the given newtypes throughout with the types they hold.
+    fn inline_named_types(&mut self, type_ids: Vec<TypeId>) {
+        let mut inner = BTreeMap::new();
+        for type_id in type_ids {
+            if let Some(TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                type_id: inner_id,
+                ..
+            })) = self.id_to_entry.remove(&type_id)
+            {
+                if self.name_to_id.get(&name) == Some(&type_id) {
+                    self.name_to_id.remove(&name);
+                }
+                inner.insert(type_id, inner_id);
+            }
+        }
+        // None reaches itself so this ends.
+        let resolve = |type_id: &mut TypeId| {
+            while let Some(inner_id) = inner.get(type_id) {
+                *type_id = inner_id.clone();
+            }
+        };
+
+        for type_entry in self.id_to_entry.values_mut() {
+            type_entry.type_ids_mut().into_iter().for_each(resolve);
+        }
+        self.ref_to_id.values_mut().for_each(resolve);
+        self.inline_types
+            .values_mut()
+            .for_each(|inline| resolve(&mut inline.type_id));
+        self.type_to_id = std::mem::take(&mut self.type_to_id)
+            .into_values()
+            .filter_map(|type_id| Some((self.id_to_entry.get(&type_id)?.clone(), type_id)))
+            .collect();
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2021 Oxide Computer Company
+
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use quote::quote;
+
+use crate::TypeSpace;
+
+impl TypeSpace {
+    /// The code as `TypeSpaceSettings::with_no_std` says, if it does: its
+    /// `std` paths in `core` or `alloc` instead, and each module, along with
+    /// the code itself if `prelude`, `use`-ing the types and macros of the
+    /// standard prelude that `alloc` holds.
+    pub(crate) fn no_std_code(&self, tokens: TokenStream, prelude: bool) -> TokenStream {
+        if !self.settings.no_std {
+            return tokens;
+        }
+        let tokens = without_std(tokens);
+        match prelude {
+            true => {
+                let prelude = alloc_prelude();
+                quote! {
+                    #prelude
+                    #tokens
+                }
+            }
+            false => tokens,
+        }
+    }
+}
+
+/// The `use`s of the items of the standard prelude missing from that of
+/// `core`.
+fn alloc_prelude() -> TokenStream {
+    quote! {
+        #[allow(unused_imports)]
+        use alloc::{
+            borrow::ToOwned,
+            boxed::Box,
+            format,
+            string::{String, ToString},
+            vec,
+            vec::Vec,
+        };
+    }
+}
+
+/// The tokens with `std::...` paths in `core` or `alloc` where they have the
+/// same items, and with `alloc_prelude` at the start of each module.
+fn without_std(tokens: TokenStream) -> TokenStream {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut output = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        let token = match token {
+            TokenTree::Ident(ident) if ident == "std" && !follows_path_sep(&tokens[..i]) => {
+                let path = path_segments(&tokens[i..]);
+                match std_replacement(&path) {
+                    Some(name) => TokenTree::Ident(proc_macro2::Ident::new(name, ident.span())),
+                    None => token.clone(),
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut stream = without_std(group.stream());
+                let is_module = group.delimiter() == Delimiter::Brace
+                    && matches!(
+                        &tokens[..i],
+                        [.., TokenTree::Ident(keyword), TokenTree::Ident(_)] if keyword == "mod"
+                    );
+                if is_module {
+                    let prelude = alloc_prelude();
+                    stream = quote! {
+                        #prelude
+                        #stream
+                    };
+                }
+                let mut new_group = Group::new(group.delimiter(), stream);
+                new_group.set_span(group.span());
+                TokenTree::Group(new_group)
+            }
+            _ => token.clone(),
+        };
+        output.push(token);
+    }
+    output.into_iter().collect()
+}
+
+/// Whether the tokens end with `::`, i.e. the next is within a path rather
+/// than at its start.
+fn follows_path_sep(tokens: &[TokenTree]) -> bool {
+    matches!(
+        tokens,
+        [.., TokenTree::Punct(first), TokenTree::Punct(second)]
+            if first.as_char() == ':' && second.as_char() == ':'
+    )
+}
+
+/// The identifiers of the path at the start of the tokens, up to the first
+/// token that isn't a `::` or an identifier.
+fn path_segments(tokens: &[TokenTree]) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut rest = tokens;
+    while let [TokenTree::Ident(ident), tail @ ..] = rest {
+        segments.push(ident.to_string());
+        match tail {
+            [TokenTree::Punct(first), TokenTree::Punct(second), tail @ ..]
+                if first.as_char() == ':' && second.as_char() == ':' =>
+            {
+                rest = tail
+            }
+            _ => break,
+        }
+    }
+    segments
+}
+
+/// The crate in place of `std` for a `std::...` path, if there is one that
+/// has the item; the rest stay in `std` and so fail to compile without it.
+fn std_replacement(path: &[String]) -> Option<&'static str> {
+    let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+    match path.as_slice() {
+        ["std", "any" | "cell" | "char" | "cmp" | "convert" | "default" | "error" | "fmt" | "hash"
+        | "iter" | "marker" | "mem" | "net" | "num" | "ops" | "option" | "ptr" | "result"
+        | "slice" | "str", ..] => Some("core"),
+        ["std", "time", "Duration", ..] => Some("core"),
+        ["std", "borrow" | "boxed" | "rc" | "string" | "vec", ..] => Some("alloc"),
+        ["std", "collections", "BTreeMap" | "BTreeSet" | "BinaryHeap" | "LinkedList" | "VecDeque" | "btree_map"
+        | "btree_set", ..] => Some("alloc"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+
+    use super::without_std;
+
+    #[test]
+    fn test_without_std() {
+        let tokens = quote! {
+            pub mod things {
+                pub struct Thing {
+                    pub std: std::collections::BTreeMap<String, std::num::NonZeroU8>,
+                    pub other: std::collections::HashMap<String, Box<Thing>>,
+                    pub instant: std::time::Instant,
+                    pub timeout: std::time::Duration,
+                }
+                impl std::error::Error for Thing {}
+                impl From<std::borrow::Cow<'static, str>> for Thing {}
+                use super::std::thing;
+            }
+        };
+        let expected = quote! {
+            pub mod things {
+                #[allow(unused_imports)]
+                use alloc::{
+                    borrow::ToOwned,
+                    boxed::Box,
+                    format,
+                    string::{String, ToString},
+                    vec,
+                    vec::Vec,
+                };
+                pub struct Thing {
+                    pub std: alloc::collections::BTreeMap<String, core::num::NonZeroU8>,
+                    pub other: std::collections::HashMap<String, Box<Thing>>,
+                    pub instant: std::time::Instant,
+                    pub timeout: core::time::Duration,
+                }
+                impl core::error::Error for Thing {}
+                impl From<alloc::borrow::Cow<'static, str>> for Thing {}
+                use super::std::thing;
+            }
+        };
+        assert_eq!(without_std(tokens).to_string(), expected.to_string());
+    }
+}
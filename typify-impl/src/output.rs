@@ -0,0 +1,96 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+
+use crate::{Error, Result, TypeSpace};
+
+/// A file of generated code that [`TypeSpace::write_to_file`] wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    path: PathBuf,
+    sources: Vec<PathBuf>,
+}
+
+impl GeneratedFile {
+    /// Where the code was written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The schema documents that the code came from, i.e. those that
+    /// [`TypeSpace::add_schema_files`] loaded.
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    /// The `cargo:rerun-if-changed` lines for a `build.rs` to print so that
+    /// it runs again when one of the schema documents changes.
+    pub fn rerun_if_changed(&self) -> Vec<String> {
+        self.sources
+            .iter()
+            .map(|path| format!("cargo:rerun-if-changed={}", path.display()))
+            .collect()
+    }
+}
+
+impl TypeSpace {
+    /// The code of [`TypeSpace::to_stream`] formatted by `rustfmt`. If
+    /// `rustfmt` isn't installed, `prettyplease` formats it instead, unless
+    /// the settings require `rustfmt` with `with_strict_formatting(true)`.
+    pub fn to_string_pretty(&self) -> Result<String> {
+        format_code(self.to_stream(), self.settings.strict_formatting)
+    }
+
+    /// Write the formatted code to `path`, as for a `build.rs`, beneath a
+    /// comment that says what generated it. As the code is then included
+    /// rather than written out in a module, it `use`s serde's derive macros
+    /// itself unless the settings wrap it in a module that does.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<GeneratedFile> {
+        let path = path.as_ref();
+        let sources = match self.schema_files.as_slice() {
+            [] => "a schema".to_string(),
+            files => files
+                .iter()
+                .map(|file| match file.file_name() {
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => file.display().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        let mut content = format!(
+            "// Generated by typify {} from {}; changes will be lost when it's\n\
+             // generated again.\n\n",
+            env!("CARGO_PKG_VERSION"),
+            sources,
+        );
+        if self.settings.wrap_module.is_none() {
+            content.push_str("use serde::{Deserialize, Serialize};\n\n");
+        }
+        content.push_str(&self.to_string_pretty()?);
+
+        std::fs::write(path, content)
+            .map_err(|e| Error::Write(path.display().to_string(), e.to_string()))?;
+        Ok(GeneratedFile {
+            path: path.to_path_buf(),
+            sources: self.schema_files.clone(),
+        })
+    }
+}
+
+fn format_code(tokens: TokenStream, strict: bool) -> Result<String> {
+    match rustfmt_wrapper::rustfmt(tokens.to_string()) {
+        Ok(code) => Ok(code),
+        Err(rustfmt_wrapper::Error::NoRustfmt) if !strict => pretty_print(tokens),
+        Err(rustfmt_wrapper::Error::Rustfmt(reason)) => Err(Error::Format(reason)),
+        Err(e) => Err(Error::Format(e.to_string())),
+    }
+}
+
+/// The code formatted by `prettyplease` for want of `rustfmt`.
+fn pretty_print(tokens: TokenStream) -> Result<String> {
+    let file = syn2::parse_file(&tokens.to_string()).map_err(|e| Error::Format(e.to_string()))?;
+    Ok(prettyplease::unparse(&file))
+}
@@ -0,0 +1,71 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeSet;
+
+use crate::{type_entry::TypeEntry, TypeId, TypeSpace};
+
+impl TypeSpace {
+    /// The names of the types left out of the generated code because the
+    /// roots that the settings give aren't made of them, in order. There are
+    /// none without roots.
+    pub fn pruned_types(&self) -> Vec<String> {
+        let reachable = self.reachable_types();
+        let mut pruned = self
+            .id_to_entry
+            .iter()
+            .filter(|(type_id, _)| !reachable.contains(type_id))
+            .filter_map(|(_, type_entry)| type_entry.name().cloned())
+            .collect::<Vec<_>>();
+        pruned.sort();
+        pruned
+    }
+
+    /// The types to generate: those of the roots and those they're made of,
+    /// or all of them without roots. Replaced types are opaque, so the types
+    /// of the definitions they replace don't count.
+    pub(crate) fn reachable_types(&self) -> BTreeSet<TypeId> {
+        if self.settings.roots.is_empty() {
+            return self.id_to_entry.keys().cloned().collect();
+        }
+
+        let mut reachable = BTreeSet::new();
+        let mut pending = self
+            .settings
+            .roots
+            .iter()
+            .filter_map(|root| self.get_type_by_name(root))
+            .map(|ty| ty.type_id)
+            .collect::<Vec<_>>();
+        while let Some(type_id) = pending.pop() {
+            if !reachable.insert(type_id.clone()) {
+                continue;
+            }
+            // The request type of a type split by `readOnly` and `writeOnly`
+            // properties goes with it.
+            pending.extend(self.request_types.get(&type_id).cloned());
+            match self.id_to_entry.get(&type_id) {
+                Some(
+                    type_entry @ (TypeEntry::Enum(_)
+                    | TypeEntry::Struct(_)
+                    | TypeEntry::Newtype(_)
+                    | TypeEntry::Alias(_)),
+                ) => pending.extend(type_entry.members()),
+                Some(
+                    TypeEntry::Option(id)
+                    | TypeEntry::Array(id)
+                    | TypeEntry::FixedArray(id, _)
+                    | TypeEntry::Set(id)
+                    | TypeEntry::Box(id)
+                    | TypeEntry::Reference(id),
+                ) => pending.push(id.clone()),
+                Some(TypeEntry::Map(key_id, value_id)) => {
+                    pending.push(key_id.clone());
+                    pending.push(value_id.clone());
+                }
+                Some(TypeEntry::Tuple(ids)) => pending.extend(ids.iter().cloned()),
+                _ => {}
+            }
+        }
+        reachable
+    }
+}
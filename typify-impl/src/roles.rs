@@ -0,0 +1,357 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use serde_json::Value;
+
+use crate::{
+    type_entry::{
+        PropertyAccess, SerdeRules, StructProperty, TypeEntry, TypeEntryEnum, TypeEntryStruct,
+        VariantDetails,
+    },
+    ReadWriteOnly, TypeId, TypeSpace,
+};
+
+impl TypeSpace {
+    /// If the settings ask for it, split each named type with `readOnly` or
+    /// `writeOnly` properties, and each named type that contains such a type,
+    /// into a request type without the `readOnly` properties and a response
+    /// type without the `writeOnly` properties. The members of request types
+    /// are the request types of the split types they contain. The response
+    /// type keeps the original type's id so that what referred to the
+    /// original type refers to it.
+    pub(crate) fn split_read_write_only(&mut self) {
+        if self.settings.read_write_only != Some(ReadWriteOnly::Split) {
+            return;
+        }
+
+        let already_split = self
+            .request_types
+            .iter()
+            .flat_map(|(response_id, request_id)| [response_id.clone(), request_id.clone()])
+            .collect::<BTreeSet<_>>();
+        let mut split = self
+            .id_to_entry
+            .iter()
+            .filter(|(type_id, type_entry)| {
+                !already_split.contains(type_id) && type_entry.has_role_properties()
+            })
+            .map(|(type_id, _)| type_id.clone())
+            .collect::<BTreeSet<_>>();
+
+        // Types that contain split types are split in turn.
+        loop {
+            let containers = self
+                .id_to_entry
+                .keys()
+                .filter(|type_id| !already_split.contains(type_id) && !split.contains(type_id))
+                .filter(|type_id| {
+                    let mut named = Vec::new();
+                    for member_id in self.members(type_id) {
+                        self.named_types(&member_id, &mut named);
+                    }
+                    named.iter().any(|named_id| {
+                        split.contains(named_id) || self.request_types.contains_key(named_id)
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if containers.is_empty() {
+                break;
+            }
+            split.extend(containers);
+        }
+
+        let requests = split
+            .into_iter()
+            .map(|response_id| (response_id, self.assign()))
+            .collect::<BTreeMap<_, _>>();
+
+        for (response_id, request_id) in &requests {
+            let type_entry = self.id_to_entry.get(response_id).unwrap().clone();
+            let name = type_entry.name().unwrap().clone();
+            let request = type_entry.for_role(format!("{}Request", name), PropertyAccess::ReadOnly);
+            let response =
+                type_entry.for_role(format!("{}Response", name), PropertyAccess::WriteOnly);
+
+            self.name_to_id.remove(&name);
+            self.name_to_id
+                .insert(request.name().unwrap().clone(), request_id.clone());
+            self.name_to_id
+                .insert(response.name().unwrap().clone(), response_id.clone());
+            self.id_to_entry.insert(request_id.clone(), request);
+            self.id_to_entry.insert(response_id.clone(), response);
+        }
+        self.request_types.extend(requests.clone());
+
+        for request_id in requests.values() {
+            for (index, member_id) in self.members(request_id).into_iter().enumerate() {
+                let request_member_id = self.request_type(&member_id);
+                if request_member_id != member_id {
+                    self.set_member(request_id, index, request_member_id);
+                }
+            }
+        }
+    }
+
+    /// The type to use in requests in place of the given type: the request
+    /// type of a split type, or the same construction of the request types
+    /// of the split types within it.
+    fn request_type(&mut self, type_id: &TypeId) -> TypeId {
+        if let Some(request_id) = self.request_types.get(type_id) {
+            return request_id.clone();
+        }
+
+        let type_entry = match self.id_to_entry.get(type_id).cloned() {
+            Some(type_entry) => type_entry,
+            None => return type_id.clone(),
+        };
+        let request = match &type_entry {
+            TypeEntry::Option(id) => TypeEntry::Option(self.request_type(id)),
+            TypeEntry::Box(id) => TypeEntry::Box(self.request_type(id)),
+            TypeEntry::Array(id) => TypeEntry::Array(self.request_type(id)),
+            TypeEntry::Set(id) => TypeEntry::Set(self.request_type(id)),
+            TypeEntry::FixedArray(id, len) => TypeEntry::FixedArray(self.request_type(id), *len),
+            TypeEntry::Map(key_id, value_id) => {
+                TypeEntry::Map(self.request_type(key_id), self.request_type(value_id))
+            }
+            TypeEntry::Tuple(ids) => {
+                TypeEntry::Tuple(ids.iter().map(|id| self.request_type(id)).collect())
+            }
+            TypeEntry::Reference(id) => return self.request_type(id),
+            _ => return type_id.clone(),
+        };
+
+        if request == type_entry {
+            type_id.clone()
+        } else {
+            self.assign_type(request)
+        }
+    }
+
+    /// The conversions between the request and response types split from a
+    /// struct, emitted with the request type of the given name: `From` each
+    /// for the other, where the properties one lacks are optional or have
+    /// defaults and the properties they share have the same types.
+    pub(crate) fn role_conversions(&self, name: &str) -> TokenStream {
+        let pair = self
+            .request_types
+            .iter()
+            .find_map(|(response_id, request_id)| {
+                match (
+                    self.id_to_entry.get(response_id),
+                    self.id_to_entry.get(request_id),
+                ) {
+                    (Some(TypeEntry::Struct(response)), Some(TypeEntry::Struct(request)))
+                        if request.name == name =>
+                    {
+                        Some((response, request))
+                    }
+                    _ => None,
+                }
+            });
+
+        match pair {
+            Some((response, request)) => {
+                let from_response = self.conversion(response, request);
+                let from_request = self.conversion(request, response);
+                quote! {
+                    #from_response
+                    #from_request
+                }
+            }
+            None => quote! {},
+        }
+    }
+
+    fn conversion(&self, from: &TypeEntryStruct, into: &TypeEntryStruct) -> Option<TokenStream> {
+        let mut shared = false;
+        let fields = into
+            .properties
+            .iter()
+            .map(|prop| {
+                let name = format_ident!("{}", prop.name);
+                let from_prop = from
+                    .properties
+                    .iter()
+                    .find(|from_prop| from_prop.name == prop.name);
+                let value = match (from_prop, &prop.serde_rules) {
+                    (Some(from_prop), _)
+                        if from_prop.type_id == prop.type_id
+                            && (from_prop.serde_rules == SerdeRules::ExplicitNull)
+                                == (prop.serde_rules == SerdeRules::ExplicitNull) =>
+                    {
+                        shared = true;
+                        quote! { value.#name }
+                    }
+                    (Some(_), _) | (None, SerdeRules::None) => return None,
+                    (None, SerdeRules::Optional | SerdeRules::ExplicitNull) => {
+                        quote! { Default::default() }
+                    }
+                    (None, SerdeRules::Default) => self.property_default(prop)?,
+                };
+                Some(quote! { #name: #value })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let from_name = format_ident!("{}", from.name);
+        let into_name = format_ident!("{}", into.name);
+        let value = if shared {
+            quote! { value }
+        } else {
+            quote! { _value }
+        };
+        Some(quote! {
+            impl From<#from_name> for #into_name {
+                fn from(#value: #from_name) -> Self {
+                    Self {
+                        #(#fields,)*
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl TypeEntry {
+    /// Whether the type has properties that are absent in requests or
+    /// responses.
+    fn has_role_properties(&self) -> bool {
+        let role_property = |prop: &StructProperty| prop.access != PropertyAccess::ReadWrite;
+        match self {
+            TypeEntry::Struct(TypeEntryStruct { properties, .. }) => {
+                properties.iter().any(role_property)
+            }
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => {
+                variants.iter().any(|variant| match &variant.details {
+                    VariantDetails::Struct(properties) => properties.iter().any(role_property),
+                    _ => false,
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// A copy of a split type for a role that lacks the properties with the
+    /// given access, with the given name.
+    fn for_role(&self, name: String, omit: PropertyAccess) -> TypeEntry {
+        let mut type_entry = self.clone();
+        match &mut type_entry {
+            TypeEntry::Struct(TypeEntryStruct {
+                properties,
+                default,
+                ..
+            }) => {
+                let omitted = properties
+                    .iter()
+                    .filter(|prop| prop.access == omit)
+                    .map(StructProperty::serde_name)
+                    .collect::<Vec<_>>();
+                properties.retain(|prop| prop.access != omit);
+
+                // The default omits the values of the omitted properties too.
+                *default = default.take().map(|default| {
+                    match serde_json::from_str::<Value>(&default).unwrap() {
+                        Value::Object(mut object) => {
+                            omitted.iter().for_each(|name| {
+                                object.remove(name);
+                            });
+                            Value::Object(object).to_string()
+                        }
+                        _ => default,
+                    }
+                });
+            }
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => {
+                for variant in variants {
+                    if let VariantDetails::Struct(properties) = &mut variant.details {
+                        properties.retain(|prop| prop.access != omit);
+                    }
+                }
+            }
+            _ => {}
+        }
+        *type_entry.name_mut().unwrap().0 = name;
+        type_entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{test_util::assert_output, ReadWriteOnly, TypeSpace, TypeSpaceSettings};
+
+    fn read_write_schema() -> RootSchema {
+        serde_json::from_value(json!({
+            "definitions": {
+                "User": {
+                    "type": "object",
+                    "required": ["id", "name", "password"],
+                    "properties": {
+                        "id": { "type": "integer", "readOnly": true },
+                        "name": { "type": "string" },
+                        "password": { "type": "string", "writeOnly": true }
+                    }
+                },
+                "Team": {
+                    "type": "object",
+                    "required": ["members"],
+                    "properties": {
+                        "members": {
+                            "type": "array",
+                            "items": { "$ref": "#/definitions/User" }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_write_only_optional() {
+        let schema = read_write_schema();
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_read_write_only(ReadWriteOnly::Optional),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "roles/read_write_only_optional");
+    }
+
+    #[test]
+    fn test_read_write_only_split() {
+        let schema = read_write_schema();
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_read_write_only(ReadWriteOnly::Split));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // Neither user type can be made from the other as each lacks a
+        // required property of the other.
+        assert_output(&type_space, "roles/read_write_only_split");
+    }
+
+    #[test]
+    fn test_read_write_only_conversions() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "id": { "type": "integer", "readOnly": true },
+                        "name": { "type": "string" },
+                        "token": { "type": "string", "writeOnly": true }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_read_write_only(ReadWriteOnly::Split));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "roles/read_write_only_conversions");
+    }
+}
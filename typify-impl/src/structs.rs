@@ -1,16 +1,24 @@
 // Copyright 2021 Oxide Computer Company
 
+use std::collections::BTreeMap;
+
 use convert_case::{Case, Casing};
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 use schemars::schema::{
-    InstanceType, Metadata, ObjectValidation, Schema, SchemaObject, SingleOrVec,
+    InstanceType, Metadata, ObjectValidation, Schema, SchemaObject, SingleOrVec, StringValidation,
 };
 
 use crate::{
-    type_entry::{SerdeNaming, SerdeRules, StructProperty, TypeEntryStruct},
-    util::{get_type_name, metadata_description, recase, schema_is_named},
-    Name, Result, TypeEntry, TypeId, TypeSpace,
+    merge::merge_all,
+    type_entry::{
+        deprecated_attr, PropertyAccess, SerdeNaming, SerdeRules, StructProperty, TypeEntryStruct,
+    },
+    util::{
+        get_type_name, metadata_access, metadata_default, metadata_deprecated,
+        metadata_description, path_type_name, recase, schema_is_named, unraw,
+    },
+    Error, Name, ReadWriteOnly, Result, SkipSerializingNone, TypeEntry, TypeId, TypeSpace,
 };
 
 impl TypeSpace {
@@ -29,10 +37,7 @@ impl TypeSpace {
             .properties
             .iter()
             .map(|(name, ty)| {
-                let prop_name = name.to_case(Case::Snake);
-                let sub_type_name = type_name
-                    .as_ref()
-                    .map(|base| format!("{}_{}", base, prop_name));
+                let sub_type_name = type_name.as_ref().map(|base| path_type_name(&[base, name]));
                 self.struct_property(sub_type_name, &validation.required, name, ty)
             })
             .collect::<Result<Vec<_>>>()?;
@@ -46,11 +51,15 @@ impl TypeSpace {
         // best known to the JSON Schema authors.
         let deny_unknown_fields = match &validation.additional_properties {
             // No additional properties allowed; we'll tag the struct with
-            // #[serde(deny_unknown_fields)]
-            Some(a) if a.as_ref() == &Schema::Bool(false) => true,
+            // #[serde(deny_unknown_fields)] unless there are pattern
+            // properties that other properties may match.
+            Some(a) if a.as_ref() == &Schema::Bool(false) => {
+                validation.pattern_properties.is_empty()
+            }
 
             // We have a permissive schema so all additional properties are
-            // allowed (None is equivalent to the permissive schema).
+            // allowed (None is equivalent to the permissive schema). If they're
+            // explicitly permitted we may capture them with a flattened map.
             Some(a)
                 if matches!(
                     a.as_ref(),
@@ -71,31 +80,65 @@ impl TypeSpace {
                         })
                 ) =>
             {
+                if self.settings.capture_extra_properties {
+                    let extra_prop = self.extra_property(type_name.as_ref(), &properties, &None)?;
+                    properties.push(extra_prop);
+                }
                 false
             }
             None => false,
 
-            // Only particular additional properties are allowed.
+            // Only particular additional properties are allowed; these are
+            // captured by a flattened map which serde doesn't allow alongside
+            // #[serde(deny_unknown_fields)] (nor is it needed).
             additional_properties @ Some(_) => {
-                let sub_type_name = type_name.as_ref().map(|base| format!("{}_extra", base));
-                let (map_type, _) = self.make_map(sub_type_name, additional_properties)?;
-                let map_type_id = self.assign_type(map_type);
-                let extra_prop = StructProperty {
-                    name: "extra".to_string(),
-                    serde_naming: SerdeNaming::Flatten,
-                    serde_rules: SerdeRules::None,
-                    description: None,
-                    type_id: map_type_id,
-                };
-
+                let extra_prop =
+                    self.extra_property(type_name.as_ref(), &properties, additional_properties)?;
                 properties.push(extra_prop);
-                true
+                false
             }
         };
 
         Ok((properties, deny_unknown_fields))
     }
 
+    /// Make the flattened map member that holds the additional properties of
+    /// a struct. Its name must differ from those of the other properties.
+    fn extra_property(
+        &mut self,
+        type_name: Option<&String>,
+        properties: &[StructProperty],
+        additional_properties: &Option<Box<Schema>>,
+    ) -> Result<StructProperty> {
+        let sub_type_name = type_name.map(|base| format!("{}_extra", base));
+        let (map_type, _) = self.make_map(sub_type_name, additional_properties)?;
+        let map_type_id = self.assign_type(map_type);
+
+        let base_name = self
+            .settings
+            .extra_properties_name
+            .clone()
+            .unwrap_or_else(|| "extra".to_string());
+        let taken = |name: &String| properties.iter().any(|prop| &prop.name == name);
+        let name = std::iter::once(base_name.clone())
+            .chain((1..).map(|n| format!("{}_{}", base_name, n)))
+            .find(|name| !taken(name))
+            .unwrap();
+
+        Ok(StructProperty {
+            name,
+            serde_naming: SerdeNaming::Flatten,
+            serde_rules: SerdeRules::None,
+            description: None,
+            deprecated: false,
+            default: None,
+            access: PropertyAccess::ReadWrite,
+            nullable: false,
+            redacted: false,
+            type_id: map_type_id,
+        })
+    }
+
     pub(crate) fn struct_property(
         &mut self,
         type_name: Option<String>,
@@ -107,10 +150,36 @@ impl TypeSpace {
             Some(name) => Name::Suggested(name),
             None => Name::Unknown,
         };
-        let (mut type_id, metadata) = self.id_for_schema(sub_type_name, schema)?;
+        let (mut type_id, metadata) = self
+            .within(["properties".into(), prop_name.into()], |type_space| {
+                type_space.id_for_schema(sub_type_name, schema)
+            })?;
 
-        let serde_rules = if required.contains(prop_name) {
+        // A property with a default (other than null) takes that value when
+        // it's absent so it needn't be optional. Properties that are absent in
+        // requests or responses may be optional even if they're required.
+        // An optional property of an enum with a default has that default
+        // unless it has its own.
+        let default = metadata_default(metadata).or_else(|| {
+            (!required.contains(prop_name))
+                .then(|| self.enum_default(schema))
+                .flatten()
+        });
+        let access = metadata_access(metadata);
+        let role_optional = self.settings.read_write_only == Some(ReadWriteOnly::Optional)
+            && access != PropertyAccess::ReadWrite;
+        let nullable = is_nullable(self, &type_id);
+        let format = match schema {
+            Schema::Object(SchemaObject { format, .. }) => format.as_deref(),
+            Schema::Bool(_) => None,
+        };
+        let redacted = self.settings.is_redacted(prop_name, format, access);
+        let serde_rules = if default.as_deref().is_some_and(|default| default != "null") {
+            SerdeRules::Default
+        } else if required.contains(prop_name) && !role_optional {
             SerdeRules::None
+        } else if self.settings.explicit_nulls && nullable {
+            SerdeRules::ExplicitNull
         } else {
             // We can use serde's `skip_serializing_of` construction for options
             // and arrays; otherwise we need to turn this into an option in order
@@ -132,10 +201,43 @@ impl TypeSpace {
             serde_naming,
             serde_rules,
             description: metadata_description(metadata),
+            deprecated: metadata_deprecated(metadata),
+            default,
+            access,
+            nullable,
+            redacted,
             type_id,
         })
     }
 
+    /// The default of the enum definition that the schema refers to, if it
+    /// has one and isn't replaced.
+    fn enum_default(&self, schema: &Schema) -> Option<String> {
+        let reference = match schema {
+            Schema::Object(SchemaObject {
+                reference: Some(reference),
+                ..
+            }) => reference,
+            _ => return None,
+        };
+        let ref_name = reference.strip_prefix("#/definitions/")?;
+        if self
+            .settings
+            .replace
+            .contains_key(crate::definition_type_name(ref_name))
+        {
+            return None;
+        }
+        match self.definitions.get(ref_name)? {
+            Schema::Object(SchemaObject {
+                metadata,
+                enum_values: Some(_),
+                ..
+            }) => metadata_default(metadata).filter(|default| default != "null"),
+            _ => None,
+        }
+    }
+
     pub(crate) fn make_map<'a>(
         &mut self,
         type_name: Option<String>,
@@ -147,7 +249,9 @@ impl TypeSpace {
                     Some(name) => Name::Suggested(format!("{}Extra", name)),
                     None => Name::Unknown,
                 };
-                self.id_for_schema(sub_type_name, schema)?
+                self.within(["additionalProperties".into()], |type_space| {
+                    type_space.id_for_schema(sub_type_name, schema)
+                })?
             }
 
             None => self.id_for_schema(Name::Unknown, &Schema::Bool(true))?,
@@ -156,7 +260,177 @@ impl TypeSpace {
         // TODO this is jank; we should be looking up the String type
         let string_type_id = self.assign_type(TypeEntry::String);
 
-        Ok((TypeEntry::Map(string_type_id, value_type_id), &None))
+        Ok((self.map_of(string_type_id, value_type_id), &None))
+    }
+
+    /// Make a map for an object whose only properties are those matching a
+    /// pattern. The keys are a newtype that checks the pattern so that other
+    /// properties are rejected.
+    pub(crate) fn make_pattern_map<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        pattern_properties: &schemars::Map<String, Schema>,
+        additional_properties: &Option<Box<Schema>>,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let map_name = get_type_name(&type_name, metadata, Case::Pascal);
+        let describe = || {
+            map_name
+                .clone()
+                .unwrap_or_else(|| "an anonymous type".to_string())
+        };
+
+        // Keys matching several patterns would need values that match each of
+        // their schemas; for now we only handle one.
+        let (pattern, schema) = match pattern_properties.iter().collect::<Vec<_>>().as_slice() {
+            [(pattern, schema)] => (*pattern, *schema),
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "{} has more than one patternProperties pattern",
+                    describe(),
+                )))
+            }
+        };
+        if !matches!(
+            additional_properties.as_deref(),
+            None | Some(Schema::Bool(false)) | Some(Schema::Bool(true))
+        ) {
+            return Err(Error::Unsupported(format!(
+                "{} has both patternProperties and an additionalProperties schema",
+                describe(),
+            )));
+        }
+
+        let (key_name, value_name) = match &map_name {
+            Some(name) => (
+                Name::Suggested(format!("{}Key", name)),
+                Name::Suggested(format!("{}Value", name)),
+            ),
+            None => {
+                self.warn(
+                    "patternProperties",
+                    format!(
+                        "patternProperties {:?} of an anonymous type can't be enforced",
+                        pattern,
+                    ),
+                );
+                (Name::Unknown, Name::Unknown)
+            }
+        };
+        let key_validation = StringValidation {
+            max_length: None,
+            min_length: None,
+            pattern: Some(pattern.clone()),
+        };
+        let (key_type, _) = self
+            .within(["patternProperties".into(), pattern.into()], |type_space| {
+                type_space.convert_validated_string(key_name, &None, &key_validation)
+            })?;
+        let key_type_id = self.assign_type(key_type);
+        let (value_type_id, _) = self
+            .within(["patternProperties".into(), pattern.into()], |type_space| {
+                type_space.id_for_schema(value_name, schema)
+            })?;
+
+        Ok((self.map_of(key_type_id, value_type_id), metadata))
+    }
+
+    /// Make a map for an object whose property names are constrained by a
+    /// `propertyNames` schema. String constraints become a key newtype that
+    /// checks them; references and enumerated values are used as the key type
+    /// directly.
+    pub(crate) fn make_key_map<'a>(
+        &mut self,
+        type_name: Name,
+        metadata: &'a Option<Box<Metadata>>,
+        property_names: &Schema,
+        additional_properties: &Option<Box<Schema>>,
+    ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let map_name = get_type_name(&type_name, metadata, Case::Pascal);
+        let (key_name, value_name) = match &map_name {
+            Some(name) => (
+                Name::Suggested(format!("{}Key", name)),
+                Name::Suggested(format!("{}Value", name)),
+            ),
+            None => (Name::Unknown, Name::Unknown),
+        };
+
+        let key_type_id = match property_names {
+            Schema::Bool(true) => self.assign_type(TypeEntry::String),
+
+            // String constraints on the property names.
+            Schema::Object(SchemaObject {
+                metadata: _,
+                instance_type,
+                format: None,
+                enum_values: None,
+                const_value: None,
+                subschemas: None,
+                number: None,
+                string: Some(validation),
+                array: None,
+                object: None,
+                reference: None,
+                extensions: _,
+            }) if instance_type
+                .as_ref()
+                .is_none_or(|it| it == &SingleOrVec::from(InstanceType::String)) =>
+            {
+                if map_name.is_none() {
+                    self.warn(
+                        "propertyNames",
+                        "propertyNames of an anonymous type can't be enforced",
+                    );
+                }
+                let (key_type, _) = self.within(["propertyNames".into()], |type_space| {
+                    type_space.convert_validated_string(key_name, &None, validation)
+                })?;
+                self.assign_type(key_type)
+            }
+
+            // Named or enumerated keys.
+            Schema::Object(SchemaObject {
+                reference: Some(_), ..
+            })
+            | Schema::Object(SchemaObject {
+                enum_values: Some(_),
+                ..
+            }) => {
+                self.within(["propertyNames".into()], |type_space| {
+                    type_space.id_for_schema(key_name, property_names)
+                })?
+                .0
+            }
+
+            _ => {
+                self.warn(
+                    "propertyNames",
+                    format!(
+                        "propertyNames of {} aren't supported and won't be enforced",
+                        map_name.unwrap_or_else(|| "an anonymous type".to_string()),
+                    ),
+                );
+                self.assign_type(TypeEntry::String)
+            }
+        };
+
+        let (value_type_id, _) = match additional_properties {
+            Some(schema) => self.within(["additionalProperties".into()], |type_space| {
+                type_space.id_for_schema(value_name, schema)
+            })?,
+            None => self.id_for_schema(Name::Unknown, &Schema::Bool(true))?,
+        };
+
+        Ok((self.map_of(key_type_id, value_type_id), metadata))
+    }
+
+    fn map_of(&mut self, key_type_id: TypeId, value_type_id: TypeId) -> TypeEntry {
+        if self.map_type() == "indexmap::IndexMap" {
+            self.uses_indexmap = true;
+        }
+        let ty = TypeEntry::Map(key_type_id, value_type_id);
+        self.warn_unreached_with(&ty);
+        ty
     }
 
     /// This is used by both any-of and all-of subschema processing. This
@@ -181,6 +455,7 @@ impl TypeSpace {
         subschemas: &[Schema],
         optional: bool,
     ) -> Result<(TypeEntry, &'a Option<Box<Metadata>>)> {
+        let keyword = if optional { "anyOf" } else { "allOf" };
         let properties = subschemas
             .iter()
             .enumerate()
@@ -190,7 +465,9 @@ impl TypeSpace {
                     None => Name::Unknown,
                 };
 
-                let (mut type_id, _) = self.id_for_schema(type_name, schema)?;
+                let (mut type_id, _) = self.within([keyword.into(), idx.into()], |type_space| {
+                    type_space.id_for_schema(type_name, schema)
+                })?;
                 if optional {
                     type_id = self.id_to_option(&type_id);
                 }
@@ -208,6 +485,11 @@ impl TypeSpace {
                         SerdeRules::None
                     },
                     description: None,
+                    deprecated: false,
+                    default: None,
+                    access: PropertyAccess::ReadWrite,
+                    nullable: false,
+                    redacted: false,
                     type_id,
                 })
             })
@@ -241,23 +523,24 @@ impl TypeSpace {
     ///
     /// Note that the super class member names are derived from the type and are
     /// flattened into the struct; the subclass properties are simply included
-    /// alongside.
+    /// alongside. Serde couldn't tell to which member a property belongs if
+    /// several define it so that's an error.
     pub(crate) fn maybe_all_of_subclass(
         &mut self,
         type_name: Name,
         metadata: &Option<Box<Metadata>>,
         subschemas: &[Schema],
-    ) -> Option<TypeEntry> {
+    ) -> Result<Option<TypeEntry>> {
         assert!(subschemas.len() > 1);
 
         // Split the subschemas into named (superclass) and unnamed (subclass)
         // schemas.
         let mut named = Vec::new();
         let mut unnamed = Vec::new();
-        for schema in subschemas {
+        for (index, schema) in subschemas.iter().enumerate() {
             match schema_is_named(schema) {
-                Some(name) => named.push((schema, name)),
-                None => unnamed.push(schema),
+                Some(name) => named.push((index, schema, name)),
+                None => unnamed.push((index, schema)),
             }
         }
 
@@ -265,11 +548,11 @@ impl TypeSpace {
         // that zero unnamed subschemas would be trivial to handle, but the generic
         // case already does so albeit slightly differently.
         if unnamed.len() != 1 {
-            return None;
+            return Ok(None);
         }
 
         // Get the object validation (or fail to match this special case).
-        let unnamed_schema = unnamed.first()?;
+        let (unnamed_index, unnamed_schema) = unnamed[0];
         let validation = match unnamed_schema {
             Schema::Object(SchemaObject {
                 metadata: _,
@@ -284,43 +567,128 @@ impl TypeSpace {
                 object: Some(validation),
                 reference: None,
                 extensions: _,
-            }) if single.as_ref() == &InstanceType::Object => Some(validation),
-            _ => None,
-        }?;
+            }) if single.as_ref() == &InstanceType::Object => validation,
+            _ => return Ok(None),
+        };
         let tmp_type_name = get_type_name(&type_name, metadata, Case::Pascal);
-        let (unnamed_properties, deny) = self.struct_members(tmp_type_name, validation).ok()?;
+        let describe = || {
+            tmp_type_name
+                .clone()
+                .unwrap_or_else(|| "an anonymous type".to_string())
+        };
+
+        // Find the super class that defines each property where we can
+        // resolve it to an object.
+        let mut owners = BTreeMap::<&str, &str>::new();
+        let super_properties = named
+            .iter()
+            .map(|(_, schema, name)| {
+                let properties = match merge_all(vec![*schema], &self.definitions) {
+                    Ok(SchemaObject {
+                        object: Some(object),
+                        ..
+                    }) => object.properties.keys().cloned().collect(),
+                    _ => Vec::new(),
+                };
+                (name.as_str(), properties)
+            })
+            .collect::<Vec<_>>();
+        for (name, properties) in &super_properties {
+            for property in properties {
+                if let Some(other) = owners.insert(property, name) {
+                    return Err(Error::Unsupported(format!(
+                        "property {:?} is defined by both {} and {}, which are flattened into {}",
+                        property,
+                        other,
+                        name,
+                        describe(),
+                    )));
+                }
+            }
+        }
+        for property in validation.properties.keys() {
+            if let Some(owner) = owners.get(property.as_str()) {
+                return Err(Error::Unsupported(format!(
+                    "property {:?} of {} is also defined by {}, which is flattened into it",
+                    property,
+                    describe(),
+                    owner,
+                )));
+            }
+        }
+
+        let (unnamed_properties, deny) = self
+            .within(["allOf".into(), unnamed_index.into()], |type_space| {
+                type_space.struct_members(tmp_type_name.clone(), validation)
+            })?;
 
         let named_properties = named
             .iter()
-            .map(|(schema, property_name)| {
-                let (type_id, metadata) = self.id_for_schema(type_name.clone(), schema)?;
+            .map(|(index, schema, property_name)| {
+                let name = property_name.to_case(Case::Snake);
+                if unnamed_properties.iter().any(|prop| prop.name == name) {
+                    return Err(Error::Unsupported(format!(
+                        "the member for {} would have the same name as property {:?} of {}",
+                        property_name,
+                        name,
+                        describe(),
+                    )));
+                }
+                let (type_id, metadata) = self
+                    .within(["allOf".into(), (*index).into()], |type_space| {
+                        type_space.id_for_schema(type_name.clone(), schema)
+                    })?;
                 Ok(StructProperty {
-                    name: property_name.to_case(Case::Snake),
+                    name,
                     serde_naming: SerdeNaming::Flatten,
                     serde_rules: SerdeRules::None,
                     description: metadata_description(metadata),
+                    deprecated: false,
+                    default: None,
+                    access: PropertyAccess::ReadWrite,
+                    nullable: false,
+                    redacted: false,
                     type_id,
                 })
             })
-            .collect::<Result<Vec<_>>>()
-            .ok()?;
+            .collect::<Result<Vec<_>>>()?;
+
+        // Serde doesn't support #[serde(deny_unknown_fields)] with flattened
+        // members; the fields of the super classes would be unknown.
+        if deny {
+            let message = format!(
+                "additionalProperties false for {} can't be enforced alongside \
+                 the properties of {}",
+                describe(),
+                named
+                    .iter()
+                    .map(|(_, _, name)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+            self.warn("additionalProperties", message);
+        }
 
         let ty = TypeEntryStruct::from_metadata(
             type_name,
             metadata,
             named_properties
                 .into_iter()
-                .chain(unnamed_properties.into_iter())
+                .chain(unnamed_properties)
                 .collect(),
-            deny,
+            false,
         );
 
-        Some(ty)
+        Ok(Some(ty))
     }
 }
 
+/// A struct member, or a member of a struct variant. The owner is the name of
+/// the struct or the enum and variant, e.g. `ShapeCircle`, which names the
+/// functions for the property's default.
 pub(crate) fn output_struct_property(
     prop: &StructProperty,
+    owner: &str,
     type_space: &TypeSpace,
     make_pub: bool,
 ) -> TokenStream {
@@ -331,50 +699,241 @@ pub(crate) fn output_struct_property(
     };
 
     let prop_type = type_space.id_to_entry.get(&prop.type_id).unwrap();
-    let type_name = prop_type.type_ident(type_space, false);
+    let type_name = property_type(prop, type_space);
     let pub_token = if make_pub {
-        quote! { pub }
+        type_space.field_visibility()
     } else {
         quote! {}
     };
-    let serde = generate_serde_attr(&prop.serde_naming, &prop.serde_rules, prop_type);
+    let deprecated = deprecated_attr(prop.deprecated, &prop.description);
+    let serde = generate_serde_attr(prop, owner, prop_type, type_space);
     quote! {
         #doc
+        #deprecated
         #serde
         #pub_token #name: #type_name,
     }
 }
 
+/// Methods that return references to the fields of a struct, e.g. for fields
+/// that aren't visible outside the module.
+pub(crate) fn output_struct_accessors(
+    name: &str,
+    properties: &[StructProperty],
+    type_space: &TypeSpace,
+) -> TokenStream {
+    let type_name = format_ident!("{}", name);
+    let accessors = properties.iter().map(|prop| {
+        let name = format_ident!("{}", prop.name);
+        let doc = prop
+            .description
+            .as_ref()
+            .map(|desc| quote! { #[doc = #desc] });
+        let prop_type = property_type(prop, type_space);
+        quote! {
+            #doc
+            pub fn #name(&self) -> &#prop_type {
+                &self.#name
+            }
+        }
+    });
+
+    quote! {
+        impl #type_name {
+            #(#accessors)*
+        }
+    }
+}
+
+/// A builder for a struct, e.g. `Thing::builder().name("x").try_into()`,
+/// whose setters take any value that converts to the property's type. Each
+/// property starts with its default, if it has one, or as missing if it's
+/// required; converting the builder to the struct fails for missing
+/// properties and for values that didn't convert, and, if `validate`, for
+/// structs that the struct's `validate` method rejects.
+pub(crate) fn output_struct_builder(
+    name: &str,
+    properties: &[StructProperty],
+    validate: bool,
+    type_space: &TypeSpace,
+) -> TokenStream {
+    let type_name = format_ident!("{}", name);
+    let builder_name = format_ident!("{}Builder", name);
+    let vis = type_space.type_visibility();
+
+    let prop_names = properties
+        .iter()
+        .map(|prop| format_ident!("{}", prop.name))
+        .collect::<Vec<_>>();
+    let prop_types = properties
+        .iter()
+        .map(|prop| property_type(prop, type_space))
+        .collect::<Vec<_>>();
+    let prop_defaults = properties.iter().map(|prop| {
+        let prop_type = type_space.id_to_entry.get(&prop.type_id).unwrap();
+        match (&prop.default, type_space.property_default(prop)) {
+            (Some(_), Some(value)) => quote! { Ok(#value) },
+            (Some(default), None) => {
+                let message = format!("invalid default for {}: {{}}", unraw(&prop.name));
+                quote! { serde_json::from_str(#default).map_err(|e| format!(#message, e)) }
+            }
+            (None, _)
+                if matches!(
+                    prop.serde_rules,
+                    SerdeRules::Optional | SerdeRules::ExplicitNull
+                ) || (prop.serde_naming == SerdeNaming::Flatten
+                    && matches!(prop_type, TypeEntry::Map(_, _))) =>
+            {
+                quote! { Ok(Default::default()) }
+            }
+            (None, _) => {
+                let message = format!("no value supplied for {}", unraw(&prop.name));
+                quote! { Err(#message.to_string()) }
+            }
+        }
+    });
+    let messages = properties.iter().map(|prop| {
+        format!(
+            "error converting supplied value for {}: {{}}",
+            unraw(&prop.name)
+        )
+    });
+
+    let conversion_error = type_space.conversion_error();
+    let finish = if validate {
+        quote! {
+            let value = Self {
+                #( #prop_names: value.#prop_names?, )*
+            };
+            value.validate()?;
+            Ok(value)
+        }
+    } else {
+        quote! {
+            Ok(Self {
+                #( #prop_names: value.#prop_names?, )*
+            })
+        }
+    };
+
+    // The builder holds the values of redacted properties too.
+    let builder_debug = redacted_debug(&builder_name, properties);
+    let builder_derives = match builder_debug {
+        Some(_) => quote! { Clone },
+        None => quote! { Debug, Clone },
+    };
+
+    quote! {
+        impl #type_name {
+            pub fn builder() -> #builder_name {
+                Default::default()
+            }
+        }
+
+        #[derive(#builder_derives)]
+        #vis struct #builder_name {
+            #( #prop_names: Result<#prop_types, String>, )*
+        }
+
+        #builder_debug
+
+        impl Default for #builder_name {
+            fn default() -> Self {
+                Self {
+                    #( #prop_names: #prop_defaults, )*
+                }
+            }
+        }
+
+        impl #builder_name {
+            #(
+                pub fn #prop_names<T>(mut self, value: T) -> Self
+                where
+                    T: std::convert::TryInto<#prop_types>,
+                    T::Error: std::fmt::Display,
+                {
+                    self.#prop_names = value
+                        .try_into()
+                        .map_err(|e| format!(#messages, e));
+                    self
+                }
+            )*
+        }
+
+        impl std::convert::TryFrom<#builder_name> for #type_name {
+            type Error = #conversion_error;
+
+            fn try_from(value: #builder_name) -> Result<Self, Self::Error> {
+                #finish
+            }
+        }
+    }
+}
+
 fn generate_serde_attr(
-    serde_naming: &SerdeNaming,
-    serde_rules: &SerdeRules,
+    prop: &StructProperty,
+    owner: &str,
     prop_type: &TypeEntry,
+    type_space: &TypeSpace,
 ) -> TokenStream {
     let mut serde_options = Vec::new();
-    match serde_naming {
+    match &prop.serde_naming {
         SerdeNaming::Rename(s) => serde_options.push(quote! { rename = #s }),
         SerdeNaming::Flatten => serde_options.push(quote! { flatten }),
         SerdeNaming::None => (),
     }
 
-    match (serde_rules, &prop_type) {
+    match (&prop.serde_rules, &prop_type) {
         (SerdeRules::Optional, TypeEntry::Option(_)) => {
             serde_options.push(quote! { default });
-            serde_options.push(quote! { skip_serializing_if = "Option::is_none" });
+            let skip = match type_space.settings.skip_serializing_none {
+                None | Some(SkipSerializingNone::Always) => true,
+                Some(SkipSerializingNone::Never) => false,
+                Some(SkipSerializingNone::UnlessNullable) => !prop.nullable,
+            };
+            if skip {
+                serde_options.push(quote! { skip_serializing_if = "Option::is_none" });
+            }
         }
         (SerdeRules::Optional, TypeEntry::Array(_)) => {
             serde_options.push(quote! { default });
-            serde_options.push(quote! { skip_serializing_if = "Vec::is_empty" });
+            if type_space.settings.skip_serializing_empty != Some(false) {
+                serde_options.push(quote! { skip_serializing_if = "Vec::is_empty" });
+            }
         }
         (SerdeRules::Optional, TypeEntry::Map(_, _)) => {
             serde_options.push(quote! { default });
-            serde_options
-                .push(quote! { skip_serializing_if = "std::collections::HashMap::is_empty" });
+            if type_space.settings.skip_serializing_empty != Some(false) {
+                let is_empty = format!("{}::is_empty", type_space.map_type());
+                serde_options.push(quote! { skip_serializing_if = #is_empty });
+            }
         }
         (SerdeRules::Optional, _) => unreachable!(),
+        (SerdeRules::ExplicitNull, _) => {
+            serde_options.push(quote! { default });
+            serde_options.push(quote! { skip_serializing_if = "Option::is_none" });
+            serde_options.push(quote! { with = "double_option" });
+        }
+        (SerdeRules::Default, _) => {
+            match type_space.default_fn(owner, prop) {
+                Some(default_fn) => {
+                    let path = format!("defaults::{}", default_fn);
+                    serde_options.push(quote! { default = #path });
+                }
+                None => serde_options.push(quote! { default }),
+            }
+            if let Some(is_default_fn) = type_space.is_default_fn(owner, prop) {
+                let path = format!("defaults::{}", is_default_fn);
+                serde_options.push(quote! { skip_serializing_if = #path });
+            }
+        }
         (SerdeRules::None, _) => (),
     }
 
+    if let Some(module) = prop_type.serde_with(type_space) {
+        serde_options.push(quote! { with = #module });
+    }
+
     if serde_options.is_empty() {
         quote! {}
     } else {
@@ -384,6 +943,59 @@ fn generate_serde_attr(
     }
 }
 
+/// A `Debug` impl for a struct with redacted properties, which shows `***`
+/// for their values; `None` if there are no such properties.
+pub(crate) fn redacted_debug(
+    type_name: &Ident,
+    properties: &[StructProperty],
+) -> Option<TokenStream> {
+    if !properties.iter().any(|prop| prop.redacted) {
+        return None;
+    }
+    let fields = properties.iter().map(|prop| {
+        let name = format_ident!("{}", prop.name);
+        let label = unraw(&prop.name);
+        if prop.redacted {
+            quote! { .field(#label, &"***") }
+        } else {
+            quote! { .field(#label, &self.#name) }
+        }
+    });
+    let label = type_name.to_string();
+    Some(quote! {
+        impl std::fmt::Debug for #type_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(#label)
+                    #(#fields)*
+                    .finish()
+            }
+        }
+    })
+}
+
+/// The type of a property's field.
+fn property_type(prop: &StructProperty, type_space: &TypeSpace) -> TokenStream {
+    let type_ident = type_space
+        .id_to_entry
+        .get(&prop.type_id)
+        .unwrap()
+        .type_ident(type_space, false);
+    match prop.serde_rules {
+        SerdeRules::ExplicitNull => quote! { Option<#type_ident> },
+        _ => type_ident,
+    }
+}
+
+/// Whether a property's type is an `Option` because its schema permits null,
+/// which `double_option` can tell from absence; types (de)serialized with
+/// their own serde modules are left as they are.
+fn is_nullable(type_space: &TypeSpace, type_id: &TypeId) -> bool {
+    match type_space.id_to_entry.get(type_id) {
+        Some(ty @ TypeEntry::Option(_)) => ty.serde_with(type_space).is_none(),
+        _ => false,
+    }
+}
+
 /// See if this type is a type that we can omit with a serde directive; note
 /// that the type id lookup will fail only for references (and only during
 /// initial reference processing).
@@ -405,7 +1017,13 @@ mod tests {
     use schemars::JsonSchema;
     use serde::Serialize;
 
-    use crate::{test_util::validate_output, Name, TypeSpace};
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{
+        test_util::{assert_output, validate_output},
+        AllOfStrategy, Error, MapType, Name, TypeSpace, TypeSpaceSettings,
+    };
 
     #[allow(dead_code)]
     #[derive(Serialize, JsonSchema, Schema)]
@@ -447,18 +1065,298 @@ mod tests {
         validate_output::<SomeMaps>();
     }
 
-    #[allow(dead_code)]
-    #[derive(Serialize, JsonSchema, Schema)]
-    #[serde(deny_unknown_fields)]
-    struct FlattenStuff {
-        number: i32,
-        #[serde(flatten)]
-        extra: std::collections::HashMap<String, String>,
+    // Note that `#[derive(JsonSchema)]` only produces additionalProperties
+    // for flattened maps with `#[serde(deny_unknown_fields)]` which serde
+    // doesn't support in that combination.
+    #[test]
+    fn test_flatten_stuff() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "FlattenStuff": {
+                    "type": "object",
+                    "required": ["number"],
+                    "properties": {
+                        "number": { "type": "integer", "format": "int32" }
+                    },
+                    "additionalProperties": { "type": "string" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "structs/flatten_stuff");
     }
 
     #[test]
-    fn test_flatten_stuff() {
-        validate_output::<FlattenStuff>();
+    fn test_capture_extra_properties() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Open": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } },
+                    "additionalProperties": true
+                },
+                "Colliding": {
+                    "type": "object",
+                    "properties": {
+                        "extra": { "type": "string" },
+                        "extra_1": { "type": "string" }
+                    },
+                    "additionalProperties": { "type": "integer" }
+                },
+                "Implicit": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "structs/capture_extra_properties");
+
+        let mut settings = TypeSpaceSettings::default();
+        settings
+            .with_capture_extra_properties(true)
+            .with_extra_properties_name("unknown");
+        let mut type_space = TypeSpace::new(&settings);
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "structs/capture_extra_properties-unknown");
+    }
+
+    #[test]
+    fn test_map_type() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" }
+                },
+                "Service": {
+                    "type": "object",
+                    "properties": {
+                        "env": { "$ref": "#/definitions/Env" },
+                        "ports": {
+                            "type": "object",
+                            "additionalProperties": { "type": "integer" }
+                        }
+                    },
+                    "additionalProperties": { "type": "boolean" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_map_type(MapType::BTreeMap));
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert!(!type_space.uses_indexmap());
+        assert_output(&type_space, "structs/map_type-btreemap");
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_map_type(MapType::IndexMap));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_eq!(type_space.uses_indexmap(), cfg!(feature = "indexmap"));
+        if cfg!(feature = "indexmap") {
+            assert_output(&type_space, "structs/map_type-indexmap");
+        } else {
+            assert_output(&type_space, "structs/map_type-hashmap");
+        }
+    }
+
+    #[test]
+    fn test_pattern_properties() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Labels": {
+                    "type": "object",
+                    "patternProperties": {
+                        "^[a-z]+$": { "type": "string" }
+                    },
+                    "additionalProperties": false
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(type_space.uses_regress());
+        assert_output(&type_space, "structs/pattern_properties");
+    }
+
+    #[test]
+    fn test_pattern_properties_unsupported() {
+        for schema in [
+            json!({
+                "type": "object",
+                "patternProperties": {
+                    "^a": { "type": "string" },
+                    "^b": { "type": "integer" }
+                }
+            }),
+            json!({
+                "type": "object",
+                "patternProperties": {
+                    "^a": { "type": "string" }
+                },
+                "additionalProperties": { "type": "integer" }
+            }),
+        ] {
+            let schema = serde_json::from_value::<RootSchema>(json!({
+                "definitions": { "Things": schema }
+            }))
+            .unwrap();
+            let mut type_space = TypeSpace::default();
+            match type_space
+                .add_ref_types(schema.definitions)
+                .as_ref()
+                .map_err(Error::reason)
+            {
+                Err(Error::Unsupported(message)) => assert!(message.starts_with("Things ")),
+                _ => panic!("expected an error"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_names() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Tags": {
+                    "type": "object",
+                    "propertyNames": { "maxLength": 8 },
+                    "additionalProperties": { "type": "integer" }
+                },
+                "Colors": {
+                    "type": "object",
+                    "propertyNames": { "type": "string", "enum": ["red", "green"] },
+                    "additionalProperties": { "type": "boolean" }
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert!(type_space.warnings().is_empty());
+        assert_output(&type_space, "structs/property_names");
+    }
+
+    #[test]
+    fn test_deny_unknown_fields() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Closed": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } },
+                    "additionalProperties": false
+                },
+                "Patterned": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } },
+                    "patternProperties": { "^x-": { "type": "string" } },
+                    "additionalProperties": false
+                },
+                "Sub": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Closed" },
+                        {
+                            "type": "object",
+                            "properties": { "b": { "type": "string" } },
+                            "additionalProperties": false
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The subclass merges the closed objects into one closed struct.
+        assert_output(&type_space, "structs/deny_unknown_fields");
+        assert!(type_space.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_all_of_flatten() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Closed": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } },
+                    "additionalProperties": false
+                },
+                "Sub": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Closed" },
+                        {
+                            "type": "object",
+                            "properties": { "b": { "type": "string" } },
+                            "additionalProperties": false
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_all_of_strategy(AllOfStrategy::Flatten),
+        );
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "structs/all_of_flatten");
+        assert_eq!(type_space.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_all_of_flatten_collision() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Base": {
+                    "type": "object",
+                    "properties": { "a": { "type": "string" } }
+                },
+                "Sub": {
+                    "allOf": [
+                        { "$ref": "#/definitions/Base" },
+                        {
+                            "type": "object",
+                            "properties": { "a": { "type": "string", "maxLength": 3 } }
+                        }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        let mut type_space = TypeSpace::new(
+            TypeSpaceSettings::default().with_all_of_strategy(AllOfStrategy::Flatten),
+        );
+        match type_space
+            .add_ref_types(schema.definitions.clone())
+            .as_ref()
+            .map_err(Error::reason)
+        {
+            Err(Error::Unsupported(message)) => assert_eq!(
+                message,
+                "property \"a\" of Sub is also defined by Base, which is flattened into it"
+            ),
+            _ => panic!("expected an error"),
+        }
+
+        // Merging has no trouble with the same property.
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "structs/all_of_flatten_collision");
     }
 
     #[test]
@@ -476,4 +1374,51 @@ mod tests {
             "std::collections::HashMap<String,serde_json::Value>"
         );
     }
+
+    #[test]
+    fn test_dependent_required() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Payment": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "credit_card": { "type": "string" },
+                        "billing_address": { "type": "string" }
+                    },
+                    "dependentRequired": {
+                        "credit_card": ["billing_address", "name"],
+                        "cvv": ["credit_card"]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        // Without object validation the rules are ignored.
+        let mut type_space = TypeSpace::default();
+        type_space
+            .add_ref_types(schema.definitions.clone())
+            .unwrap();
+        assert_output(&type_space, "structs/dependent_required-unchecked");
+        assert_eq!(
+            type_space.warnings()[0].message,
+            "dependentRequired isn't checked without object validation"
+        );
+
+        let mut type_space =
+            TypeSpace::new(TypeSpaceSettings::default().with_object_validation(true));
+        type_space.add_ref_types(schema.definitions).unwrap();
+        // The required name needs no check.
+        assert_output(&type_space, "structs/dependent_required");
+        assert_eq!(
+            type_space
+                .warnings()
+                .iter()
+                .map(|warning| warning.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["the dependentRequired of cvv isn't checked as it isn't a property"]
+        );
+    }
 }
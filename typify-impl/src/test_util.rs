@@ -61,10 +61,26 @@ fn validate_output_impl<T: JsonSchema + Schema>(ignore_variant_names: bool) {
     }
 }
 
+/// Compare the code that the type space generates, formatted, with the
+/// contents of `tests/output/<name>.out`; run with `EXPECTORATE=overwrite` to
+/// update them.
+#[track_caller]
+pub(crate) fn assert_output(type_space: &TypeSpace, name: &str) {
+    assert_stream_output(type_space.to_stream(), name)
+}
+
+/// Compare generated code, formatted, with the contents of
+/// `tests/output/<name>.out`, as for `assert_output`.
+#[track_caller]
+pub(crate) fn assert_stream_output(stream: TokenStream, name: &str) {
+    let output = rustfmt(stream.to_string()).unwrap();
+    expectorate::assert_contents(format!("tests/output/{}.out", name), &output);
+}
+
 #[macro_export]
 macro_rules! validate_builtin {
     ($t:ty) => {
-        crate::test_util::validate_builtin_impl::<$t>(stringify!($t))
+        $crate::test_util::validate_builtin_impl::<$t>(stringify!($t))
     };
 }
 
@@ -88,7 +104,7 @@ pub(crate) fn validate_builtin_impl<T: JsonSchema>(name: &str) {
     // Make sure they match.
     if let Err(err) = expected.syn_cmp(&actual, false) {
         println!("{:#?}", schema);
-        println!("actual: {}", output.to_string());
+        println!("actual: {}", output);
         println!("expected: {}", name);
         panic!("{}", err);
     }
@@ -168,11 +184,7 @@ fn get_serde(attrs: &[Attribute]) -> HashSet<String> {
 impl SynCompare for syn::Ident {
     fn syn_cmp(&self, other: &Self, _: bool) -> Result<(), String> {
         if self != other {
-            Err(format!(
-                "idents differ: {} {}",
-                self.to_string(),
-                other.to_string()
-            ))
+            Err(format!("idents differ: {} {}", self, other))
         } else {
             Ok(())
         }
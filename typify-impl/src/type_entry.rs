@@ -1,15 +1,23 @@
 // Copyright 2021 Oxide Computer Company
 
 use convert_case::Case;
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, Literal, TokenStream};
 use quote::{format_ident, quote, ToTokens};
 use schemars::schema::Metadata;
 
 use crate::{
-    enums::{enum_impl, output_variant},
-    structs::output_struct_property,
-    util::{get_type_name, metadata_description},
-    Name, TypeId, TypeSpace,
+    case::{rename_all_properties, rename_all_variants},
+    dependent_required::output_struct_validation,
+    derives::minimal_traits,
+    enums::{enum_impl, output_variant, redacted_enum_debug},
+    structs::{
+        output_struct_accessors, output_struct_builder, output_struct_property, redacted_debug,
+    },
+    util::{
+        get_type_name, metadata_default, metadata_deprecated, metadata_type_description,
+        unique_names,
+    },
+    Name, NonExhaustive, PatternEngine, TypeId, TypeSpace,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -17,9 +25,12 @@ pub(crate) struct TypeEntryEnum {
     pub name: String,
     pub rename: Option<String>,
     pub description: Option<String>,
+    pub deprecated: bool,
     pub tag_type: EnumTagType,
     pub variants: Vec<Variant>,
     pub deny_unknown_fields: bool,
+    /// The default value from the schema, as JSON.
+    pub default: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -27,8 +38,14 @@ pub(crate) struct TypeEntryStruct {
     pub name: String,
     pub rename: Option<String>,
     pub description: Option<String>,
+    pub deprecated: bool,
     pub properties: Vec<StructProperty>,
     pub deny_unknown_fields: bool,
+    /// The default value from the schema, as JSON.
+    pub default: Option<String>,
+    /// The `dependentRequired` rules that the struct checks, by the JSON
+    /// names of its properties.
+    pub dependent_required: Vec<(String, Vec<String>)>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -36,7 +53,75 @@ pub(crate) struct TypeEntryNewtype {
     pub name: String,
     pub rename: Option<String>,
     pub description: Option<String>,
+    pub deprecated: bool,
     pub type_id: TypeId,
+    pub constraints: NewtypeConstraints,
+    /// The default value from the schema, as JSON.
+    pub default: Option<String>,
+}
+
+/// A `type` alias of another type, for a definition whose schema is an array,
+/// map, primitive, or the like as `NamedSimpleTypes::Alias` prescribes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TypeEntryAlias {
+    pub name: String,
+    pub rename: Option<String>,
+    pub description: Option<String>,
+    pub deprecated: bool,
+    pub type_id: TypeId,
+}
+
+/// Constraints on the values of a newtype that are checked when a value is
+/// constructed or deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum NewtypeConstraints {
+    None,
+    String {
+        max_length: Option<u32>,
+        min_length: Option<u32>,
+        pattern: Option<String>,
+    },
+    /// Inclusive bounds and a factor for an integer.
+    Integer {
+        minimum: Option<i128>,
+        maximum: Option<i128>,
+        multiple_of: Option<i128>,
+    },
+    /// Bounds on the number of items in an array.
+    Array {
+        max_items: Option<u32>,
+        min_items: Option<u32>,
+    },
+    Number {
+        minimum: Option<FloatBits>,
+        exclusive_minimum: Option<FloatBits>,
+        maximum: Option<FloatBits>,
+        exclusive_maximum: Option<FloatBits>,
+        multiple_of: Option<FloatBits>,
+    },
+    /// A single value, as JSON, of the type. The newtype is a unit struct that
+    /// (de)serializes only as the value.
+    Const(String),
+    /// A JSON document in a string. The newtype holds the document's type and
+    /// (de)serializes as a string of its JSON.
+    Json,
+}
+
+/// A floating-point value compared and ordered by its bits so that it may be
+/// part of a type's identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FloatBits(u64);
+
+impl From<f64> for FloatBits {
+    fn from(value: f64) -> Self {
+        Self(value.to_bits())
+    }
+}
+
+impl FloatBits {
+    pub fn value(self) -> f64 {
+        f64::from_bits(self.0)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -44,15 +129,27 @@ pub(crate) enum TypeEntry {
     Enum(TypeEntryEnum),
     Struct(TypeEntryStruct),
     Newtype(TypeEntryNewtype),
+    Alias(TypeEntryAlias),
 
     Option(TypeId),
     Array(TypeId),
+    /// Arrays with exactly the given number of items
+    FixedArray(TypeId, usize),
     Map(TypeId, TypeId),
     Set(TypeId),
+    /// A boxed type, which breaks a cycle of types that contain one another.
+    Box(TypeId),
     Tuple(Vec<TypeId>),
     Unit,
     /// Built-in complex types with no type generics such as Uuid
     BuiltIn(String),
+    /// Built-in types with the given type parameters, e.g. as named by an
+    /// `x-rust-type` extension
+    BuiltInGeneric(String, Vec<TypeId>),
+    /// Built-in types that must be (de)serialized with the given serde `with`
+    /// modules, for the type itself and for an `Option` of the type, rather
+    /// than their own Serialize and Deserialize impls.
+    BuiltInWith(String, String, String),
     /// Integers and booleans
     Integral(String),
     /// Floating point numbers; critical values that cannot be Eq
@@ -79,12 +176,21 @@ pub(crate) struct Variant {
     pub name: String,
     pub rename: Option<String>,
     pub description: Option<String>,
+    pub deprecated: bool,
     pub details: VariantDetails,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum VariantDetails {
     Simple,
+    /// A unit variant represented by the integer, its discriminant.
+    Integer(i64),
+    /// A unit variant for the values of a string enum that the schema
+    /// doesn't list, deserialized with `#[serde(other)]`.
+    Unknown,
+    /// A variant of the string type that holds the values of a string enum
+    /// that the schema doesn't list, (de)serialized as them.
+    Other(TypeId),
     Tuple(Vec<TypeId>),
     Struct(Vec<StructProperty>),
 }
@@ -95,6 +201,15 @@ pub(crate) struct StructProperty {
     pub serde_naming: SerdeNaming,
     pub serde_rules: SerdeRules,
     pub description: Option<String>,
+    pub deprecated: bool,
+    /// The schema's default value, as JSON.
+    pub default: Option<String>,
+    pub access: PropertyAccess,
+    /// Whether the schema permits null, i.e. the type was an `Option` even
+    /// before the property was made optional.
+    pub nullable: bool,
+    /// Whether `Debug` shows `***` rather than the value, as for passwords.
+    pub redacted: bool,
     pub type_id: TypeId,
 }
 
@@ -105,10 +220,24 @@ pub(crate) enum SerdeNaming {
     Flatten,
 }
 
+/// Whether a property is present in requests, responses, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PropertyAccess {
+    ReadWrite,
+    ReadOnly,
+    WriteOnly,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum SerdeRules {
     None,
     Optional,
+    /// The property takes its schema default when it's absent.
+    Default,
+    /// The property is optional and nullable, and its field an `Option` of
+    /// its `Option` type so that null (`Some(None)`) differs from absence
+    /// (`None`).
+    ExplicitNull,
 }
 
 impl TypeEntryEnum {
@@ -116,68 +245,188 @@ impl TypeEntryEnum {
         type_name: Name,
         metadata: &Option<Box<Metadata>>,
         tag_type: EnumTagType,
-        variants: Vec<Variant>,
+        mut variants: Vec<Variant>,
         deny_unknown_fields: bool,
     ) -> TypeEntry {
         let name = get_type_name(&type_name, metadata, Case::Pascal).unwrap();
+        unique_variant_names(&mut variants);
+        for variant in &mut variants {
+            if let VariantDetails::Struct(properties) = &mut variant.details {
+                unique_property_names(properties);
+            }
+        }
         let rename = None;
-        let description = metadata_description(metadata);
+        let description = metadata_type_description(metadata);
+        let deprecated = metadata_deprecated(metadata);
+        let default = metadata_default(metadata);
 
         TypeEntry::Enum(Self {
             name,
             rename,
             description,
+            deprecated,
             tag_type,
             variants,
             deny_unknown_fields,
+            default,
         })
     }
+
+    /// Whether the enum represents integers, i.e. its variants are those of
+    /// an integer `enum`.
+    pub(crate) fn is_integer(&self) -> bool {
+        !self.variants.is_empty()
+            && self
+                .variants
+                .iter()
+                .all(|variant| matches!(variant.details, VariantDetails::Integer(_)))
+    }
 }
 
 impl TypeEntryStruct {
     pub(crate) fn from_metadata(
         type_name: Name,
         metadata: &Option<Box<Metadata>>,
-        properties: Vec<StructProperty>,
+        mut properties: Vec<StructProperty>,
         deny_unknown_fields: bool,
     ) -> TypeEntry {
         let name = get_type_name(&type_name, metadata, Case::Pascal).unwrap();
+        unique_property_names(&mut properties);
         let rename = None;
-        let description = metadata_description(metadata);
+        let description = metadata_type_description(metadata);
+        let deprecated = metadata_deprecated(metadata);
+        let default = metadata_default(metadata);
 
         TypeEntry::Struct(Self {
             name,
             rename,
             description,
+            deprecated,
             properties,
             deny_unknown_fields,
+            default,
+            dependent_required: Vec::new(),
         })
     }
 }
 
+/// Number the properties whose names are the same, e.g. as they were
+/// sanitized from `foo-bar` and `foo_bar`, keeping their names in JSON.
+fn unique_property_names(properties: &mut [StructProperty]) {
+    let names = properties
+        .iter()
+        .map(|prop| prop.name.clone())
+        .collect::<Vec<_>>();
+    let unique = unique_names(&names, Case::Snake);
+    for (prop, name) in properties.iter_mut().zip(unique) {
+        if name != prop.name {
+            if prop.serde_naming == SerdeNaming::None {
+                prop.serde_naming = SerdeNaming::Rename(prop.name.clone());
+            }
+            prop.name = name;
+        }
+    }
+}
+
+/// Number the variants whose names are the same, e.g. as they were
+/// sanitized from `utf-8` and `utf_8`, keeping their names in JSON.
+fn unique_variant_names(variants: &mut [Variant]) {
+    let names = variants
+        .iter()
+        .map(|variant| variant.name.clone())
+        .collect::<Vec<_>>();
+    let unique = unique_names(&names, Case::Pascal);
+    for (variant, name) in variants.iter_mut().zip(unique) {
+        if name != variant.name {
+            if variant.rename.is_none() {
+                variant.rename = Some(variant.name.clone());
+            }
+            variant.name = name;
+        }
+    }
+}
+
 impl TypeEntryNewtype {
     pub(crate) fn from_metadata(
         type_name: Name,
         metadata: &Option<Box<Metadata>>,
         type_id: TypeId,
+    ) -> TypeEntry {
+        Self::from_metadata_with_constraints(type_name, metadata, type_id, NewtypeConstraints::None)
+    }
+
+    pub(crate) fn from_metadata_with_constraints(
+        type_name: Name,
+        metadata: &Option<Box<Metadata>>,
+        type_id: TypeId,
+        constraints: NewtypeConstraints,
     ) -> TypeEntry {
         let name = get_type_name(&type_name, metadata, Case::Pascal).unwrap();
         let rename = None;
-        let description = metadata_description(metadata);
+        let description = metadata_type_description(metadata);
+        let deprecated = metadata_deprecated(metadata);
+        let default = metadata_default(metadata);
 
         TypeEntry::Newtype(Self {
             name,
             rename,
             description,
+            deprecated,
             type_id,
+            constraints,
+            default,
         })
     }
 }
 
+/// The shapes of the collections of values of built-in types with serde
+/// modules that `nested_serde` (de)serializes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NestedShape {
+    Seq,
+    Map,
+    OptionSeq,
+    OptionMap,
+}
+
+impl NestedShape {
+    pub(crate) fn module_name(self) -> &'static str {
+        match self {
+            NestedShape::Seq => "seq",
+            NestedShape::Map => "map",
+            NestedShape::OptionSeq => "option_seq",
+            NestedShape::OptionMap => "option_map",
+        }
+    }
+}
+
+/// The name of the module of `nested_serde` for the values that a serde
+/// module (de)serializes, e.g. `time_serde_rfc3339` for
+/// `time::serde::rfc3339`.
+pub(crate) fn nested_module_name(module: &str) -> String {
+    module.replace("::", "_")
+}
+
 impl TypeEntry {
     pub(crate) fn new_builtin<S: ToString>(type_name: S) -> Self {
         TypeEntry::BuiltIn(type_name.to_string())
     }
+    pub(crate) fn new_builtin_with<S: ToString, M: ToString>(type_name: S, module: M) -> Self {
+        let module = module.to_string();
+        let option_module = format!("{}::option", module);
+        TypeEntry::BuiltInWith(type_name.to_string(), module, option_module)
+    }
+    pub(crate) fn new_builtin_with_option<S: ToString, M: ToString, O: ToString>(
+        type_name: S,
+        module: M,
+        option_module: O,
+    ) -> Self {
+        TypeEntry::BuiltInWith(
+            type_name.to_string(),
+            module.to_string(),
+            option_module.to_string(),
+        )
+    }
     pub(crate) fn new_integer<S: ToString>(type_name: S) -> Self {
         TypeEntry::Integral(type_name.to_string())
     }
@@ -189,21 +438,295 @@ impl TypeEntry {
         match self {
             TypeEntry::Enum(TypeEntryEnum { name, .. })
             | TypeEntry::Struct(TypeEntryStruct { name, .. })
-            | TypeEntry::Newtype(TypeEntryNewtype { name, .. }) => Some(name),
+            | TypeEntry::Newtype(TypeEntryNewtype { name, .. })
+            | TypeEntry::Alias(TypeEntryAlias { name, .. }) => Some(name),
+
+            _ => None,
+        }
+    }
+
+    /// The types of the members of a named type, in order.
+    pub(crate) fn members(&self) -> Vec<TypeId> {
+        match self {
+            TypeEntry::Struct(TypeEntryStruct { properties, .. }) => {
+                properties.iter().map(|prop| prop.type_id.clone()).collect()
+            }
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => variants
+                .iter()
+                .flat_map(|variant| match &variant.details {
+                    VariantDetails::Simple
+                    | VariantDetails::Integer(_)
+                    | VariantDetails::Unknown => Vec::new(),
+                    VariantDetails::Other(type_id) => vec![type_id.clone()],
+                    VariantDetails::Tuple(items) => items.clone(),
+                    VariantDetails::Struct(properties) => {
+                        properties.iter().map(|prop| prop.type_id.clone()).collect()
+                    }
+                })
+                .collect(),
+            // A constant holds no value.
+            TypeEntry::Newtype(TypeEntryNewtype {
+                constraints: NewtypeConstraints::Const(_),
+                ..
+            }) => Vec::new(),
+            TypeEntry::Newtype(TypeEntryNewtype { type_id, .. })
+            | TypeEntry::Alias(TypeEntryAlias { type_id, .. }) => vec![type_id.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Every type id within the type, for replacing them.
+    pub(crate) fn type_ids_mut(&mut self) -> Vec<&mut TypeId> {
+        match self {
+            TypeEntry::Struct(TypeEntryStruct { properties, .. }) => properties
+                .iter_mut()
+                .map(|prop| &mut prop.type_id)
+                .collect(),
+            TypeEntry::Enum(TypeEntryEnum { variants, .. }) => variants
+                .iter_mut()
+                .flat_map(|variant| match &mut variant.details {
+                    VariantDetails::Simple
+                    | VariantDetails::Integer(_)
+                    | VariantDetails::Unknown => Vec::new(),
+                    VariantDetails::Other(type_id) => vec![type_id],
+                    VariantDetails::Tuple(items) => items.iter_mut().collect(),
+                    VariantDetails::Struct(properties) => properties
+                        .iter_mut()
+                        .map(|prop| &mut prop.type_id)
+                        .collect(),
+                })
+                .collect(),
+            TypeEntry::Newtype(TypeEntryNewtype { type_id, .. })
+            | TypeEntry::Alias(TypeEntryAlias { type_id, .. })
+            | TypeEntry::Option(type_id)
+            | TypeEntry::Array(type_id)
+            | TypeEntry::FixedArray(type_id, _)
+            | TypeEntry::Set(type_id)
+            | TypeEntry::Box(type_id)
+            | TypeEntry::Reference(type_id) => vec![type_id],
+            TypeEntry::Map(key_id, value_id) => vec![key_id, value_id],
+            TypeEntry::Tuple(type_ids) | TypeEntry::BuiltInGeneric(_, type_ids) => {
+                type_ids.iter_mut().collect()
+            }
+            TypeEntry::Unit
+            | TypeEntry::BuiltIn(_)
+            | TypeEntry::BuiltInWith(_, _, _)
+            | TypeEntry::Integral(_)
+            | TypeEntry::Float(_)
+            | TypeEntry::String => Vec::new(),
+        }
+    }
+
+    /// The name of a named type and the name from which it was renamed, for
+    /// patching.
+    pub(crate) fn name_mut(&mut self) -> Option<(&mut String, &mut Option<String>)> {
+        match self {
+            TypeEntry::Enum(TypeEntryEnum { name, rename, .. })
+            | TypeEntry::Struct(TypeEntryStruct { name, rename, .. })
+            | TypeEntry::Newtype(TypeEntryNewtype { name, rename, .. })
+            | TypeEntry::Alias(TypeEntryAlias { name, rename, .. }) => Some((name, rename)),
+
+            _ => None,
+        }
+    }
+
+    /// The description of a named type, for patching.
+    pub(crate) fn description_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            TypeEntry::Enum(TypeEntryEnum { description, .. })
+            | TypeEntry::Struct(TypeEntryStruct { description, .. })
+            | TypeEntry::Newtype(TypeEntryNewtype { description, .. })
+            | TypeEntry::Alias(TypeEntryAlias { description, .. }) => Some(description),
 
             _ => None,
         }
     }
 
+    /// Whether the type is an enum or struct to mark `#[non_exhaustive]`, as
+    /// its patch or else the settings prescribe.
+    fn is_non_exhaustive(&self, type_space: &TypeSpace) -> bool {
+        let (name, is_enum) = match self {
+            TypeEntry::Enum(TypeEntryEnum { name, .. }) => (name, true),
+            TypeEntry::Struct(TypeEntryStruct { name, .. }) => (name, false),
+            _ => return false,
+        };
+        let patch = type_space
+            .settings
+            .patch_for(name)
+            .and_then(|patch| patch.non_exhaustive);
+        patch.unwrap_or(match type_space.settings.non_exhaustive {
+            Some(NonExhaustive::Enums) => is_enum,
+            Some(NonExhaustive::EnumsAndStructs) => true,
+            None => false,
+        })
+    }
+
+    /// Whether the type's definition has a conversion that can fail, i.e.
+    /// a `TryFrom` impl for an integer enum or a newtype with constraints, or
+    /// a builder.
+    pub(crate) fn uses_conversion_error(&self, type_space: &TypeSpace) -> bool {
+        match self {
+            TypeEntry::Enum(type_enum) => type_enum.is_integer() && !cfg!(feature = "serde_repr"),
+            TypeEntry::Struct(TypeEntryStruct {
+                dependent_required, ..
+            }) => {
+                type_space.settings.struct_builder
+                    || self.is_non_exhaustive(type_space)
+                    || !dependent_required.is_empty()
+            }
+            TypeEntry::Newtype(TypeEntryNewtype { constraints, .. }) => !matches!(
+                constraints,
+                NewtypeConstraints::None | NewtypeConstraints::Const(_)
+            ),
+            _ => false,
+        }
+    }
+
     pub(crate) fn output(&self, type_space: &TypeSpace) -> TokenStream {
+        // Traits for types whose members all implement them.
+        let traits = match self {
+            TypeEntry::Enum(_) | TypeEntry::Struct(_) | TypeEntry::Newtype(_)
+                if !type_space.settings.minimal_derives =>
+            {
+                type_space.derivable_traits(self)
+            }
+            _ => minimal_traits(self),
+        };
+
         let mut derives = vec![
             quote! {Serialize},
             quote! {Deserialize},
             quote! {Debug},
             quote! {Clone},
         ];
+        if traits.is_copy() {
+            derives.push(quote! {Copy});
+        }
+
+        // Derives for all types and for this type in particular.
+        if let Some(name) = self.name() {
+            let patch_derives = type_space
+                .settings
+                .patch_for(name)
+                .into_iter()
+                .flat_map(|patch| &patch.derives);
+            // `TypeSpace::check_settings` has made sure that they parse.
+            for derive in type_space.settings.derives.iter().chain(patch_derives) {
+                let derive = syn::parse_str::<syn::Path>(derive)
+                    .unwrap_or_else(|e| panic!("invalid derive {:?}: {}", derive, e))
+                    .to_token_stream();
+                add_derives(&mut derives, vec![derive]);
+            }
+        }
+
+        add_derives(&mut derives, traits.derives());
+
+        let deprecated = match self {
+            TypeEntry::Enum(TypeEntryEnum {
+                deprecated,
+                description,
+                ..
+            })
+            | TypeEntry::Struct(TypeEntryStruct {
+                deprecated,
+                description,
+                ..
+            })
+            | TypeEntry::Newtype(TypeEntryNewtype {
+                deprecated,
+                description,
+                ..
+            })
+            | TypeEntry::Alias(TypeEntryAlias {
+                deprecated,
+                description,
+                ..
+            }) => deprecated_attr(*deprecated, description),
+            _ => None,
+        };
+
+        let non_exhaustive = self
+            .is_non_exhaustive(type_space)
+            .then(|| quote! { #[non_exhaustive] });
+        let vis = type_space.type_visibility();
+        let field_vis = type_space.field_visibility();
+        let conversion_error = type_space.conversion_error();
+
+        let output = match self {
+            // An enum of integers is (de)serialized as its discriminants,
+            // with serde_repr if it's available.
+            TypeEntry::Enum(type_enum @ TypeEntryEnum { name, variants, .. })
+                if type_enum.is_integer() =>
+            {
+                let doc = type_enum
+                    .description
+                    .as_ref()
+                    .map(|desc| quote! { #[doc = #desc] });
+                let type_name = format_ident!("{}", name);
+                let variants_decl = variants
+                    .iter()
+                    .map(|variant| output_variant(name, variant, type_space))
+                    .collect::<Vec<_>>();
+                let enum_impl = enum_impl(&type_name, variants);
+
+                let (serde, conversions) = if cfg!(feature = "serde_repr") {
+                    derives.splice(
+                        0..2,
+                        vec![
+                            quote! { serde_repr::Serialize_repr },
+                            quote! { serde_repr::Deserialize_repr },
+                        ],
+                    );
+                    (quote! {}, quote! {})
+                } else {
+                    let match_values = variants.iter().map(|variant| {
+                        let variant_name = format_ident!("{}", variant.name);
+                        let value = match variant.details {
+                            VariantDetails::Integer(value) => Literal::i64_unsuffixed(value),
+                            _ => unreachable!(),
+                        };
+                        quote! { #value => Ok(#type_name::#variant_name) }
+                    });
+                    let conversions = quote! {
+                        impl From<#type_name> for i64 {
+                            fn from(value: #type_name) -> Self {
+                                value as i64
+                            }
+                        }
+
+                        impl std::convert::TryFrom<i64> for #type_name {
+                            type Error = #conversion_error;
+
+                            fn try_from(value: i64) -> Result<Self, Self::Error> {
+                                match value {
+                                    #(#match_values,)*
+                                    _ => Err(format!("invalid value {}", value).into()),
+                                }
+                            }
+                        }
+                    };
+                    (
+                        quote! { #[serde(try_from = "i64", into = "i64")] },
+                        conversions,
+                    )
+                };
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #serde
+                    #non_exhaustive
+                    #[repr(i64)]
+                    #vis enum #type_name {
+                        #(#variants_decl)*
+                    }
+
+                    #enum_impl
+                    #conversions
+                }
+            }
 
-        match self {
             TypeEntry::Enum(TypeEntryEnum {
                 name,
                 rename,
@@ -211,26 +734,10 @@ impl TypeEntry {
                 tag_type,
                 variants,
                 deny_unknown_fields,
+                ..
             }) => {
                 let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
 
-                // TODO this is a one-off for Eq
-                if variants
-                    .iter()
-                    .all(|variant| matches!(variant.details, VariantDetails::Simple))
-                {
-                    derives.extend(
-                        vec![
-                            quote! { PartialOrd },
-                            quote! { Ord },
-                            quote! { PartialEq },
-                            quote! { Eq },
-                            quote! { Hash },
-                        ]
-                        .into_iter(),
-                    );
-                }
-
                 let mut serde_options = Vec::new();
                 if let Some(old_name) = rename {
                     serde_options.push(quote! { rename = #old_name });
@@ -248,6 +755,15 @@ impl TypeEntry {
                         serde_options.push(quote! { untagged });
                     }
                 }
+                // The variants' names in JSON may mostly follow a rule.
+                let (rename_all, output_variants) = match tag_type {
+                    EnumTagType::Untagged => (None, variants.clone()),
+                    _ => rename_all_variants(variants),
+                };
+                if let Some(rule) = rename_all {
+                    let rule = rule.as_str();
+                    serde_options.push(quote! { rename_all = #rule });
+                }
                 if *deny_unknown_fields {
                     serde_options.push(quote! { deny_unknown_fields });
                 }
@@ -259,21 +775,28 @@ impl TypeEntry {
 
                 let type_name = format_ident!("{}", name);
 
-                let variants_decl = variants
+                let variants_decl = output_variants
                     .iter()
-                    .map(|variant| output_variant(variant, type_space))
+                    .map(|variant| output_variant(name, variant, type_space))
                     .collect::<Vec<_>>();
 
                 let enum_impl = enum_impl(&type_name, variants);
+                let debug = redacted_enum_debug(&type_name, variants);
+                if debug.is_some() {
+                    derives.retain(|derive| derive.to_string() != "Debug");
+                }
 
                 quote! {
                     #doc
+                    #deprecated
                     #[derive(#(#derives),*)]
                     #serde
-                    pub enum #type_name {
+                    #non_exhaustive
+                    #vis enum #type_name {
                         #(#variants_decl)*
                     }
 
+                    #debug
                     #enum_impl
                 }
             }
@@ -284,6 +807,8 @@ impl TypeEntry {
                 description,
                 properties,
                 deny_unknown_fields,
+                dependent_required,
+                ..
             }) => {
                 let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
 
@@ -291,9 +816,29 @@ impl TypeEntry {
                 if let Some(old_name) = rename {
                     serde_options.push(quote! { rename = #old_name });
                 }
+                // The properties' names in JSON may mostly follow a rule.
+                let (rename_all, output_properties) = rename_all_properties(properties);
+                if let Some(rule) = rename_all {
+                    let rule = rule.as_str();
+                    serde_options.push(quote! { rename_all = #rule });
+                }
                 if *deny_unknown_fields {
                     serde_options.push(quote! { deny_unknown_fields });
                 }
+                // Deserialization checks the dependentRequired rules through
+                // a mirror of the struct.
+                let validation = output_struct_validation(
+                    name,
+                    properties,
+                    &output_properties,
+                    dependent_required,
+                    &serde_options,
+                    type_space,
+                );
+                let validation = validation.map(|(try_from, code)| {
+                    serde_options.push(try_from);
+                    code
+                });
                 let serde = if serde_options.is_empty() {
                     quote! {}
                 } else {
@@ -301,18 +846,257 @@ impl TypeEntry {
                 };
 
                 let type_name = format_ident!("{}", name);
-                let properties = properties
+                let props = properties;
+                let debug = redacted_debug(&type_name, props);
+                if debug.is_some() {
+                    derives.retain(|derive| derive.to_string() != "Debug");
+                }
+                let properties = output_properties
                     .iter()
-                    .map(|prop| output_struct_property(prop, type_space, true))
+                    .map(|prop| output_struct_property(prop, name, type_space, true))
                     .collect::<Vec<_>>();
 
+                // Other crates can only construct non-exhaustive structs with
+                // builders.
+                let builder = (type_space.settings.struct_builder || non_exhaustive.is_some())
+                    .then(|| output_struct_builder(name, props, validation.is_some(), type_space));
+                let accessors = type_space
+                    .settings
+                    .field_accessors
+                    .then(|| output_struct_accessors(name, props, type_space));
+                let conversions = type_space.role_conversions(name);
+
                 quote! {
                     #doc
+                    #deprecated
                     #[derive(#(#derives),*)]
                     #serde
-                    pub struct #type_name {
+                    #non_exhaustive
+                    #vis struct #type_name {
                         #(#properties)*
                     }
+
+                    #debug
+                    #validation
+                    #builder
+                    #accessors
+                    #conversions
+                }
+            }
+
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                description,
+                constraints: NewtypeConstraints::Const(json),
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+                let type_name = format_ident!("{}", name);
+
+                // We implement serde's traits rather than deriving them.
+                derives.drain(0..2);
+                add_derives(&mut derives, vec![quote! { Default }]);
+
+                let value = serde_json::from_str::<serde_json::Value>(json).unwrap();
+                let (serialize, deserialize_type, matches) = match &value {
+                    serde_json::Value::String(s) => (
+                        quote! { serializer.serialize_str(#s) },
+                        quote! { String },
+                        quote! { value == #s },
+                    ),
+                    serde_json::Value::Bool(b) => (
+                        quote! { serializer.serialize_bool(#b) },
+                        quote! { bool },
+                        if *b {
+                            quote! { value }
+                        } else {
+                            quote! { !value }
+                        },
+                    ),
+                    serde_json::Value::Number(n) if n.is_i64() => {
+                        let n = Literal::i64_unsuffixed(n.as_i64().unwrap());
+                        (
+                            quote! { serializer.serialize_i64(#n) },
+                            quote! { i64 },
+                            quote! { value == #n },
+                        )
+                    }
+                    _ => (
+                        quote! {
+                            serde::Serialize::serialize(
+                                &serde_json::from_str::<serde_json::Value>(#json).unwrap(),
+                                serializer,
+                            )
+                        },
+                        quote! { serde_json::Value },
+                        quote! {
+                            value == serde_json::from_str::<serde_json::Value>(#json).unwrap()
+                        },
+                    ),
+                };
+                let message = format!("expected {}", json);
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #vis struct #type_name;
+
+                    impl serde::Serialize for #type_name {
+                        fn serialize<S: serde::Serializer>(
+                            &self,
+                            serializer: S,
+                        ) -> Result<S::Ok, S::Error> {
+                            #serialize
+                        }
+                    }
+
+                    impl<'de> serde::Deserialize<'de> for #type_name {
+                        fn deserialize<D: serde::Deserializer<'de>>(
+                            deserializer: D,
+                        ) -> Result<Self, D::Error> {
+                            let value =
+                                <#deserialize_type as serde::Deserialize>::deserialize(deserializer)?;
+                            if #matches {
+                                Ok(Self)
+                            } else {
+                                Err(serde::de::Error::custom(#message))
+                            }
+                        }
+                    }
+                }
+            }
+
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                description,
+                type_id,
+                constraints: NewtypeConstraints::Json,
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+                let type_name = format_ident!("{}", name);
+                let sub_type = type_space.id_to_entry.get(type_id).unwrap();
+                let sub_type_name = sub_type.type_ident(type_space, false);
+
+                // We implement serde's traits rather than deriving them.
+                derives.drain(0..2);
+
+                // A document that's a string would convert from a string
+                // both ways.
+                let from = (!matches!(sub_type, TypeEntry::String)).then(|| {
+                    quote! {
+                        impl From<#sub_type_name> for #type_name {
+                            fn from(value: #sub_type_name) -> Self {
+                                Self(value)
+                            }
+                        }
+                    }
+                });
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #vis struct #type_name(#field_vis #sub_type_name);
+
+                    impl std::ops::Deref for #type_name {
+                        type Target = #sub_type_name;
+                        fn deref(&self) -> &Self::Target {
+                            &self.0
+                        }
+                    }
+
+                    #from
+
+                    impl std::convert::TryFrom<&str> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: &str) -> Result<Self, Self::Error> {
+                            serde_json::from_str(value)
+                                .map(Self)
+                                .map_err(|e| e.to_string().into())
+                        }
+                    }
+
+                    impl std::convert::TryFrom<String> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: String) -> Result<Self, Self::Error> {
+                            Self::try_from(value.as_str())
+                        }
+                    }
+
+                    impl serde::Serialize for #type_name {
+                        fn serialize<S: serde::Serializer>(
+                            &self,
+                            serializer: S,
+                        ) -> Result<S::Ok, S::Error> {
+                            let json =
+                                serde_json::to_string(&self.0).map_err(serde::ser::Error::custom)?;
+                            serializer.serialize_str(&json)
+                        }
+                    }
+
+                    impl<'de> serde::Deserialize<'de> for #type_name {
+                        fn deserialize<D: serde::Deserializer<'de>>(
+                            deserializer: D,
+                        ) -> Result<Self, D::Error> {
+                            let json = <String as serde::Deserialize>::deserialize(deserializer)?;
+                            serde_json::from_str(&json)
+                                .map(Self)
+                                .map_err(serde::de::Error::custom)
+                        }
+                    }
+                }
+            }
+
+            // A named tuple becomes a tuple struct, which serde also
+            // represents as an array. A tuple struct with one field would be
+            // represented as that field so this excludes 1-tuples.
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                rename,
+                description,
+                type_id,
+                constraints: NewtypeConstraints::None,
+                ..
+            }) if matches!(
+                type_space.id_to_entry.get(type_id),
+                Some(TypeEntry::Tuple(items)) if items.len() > 1
+            ) =>
+            {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+
+                let serde = rename.as_ref().map(|old_name| {
+                    quote! {
+                        #[serde(rename = #old_name)]
+                    }
+                });
+
+                let type_name = format_ident!("{}", name);
+                let items = match type_space.id_to_entry.get(type_id) {
+                    Some(TypeEntry::Tuple(items)) => items,
+                    _ => unreachable!(),
+                };
+                let fields = items.iter().map(|item| {
+                    let item_type = type_space
+                        .id_to_entry
+                        .get(item)
+                        .expect("unresolved type id for tuple");
+                    let item_type_name = item_type.type_ident(type_space, false);
+                    let item_serde = item_type
+                        .serde_with(type_space)
+                        .map(|module| quote! { #[serde(with = #module)] });
+                    quote! { #item_serde #field_vis #item_type_name }
+                });
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #serde
+                    #vis struct #type_name(#(#fields),*);
                 }
             }
 
@@ -321,6 +1105,8 @@ impl TypeEntry {
                 rename,
                 description,
                 type_id,
+                constraints: NewtypeConstraints::None,
+                ..
             }) => {
                 let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
 
@@ -333,12 +1119,16 @@ impl TypeEntry {
                 let type_name = format_ident!("{}", name);
                 let sub_type = type_space.id_to_entry.get(type_id).unwrap();
                 let sub_type_name = sub_type.type_ident(type_space, false);
+                let sub_type_serde = sub_type
+                    .serde_with(type_space)
+                    .map(|module| quote! { #[serde(with = #module)] });
 
                 quote! {
                     #doc
+                    #deprecated
                     #[derive(#(#derives),*)]
                     #serde
-                    pub struct #type_name(pub #sub_type_name);
+                    #vis struct #type_name(#sub_type_serde #field_vis #sub_type_name);
 
                     impl std::ops::Deref for #type_name {
                         type Target = #sub_type_name;
@@ -349,22 +1139,407 @@ impl TypeEntry {
                 }
             }
 
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                rename,
+                description,
+                type_id: _,
+                constraints:
+                    NewtypeConstraints::String {
+                        max_length,
+                        min_length,
+                        pattern,
+                    },
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+
+                let mut serde_options = vec![quote! { try_from = "String" }];
+                if let Some(old_name) = rename {
+                    serde_options.push(quote! { rename = #old_name });
+                }
+
+                let type_name = format_ident!("{}", name);
+
+                // Lengths are measured in characters as JSON Schema specifies.
+                let max_check = max_length.map(|max| {
+                    let message = format!("longer than maxLength {}", max);
+                    let max = max as usize;
+                    quote! {
+                        if value.chars().count() > #max {
+                            return Err(#message.into());
+                        }
+                    }
+                });
+                let min_check = min_length.map(|min| {
+                    let message = format!("shorter than minLength {}", min);
+                    let min = min as usize;
+                    quote! {
+                        if value.chars().count() < #min {
+                            return Err(#message.into());
+                        }
+                    }
+                });
+
+                let inner_fns = newtype_inner_fns(&type_name, quote! { String });
+
+                // The regex is compiled once, the first time it's needed.
+                let pattern_check = pattern.as_ref().map(|pattern| {
+                    let message = format!("doesn't match pattern {:?}", pattern);
+                    let (regex, mismatch) = match type_space.settings.pattern_engine {
+                        None | Some(PatternEngine::Regress) => (
+                            quote! { regress::Regex },
+                            quote! { PATTERN.find(value).is_none() },
+                        ),
+                        Some(PatternEngine::Regex) => {
+                            (quote! { regex::Regex }, quote! { !PATTERN.is_match(value) })
+                        }
+                    };
+                    quote! {
+                        static PATTERN: once_cell::sync::Lazy<#regex> =
+                            once_cell::sync::Lazy::new(|| #regex::new(#pattern).unwrap());
+                        if #mismatch {
+                            return Err(#message.into());
+                        }
+                    }
+                });
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #[serde( #( #serde_options ),* )]
+                    #vis struct #type_name(String);
+
+                    impl std::ops::Deref for #type_name {
+                        type Target = str;
+                        fn deref(&self) -> &Self::Target {
+                            &self.0
+                        }
+                    }
+
+                    impl AsRef<str> for #type_name {
+                        fn as_ref(&self) -> &str {
+                            &self.0
+                        }
+                    }
+
+                    #inner_fns
+
+                    impl std::fmt::Display for #type_name {
+                        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                            self.0.fmt(f)
+                        }
+                    }
+
+                    impl std::convert::TryFrom<&str> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: &str) -> Result<Self, Self::Error> {
+                            #max_check
+                            #min_check
+                            #pattern_check
+                            Ok(Self(value.to_string()))
+                        }
+                    }
+
+                    impl std::convert::TryFrom<String> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: String) -> Result<Self, Self::Error> {
+                            Self::try_from(value.as_str())
+                        }
+                    }
+                }
+            }
+
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                rename,
+                description,
+                type_id,
+                constraints:
+                    NewtypeConstraints::Array {
+                        max_items,
+                        min_items,
+                    },
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+
+                let type_name = format_ident!("{}", name);
+                let sub_type = type_space.id_to_entry.get(type_id).unwrap();
+                let sub_type_name = sub_type.type_ident(type_space, false);
+                let item_type_name = match sub_type {
+                    TypeEntry::Array(item_id) | TypeEntry::Set(item_id) => type_space
+                        .id_to_entry
+                        .get(item_id)
+                        .expect("unresolved type id for array")
+                        .type_ident(type_space, false),
+                    _ => unreachable!(),
+                };
+
+                let inner = sub_type.type_name(type_space);
+                let mut serde_options = vec![quote! { try_from = #inner }];
+                if let Some(old_name) = rename {
+                    serde_options.push(quote! { rename = #old_name });
+                }
+
+                let max_check = max_items.map(|max| {
+                    let message = format!("more than maxItems {}", max);
+                    let max = max as usize;
+                    quote! {
+                        if value.len() > #max {
+                            return Err(#message.into());
+                        }
+                    }
+                });
+                let min_check = min_items.map(|min| {
+                    let message = format!("fewer than minItems {}", min);
+                    let too_few = match min {
+                        1 => quote! { value.is_empty() },
+                        min => {
+                            let min = min as usize;
+                            quote! { value.len() < #min }
+                        }
+                    };
+                    quote! {
+                        if #too_few {
+                            return Err(#message.into());
+                        }
+                    }
+                });
+
+                let inner_fns = newtype_inner_fns(&type_name, sub_type_name.clone());
+
+                // There's no DerefMut so that the length can't change once
+                // it's been checked.
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #[serde( #( #serde_options ),* )]
+                    #vis struct #type_name(#sub_type_name);
+
+                    impl std::ops::Deref for #type_name {
+                        type Target = #sub_type_name;
+                        fn deref(&self) -> &Self::Target {
+                            &self.0
+                        }
+                    }
+
+                    impl AsRef<#sub_type_name> for #type_name {
+                        fn as_ref(&self) -> &#sub_type_name {
+                            &self.0
+                        }
+                    }
+
+                    #inner_fns
+
+                    impl AsRef<[#item_type_name]> for #type_name {
+                        fn as_ref(&self) -> &[#item_type_name] {
+                            &self.0
+                        }
+                    }
+
+                    impl IntoIterator for #type_name {
+                        type Item = #item_type_name;
+                        type IntoIter = std::vec::IntoIter<#item_type_name>;
+                        fn into_iter(self) -> Self::IntoIter {
+                            self.0.into_iter()
+                        }
+                    }
+
+                    impl<'a> IntoIterator for &'a #type_name {
+                        type Item = &'a #item_type_name;
+                        type IntoIter = std::slice::Iter<'a, #item_type_name>;
+                        fn into_iter(self) -> Self::IntoIter {
+                            self.0.iter()
+                        }
+                    }
+
+                    impl std::convert::TryFrom<#sub_type_name> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: #sub_type_name) -> Result<Self, Self::Error> {
+                            #max_check
+                            #min_check
+                            Ok(Self(value))
+                        }
+                    }
+                }
+            }
+
+            TypeEntry::Newtype(TypeEntryNewtype {
+                name,
+                rename,
+                description,
+                type_id,
+                constraints,
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+
+                let type_name = format_ident!("{}", name);
+                let sub_type = type_space.id_to_entry.get(type_id).unwrap();
+                let sub_type_name = sub_type.type_ident(type_space, false);
+
+                let inner = sub_type.type_name(type_space);
+                let mut serde_options = vec![quote! { try_from = #inner }];
+                if let Some(old_name) = rename {
+                    serde_options.push(quote! { rename = #old_name });
+                }
+
+                let checks = number_checks(constraints, sub_type);
+                let inner_fns = newtype_inner_fns(&type_name, sub_type_name.clone());
+
+                quote! {
+                    #doc
+                    #deprecated
+                    #[derive(#(#derives),*)]
+                    #[serde( #( #serde_options ),* )]
+                    #vis struct #type_name(#sub_type_name);
+
+                    impl std::ops::Deref for #type_name {
+                        type Target = #sub_type_name;
+                        fn deref(&self) -> &Self::Target {
+                            &self.0
+                        }
+                    }
+
+                    impl AsRef<#sub_type_name> for #type_name {
+                        fn as_ref(&self) -> &#sub_type_name {
+                            &self.0
+                        }
+                    }
+
+                    #inner_fns
+
+                    impl std::fmt::Display for #type_name {
+                        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                            self.0.fmt(f)
+                        }
+                    }
+
+                    impl std::convert::TryFrom<#sub_type_name> for #type_name {
+                        type Error = #conversion_error;
+
+                        fn try_from(value: #sub_type_name) -> Result<Self, Self::Error> {
+                            #(#checks)*
+                            Ok(Self(value))
+                        }
+                    }
+                }
+            }
+
+            TypeEntry::Alias(TypeEntryAlias {
+                name,
+                description,
+                type_id,
+                ..
+            }) => {
+                let doc = description.as_ref().map(|desc| quote! { #[doc = #desc] });
+                let type_name = format_ident!("{}", name);
+                let sub_type_name = type_space.id_to_entry[type_id].type_ident(type_space, false);
+                quote! {
+                    #doc
+                    #deprecated
+                    #vis type #type_name = #sub_type_name;
+                }
+            }
+
             // These types require no definition as they're already defined.
             TypeEntry::BuiltIn(_)
+            | TypeEntry::BuiltInGeneric(_, _)
+            | TypeEntry::BuiltInWith(_, _, _)
             | TypeEntry::Integral(_)
             | TypeEntry::Float(_)
             | TypeEntry::String
             | TypeEntry::Option(_)
             | TypeEntry::Array(_)
+            | TypeEntry::FixedArray(_, _)
             | TypeEntry::Map(_, _)
             | TypeEntry::Set(_)
+            | TypeEntry::Box(_)
             | TypeEntry::Unit
             | TypeEntry::Tuple(_) => quote! {},
 
             // We should never get here as reference types should only be used
             // in-flight, but never recorded into the type space.
             TypeEntry::Reference(_) => unreachable!(),
+        };
+
+        // A type that derives `Default` already has an implementation.
+        let derives_default = derives
+            .iter()
+            .any(|derive| derive.to_string().rsplit(' ').next() == Some("Default"));
+        let default = (!derives_default)
+            .then(|| type_space.default_impl(self))
+            .flatten();
+        let arbitrary = type_space.arbitrary_impl(self);
+        let output = quote! {
+            #output
+            #default
+            #arbitrary
+        };
+
+        // Our own code for deprecated types and members mustn't warn.
+        if self.uses_deprecated(type_space) {
+            allow_deprecated(output)
+        } else {
+            output
+        }
+    }
+
+    /// Whether the type is deprecated, has deprecated members or variants,
+    /// or refers to deprecated types.
+    fn uses_deprecated(&self, type_space: &TypeSpace) -> bool {
+        let prop_deprecated = |prop: &StructProperty| prop.deprecated;
+        let own = match self {
+            TypeEntry::Enum(TypeEntryEnum {
+                deprecated,
+                variants,
+                ..
+            }) => {
+                *deprecated
+                    || variants.iter().any(|variant| {
+                        variant.deprecated
+                            || matches!(&variant.details, VariantDetails::Struct(props)
+                                if props.iter().any(prop_deprecated))
+                    })
+            }
+            TypeEntry::Struct(TypeEntryStruct {
+                deprecated,
+                properties,
+                ..
+            }) => *deprecated || properties.iter().any(prop_deprecated),
+            TypeEntry::Newtype(TypeEntryNewtype { deprecated, .. })
+            | TypeEntry::Alias(TypeEntryAlias { deprecated, .. }) => *deprecated,
+            _ => false,
+        };
+
+        let mut named = Vec::new();
+        for member_id in self.members() {
+            type_space.named_types(&member_id, &mut named);
         }
+        own || named.iter().any(|type_id| {
+            matches!(
+                type_space.id_to_entry.get(type_id),
+                Some(TypeEntry::Enum(TypeEntryEnum {
+                    deprecated: true,
+                    ..
+                })) | Some(TypeEntry::Struct(TypeEntryStruct {
+                    deprecated: true,
+                    ..
+                })) | Some(TypeEntry::Newtype(TypeEntryNewtype {
+                    deprecated: true,
+                    ..
+                })) | Some(TypeEntry::Alias(TypeEntryAlias {
+                    deprecated: true,
+                    ..
+                }))
+            )
+        })
     }
 
     pub(crate) fn type_name(&self, type_space: &TypeSpace) -> String {
@@ -376,7 +1551,8 @@ impl TypeEntry {
             // Named types.
             TypeEntry::Enum(TypeEntryEnum { name, .. })
             | TypeEntry::Struct(TypeEntryStruct { name, .. })
-            | TypeEntry::Newtype(TypeEntryNewtype { name, .. }) => match &type_space.type_mod {
+            | TypeEntry::Newtype(TypeEntryNewtype { name, .. })
+            | TypeEntry::Alias(TypeEntryAlias { name, .. }) => match &type_space.type_mod {
                 Some(type_mod) if external => {
                     let type_mod = format_ident!("{}", type_mod);
                     let type_name = format_ident!("{}", name);
@@ -413,6 +1589,17 @@ impl TypeEntry {
                 quote! { Vec<#item> }
             }
 
+            TypeEntry::FixedArray(id, len) => {
+                let inner_ty = type_space
+                    .id_to_entry
+                    .get(id)
+                    .expect("unresolved type id for array");
+                let item = inner_ty.type_ident(type_space, external);
+                let len = proc_macro2::Literal::usize_unsuffixed(*len);
+
+                quote! { [#item; #len] }
+            }
+
             TypeEntry::Map(key_id, value_id) => {
                 let key_ty = type_space
                     .id_to_entry
@@ -425,7 +1612,9 @@ impl TypeEntry {
                     .expect("unresolved type id for map")
                     .type_ident(type_space, external);
 
-                quote! { std::collections::HashMap<#key_ty, #value_ty> }
+                let map_type = syn::parse_str::<syn::Path>(type_space.map_type()).unwrap();
+
+                quote! { #map_type<#key_ty, #value_ty> }
             }
 
             TypeEntry::Set(id) => {
@@ -439,6 +1628,16 @@ impl TypeEntry {
                 quote! { Vec<#item> }
             }
 
+            TypeEntry::Box(id) => {
+                let inner_ty = type_space
+                    .id_to_entry
+                    .get(id)
+                    .expect("unresolved type id for box");
+                let inner_ident = inner_ty.type_ident(type_space, external);
+
+                quote! { Box<#inner_ident> }
+            }
+
             TypeEntry::Tuple(items) => {
                 let type_streams = items.iter().map(|item| {
                     type_space
@@ -448,16 +1647,31 @@ impl TypeEntry {
                         .type_ident(type_space, external)
                 });
 
-                quote! { ( #(#type_streams),* ) }
+                // A 1-tuple needs a trailing comma to be a tuple.
+                let trailing = (items.len() == 1).then(|| quote! { , });
+
+                quote! { ( #(#type_streams),* #trailing ) }
             }
 
             TypeEntry::Unit => quote! { () },
             TypeEntry::String => quote! { String },
-            TypeEntry::BuiltIn(name) | TypeEntry::Integral(name) | TypeEntry::Float(name) => {
-                syn::parse_str::<syn::TypePath>(name)
-                    .unwrap()
-                    .to_token_stream()
+            TypeEntry::BuiltInGeneric(name, type_ids) => {
+                let path = syn::parse_str::<syn::TypePath>(name).unwrap();
+                let type_streams = type_ids.iter().map(|type_id| {
+                    type_space
+                        .id_to_entry
+                        .get(type_id)
+                        .expect("unresolved type id for type parameter")
+                        .type_ident(type_space, external)
+                });
+                quote! { #path<#(#type_streams),*> }
             }
+            TypeEntry::BuiltIn(name)
+            | TypeEntry::BuiltInWith(name, _, _)
+            | TypeEntry::Integral(name)
+            | TypeEntry::Float(name) => syn::parse_str::<syn::TypePath>(name)
+                .unwrap()
+                .to_token_stream(),
 
             TypeEntry::Reference(_) => panic!("references should be resolved by now"),
         }
@@ -470,9 +1684,14 @@ impl TypeEntry {
             TypeEntry::Enum(TypeEntryEnum{ variants, .. })
                 // TODO we should probably cache this rather than iterating
                 // every time. We'll know it when the enum is constructed.
-                if variants
-                    .iter()
-                    .all(|variant| matches!(variant.details, VariantDetails::Simple)) =>
+                if variants.iter().all(|variant| {
+                    matches!(
+                        variant.details,
+                        VariantDetails::Simple
+                            | VariantDetails::Integer(_)
+                            | VariantDetails::Unknown
+                    )
+                }) =>
             {
                 self.type_ident(type_space, true)
             }
@@ -481,9 +1700,13 @@ impl TypeEntry {
             | TypeEntry::Struct(_)
             | TypeEntry::Newtype(_)
             | TypeEntry::Array(_)
+            | TypeEntry::FixedArray(_, _)
             | TypeEntry::Map(_, _)
             | TypeEntry::Set(_)
-            | TypeEntry::BuiltIn(_) => {
+            | TypeEntry::Box(_)
+            | TypeEntry::BuiltIn(_)
+            | TypeEntry::BuiltInGeneric(_, _)
+            | TypeEntry::BuiltInWith(_, _, _) => {
                 let ident = self.type_ident(type_space, true);
                 quote! {
                     &#ident
@@ -513,7 +1736,10 @@ impl TypeEntry {
                         .type_parameter_ident(type_space)
                 });
 
-                quote! { ( #(#type_streams),* ) }
+                // A 1-tuple needs a trailing comma to be a tuple.
+                let trailing = (items.len() == 1).then(|| quote! { , });
+
+                quote! { ( #(#type_streams),* #trailing ) }
             }
 
             TypeEntry::Unit | TypeEntry::Integral(_) | TypeEntry::Float(_) => {
@@ -521,10 +1747,107 @@ impl TypeEntry {
             }
             TypeEntry::String => quote! { &str },
 
+            // An alias is passed as the type it names is.
+            TypeEntry::Alias(TypeEntryAlias { type_id, .. }) => type_space
+                .id_to_entry
+                .get(type_id)
+                .expect("unresolved type id for alias")
+                .type_parameter_ident(type_space),
+
             TypeEntry::Reference(_) => panic!("references should be resolved by now"),
         }
     }
 
+    /// The serde `with` module required to (de)serialize a value of this
+    /// type, if any. This covers built-in types that need a `with` module,
+    /// `Option`s of those types, and the arrays, sets, and maps of them that
+    /// the modules of `nested_serde` handle.
+    pub(crate) fn serde_with(&self, type_space: &TypeSpace) -> Option<String> {
+        match self {
+            TypeEntry::BuiltInWith(_, module, _) => Some(module.clone()),
+            TypeEntry::Option(id) => match type_space.id_to_entry.get(id) {
+                Some(TypeEntry::BuiltInWith(_, _, option_module)) => Some(option_module.clone()),
+                _ => self.serde_with_nested(type_space),
+            },
+            _ => self.serde_with_nested(type_space),
+        }
+    }
+
+    fn serde_with_nested(&self, type_space: &TypeSpace) -> Option<String> {
+        let (shape, _, module) = self.nested_with(type_space)?;
+        Some(format!(
+            "nested_serde::{}::{}",
+            nested_module_name(module),
+            shape.module_name()
+        ))
+    }
+
+    /// For an array, set, or map of values of a built-in type with a serde
+    /// module, or an `Option` of one, its shape along with the type and the
+    /// module of its values.
+    pub(crate) fn nested_with<'a>(
+        &self,
+        type_space: &'a TypeSpace,
+    ) -> Option<(NestedShape, &'a str, &'a str)> {
+        let with = |type_id: &TypeId| match type_space.id_to_entry.get(type_id) {
+            Some(TypeEntry::BuiltInWith(type_name, module, _)) => {
+                Some((type_name.as_str(), module.as_str()))
+            }
+            _ => None,
+        };
+        match self {
+            TypeEntry::Array(id) | TypeEntry::Set(id) => {
+                with(id).map(|(type_name, module)| (NestedShape::Seq, type_name, module))
+            }
+            TypeEntry::Map(_, id) => {
+                with(id).map(|(type_name, module)| (NestedShape::Map, type_name, module))
+            }
+            TypeEntry::Option(id) => {
+                match type_space.id_to_entry.get(id)?.nested_with(type_space)? {
+                    (NestedShape::Seq, type_name, module) => {
+                        Some((NestedShape::OptionSeq, type_name, module))
+                    }
+                    (NestedShape::Map, type_name, module) => {
+                        Some((NestedShape::OptionMap, type_name, module))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// For an array, set, map, or tuple, the name of a built-in type with a
+    /// serde module among its values that no module reaches, such as the
+    /// `time::Date` of `Vec<Vec<time::Date>>` or `Vec<Option<time::Date>>`.
+    /// Such values use the type's own serde implementation.
+    pub(crate) fn unreached_with<'a>(&self, type_space: &'a TypeSpace) -> Option<&'a str> {
+        fn within<'a>(type_id: &TypeId, type_space: &'a TypeSpace) -> Option<&'a str> {
+            match type_space.id_to_entry.get(type_id)? {
+                TypeEntry::BuiltInWith(type_name, _, _) => Some(type_name.as_str()),
+                TypeEntry::Option(id)
+                | TypeEntry::Array(id)
+                | TypeEntry::Set(id)
+                | TypeEntry::FixedArray(id, _)
+                | TypeEntry::Map(_, id) => within(id, type_space),
+                TypeEntry::Tuple(ids) => ids.iter().find_map(|id| within(id, type_space)),
+                _ => None,
+            }
+        }
+
+        if self.nested_with(type_space).is_some() {
+            return None;
+        }
+        match self {
+            TypeEntry::Array(id)
+            | TypeEntry::Set(id)
+            | TypeEntry::FixedArray(id, _)
+            | TypeEntry::Map(_, id) => within(id, type_space),
+            TypeEntry::Tuple(ids) => ids.iter().find_map(|id| within(id, type_space)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn describe(&self) -> String {
         match self {
             TypeEntry::Enum(TypeEntryEnum { name, .. }) => format!("enum {}", name),
@@ -532,12 +1855,17 @@ impl TypeEntry {
             TypeEntry::Newtype(TypeEntryNewtype { name, type_id, .. }) => {
                 format!("newtype {} {}", name, type_id.0)
             }
+            TypeEntry::Alias(TypeEntryAlias { name, type_id, .. }) => {
+                format!("alias {} {}", name, type_id.0)
+            }
 
             TypeEntry::Unit => "()".to_string(),
             TypeEntry::Option(type_id) => format!("option {}", type_id.0),
             TypeEntry::Array(type_id) => format!("array {}", type_id.0),
+            TypeEntry::FixedArray(type_id, len) => format!("array {} [{}]", type_id.0, len),
             TypeEntry::Map(key_id, value_id) => format!("map {} {}", key_id.0, value_id.0),
             TypeEntry::Set(type_id) => format!("set {}", type_id.0),
+            TypeEntry::Box(type_id) => format!("box {}", type_id.0),
             TypeEntry::Tuple(type_ids) => {
                 format!(
                     "tuple ({})",
@@ -548,9 +1876,19 @@ impl TypeEntry {
                         .join(", ")
                 )
             }
-            TypeEntry::BuiltIn(name) | TypeEntry::Integral(name) | TypeEntry::Float(name) => {
-                name.clone()
-            }
+            TypeEntry::BuiltInGeneric(name, type_ids) => format!(
+                "{}<{}>",
+                name,
+                type_ids
+                    .iter()
+                    .map(|type_id| type_id.0.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            TypeEntry::BuiltIn(name)
+            | TypeEntry::BuiltInWith(name, _, _)
+            | TypeEntry::Integral(name)
+            | TypeEntry::Float(name) => name.clone(),
             TypeEntry::String => "string".to_string(),
 
             TypeEntry::Reference(_) => unreachable!(),
@@ -558,9 +1896,174 @@ impl TypeEntry {
     }
 }
 
+/// Add derives that aren't already present.
+/// Add `#[allow(deprecated)]` to the items of the code for a type.
+fn allow_deprecated(output: TokenStream) -> TokenStream {
+    let mut file = syn::parse2::<syn::File>(output).unwrap();
+    let allow: syn::Attribute = syn::parse_quote! { #[allow(deprecated)] };
+    for item in &mut file.items {
+        match item {
+            syn::Item::Enum(syn::ItemEnum { attrs, .. })
+            | syn::Item::Impl(syn::ItemImpl { attrs, .. })
+            | syn::Item::Struct(syn::ItemStruct { attrs, .. })
+            | syn::Item::Type(syn::ItemType { attrs, .. }) => attrs.push(allow.clone()),
+            _ => {}
+        }
+    }
+    file.into_token_stream()
+}
+
+/// `#[deprecated]`, with the description as its note, for deprecated types,
+/// members, and variants.
+pub(crate) fn deprecated_attr(
+    deprecated: bool,
+    description: &Option<String>,
+) -> Option<TokenStream> {
+    deprecated.then(|| match description {
+        Some(note) => quote! { #[deprecated(note = #note)] },
+        None => quote! { #[deprecated] },
+    })
+}
+
+fn add_derives(derives: &mut Vec<TokenStream>, new_derives: Vec<TokenStream>) {
+    for derive in new_derives {
+        if !derives
+            .iter()
+            .any(|existing| existing.to_string() == derive.to_string())
+        {
+            derives.push(derive);
+        }
+    }
+}
+
+/// The methods of a newtype with constraints that bypass and unwrap it, for
+/// values known to satisfy the constraints.
+fn newtype_inner_fns(type_name: &Ident, inner_type: TokenStream) -> TokenStream {
+    quote! {
+        impl #type_name {
+            /// Wrap a value without checking it. The value must satisfy the
+            /// schema's constraints, which the type otherwise guarantees.
+            pub fn new_unchecked(value: #inner_type) -> Self {
+                Self(value)
+            }
+
+            /// Unwrap the value.
+            pub fn into_inner(self) -> #inner_type {
+                self.0
+            }
+        }
+    }
+}
+
+/// Generate the checks for integer and floating-point newtype constraints.
+/// Each check returns an error naming the violated constraint.
+fn number_checks(constraints: &NewtypeConstraints, sub_type: &TypeEntry) -> Vec<TokenStream> {
+    // `NonZero` types need to be unwrapped to be compared.
+    let value = match sub_type {
+        TypeEntry::Integral(name) if name.starts_with("std::num::NonZero") => {
+            quote! { value.get() }
+        }
+        _ => quote! { value },
+    };
+
+    let check = |condition: TokenStream, message: String| {
+        quote! {
+            if #condition {
+                return Err(#message.into());
+            }
+        }
+    };
+
+    match constraints {
+        NewtypeConstraints::Integer {
+            minimum,
+            maximum,
+            multiple_of,
+        } => {
+            let minimum = minimum.map(|min| {
+                let lit = proc_macro2::Literal::i128_unsuffixed(min);
+                check(
+                    quote! { #value < #lit },
+                    format!("less than minimum {}", min),
+                )
+            });
+            let maximum = maximum.map(|max| {
+                let lit = proc_macro2::Literal::i128_unsuffixed(max);
+                check(
+                    quote! { #value > #lit },
+                    format!("greater than maximum {}", max),
+                )
+            });
+            let multiple_of = multiple_of.map(|factor| {
+                let lit = proc_macro2::Literal::i128_unsuffixed(factor);
+                check(
+                    quote! { #value % #lit != 0 },
+                    format!("not a multiple of {}", factor),
+                )
+            });
+            vec![minimum, maximum, multiple_of]
+                .into_iter()
+                .flatten()
+                .collect()
+        }
+        NewtypeConstraints::Number {
+            minimum,
+            exclusive_minimum,
+            maximum,
+            exclusive_maximum,
+            multiple_of,
+        } => {
+            let bound = |bound: &Option<FloatBits>, op: TokenStream, message: &str| {
+                bound.map(|bound| {
+                    let bound = bound.value();
+                    let lit = proc_macro2::Literal::f64_unsuffixed(bound);
+                    check(quote! { #value #op #lit }, format!("{} {}", message, bound))
+                })
+            };
+            // Floating-point remainders are inexact so this check is only a
+            // best-effort; values that are very nearly multiples may be
+            // rejected.
+            let multiple_of = multiple_of.map(|factor| {
+                let factor = factor.value();
+                let lit = proc_macro2::Literal::f64_unsuffixed(factor);
+                check(
+                    quote! { (#value / #lit).fract() != 0.0 },
+                    format!("not a multiple of {}", factor),
+                )
+            });
+            vec![
+                bound(minimum, quote! { < }, "less than minimum"),
+                bound(
+                    exclusive_minimum,
+                    quote! { <= },
+                    "not greater than exclusiveMinimum",
+                ),
+                bound(maximum, quote! { > }, "greater than maximum"),
+                bound(
+                    exclusive_maximum,
+                    quote! { >= },
+                    "not less than exclusiveMaximum",
+                ),
+                multiple_of,
+            ]
+            .into_iter()
+            .flatten()
+            .collect()
+        }
+        NewtypeConstraints::None
+        | NewtypeConstraints::String { .. }
+        | NewtypeConstraints::Array { .. }
+        | NewtypeConstraints::Const(_)
+        | NewtypeConstraints::Json => unreachable!(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{type_entry::TypeEntryStruct, TypeEntry, TypeSpace};
+    use schemars::schema::RootSchema;
+    use serde_json::json;
+
+    use crate::{test_util::assert_output, type_entry::TypeEntryStruct, TypeEntry, TypeSpace};
 
     #[test]
     fn test_ident() {
@@ -588,8 +2091,11 @@ mod tests {
             name: "SomeType".to_string(),
             rename: None,
             description: None,
+            deprecated: false,
             properties: vec![],
             deny_unknown_fields: false,
+            default: None,
+            dependent_required: vec![],
         });
 
         let ident = t.type_ident(&ts, true);
@@ -597,4 +2103,39 @@ mod tests {
         assert_eq!(ident.to_string(), "SomeType");
         assert_eq!(parameter.to_string(), "& SomeType");
     }
+
+    #[test]
+    fn test_deprecated() {
+        let schema = serde_json::from_value::<RootSchema>(json!({
+            "definitions": {
+                "Old": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" }
+                    },
+                    "deprecated": true
+                },
+                "Thing": {
+                    "type": "object",
+                    "properties": {
+                        "legacy": {
+                            "description": "Ignored.",
+                            "type": "string",
+                            "deprecated": true
+                        },
+                        "size": {
+                            "oneOf": [
+                                { "type": "string", "enum": ["small"] },
+                                { "type": "string", "enum": ["huge"], "deprecated": true }
+                            ]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let mut type_space = TypeSpace::default();
+        type_space.add_ref_types(schema.definitions).unwrap();
+        assert_output(&type_space, "type_entry/deprecated");
+    }
 }
@@ -8,12 +8,259 @@ use schemars::schema::{
     SubschemaValidation,
 };
 
-use crate::Name;
+use crate::{type_entry::PropertyAccess, Error, Name, Result};
 
+/// The documentation for a subschema: its description or, failing that, its
+/// title.
 pub(crate) fn metadata_description(metadata: &Option<Box<Metadata>>) -> Option<String> {
     metadata
         .as_ref()
-        .and_then(|metadata| metadata.description.as_ref().cloned())
+        .and_then(|metadata| metadata.description.as_ref().or(metadata.title.as_ref()))
+        .map(|description| doc_text(description))
+}
+
+/// The documentation for a type, which also shows the schema's examples.
+pub(crate) fn metadata_type_description(metadata: &Option<Box<Metadata>>) -> Option<String> {
+    let examples = metadata
+        .as_ref()
+        .map(|metadata| metadata.examples.as_slice())
+        .unwrap_or_default();
+    if examples.is_empty() {
+        return metadata_description(metadata);
+    }
+
+    let examples = examples
+        .iter()
+        .map(|example| {
+            format!(
+                "```json\n{}\n```",
+                serde_json::to_string_pretty(example).unwrap()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Some(match metadata_description(metadata) {
+        Some(description) => format!("{}\n\n# Examples\n\n{}", description, examples),
+        None => format!("# Examples\n\n{}", examples),
+    })
+}
+
+/// Schema text made safe for doc attributes: line endings are normalized,
+/// and brackets and angle brackets outside code are escaped so that rustdoc
+/// doesn't take them for intra-doc links or HTML. Code blocks without a
+/// language are marked as text so that they aren't run as doctests. (As
+/// these are attributes rather than comments, `*/` needs no escaping.)
+pub(crate) fn doc_text(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut in_block = false;
+    text.split('\n')
+        .map(|line| {
+            let fence = line.trim_start();
+            if fence.starts_with("```") {
+                in_block = !in_block;
+                if in_block && fence.trim_end() == "```" {
+                    return format!("{}text", line.trim_end());
+                }
+                line.to_string()
+            } else if in_block {
+                line.to_string()
+            } else {
+                let chars = line.chars().collect::<Vec<_>>();
+                let mut escaped = String::new();
+                let mut in_code = false;
+                // The closing bracket of a Markdown link, `[text](url)`.
+                let mut link_end = None;
+                for (index, &c) in chars.iter().enumerate() {
+                    match c {
+                        '`' => {
+                            in_code = !in_code;
+                            escaped.push(c);
+                        }
+                        '[' if !in_code => {
+                            let close = chars[index..].iter().position(|&c| c == ']');
+                            match close.map(|close| index + close) {
+                                Some(close) if chars.get(close + 1) == Some(&'(') => {
+                                    link_end = Some(close);
+                                    escaped.push(c);
+                                }
+                                _ => escaped.push_str("\\["),
+                            }
+                        }
+                        ']' if link_end == Some(index) => escaped.push(c),
+                        ']' if !in_code => escaped.push_str("\\]"),
+                        '<' if !in_code => escaped.push_str("&lt;"),
+                        '>' if !in_code => escaped.push_str("&gt;"),
+                        _ => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn metadata_default(metadata: &Option<Box<Metadata>>) -> Option<String> {
+    metadata
+        .as_ref()
+        .and_then(|metadata| metadata.default.as_ref())
+        .map(ToString::to_string)
+}
+
+pub(crate) fn metadata_deprecated(metadata: &Option<Box<Metadata>>) -> bool {
+    metadata
+        .as_ref()
+        .is_some_and(|metadata| metadata.deprecated)
+}
+
+pub(crate) fn metadata_access(metadata: &Option<Box<Metadata>>) -> PropertyAccess {
+    match metadata.as_deref() {
+        Some(Metadata {
+            read_only: true, ..
+        }) => PropertyAccess::ReadOnly,
+        Some(Metadata {
+            write_only: true, ..
+        }) => PropertyAccess::WriteOnly,
+        _ => PropertyAccess::ReadWrite,
+    }
+}
+
+/// The extensions that give names for the values of an `enum`, as NSwag and
+/// openapi-generator spell them.
+const ENUM_NAMES: [&str; 2] = ["x-enumNames", "x-enum-varnames"];
+
+/// The extensions that describe the values of an `enum`, as openapi-generator
+/// and NSwag spell them.
+const ENUM_DESCRIPTIONS: [&str; 2] = ["x-enum-descriptions", "x-enumDescriptions"];
+
+/// The variant names, in Pascal case, that an `x-enumNames` or
+/// `x-enum-varnames` extension gives for the values of an `enum` (of the
+/// type described), or an error if the names don't correspond to the values.
+pub(crate) fn enum_names(
+    describe: &str,
+    extensions: &schemars::Map<String, serde_json::Value>,
+    enum_values: &[serde_json::Value],
+) -> Result<Option<Vec<String>>> {
+    let (extension, names) = match ENUM_NAMES
+        .iter()
+        .find_map(|extension| Some((*extension, extensions.get(*extension)?)))
+    {
+        Some(found) => found,
+        None => return Ok(None),
+    };
+    let invalid = |reason: String| {
+        Err(Error::InvalidSchema(describe.to_string(), reason).within([extension.into()]))
+    };
+
+    let names = match names.as_array().and_then(|names| {
+        names
+            .iter()
+            .map(serde_json::Value::as_str)
+            .collect::<Option<Vec<_>>>()
+    }) {
+        Some(names) => names,
+        None => return invalid(format!("{} isn't an array of strings", extension)),
+    };
+    if names.len() != enum_values.len() {
+        return invalid(format!(
+            "{} has {} names for {} values",
+            extension,
+            names.len(),
+            enum_values.len()
+        ));
+    }
+
+    let names = names
+        .into_iter()
+        .map(|name| recase(name.to_string(), Case::Pascal).0)
+        .collect::<Vec<_>>();
+    for (index, name) in names.iter().enumerate() {
+        if name.is_empty() || names[..index].contains(name) {
+            return invalid(format!(
+                "{} has the name {:?} more than once",
+                extension, name
+            ));
+        }
+    }
+    Ok(Some(names))
+}
+
+/// The documentation for each value of an `enum` (of the type described) from
+/// an `x-enum-descriptions` or `x-enumDescriptions` extension, where it has a
+/// description, or an error if the descriptions don't correspond to the
+/// values.
+pub(crate) fn enum_descriptions(
+    describe: &str,
+    extensions: &schemars::Map<String, serde_json::Value>,
+    enum_values: &[serde_json::Value],
+) -> Result<Vec<Option<String>>> {
+    let (extension, descriptions) = match ENUM_DESCRIPTIONS
+        .iter()
+        .find_map(|extension| Some((*extension, extensions.get(*extension)?)))
+    {
+        Some(found) => found,
+        None => return Ok(vec![None; enum_values.len()]),
+    };
+    let invalid = |reason: String| {
+        Err(Error::InvalidSchema(describe.to_string(), reason).within([extension.into()]))
+    };
+
+    // Values without descriptions may have nulls or empty strings instead.
+    let descriptions = match descriptions.as_array().and_then(|descriptions| {
+        descriptions
+            .iter()
+            .map(|description| match description {
+                serde_json::Value::Null => Some(None),
+                serde_json::Value::String(description) => {
+                    Some((!description.is_empty()).then(|| doc_text(description)))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    }) {
+        Some(descriptions) => descriptions,
+        None => return invalid(format!("{} isn't an array of strings", extension)),
+    };
+    if descriptions.len() != enum_values.len() {
+        return invalid(format!(
+            "{} has {} descriptions for {} values",
+            extension,
+            descriptions.len(),
+            enum_values.len()
+        ));
+    }
+    Ok(descriptions)
+}
+
+/// The values of an `enum` that we keep, along with the extensions with the
+/// names and descriptions (if any) of only those values.
+pub(crate) fn retain_enum_values(
+    enum_values: &Option<Vec<serde_json::Value>>,
+    extensions: &schemars::Map<String, serde_json::Value>,
+    keep: impl Fn(&serde_json::Value) -> bool,
+) -> (
+    Option<Vec<serde_json::Value>>,
+    schemars::Map<String, serde_json::Value>,
+) {
+    let mut extensions = extensions.clone();
+    let values = match enum_values {
+        Some(values) => values,
+        None => return (None, extensions),
+    };
+    for extension in ENUM_NAMES.iter().chain(&ENUM_DESCRIPTIONS) {
+        if let Some(serde_json::Value::Array(names)) = extensions.get_mut(*extension) {
+            if names.len() == values.len() {
+                *names = names
+                    .iter()
+                    .zip(values)
+                    .filter(|(_, value)| keep(value))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+            }
+        }
+    }
+    let values = values.iter().filter(|value| keep(value)).cloned().collect();
+    (Some(values), extensions)
 }
 
 pub(crate) fn metadata_title(metadata: &Option<Box<Metadata>>) -> Option<String> {
@@ -151,6 +398,18 @@ fn schemas_mutually_exclusive(a: &Schema, b: &Schema) -> bool {
                     }
                 }
 
+                // IPv4 and IPv6 addresses have disjoint string representations.
+                (Some(SingleOrVec::Single(a_single)), Some(SingleOrVec::Single(b_single)))
+                    if a_single == b_single
+                        && a_single.as_ref() == &InstanceType::String
+                        && matches!(
+                            (a.format.as_deref(), b.format.as_deref()),
+                            (Some("ipv4"), Some("ipv6")) | (Some("ipv6"), Some("ipv4"))
+                        ) =>
+                {
+                    true
+                }
+
                 // For other simple types, check if the single type is the same
                 // or not.
                 (Some(SingleOrVec::Single(a_single)), Some(SingleOrVec::Single(b_single))) => {
@@ -345,16 +604,165 @@ pub(crate) fn constant_string_value(schema: &Schema) -> Option<String> {
         }) if single.as_ref() == &InstanceType::String => {
             if values.len() == 1 {
                 values
-                    .get(0)
+                    .first()
                     .and_then(|value| value.as_str().map(ToString::to_string))
             } else {
                 None
             }
         }
+
+        // ... or an enumeration of one or a const without the type.
+        Schema::Object(SchemaObject {
+            metadata: _,
+            instance_type,
+            format: None,
+            enum_values,
+            const_value,
+            subschemas: None,
+            number: None,
+            string: None,
+            array: None,
+            object: None,
+            reference: None,
+            extensions: _,
+        }) if instance_type
+            .as_ref()
+            .is_none_or(|it| it == &SingleOrVec::from(InstanceType::String)) =>
+        {
+            let value = match (enum_values.as_deref(), const_value) {
+                (None, Some(value)) => value,
+                (Some([value]), None) => value,
+                (Some([value]), Some(const_value)) if value == const_value => value,
+                _ => return None,
+            };
+            value.as_str().map(ToString::to_string)
+        }
         _ => None,
     }
 }
 
+/// If this schema represents a simple string with a format, return that
+/// format, otherwise return None.
+pub(crate) fn string_format(schema: &Schema) -> Option<&str> {
+    match schema {
+        Schema::Object(SchemaObject {
+            metadata: _,
+            instance_type: Some(SingleOrVec::Single(single)),
+            format: Some(format),
+            enum_values: None,
+            const_value: None,
+            subschemas: None,
+            number: None,
+            string: None,
+            array: None,
+            object: None,
+            reference: None,
+            extensions: _,
+        }) if single.as_ref() == &InstanceType::String => Some(format),
+        _ => None,
+    }
+}
+
+/// Whether a schema accepts only null, i.e. is `{ "type": "null" }`,
+/// `{ "const": null }`, or `{ "enum": [null] }`.
+pub(crate) fn schema_is_null(schema: &Schema) -> bool {
+    match schema {
+        Schema::Object(SchemaObject {
+            instance_type: Some(SingleOrVec::Single(single)),
+            ..
+        }) => single.as_ref() == &InstanceType::Null,
+        Schema::Object(SchemaObject {
+            instance_type: None,
+            const_value: Some(serde_json::Value::Null),
+            ..
+        }) => true,
+        Schema::Object(SchemaObject {
+            instance_type: None,
+            enum_values: Some(values),
+            ..
+        }) => values == &[serde_json::Value::Null],
+        _ => false,
+    }
+}
+
+/// If some but not all of the subschemas of a `oneOf` or `anyOf` accept only
+/// null, return the others; the union is then an `Option` of them.
+pub(crate) fn non_null_subschemas(subschemas: &[Schema]) -> Option<Vec<Schema>> {
+    let non_nulls = subschemas
+        .iter()
+        .filter(|schema| !schema_is_null(schema))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if non_nulls.is_empty() || non_nulls.len() == subschemas.len() {
+        None
+    } else {
+        Some(non_nulls)
+    }
+}
+
+/// Whether a JSON value is an instance of the given type.
+pub(crate) fn value_matches_type(value: &serde_json::Value, instance_type: &InstanceType) -> bool {
+    match (value, instance_type) {
+        (serde_json::Value::Null, InstanceType::Null)
+        | (serde_json::Value::Bool(_), InstanceType::Boolean)
+        | (serde_json::Value::String(_), InstanceType::String)
+        | (serde_json::Value::Array(_), InstanceType::Array)
+        | (serde_json::Value::Object(_), InstanceType::Object) => true,
+        (serde_json::Value::Number(number), InstanceType::Integer) => {
+            number.is_i64() || number.is_u64()
+        }
+        (serde_json::Value::Number(_), InstanceType::Number) => true,
+        _ => false,
+    }
+}
+
+/// Whether two versions or version requirements of a crate, e.g. `0.4` and
+/// `^0.4.38`, are compatible as cargo considers them: they agree up to and
+/// including the first nonzero component. `*` is compatible with anything.
+pub(crate) fn versions_compatible(a: &str, b: &str) -> bool {
+    fn series(version: &str) -> Option<Vec<u64>> {
+        let components = version
+            .trim()
+            .trim_start_matches(['^', '='])
+            .trim()
+            .split('.')
+            .map(|component| component.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let significant = components
+            .iter()
+            .position(|component| *component != 0)
+            .map_or(components.len(), |index| index + 1);
+        Some(components[..significant].to_vec())
+    }
+
+    if a.trim() == "*" || b.trim() == "*" {
+        return true;
+    }
+    match (series(a), series(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether a schema has all the properties of a partial schema, other than its
+/// metadata; e.g. `{ "type": "string", "format": "money" }` matches any string
+/// schema with that format regardless of its other properties.
+pub(crate) fn schema_matches_fragment(schema: &SchemaObject, fragment: &SchemaObject) -> bool {
+    let fragment = SchemaObject {
+        metadata: None,
+        ..fragment.clone()
+    };
+    match (serde_json::to_value(fragment), serde_json::to_value(schema)) {
+        (Ok(serde_json::Value::Object(fragment)), Ok(serde_json::Value::Object(schema))) => {
+            fragment
+                .iter()
+                .all(|(key, value)| schema.get(key) == Some(value))
+        }
+        _ => false,
+    }
+}
+
 fn resolve<'a>(schema: &'a Schema, definitions: &'a schemars::Map<String, Schema>) -> &'a Schema {
     match schema {
         Schema::Bool(_) => schema,
@@ -403,7 +811,7 @@ pub(crate) fn schema_is_named(schema: &Schema) -> Option<String> {
             extensions: _,
         }) => {
             let idx = reference.rfind('/')?;
-            Some((&reference[idx + 1..]).to_string())
+            Some(reference[idx + 1..].to_string())
         }
         Schema::Object(SchemaObject {
             metadata: Some(metadata),
@@ -462,28 +870,259 @@ pub(crate) fn schema_is_named(schema: &Schema) -> Option<String> {
     Some(sanitize(&raw_name, Case::Pascal))
 }
 
-fn sanitize(input: &str, case: Case) -> String {
-    let out = input
-        .replace("$", "-")
-        .replace("@", "-")
-        .replace("/", "-")
+/// The name of the type of an inline subschema at the given path: the name of
+/// the type that contains it followed by the names of the properties that
+/// lead to it, e.g. `ServerTlsCert` for `["Server", "tls", "cert"]`. Types
+/// get these names, before any prefix or suffix, unless another type has the
+/// name already. The items of an array property are named for the property,
+/// or with `Item` appended if that's taken, and the members of tuples with
+/// `Item` and their index appended, e.g. `PairItem0`.
+pub fn path_type_name<S: AsRef<str>>(path: &[S]) -> String {
+    let path = path.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    sanitize(&path.join("_"), Case::Pascal)
+}
+
+pub(crate) fn sanitize(input: &str, case: Case) -> String {
+    // A leading minus is spelled out so that e.g. `-1` and `1` differ.
+    let input = match input.strip_prefix('-') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => format!("minus-{}", rest),
+        _ => input.to_string(),
+    };
+    // Other punctuation separates words, e.g. in `foo.bar`.
+    let out = transliterate(&input)
         .replace("+", "-plus-")
         .replace("'", "")
+        .chars()
+        .map(|c| match c {
+            '_' => c,
+            c if c.is_ascii_punctuation() => '-',
+            c => c,
+        })
+        .collect::<String>()
         .to_case(case);
+    let out = encode_non_ascii(&out, case);
     let out = match out.chars().next() {
         None => "x".to_case(case),
         Some('a'..='z' | 'A'..='Z' | '_') => out,
         Some(_) => format!("_{}", out),
     };
 
-    // Make sure the string is a valid Rust identifier.
-    if syn::parse_str::<syn::Ident>(&out).is_ok() {
+    // Make sure the string is a valid Rust identifier. Keywords are raw
+    // identifiers, e.g. `r#type`, but for those that can't be.
+    if KEYWORDS.contains(&out.as_str()) {
+        if NON_RAW_KEYWORDS.contains(&out.as_str()) {
+            format!("{}_", out)
+        } else {
+            format!("r#{}", out)
+        }
+    } else if syn::parse_str::<syn::Ident>(&out).is_ok() {
         out
     } else {
         format!("{}_", out)
     }
 }
 
+/// The string with the Latin letters with diacritics, and the like, replaced
+/// with the ASCII letters they're based on, e.g. `u` for `ü`.
+fn transliterate(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| {
+            match TRANSLITERATIONS
+                .iter()
+                .find(|(letters, _)| letters.contains(c))
+            {
+                Some((_, ascii)) => ascii.to_string(),
+                None => c.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The identifier in the case with its other non-ASCII characters replaced
+/// with words of their code points, e.g. `u65e5` or `U65e5` for `日`, so
+/// that identifiers are ASCII.
+fn encode_non_ascii(input: &str, case: Case) -> String {
+    let mut out = String::new();
+    let mut after_word = false;
+    for c in input.chars() {
+        if c.is_ascii() {
+            if after_word && case != Case::Pascal && c != '_' {
+                out.push('_');
+            }
+            out.push(c);
+            after_word = false;
+        } else {
+            if case == Case::Pascal {
+                out.push_str(&format!("U{:04x}", c as u32));
+            } else {
+                if !out.is_empty() && !out.ends_with('_') {
+                    out.push('_');
+                }
+                out.push_str(&format!("u{:04x}", c as u32));
+            }
+            after_word = true;
+        }
+    }
+    out
+}
+
+/// Non-ASCII letters and the ASCII we write for each.
+const TRANSLITERATIONS: &[(&str, &str)] = &[
+    ("ÀÁÂÃÄÅĀĂĄ", "A"),
+    ("àáâãäåāăą", "a"),
+    ("ÇĆĈĊČ", "C"),
+    ("çćĉċč", "c"),
+    ("ÐĎĐ", "D"),
+    ("ðďđ", "d"),
+    ("ÈÉÊËĒĔĖĘĚ", "E"),
+    ("èéêëēĕėęě", "e"),
+    ("ĜĞĠĢ", "G"),
+    ("ĝğġģ", "g"),
+    ("ĤĦ", "H"),
+    ("ĥħ", "h"),
+    ("ÌÍÎÏĨĪĬĮİ", "I"),
+    ("ìíîïĩīĭįı", "i"),
+    ("Ĵ", "J"),
+    ("ĵ", "j"),
+    ("Ķ", "K"),
+    ("ķ", "k"),
+    ("ĹĻĽĿŁ", "L"),
+    ("ĺļľŀł", "l"),
+    ("ÑŃŅŇ", "N"),
+    ("ñńņň", "n"),
+    ("ÒÓÔÕÖØŌŎŐ", "O"),
+    ("òóôõöøōŏő", "o"),
+    ("ŔŖŘ", "R"),
+    ("ŕŗř", "r"),
+    ("ŚŜŞŠ", "S"),
+    ("śŝşš", "s"),
+    ("ŢŤŦ", "T"),
+    ("ţťŧ", "t"),
+    ("ÙÚÛÜŨŪŬŮŰŲ", "U"),
+    ("ùúûüũūŭůűų", "u"),
+    ("Ŵ", "W"),
+    ("ŵ", "w"),
+    ("ÝŶŸ", "Y"),
+    ("ýÿŷ", "y"),
+    ("ŹŻŽ", "Z"),
+    ("źżž", "z"),
+    ("Æ", "Ae"),
+    ("æ", "ae"),
+    ("Œ", "Oe"),
+    ("œ", "oe"),
+    ("Þ", "Th"),
+    ("þ", "th"),
+    ("ß", "ss"),
+];
+
+/// Names that are the same made different: for each name that others
+/// share, the first of them keeps it, and the others get the first numbers
+/// after it that no name has in the order they're given, e.g. `foo_bar_2`
+/// and then `foo_bar_3`, or `FooBar2`.
+pub(crate) fn unique_names(names: &[String], case: Case) -> Vec<String> {
+    let mut taken = names.iter().cloned().collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    names
+        .iter()
+        .map(|name| {
+            if seen.insert(name) {
+                return name.clone();
+            }
+            let separator = match case {
+                Case::Pascal if !name.ends_with(|c: char| c.is_ascii_digit()) => "",
+                _ => "_",
+            };
+            let numbered = (2..)
+                .map(|n| format!("{}{}{}", name, separator, n))
+                .find(|numbered| !taken.contains(numbered))
+                .unwrap();
+            taken.insert(numbered.clone());
+            numbered
+        })
+        .collect()
+}
+
+/// Words that tell apart names that `sanitize` makes the same by how they're
+/// spelled, as a last resort in that order: the punctuation in the name, e.g.
+/// `Dot` for `foo.bar`, and then that and its case, e.g. `Upper` for
+/// `HTTPServer` or `Lower` for `httpserver`.
+pub(crate) fn spelling_suffixes(name: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut punctuation = String::new();
+    for c in name.chars().filter(|c| !c.is_alphanumeric()) {
+        if !seen.contains(&c) {
+            seen.push(c);
+            punctuation.push_str(&punctuation_name(c));
+        }
+    }
+    let letters = name
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect::<Vec<_>>();
+    let case = if letters
+        .windows(2)
+        .any(|pair| pair[0].is_uppercase() && pair[1].is_uppercase())
+    {
+        "Upper"
+    } else if !letters.iter().any(|c| c.is_uppercase()) {
+        "Lower"
+    } else if letters.first().is_some_and(|c| c.is_lowercase()) {
+        "Camel"
+    } else {
+        "Pascal"
+    };
+    match punctuation.is_empty() {
+        true => vec![case.to_string()],
+        false => vec![punctuation.clone(), format!("{}{}", punctuation, case)],
+    }
+}
+
+/// A word for a character that isn't a letter or a digit.
+fn punctuation_name(c: char) -> String {
+    let name = match c {
+        '.' => "Dot",
+        '_' => "Underscore",
+        '-' => "Dash",
+        ' ' => "Space",
+        ':' => "Colon",
+        '/' => "Slash",
+        '$' => "Dollar",
+        '@' => "At",
+        '+' => "Plus",
+        '#' => "Hash",
+        '~' => "Tilde",
+        '\'' => "Apostrophe",
+        c => return format!("U{:04x}", c as u32),
+    };
+    name.to_string()
+}
+
+/// The strict and reserved keywords of all editions.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// The keywords that may not be raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// The keyword of an identifier that `sanitize` made of one, without the `r#`
+/// or the `_` after it, e.g. to make another identifier of it; otherwise the
+/// identifier.
+pub(crate) fn unraw(ident: &str) -> &str {
+    match ident.strip_prefix("r#") {
+        Some(keyword) => keyword,
+        None => match ident.strip_suffix('_') {
+            Some(keyword) if NON_RAW_KEYWORDS.contains(&keyword) => keyword,
+            _ => ident,
+        },
+    }
+}
+
 pub(crate) fn recase(input: String, case: Case) -> (String, Option<String>) {
     let new = sanitize(&input, case);
     let rename = if new == input { None } else { Some(input) };
@@ -510,7 +1149,10 @@ mod tests {
     use convert_case::Case;
     use schemars::{schema_for, JsonSchema};
 
-    use crate::util::{sanitize, schemas_mutually_exclusive};
+    use crate::util::{
+        doc_text, metadata_type_description, sanitize, schemas_mutually_exclusive,
+        spelling_suffixes, unique_names, unraw, versions_compatible,
+    };
 
     #[test]
     fn test_non_exclusive_structs() {
@@ -616,10 +1258,109 @@ mod tests {
 
     #[test]
     fn test_sanitize() {
-        assert_eq!(sanitize("type", Case::Snake), "type_");
-        assert_eq!(sanitize("ref", Case::Snake), "ref_");
+        assert_eq!(sanitize("type", Case::Snake), "r#type");
+        assert_eq!(sanitize("ref", Case::Snake), "r#ref");
+        assert_eq!(sanitize("self", Case::Snake), "self_");
+        assert_eq!(sanitize("self", Case::Pascal), "Self_");
+        assert_eq!(sanitize("crate", Case::Pascal), "Crate");
+        assert_eq!(unraw("r#type"), "type");
+        assert_eq!(unraw("self_"), "self");
+        assert_eq!(unraw("ref_"), "ref_");
         assert_eq!(sanitize("+1", Case::Snake), "plus_1");
-        assert_eq!(sanitize("-1", Case::Snake), "_1");
+        assert_eq!(sanitize("-1", Case::Snake), "minus_1");
+        assert_eq!(sanitize("-1", Case::Pascal), "Minus1");
+        assert_eq!(sanitize("2fa_enabled", Case::Snake), "_2_fa_enabled");
+        assert_eq!(sanitize("content-type", Case::Snake), "content_type");
+        assert_eq!(sanitize("$schema", Case::Snake), "schema");
+        assert_eq!(sanitize("ünïcödé", Case::Snake), "unicode");
+        assert_eq!(sanitize("Ærø", Case::Pascal), "Aero");
+        assert_eq!(sanitize("日本", Case::Snake), "u65e5_u672c");
+        assert_eq!(sanitize("日本", Case::Pascal), "U65e5U672c");
+        assert_eq!(sanitize("a日b", Case::Snake), "a_u65e5_b");
         assert_eq!(sanitize("@timestamp", Case::Pascal), "Timestamp");
+        assert_eq!(sanitize("foo.bar", Case::Pascal), "FooBar");
+        assert_eq!(sanitize("core.v1:Pod", Case::Snake), "core_v_1_pod");
+    }
+
+    #[test]
+    fn test_spelling_suffixes() {
+        assert_eq!(spelling_suffixes("HTTPServer"), vec!["Upper"]);
+        assert_eq!(spelling_suffixes("httpServer"), vec!["Camel"]);
+        assert_eq!(
+            spelling_suffixes("foo_bar"),
+            vec!["Underscore", "UnderscoreLower"]
+        );
+        assert_eq!(
+            spelling_suffixes("Foo.Bar-baz"),
+            vec!["DotDash", "DotDashPascal"]
+        );
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let names = ["foo-bar", "foo_bar", "foo_bar_2", "Foo Bar"]
+            .iter()
+            .map(|name| sanitize(name, Case::Snake))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unique_names(&names, Case::Snake),
+            vec!["foo_bar", "foo_bar_3", "foo_bar_2", "foo_bar_4"]
+        );
+
+        // The first keeps its name, and the others are numbered in order.
+        for (values, expected) in [
+            (vec!["red", "Red"], vec!["Red", "Red2"]),
+            (vec!["a-b", "a_b", "A B"], vec!["AB", "AB2", "AB3"]),
+            (
+                vec!["utf-8", "UTF8", "+", "plus"],
+                vec!["Utf8", "Utf8_2", "Plus", "Plus2"],
+            ),
+        ] {
+            let names = values
+                .iter()
+                .map(|value| sanitize(value, Case::Pascal))
+                .collect::<Vec<_>>();
+            assert_eq!(unique_names(&names, Case::Pascal), expected, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_versions_compatible() {
+        assert!(versions_compatible("0.4", "0.4.38"));
+        assert!(versions_compatible("^1.2", "1.8.0"));
+        assert!(versions_compatible("0.0.3", "=0.0.3"));
+        assert!(versions_compatible("*", "2.0"));
+        assert!(!versions_compatible("0.4", "0.3.1"));
+        assert!(!versions_compatible("0.0.3", "0.0.4"));
+        assert!(!versions_compatible("1", ">=1, <3"));
+    }
+
+    #[test]
+    fn test_doc_text() {
+        assert_eq!(
+            doc_text("See [RFC 3339] or <b>this</b>.\r\n\r\nUse `a[0] < b` instead."),
+            "See \\[RFC 3339\\] or &lt;b&gt;this&lt;/b&gt;.\n\nUse `a[0] < b` instead."
+        );
+        assert_eq!(
+            doc_text("The [`ref`](https://example.com) [resource]."),
+            "The [`ref`](https://example.com) \\[resource\\]."
+        );
+        assert_eq!(
+            doc_text("Like this:\n```\nx[0] <y>\n```\n```json\n[]\n```"),
+            "Like this:\n```text\nx[0] <y>\n```\n```json\n[]\n```"
+        );
+    }
+
+    #[test]
+    fn test_metadata_type_description() {
+        let metadata = Some(Box::new(schemars::schema::Metadata {
+            title: Some("Thing".to_string()),
+            examples: vec![serde_json::json!({ "a": 1 })],
+            ..Default::default()
+        }));
+        assert_eq!(
+            metadata_type_description(&metadata).unwrap(),
+            "Thing\n\n# Examples\n\n```json\n{\n  \"a\": 1\n}\n```"
+        );
     }
 }
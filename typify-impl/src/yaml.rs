@@ -0,0 +1,76 @@
+// Copyright 2021 Oxide Computer Company
+
+use serde_json::Value;
+
+/// Parse a YAML document into the JSON value it represents, expanding merge
+/// keys (`<<: *base`). Errors include the line and column where parsing
+/// failed.
+pub(crate) fn from_str(content: &str) -> Result<Value, String> {
+    let mut value = serde_yaml::from_str::<serde_yaml::Value>(content).map_err(describe)?;
+    value.apply_merge().map_err(describe)?;
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn describe(e: serde_yaml::Error) -> String {
+    match e.location() {
+        Some(location) if !e.to_string().contains(" at line ") => format!(
+            "{} at line {} column {}",
+            e,
+            location.line(),
+            location.column()
+        ),
+        _ => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::from_str;
+
+    #[test]
+    fn test_from_str() {
+        let value = from_str(
+            r#"
+            definitions:
+              Base: &base
+                type: object
+                properties:
+                  enabled:
+                    enum: [yes, no, on, off]
+              Derived:
+                <<: *base
+                title: Derived
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "definitions": {
+                    "Base": {
+                        "type": "object",
+                        "properties": {
+                            "enabled": { "enum": ["yes", "no", "on", "off"] }
+                        }
+                    },
+                    "Derived": {
+                        "type": "object",
+                        "properties": {
+                            "enabled": { "enum": ["yes", "no", "on", "off"] }
+                        },
+                        "title": "Derived"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_str_error() {
+        let error = from_str("definitions:\n  Base:\n    type: [object\n").unwrap_err();
+        assert!(error.contains("line 4 column 1"), "{}", error);
+    }
+}
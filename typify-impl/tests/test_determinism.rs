@@ -0,0 +1,49 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::{env, fs, path::Path, process::Command};
+
+use schemars::schema::{RootSchema, Schema};
+use typify_impl::TypeSpace;
+
+/// The variable that tells a child process of the test where to write its
+/// output.
+const OUTPUT_VAR: &str = "TYPIFY_DETERMINISM_OUTPUT";
+
+fn generate() -> String {
+    let file = fs::File::open(Path::new("tests/github.json")).unwrap();
+    let mut schema: RootSchema = serde_json::from_reader(file).unwrap();
+    schema.schema.metadata().title = Some("Everything".to_string());
+
+    let mut type_space = TypeSpace::default();
+    type_space.add_ref_types(schema.definitions).unwrap();
+    type_space.add_type(&Schema::Object(schema.schema)).unwrap();
+    type_space.to_stream().to_string()
+}
+
+#[test]
+fn test_deterministic_output() {
+    let output = generate();
+    if let Some(path) = env::var_os(OUTPUT_VAR) {
+        fs::write(path, output).unwrap();
+        return;
+    }
+
+    // The same process...
+    assert!(generate() == output, "output differs within a process");
+
+    // ...and other processes, whose hash maps have other seeds.
+    for index in 0..2 {
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR"))
+            .join(format!("test_deterministic_output_{}.rs", index));
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "test_deterministic_output"])
+            .env(OUTPUT_VAR, &path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(
+            fs::read_to_string(&path).unwrap() == output,
+            "output differs in another process"
+        );
+    }
+}
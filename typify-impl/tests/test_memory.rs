@@ -0,0 +1,58 @@
+// Copyright 2021 Oxide Computer Company
+
+//! The memory that converting a large schema takes, measured by an allocator
+//! that tracks the peak of the bytes allocated. This is the only test in its
+//! binary so that no other test allocates at the same time.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use schemars::schema::RootSchema;
+use typify_impl::TypeSpace;
+
+struct Tracking;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Tracking = Tracking;
+
+#[test]
+fn test_memory() {
+    let content = std::fs::read_to_string("tests/github.json").unwrap();
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    let mut schema = serde_json::from_str::<RootSchema>(&content).unwrap();
+    schema.schema.metadata().title = Some("Everything".to_string());
+    drop(content);
+    let schema_size = ALLOCATED.load(Ordering::SeqCst) - before;
+
+    let start = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(start, Ordering::SeqCst);
+    let mut type_space = TypeSpace::default();
+    type_space.add_root_schema(schema).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - start;
+
+    // Converting the schema once needs less memory than copies of it would.
+    assert!(
+        peak < schema_size * 3 / 2,
+        "{} bytes of schema took at most {} more",
+        schema_size,
+        peak,
+    );
+}
@@ -0,0 +1,63 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::{env, fs, path::Path};
+
+use typify_impl::{Error, TypeSpace, TypeSpaceSettings};
+
+// A single test, as it changes the environment in which `rustfmt` is found.
+#[test]
+fn test_write_to_file() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("test_write_to_file");
+    fs::create_dir_all(&dir).unwrap();
+    let schema_path = dir.join("pets.json");
+    fs::write(
+        &schema_path,
+        r#"{
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "properties": { "name": { "type": "string" } },
+                    "required": ["name"]
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let mut type_space = TypeSpace::default();
+    type_space.add_schema_file(&schema_path).unwrap();
+    let generated = type_space.write_to_file(dir.join("pets.rs")).unwrap();
+    let code = fs::read_to_string(generated.path()).unwrap();
+    assert!(code.starts_with(&format!(
+        "// Generated by typify {} from pets.json; changes will be lost when it's\n\
+         // generated again.\n\nuse serde::{{Deserialize, Serialize}};\n\n",
+        env!("CARGO_PKG_VERSION"),
+    )));
+    assert!(code.contains("pub struct Pet {\n    pub name: String,\n}\n"));
+    assert_eq!(
+        generated.rerun_if_changed(),
+        [format!(
+            "cargo:rerun-if-changed={}",
+            schema_path.canonicalize().unwrap().display()
+        )],
+    );
+
+    // Without `rustfmt`, `prettyplease` formats the code unless that's an
+    // error.
+    env::set_var("RUSTFMT", "");
+    let code = type_space.to_string_pretty().unwrap();
+    assert!(
+        code.contains("pub struct Pet {\n    pub name: String,\n}\n"),
+        "{}",
+        code
+    );
+    let mut settings = TypeSpaceSettings::default();
+    settings.with_strict_formatting(true);
+    let mut type_space = TypeSpace::new(&settings);
+    type_space.add_schema_file(&schema_path).unwrap();
+    assert!(matches!(
+        type_space.write_to_file(dir.join("strict.rs")),
+        Err(Error::Format(_))
+    ));
+    assert!(!dir.join("strict.rs").exists());
+}
@@ -1,15 +1,67 @@
 // Copyright 2021 Oxide Computer Company
 
-use std::path::Path;
+#![cfg_attr(feature = "nightly", feature(proc_macro_tracked_path))]
+
+use std::path::{Path, PathBuf};
 
 use proc_macro::TokenStream;
-use quote::quote;
-use schemars::schema::Schema;
-use syn::LitStr;
-use typify_impl::TypeSpace;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use schemars::schema::RootSchema;
+use syn::{
+    braced, bracketed,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitStr, Token,
+};
+use typify_impl::{NamedSimpleTypes, TypePatch, TypeSpace, TypeSpaceSettings};
 
 /// Import types by providing a pathname for a JSON Schema file. The path must
 /// be relative to `$CARGO_MANIFEST_DIR`.
+///
+/// Several files may be given, e.g. `import_types!("a.json", "b.json")`, as
+/// may a directory (for all the `.json` files in it) or a pattern such as
+/// `"schemas/*.json"`. With the `serde_yaml` feature, `.yaml` and `.yml` files
+/// are read as YAML (and included from directories). The types of all the
+/// files are generated together so that they may refer to each other's
+/// definitions.
+///
+/// Settings may be given along with the path:
+///
+/// ```ignore
+/// import_types!(
+///     schema = "api.json", // or ["a.json", "b.json"]
+///     derives = [PartialEq, Eq, Hash],
+///     replace = { "Uuid" = uuid::Uuid, "IpNet" = crate::net::IpNet: Display + Hash },
+///     patch = { "Thing" = { rename = "MyThing", derives = [Default] } },
+///     crates = { "chrono" = "0.4" },
+/// );
+/// ```
+///
+/// - `derives` are derived for every type.
+/// - `replace` uses the given types rather than generating the named types,
+///   optionally followed by the traits they implement.
+/// - `patch` renames types, adds derives to them, or appends `doc`
+///   paragraphs to their documentation.
+/// - `crates` permits types that schemas name in `x-rust-type` extensions from
+///   the given crates.
+/// - `roots = ["Instance", "Disk"]` generates only the types of the given
+///   definitions and those they're made of.
+/// - `deny_lossy = true` fails where the types wouldn't represent part of a
+///   schema, which is otherwise a warning.
+/// - `explicit_nulls = true` distinguishes null from absence for properties
+///   that are optional and nullable, with fields of `Option<Option<T>>`.
+/// - `named_simple_types = "alias"` (or `"inline"`, or `"newtype"`, the
+///   default) generates type aliases for definitions that are arrays, maps,
+///   primitives and the like, or uses their types in their place.
+/// - `no_std = true` generates code for `no_std` crates that declare
+///   `extern crate alloc;`.
+/// - `json_schema_impls = true` implements `schemars::JsonSchema` to give the
+///   schemas that types came from, with the `schemars` feature enabled.
+/// - `arbitrary_impls = true` implements `proptest::arbitrary::Arbitrary` for
+///   the types, with the `proptest` feature enabled.
+///
+/// The schema may instead be given inline with `schema_str = r#"{ ... }"#`.
 #[proc_macro]
 pub fn import_types(item: TokenStream) -> TokenStream {
     match do_import_types(item) {
@@ -18,48 +70,391 @@ pub fn import_types(item: TokenStream) -> TokenStream {
     }
 }
 
-fn do_import_types(item: TokenStream) -> Result<TokenStream, syn::Error> {
-    let arg = syn::parse::<LitStr>(item)?;
-    let dir = std::env::var("CARGO_MANIFEST_DIR").map_or_else(
-        |_| std::env::current_dir().unwrap(),
-        |s| Path::new(&s).to_path_buf(),
-    );
-
-    let path = dir.join(arg.value());
-
-    let schema: schemars::schema::RootSchema =
-        serde_json::from_reader(std::fs::File::open(&path).map_err(|e| {
-            syn::Error::new(
-                arg.span(),
-                format!("couldn't read file {}: {}", arg.value(), e.to_string()),
-            )
-        })?)
-        .unwrap();
-
-    let mut type_space = TypeSpace::default();
-    type_space
-        .add_ref_types(schema.definitions)
-        .map_err(|e| into_syn_err(e, arg.span()))?;
-    let base_type = &schema.schema;
-    // Only convert the top-level type if it has a name
-    if (|| base_type.metadata.as_ref()?.title.as_ref())().is_some() {
-        let _ = type_space
-            .add_type(&Schema::Object(schema.schema))
-            .map_err(|e| into_syn_err(e, arg.span()))?;
+struct MacroSettings {
+    schema: Schema,
+    settings: TypeSpaceSettings,
+}
+
+/// Where to find the schema.
+enum Schema {
+    /// Paths relative to `$CARGO_MANIFEST_DIR`.
+    Files(Vec<LitStr>),
+    /// The JSON itself.
+    Inline(LitStr),
+}
+
+impl Parse for MacroSettings {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // The original form is just the path (or paths).
+        if input.peek(LitStr) {
+            let paths = Punctuated::<LitStr, Token![,]>::parse_terminated(input)?;
+            return Ok(Self {
+                schema: Schema::Files(paths.into_iter().collect()),
+                settings: TypeSpaceSettings::default(),
+            });
+        }
+
+        let mut schema = None;
+        let mut settings = TypeSpaceSettings::default();
+
+        while !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+
+            match key.to_string().as_str() {
+                "schema" | "schema_str" => {
+                    if schema.is_some() {
+                        return Err(syn::Error::new(
+                            key.span(),
+                            "only one of `schema` and `schema_str` may be given",
+                        ));
+                    }
+                    schema = Some(if key == "schema_str" {
+                        Schema::Inline(input.parse()?)
+                    } else if input.peek(syn::token::Bracket) {
+                        let content;
+                        bracketed!(content in input);
+                        let paths = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                        Schema::Files(paths.into_iter().collect())
+                    } else {
+                        Schema::Files(vec![input.parse()?])
+                    });
+                }
+                "derives" => {
+                    for derive in parse_derives(input)? {
+                        settings.with_derive(derive);
+                    }
+                }
+                "replace" => {
+                    let content;
+                    braced!(content in input);
+                    while !content.is_empty() {
+                        let type_name = content.parse::<LitStr>()?;
+                        content.parse::<Token![=]>()?;
+                        let replace_type = content.parse::<syn::Path>()?;
+                        let mut impls = Vec::new();
+                        if content.peek(Token![:]) {
+                            content.parse::<Token![:]>()?;
+                            let traits =
+                                Punctuated::<syn::Path, Token![+]>::parse_separated_nonempty(
+                                    &content,
+                                )?;
+                            impls
+                                .extend(traits.iter().map(|imp| imp.to_token_stream().to_string()));
+                        }
+                        settings.with_replacement(
+                            type_name.value(),
+                            replace_type.to_token_stream().to_string(),
+                            impls,
+                        );
+                        if !content.is_empty() {
+                            content.parse::<Token![,]>()?;
+                        }
+                    }
+                }
+                "crates" => {
+                    let content;
+                    braced!(content in input);
+                    while !content.is_empty() {
+                        let crate_name = content.parse::<LitStr>()?;
+                        content.parse::<Token![=]>()?;
+                        let version = content.parse::<LitStr>()?;
+                        settings.with_crate(crate_name.value(), version.value());
+                        if !content.is_empty() {
+                            content.parse::<Token![,]>()?;
+                        }
+                    }
+                }
+                "roots" => {
+                    let content;
+                    bracketed!(content in input);
+                    let roots = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    settings.with_roots(roots.iter().map(LitStr::value));
+                }
+                "deny_lossy" => {
+                    settings.with_deny_lossy(input.parse::<syn::LitBool>()?.value);
+                }
+                "explicit_nulls" => {
+                    settings.with_explicit_nulls(input.parse::<syn::LitBool>()?.value);
+                }
+                "named_simple_types" => {
+                    let mode = input.parse::<LitStr>()?;
+                    settings.with_named_simple_types(match mode.value().as_str() {
+                        "newtype" => NamedSimpleTypes::Newtype,
+                        "alias" => NamedSimpleTypes::Alias,
+                        "inline" => NamedSimpleTypes::Inline,
+                        _ => {
+                            return Err(syn::Error::new(
+                                mode.span(),
+                                "expected \"newtype\", \"alias\", or \"inline\"",
+                            ))
+                        }
+                    });
+                }
+                "no_std" => {
+                    settings.with_no_std(input.parse::<syn::LitBool>()?.value);
+                }
+                "json_schema_impls" => {
+                    settings.with_json_schema_impls(input.parse::<syn::LitBool>()?.value);
+                }
+                "arbitrary_impls" => {
+                    settings.with_arbitrary_impls(input.parse::<syn::LitBool>()?.value);
+                }
+                "patch" => {
+                    let content;
+                    braced!(content in input);
+                    while !content.is_empty() {
+                        let type_name = content.parse::<LitStr>()?;
+                        content.parse::<Token![=]>()?;
+                        let type_patch = parse_patch(&content)?;
+                        settings.with_patch(type_name.value(), &type_patch);
+                        if !content.is_empty() {
+                            content.parse::<Token![,]>()?;
+                        }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("unknown option `{}`", key),
+                    ))
+                }
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        let schema =
+            schema.ok_or_else(|| input.error("one of `schema` and `schema_str` is required"))?;
+
+        Ok(Self { schema, settings })
     }
+}
+
+/// A list of derives, e.g. `[PartialEq, schemars::JsonSchema]`.
+fn parse_derives(input: ParseStream) -> syn::Result<Vec<String>> {
+    let content;
+    bracketed!(content in input);
+    let derives = Punctuated::<syn::Path, Token![,]>::parse_terminated(&content)?;
+    Ok(derives
+        .iter()
+        .map(|derive| derive.to_token_stream().to_string())
+        .collect())
+}
+
+/// The changes to a type, e.g. `{ rename = "MyThing", derives = [Hash] }`.
+fn parse_patch(input: ParseStream) -> syn::Result<TypePatch> {
+    let content;
+    braced!(content in input);
+    let mut type_patch = TypePatch::default();
+    while !content.is_empty() {
+        let key = content.parse::<Ident>()?;
+        content.parse::<Token![=]>()?;
+        match key.to_string().as_str() {
+            "rename" => {
+                let rename = content.parse::<LitStr>()?;
+                if syn::parse_str::<Ident>(&rename.value()).is_err() {
+                    return Err(syn::Error::new(
+                        rename.span(),
+                        format!("`{}` isn't an identifier", rename.value()),
+                    ));
+                }
+                type_patch.with_rename(rename.value());
+            }
+            "derives" => {
+                for derive in parse_derives(&content)? {
+                    type_patch.with_derive(derive);
+                }
+            }
+            "doc" => {
+                type_patch.with_doc(content.parse::<LitStr>()?.value());
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("unknown patch option `{}`", key),
+                ))
+            }
+        }
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+    }
+    Ok(type_patch)
+}
+
+fn do_import_types(item: TokenStream) -> Result<TokenStream, syn::Error> {
+    let MacroSettings { schema, settings } = syn::parse::<MacroSettings>(item)?;
+    let mut type_space = TypeSpace::new(&settings);
+
+    let (paths, span, dir) = match schema {
+        Schema::Files(lits) => {
+            let dir = std::env::var("CARGO_MANIFEST_DIR").map_or_else(
+                |_| std::env::current_dir().unwrap(),
+                |s| Path::new(&s).to_path_buf(),
+            );
+            let span = match lits.first() {
+                Some(lit) => lit.span(),
+                None => return Err(syn::Error::new(Span::call_site(), "no schema was given")),
+            };
+            let mut paths = Vec::new();
+            for lit in &lits {
+                paths.extend(expand_path(&dir, lit)?);
+            }
+            let paths = type_space
+                .add_schema_files(&paths)
+                .map_err(|e| into_syn_err(e, span, &dir))?;
+            (paths, span, dir)
+        }
+        Schema::Inline(lit) => {
+            let root_schema = serde_json::from_str::<RootSchema>(&lit.value())
+                .map_err(|e| syn::Error::new(lit.span(), format!("invalid schema: {}", e)))?;
+            type_space
+                .add_root_schema(root_schema)
+                .map_err(|e| into_syn_err(e, lit.span(), Path::new("")))?;
+            (Vec::new(), lit.span(), PathBuf::new())
+        }
+    };
 
     let types = type_space.to_stream();
-    let path_str = path.to_string_lossy();
+    let tracking = track_paths(&paths);
+    let warnings = warnings(&type_space, span, &dir);
     let output = quote! {
         #types
 
-        // Force a rebuild when the given file is modified.
-        const _: &str = include_str!(#path_str);
+        #tracking
+        #warnings
     };
 
     Ok(output.into())
 }
 
-fn into_syn_err(e: typify_impl::Error, span: proc_macro2::Span) -> syn::Error {
-    syn::Error::new(span, e.to_string())
+/// Force a rebuild when the given files, or the files they reference, are
+/// modified. On nightly we tell the compiler about them directly; otherwise we
+/// include them in code that's never used.
+#[cfg(feature = "nightly")]
+fn track_paths(paths: &[PathBuf]) -> proc_macro2::TokenStream {
+    for path in paths {
+        proc_macro::tracked::path(path);
+    }
+    quote! {}
+}
+
+#[cfg(not(feature = "nightly"))]
+fn track_paths(paths: &[PathBuf]) -> proc_macro2::TokenStream {
+    let path_strs = paths.iter().map(|path| path.to_string_lossy());
+    quote! {
+        #( const _: &str = include_str!(#path_strs); )*
+    }
+}
+
+/// The files for a path, which may be a directory or have a pattern with `*`
+/// and `?` wildcards in its file name, in order so that the generated code
+/// doesn't depend on the order of directory entries.
+fn expand_path(dir: &Path, lit: &LitStr) -> Result<Vec<PathBuf>, syn::Error> {
+    let path = dir.join(lit.value());
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let (dir, patterns) = if path.is_dir() {
+        let mut patterns = vec!["*.json".to_string()];
+        if cfg!(feature = "serde_yaml") {
+            patterns.push("*.yaml".to_string());
+            patterns.push("*.yml".to_string());
+        }
+        (path.clone(), patterns)
+    } else if file_name.contains(['*', '?']) {
+        (path.parent().unwrap().to_path_buf(), vec![file_name])
+    } else {
+        return Ok(vec![path]);
+    };
+    let patterns = patterns
+        .iter()
+        .map(|pattern| pattern.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let entries = std::fs::read_dir(&dir).map_err(|e| {
+        syn::Error::new(
+            lit.span(),
+            format!("couldn't read {}: {}", dir.display(), e),
+        )
+    })?;
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.file_name().is_some_and(|name| {
+                    let name = name.to_string_lossy().chars().collect::<Vec<_>>();
+                    patterns
+                        .iter()
+                        .any(|pattern| wildcard_match(pattern, &name))
+                })
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(syn::Error::new(lit.span(), "no schema files match"));
+    }
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Uses of deprecated items for the conversions' warnings, as stable Rust
+/// has no other way for a macro to warn.
+fn warnings(type_space: &TypeSpace, span: Span, dir: &Path) -> proc_macro2::TokenStream {
+    let notes = type_space.warnings().iter().map(|warning| {
+        format!(
+            "typify: {}: {}",
+            relative(&warning.location, dir),
+            warning.message
+        )
+    });
+    quote::quote_spanned! {span=>
+        #(
+            const _: () = {
+                #[deprecated(note = #notes)]
+                #[allow(non_upper_case_globals)]
+                const typify_warning: () = ();
+                typify_warning
+            };
+        )*
+    }
+}
+
+/// An error with the location in the schema where it occurred, if we know it,
+/// and files named relative to `dir`.
+fn into_syn_err(e: typify_impl::Error, span: proc_macro2::Span, dir: &Path) -> syn::Error {
+    let message = match e.location() {
+        Some(location) => format!("{}: {}", relative(location, dir), e.reason()),
+        None => e.to_string(),
+    };
+    syn::Error::new(span, message)
+}
+
+/// The location with its file named relative to `dir`.
+fn relative(location: &typify_impl::SchemaLocation, dir: &Path) -> typify_impl::SchemaLocation {
+    let mut location = location.clone();
+    if let Some(file) = &mut location.file {
+        if let Ok(relative) = file.strip_prefix(dir) {
+            *file = relative.to_path_buf();
+        }
+    }
+    location
 }
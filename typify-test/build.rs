@@ -0,0 +1,225 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::{env, fs, path::Path};
+
+use typify::{
+    AllOfStrategy, DateTimeCrate, DecimalImpl, MapType, ModuleSplit, NamedSimpleTypes,
+    NonExhaustive, PatternEngine, ReadWriteOnly, SkipSerializingNone, TypePatch, TypeSpace,
+    TypeSpaceSettings, UnknownValues,
+};
+
+/// Generate types for `schemas/<name>.json` (and any documents it references)
+/// into `$OUT_DIR/<name>.rs`.
+fn generate(name: &str, settings: &TypeSpaceSettings) {
+    let path = format!("schemas/{}.json", name);
+
+    let mut type_space = TypeSpace::new(settings);
+    for path in type_space.add_schema_file(&path).unwrap() {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    let content = format!("{}\n{}", "use serde::{Deserialize, Serialize};", type_space);
+
+    let mut out_file = Path::new(&env::var("OUT_DIR").unwrap()).to_path_buf();
+    out_file.push(format!("{}.rs", name));
+    fs::write(out_file, &content).unwrap();
+}
+
+fn main() {
+    generate("adjacently_tagged", &TypeSpaceSettings::default());
+    generate(
+        "aliases",
+        TypeSpaceSettings::default().with_named_simple_types(NamedSimpleTypes::Alias),
+    );
+    generate("all_of", &TypeSpaceSettings::default());
+    generate(
+        "all_of_flatten",
+        TypeSpaceSettings::default().with_all_of_strategy(AllOfStrategy::Flatten),
+    );
+    generate("any", &TypeSpaceSettings::default());
+    generate("binary", &TypeSpaceSettings::default());
+    generate(
+        "builder",
+        TypeSpaceSettings::default()
+            .with_string_validation(true)
+            .with_struct_builder(true),
+    );
+    generate("closed", &TypeSpaceSettings::default());
+    generate("consts", &TypeSpaceSettings::default());
+    generate(
+        "conversion_error",
+        TypeSpaceSettings::default()
+            .with_string_validation(true)
+            .with_struct_builder(true)
+            .with_conversion_error("Error"),
+    );
+    generate(
+        "datetime",
+        TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Chrono),
+    );
+    generate(
+        "datetime_time",
+        TypeSpaceSettings::default().with_datetime_crate(DateTimeCrate::Time),
+    );
+    generate(
+        "decimal",
+        TypeSpaceSettings::default().with_decimal_type(DecimalImpl::RustDecimal),
+    );
+    generate(
+        "decimal_float",
+        TypeSpaceSettings::default().with_decimal_type(DecimalImpl::RustDecimalFloat),
+    );
+    generate(
+        "defaults",
+        TypeSpaceSettings::default().with_skip_serializing_defaults(true),
+    );
+    generate(
+        "deprecated",
+        TypeSpaceSettings::default().with_struct_builder(true),
+    );
+    generate("defs", &TypeSpaceSettings::default());
+    generate(
+        "dependent_required",
+        TypeSpaceSettings::default()
+            .with_object_validation(true)
+            .with_struct_builder(true),
+    );
+    generate(
+        "derives",
+        TypeSpaceSettings::default()
+            .with_derive("PartialEq")
+            .with_derive("Eq")
+            .with_derive("schemars::JsonSchema"),
+    );
+    generate("discriminator", &TypeSpaceSettings::default());
+    generate("embedded_json", &TypeSpaceSettings::default());
+    generate(
+        "draft04",
+        TypeSpaceSettings::default().with_number_validation(true),
+    );
+    generate(
+        "explicit_nulls",
+        TypeSpaceSettings::default()
+            .with_explicit_nulls(true)
+            .with_struct_builder(true),
+    );
+    generate(
+        "extra",
+        TypeSpaceSettings::default().with_capture_extra_properties(true),
+    );
+    generate("external_refs", &TypeSpaceSettings::default());
+    generate("fixed_array", &TypeSpaceSettings::default());
+    generate("identifiers", &TypeSpaceSettings::default());
+    generate("if_then_else", &TypeSpaceSettings::default());
+    generate("integer_enums", &TypeSpaceSettings::default());
+    generate("internally_tagged", &TypeSpaceSettings::default());
+    generate(
+        "items",
+        TypeSpaceSettings::default().with_array_validation(true),
+    );
+    generate(
+        "json_schema",
+        TypeSpaceSettings::default().with_json_schema_impls(true),
+    );
+    generate("keywords", &TypeSpaceSettings::default());
+    generate(
+        "length",
+        TypeSpaceSettings::default()
+            .with_string_validation(true)
+            .with_pattern_engine(PatternEngine::Regex),
+    );
+    generate(
+        "maps",
+        TypeSpaceSettings::default().with_map_type(MapType::BTreeMap),
+    );
+    generate(
+        "maps_ordered",
+        TypeSpaceSettings::default().with_map_type(MapType::IndexMap),
+    );
+    generate("network", &TypeSpaceSettings::default());
+    generate(
+        "no_std",
+        TypeSpaceSettings::default()
+            .with_no_std(true)
+            .with_string_validation(true)
+            .with_nonzero_integers(true)
+            .with_struct_builder(true),
+    );
+    generate(
+        "non_exhaustive",
+        TypeSpaceSettings::default().with_non_exhaustive(NonExhaustive::EnumsAndStructs),
+    );
+    generate(
+        "numbers",
+        TypeSpaceSettings::default().with_number_validation(true),
+    );
+    generate(
+        "pattern",
+        TypeSpaceSettings::default()
+            .with_string_validation(true)
+            .with_pattern_engine(PatternEngine::Regex),
+    );
+    generate(
+        "pattern_ecma",
+        TypeSpaceSettings::default().with_string_validation(true),
+    );
+    generate(
+        "pattern_properties",
+        TypeSpaceSettings::default().with_pattern_engine(PatternEngine::Regex),
+    );
+    generate("pointers", &TypeSpaceSettings::default());
+    generate(
+        "prefixed",
+        TypeSpaceSettings::default()
+            .with_type_prefix("Api")
+            .with_module("types", "pub"),
+    );
+    generate(
+        "property_names",
+        TypeSpaceSettings::default().with_pattern_engine(PatternEngine::Regex),
+    );
+    generate(
+        "read_write",
+        TypeSpaceSettings::default().with_read_write_only(ReadWriteOnly::Split),
+    );
+    generate("recursive", &TypeSpaceSettings::default());
+    generate(
+        "redacted",
+        TypeSpaceSettings::default()
+            .with_redacted_debug(true)
+            .with_redacted_property("pin")
+            .with_struct_builder(true),
+    );
+    generate("rename_all", &TypeSpaceSettings::default());
+    generate(
+        "skip_serializing",
+        TypeSpaceSettings::default()
+            .with_skip_serializing_none(SkipSerializingNone::UnlessNullable)
+            .with_skip_serializing_empty(false),
+    );
+    generate(
+        "split",
+        TypeSpaceSettings::default().with_module_split(ModuleSplit::Document),
+    );
+    generate("tuples", &TypeSpaceSettings::default());
+    generate(
+        "visibility",
+        TypeSpaceSettings::default()
+            .with_field_visibility("")
+            .with_field_accessors(true)
+            .with_struct_builder(true),
+    );
+    generate(
+        "type_arrays",
+        TypeSpaceSettings::default().with_string_validation(true),
+    );
+    generate(
+        "unknown_values",
+        TypeSpaceSettings::default()
+            .with_unknown_values(UnknownValues::Other)
+            .with_patch(
+                "Size",
+                TypePatch::default().with_unknown_values(UnknownValues::Unknown),
+            ),
+    );
+}
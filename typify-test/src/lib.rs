@@ -0,0 +1,281 @@
+// Copyright 2021 Oxide Computer Company
+
+//! Types generated by `build.rs` from the schemas in `schemas/`. The tests
+//! for this crate exercise the generated code.
+
+// For the code generated with `with_no_std(true)`.
+extern crate alloc;
+
+pub mod adjacently_tagged {
+    include!(concat!(env!("OUT_DIR"), "/adjacently_tagged.rs"));
+}
+
+pub mod aliases {
+    include!(concat!(env!("OUT_DIR"), "/aliases.rs"));
+}
+
+pub mod all_of {
+    include!(concat!(env!("OUT_DIR"), "/all_of.rs"));
+}
+
+pub mod all_of_flatten {
+    include!(concat!(env!("OUT_DIR"), "/all_of_flatten.rs"));
+}
+
+pub mod any {
+    include!(concat!(env!("OUT_DIR"), "/any.rs"));
+}
+
+pub mod binary {
+    include!(concat!(env!("OUT_DIR"), "/binary.rs"));
+}
+
+pub mod builder {
+    include!(concat!(env!("OUT_DIR"), "/builder.rs"));
+}
+
+pub mod closed {
+    include!(concat!(env!("OUT_DIR"), "/closed.rs"));
+}
+
+pub mod consts {
+    include!(concat!(env!("OUT_DIR"), "/consts.rs"));
+}
+
+pub mod conversion_error {
+    /// The existing error type that the generated conversions use.
+    #[derive(Debug, PartialEq)]
+    pub struct Error(pub String);
+
+    impl From<&'static str> for Error {
+        fn from(value: &'static str) -> Self {
+            Self(value.to_string())
+        }
+    }
+
+    impl From<String> for Error {
+        fn from(value: String) -> Self {
+            Self(value)
+        }
+    }
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/conversion_error.rs"));
+}
+
+pub mod datetime {
+    include!(concat!(env!("OUT_DIR"), "/datetime.rs"));
+}
+
+pub mod datetime_time {
+    include!(concat!(env!("OUT_DIR"), "/datetime_time.rs"));
+}
+
+pub mod pattern {
+    include!(concat!(env!("OUT_DIR"), "/pattern.rs"));
+}
+
+pub mod pattern_ecma {
+    include!(concat!(env!("OUT_DIR"), "/pattern_ecma.rs"));
+}
+
+pub mod pattern_properties {
+    include!(concat!(env!("OUT_DIR"), "/pattern_properties.rs"));
+}
+
+pub mod decimal {
+    include!(concat!(env!("OUT_DIR"), "/decimal.rs"));
+}
+
+pub mod decimal_float {
+    include!(concat!(env!("OUT_DIR"), "/decimal_float.rs"));
+}
+
+// The generated code implements `ToString` and `Default` for enums of unit
+// variants rather than deriving them, and has its own `defaults` module.
+#[allow(
+    clippy::derivable_impls,
+    clippy::module_inception,
+    clippy::to_string_trait_impl
+)]
+pub mod defaults {
+    include!(concat!(env!("OUT_DIR"), "/defaults.rs"));
+}
+
+pub mod deprecated {
+    include!(concat!(env!("OUT_DIR"), "/deprecated.rs"));
+}
+
+pub mod defs {
+    include!(concat!(env!("OUT_DIR"), "/defs.rs"));
+}
+
+pub mod dependent_required {
+    include!(concat!(env!("OUT_DIR"), "/dependent_required.rs"));
+}
+
+pub mod derives {
+    include!(concat!(env!("OUT_DIR"), "/derives.rs"));
+}
+
+pub mod discriminator {
+    include!(concat!(env!("OUT_DIR"), "/discriminator.rs"));
+}
+
+pub mod draft04 {
+    include!(concat!(env!("OUT_DIR"), "/draft04.rs"));
+}
+
+pub mod embedded_json {
+    include!(concat!(env!("OUT_DIR"), "/embedded_json.rs"));
+}
+
+pub mod explicit_nulls {
+    include!(concat!(env!("OUT_DIR"), "/explicit_nulls.rs"));
+}
+
+pub mod extra {
+    include!(concat!(env!("OUT_DIR"), "/extra.rs"));
+}
+
+pub mod external_refs {
+    include!(concat!(env!("OUT_DIR"), "/external_refs.rs"));
+}
+
+pub mod fixed_array {
+    include!(concat!(env!("OUT_DIR"), "/fixed_array.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod identifiers {
+    include!(concat!(env!("OUT_DIR"), "/identifiers.rs"));
+}
+
+pub mod if_then_else {
+    include!(concat!(env!("OUT_DIR"), "/if_then_else.rs"));
+}
+
+// The generated code implements `ToString` for enums of integers.
+#[allow(clippy::to_string_trait_impl)]
+pub mod integer_enums {
+    include!(concat!(env!("OUT_DIR"), "/integer_enums.rs"));
+}
+
+pub mod internally_tagged {
+    include!(concat!(env!("OUT_DIR"), "/internally_tagged.rs"));
+}
+
+pub mod items {
+    include!(concat!(env!("OUT_DIR"), "/items.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod json_schema {
+    include!(concat!(env!("OUT_DIR"), "/json_schema.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod keywords {
+    include!(concat!(env!("OUT_DIR"), "/keywords.rs"));
+}
+
+pub mod length {
+    include!(concat!(env!("OUT_DIR"), "/length.rs"));
+}
+
+pub mod maps {
+    include!(concat!(env!("OUT_DIR"), "/maps.rs"));
+}
+
+pub mod maps_ordered {
+    include!(concat!(env!("OUT_DIR"), "/maps_ordered.rs"));
+}
+
+pub mod network {
+    include!(concat!(env!("OUT_DIR"), "/network.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod no_std {
+    include!(concat!(env!("OUT_DIR"), "/no_std.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod non_exhaustive {
+    include!(concat!(env!("OUT_DIR"), "/non_exhaustive.rs"));
+}
+
+pub mod numbers {
+    include!(concat!(env!("OUT_DIR"), "/numbers.rs"));
+}
+
+pub mod pointers {
+    include!(concat!(env!("OUT_DIR"), "/pointers.rs"));
+}
+
+// The generated code is wrapped in a module that has its own `use`s.
+#[allow(unused_imports)]
+pub mod prefixed {
+    include!(concat!(env!("OUT_DIR"), "/prefixed.rs"));
+}
+
+pub mod property_names {
+    include!(concat!(env!("OUT_DIR"), "/property_names.rs"));
+}
+
+pub mod read_write {
+    include!(concat!(env!("OUT_DIR"), "/read_write.rs"));
+}
+
+pub mod recursive {
+    include!(concat!(env!("OUT_DIR"), "/recursive.rs"));
+}
+
+pub mod redacted {
+    include!(concat!(env!("OUT_DIR"), "/redacted.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod rename_all {
+    include!(concat!(env!("OUT_DIR"), "/rename_all.rs"));
+}
+
+pub mod skip_serializing {
+    include!(concat!(env!("OUT_DIR"), "/skip_serializing.rs"));
+}
+
+// The modules of the split `use` serde's derive macros themselves, and one
+// is named for the document as this module is.
+#[allow(unused_imports, clippy::module_inception)]
+pub mod split {
+    include!(concat!(env!("OUT_DIR"), "/split.rs"));
+}
+
+pub mod tuples {
+    include!(concat!(env!("OUT_DIR"), "/tuples.rs"));
+}
+
+pub mod type_arrays {
+    include!(concat!(env!("OUT_DIR"), "/type_arrays.rs"));
+}
+
+pub mod visibility {
+    include!(concat!(env!("OUT_DIR"), "/visibility.rs"));
+}
+
+// The generated code implements `ToString` for enums of strings.
+#[allow(clippy::to_string_trait_impl)]
+pub mod unknown_values {
+    include!(concat!(env!("OUT_DIR"), "/unknown_values.rs"));
+}
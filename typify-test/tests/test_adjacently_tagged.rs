@@ -0,0 +1,28 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::adjacently_tagged::{Event, Size};
+
+#[test]
+fn test_adjacently_tagged_round_trip() {
+    let json = r#"[{"type":"close"},{"type":"title","value":"hello"},{"type":"resize","value":{"height":2,"width":3}}]"#;
+
+    let events: Vec<Event> = serde_json::from_str(json).unwrap();
+    assert!(matches!(events[0], Event::Close));
+    assert!(matches!(&events[1], Event::Title(title) if title == "hello"));
+    assert!(matches!(
+        events[2],
+        Event::Resize(Size {
+            height: 2,
+            width: 3
+        })
+    ));
+
+    assert_eq!(serde_json::to_string(&events).unwrap(), json);
+}
+
+#[test]
+fn test_adjacently_tagged_unknown_field() {
+    let err =
+        serde_json::from_str::<Event>(r#"{"type":"title","value":"hello","extra":1}"#).unwrap_err();
+    assert!(err.to_string().contains(r#"expected "type" or "value""#));
+}
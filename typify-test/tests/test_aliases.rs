@@ -0,0 +1,27 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::HashMap;
+
+use typify_test::aliases::{Count, Inventory, Labels, Nickname, Widget, WidgetList};
+
+#[test]
+fn test_aliases() {
+    let count: Count = 3u32;
+    let labels: Labels = HashMap::from([("color".to_string(), "red".to_string())]);
+    let nickname: Nickname = Some("bin".to_string());
+    let widgets: WidgetList = vec![Widget {
+        id: "a".to_string(),
+        parts: None,
+    }];
+    let inventory = Inventory {
+        count,
+        labels,
+        nickname,
+        widgets,
+    };
+
+    let json = r#"{"count":3,"labels":{"color":"red"},"nickname":"bin","widgets":[{"id":"a"}]}"#;
+    assert_eq!(serde_json::to_string(&inventory).unwrap(), json);
+    let parsed: Inventory = serde_json::from_str(json).unwrap();
+    assert_eq!(parsed, inventory);
+}
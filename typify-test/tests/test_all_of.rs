@@ -0,0 +1,34 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::all_of::{Cat, Dog};
+
+#[test]
+fn test_all_of_round_trip() {
+    let json = r#"{"age":3,"breed":"beagle","name":"Snoopy"}"#;
+
+    let dog: Dog = serde_json::from_str(json).unwrap();
+    assert_eq!(dog.name, "Snoopy");
+    assert_eq!(dog.breed, "beagle");
+    assert_eq!(dog.age, Some(3));
+
+    assert_eq!(serde_json::to_string(&dog).unwrap(), json);
+}
+
+#[test]
+fn test_all_of_sibling_properties() {
+    let json = r#"{"indoor":true,"name":"Garfield"}"#;
+
+    let cat: Cat = serde_json::from_str(json).unwrap();
+    assert!(cat.indoor);
+    assert_eq!(cat.name, "Garfield");
+
+    assert_eq!(serde_json::to_string(&cat).unwrap(), json);
+}
+
+#[test]
+fn test_all_of_required() {
+    // Both the inherited and the extension's required properties are
+    // required.
+    assert!(serde_json::from_str::<Dog>(r#"{"breed":"beagle"}"#).is_err());
+    assert!(serde_json::from_str::<Dog>(r#"{"name":"Snoopy"}"#).is_err());
+}
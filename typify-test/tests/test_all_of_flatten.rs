@@ -0,0 +1,17 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::all_of_flatten::{Dog, Pet};
+
+#[test]
+fn test_all_of_flatten_round_trip() {
+    let json = r#"{"name":"Snoopy","breed":"beagle"}"#;
+
+    let dog: Dog = serde_json::from_str(json).unwrap();
+    assert_eq!(dog.breed, "beagle");
+
+    // The base type is embedded so it may be used on its own.
+    let pet: &Pet = &dog.pet;
+    assert_eq!(pet.name, "Snoopy");
+
+    assert_eq!(serde_json::to_string(&dog).unwrap(), json);
+}
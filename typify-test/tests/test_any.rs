@@ -0,0 +1,34 @@
+// Copyright 2021 Oxide Computer Company
+
+use serde_json::json;
+use typify_test::any::{Envelope, EnvelopeForbidden};
+
+#[test]
+fn test_any_round_trip() {
+    let json = r#"{"attachments":[1,"two",null],"extra":{"nested":[true]},"headers":{"x-count":3},"payload":[{"a":1}]}"#;
+
+    let envelope: Envelope = serde_json::from_str(json).unwrap();
+    assert_eq!(envelope.payload, json!([{"a": 1}]));
+    assert_eq!(envelope.extra, Some(json!({"nested": [true]})));
+    assert_eq!(envelope.headers.get("x-count"), Some(&json!(3)));
+    assert_eq!(
+        envelope.attachments,
+        vec![json!(1), json!("two"), json!(null)]
+    );
+    assert!(envelope.forbidden.is_none());
+    assert!(envelope.unused.is_empty());
+
+    assert_eq!(serde_json::to_string(&envelope).unwrap(), json);
+}
+
+#[test]
+fn test_never_invalid() {
+    let json = r#"{"payload":null,"forbidden":"anything"}"#;
+    assert!(serde_json::from_str::<Envelope>(json).is_err());
+
+    let json = r#"{"payload":null,"unused":[1]}"#;
+    assert!(serde_json::from_str::<Envelope>(json).is_err());
+
+    assert!(serde_json::from_str::<EnvelopeForbidden>("null").is_err());
+    assert!(serde_json::from_str::<EnvelopeForbidden>("{}").is_err());
+}
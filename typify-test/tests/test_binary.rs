@@ -0,0 +1,56 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::binary::Attachment;
+
+#[test]
+fn test_binary_round_trip() {
+    let json = r#"{"checksum":"00ff7f","content":"aGVsbG8=","thumbnail":"AQID"}"#;
+    let attachment: Attachment = serde_json::from_str(json).unwrap();
+    assert_eq!(attachment.content, b"hello");
+    assert_eq!(
+        attachment.checksum.as_deref(),
+        Some(&[0x00, 0xff, 0x7f][..])
+    );
+    assert_eq!(attachment.thumbnail.as_deref(), Some(&[1, 2, 3][..]));
+    assert_eq!(serde_json::to_string(&attachment).unwrap(), json);
+
+    // Every length of a final group is padded.
+    for (bytes, encoded) in [
+        (&b""[..], ""),
+        (&b"h"[..], "aA=="),
+        (&b"he"[..], "aGU="),
+        (&b"hel"[..], "aGVs"),
+    ] {
+        let attachment = Attachment {
+            checksum: None,
+            content: bytes.to_vec(),
+            thumbnail: None,
+        };
+        let json = format!(r#"{{"content":"{}"}}"#, encoded);
+        assert_eq!(serde_json::to_string(&attachment).unwrap(), json);
+        assert_eq!(
+            serde_json::from_str::<Attachment>(&json).unwrap(),
+            attachment
+        );
+    }
+
+    // Padding may be omitted, and hexadecimal digits may be uppercase.
+    let json = r#"{"checksum":"00FF","content":"aGU"}"#;
+    let attachment: Attachment = serde_json::from_str(json).unwrap();
+    assert_eq!(attachment.content, b"he");
+    assert_eq!(attachment.checksum.as_deref(), Some(&[0x00, 0xff][..]));
+}
+
+#[test]
+fn test_binary_invalid() {
+    let err = serde_json::from_str::<Attachment>(r#"{"content":"a*=="}"#).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains(r#"invalid base64 "a*==": invalid character '*'"#),
+        "{}",
+        err
+    );
+    assert!(serde_json::from_str::<Attachment>(r#"{"content":"a"}"#).is_err());
+    assert!(serde_json::from_str::<Attachment>(r#"{"content":"","checksum":"0"}"#).is_err());
+    assert!(serde_json::from_str::<Attachment>(r#"{"content":"","checksum":"+f"}"#).is_err());
+}
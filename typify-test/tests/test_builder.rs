@@ -0,0 +1,40 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryInto;
+
+use typify_test::builder::{error::ConversionError, Code, Thing};
+
+#[test]
+fn test_builder() {
+    let thing: Thing = Thing::builder()
+        .code("abc")
+        .name("widget")
+        .tags(vec!["new".to_string()])
+        .try_into()
+        .unwrap();
+    assert_eq!(thing.code.to_string(), "abc");
+    assert_eq!(thing.name, "widget");
+    assert_eq!(thing.size, 3);
+    assert_eq!(thing.tags, ["new"]);
+
+    let result: Result<Thing, ConversionError> = Thing::builder().code("abc").try_into();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "no value supplied for name"
+    );
+
+    let result: Result<Thing, ConversionError> =
+        Thing::builder().code("abcde").name("x").try_into();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "error converting supplied value for code: longer than maxLength 4"
+    );
+
+    // Newtype values are taken as they are rather than checked again.
+    let thing: Thing = Thing::builder()
+        .code(Code::new_unchecked("abcde".to_string()))
+        .name("x")
+        .try_into()
+        .unwrap();
+    assert_eq!(thing.code.into_inner(), "abcde");
+}
@@ -0,0 +1,29 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::closed::{Config, Labels};
+
+#[test]
+fn test_closed_round_trip() {
+    let json = r#"{"name":"api","port":8080}"#;
+    let config: Config = serde_json::from_str(json).unwrap();
+    assert_eq!(config.port, Some(8080));
+    assert_eq!(serde_json::to_string(&config).unwrap(), json);
+}
+
+#[test]
+fn test_closed_unknown_field() {
+    let json = r#"{"name":"api","prot":8080}"#;
+    let err = serde_json::from_str::<Config>(json).unwrap_err();
+    assert!(err.to_string().contains("unknown field `prot`"));
+}
+
+#[test]
+fn test_open_extra_fields() {
+    let json = r#"{"name":"api","tier":"frontend"}"#;
+    let labels: Labels = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        labels.extra.get("tier").map(String::as_str),
+        Some("frontend")
+    );
+    assert_eq!(serde_json::to_string(&labels).unwrap(), json);
+}
@@ -0,0 +1,30 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::consts::{Document, DocumentDraft, DocumentOrigin, DocumentRevision, Version};
+
+#[test]
+fn test_consts() {
+    let doc = Document {
+        version: Version,
+        draft: DocumentDraft,
+        revision: DocumentRevision,
+        origin: DocumentOrigin,
+        title: None,
+    };
+    let json = serde_json::to_value(&doc).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "version": "v1",
+            "draft": false,
+            "revision": 2,
+            "origin": { "x": 0, "y": 0 }
+        })
+    );
+    assert_eq!(serde_json::from_value::<Document>(json).unwrap(), doc);
+
+    assert!(serde_json::from_str::<Version>(r#""v2""#).is_err());
+    assert!(serde_json::from_str::<DocumentDraft>("true").is_err());
+    assert!(serde_json::from_str::<DocumentRevision>("3").is_err());
+    assert!(serde_json::from_str::<DocumentOrigin>(r#"{"x":1,"y":0}"#).is_err());
+}
@@ -0,0 +1,31 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::{TryFrom, TryInto};
+
+use typify_test::conversion_error::{Error, Handle, User};
+
+#[test]
+fn test_conversion_error() {
+    // Conversions and builders fail with the existing error type.
+    assert_eq!(
+        Handle::try_from("abcdefghi").unwrap_err(),
+        Error("longer than maxLength 8".to_string())
+    );
+    assert_eq!(Handle::try_from("ada").unwrap().to_string(), "ada");
+
+    let result: Result<User, Error> = User::builder().try_into();
+    assert_eq!(
+        result.unwrap_err(),
+        Error("no value supplied for handle".to_string())
+    );
+    let user: User = User::builder().handle("ada").try_into().unwrap();
+    assert_eq!(serde_json::to_string(&user).unwrap(), r#"{"handle":"ada"}"#);
+
+    // Deserialization reports the error's message.
+    let err = serde_json::from_str::<User>(r#"{"handle":""}"#).unwrap_err();
+    assert!(
+        err.to_string().contains("shorter than minLength 1"),
+        "{}",
+        err
+    );
+}
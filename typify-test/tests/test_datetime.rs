@@ -0,0 +1,25 @@
+// Copyright 2021 Oxide Computer Company
+
+use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+use typify_test::datetime::Appointment;
+
+#[test]
+fn test_datetime_round_trip() {
+    let json = r#"{"day":"2021-12-07","start":"13:37:00","when":"2021-12-07T13:37:00Z"}"#;
+
+    let appointment: Appointment = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        appointment.day,
+        NaiveDate::from_ymd_opt(2021, 12, 7).unwrap()
+    );
+    assert_eq!(
+        appointment.start,
+        NaiveTime::from_hms_opt(13, 37, 0).unwrap()
+    );
+    assert_eq!(
+        appointment.when,
+        Utc.with_ymd_and_hms(2021, 12, 7, 13, 37, 0).unwrap()
+    );
+
+    assert_eq!(serde_json::to_string(&appointment).unwrap(), json);
+}
@@ -0,0 +1,77 @@
+// Copyright 2021 Oxide Computer Company
+
+use time::macros::{date, datetime, time};
+use typify_test::datetime_time::{Calendar, Meeting};
+
+#[test]
+fn test_datetime_time_round_trip() {
+    let json = r#"{"cancelled":"2021-12-06T09:00:00Z","day":"2021-12-07","slot":{"end":"14:00:00","start":"13:37:00"}}"#;
+
+    let meeting: Meeting = serde_json::from_str(json).unwrap();
+    assert_eq!(meeting.day, date!(2021 - 12 - 07));
+    assert_eq!(meeting.slot.start, time!(13:37:00));
+    assert_eq!(meeting.slot.end, Some(time!(14:00:00)));
+    assert_eq!(meeting.cancelled, Some(datetime!(2021-12-06 09:00:00 UTC)));
+
+    // Times are always serialized with fractional seconds.
+    let json = serde_json::to_string(&meeting).unwrap();
+    assert_eq!(
+        json,
+        r#"{"cancelled":"2021-12-06T09:00:00Z","day":"2021-12-07","slot":{"end":"14:00:00.0","start":"13:37:00.0"}}"#
+    );
+    let meeting: Meeting = serde_json::from_str(&json).unwrap();
+    assert_eq!(meeting.slot.start, time!(13:37:00));
+}
+
+#[test]
+fn test_datetime_time_absent() {
+    let json = r#"{"day":"2021-12-07","slot":{"start":"13:37:00.25"}}"#;
+
+    let meeting: Meeting = serde_json::from_str(json).unwrap();
+    assert_eq!(meeting.cancelled, None);
+    assert_eq!(meeting.slot.start, time!(13:37:00.25));
+    assert_eq!(meeting.slot.end, None);
+
+    assert_eq!(serde_json::to_string(&meeting).unwrap(), json);
+}
+
+#[test]
+fn test_datetime_time_collections() {
+    let json = r#"{"history":["2021-12-01T08:00:00Z"],"holidays":["2021-12-24","2021-12-25"],"postponed":["2021-12-08T10:30:00Z"],"reminders":{"standup":"2021-12-06T09:00:00Z"}}"#;
+
+    let calendar: Calendar = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        calendar.holidays,
+        vec![date!(2021 - 12 - 24), date!(2021 - 12 - 25)]
+    );
+    assert_eq!(
+        calendar.reminders.get("standup"),
+        Some(&datetime!(2021-12-06 09:00:00 UTC))
+    );
+    assert_eq!(
+        calendar.postponed,
+        Some(vec![datetime!(2021-12-08 10:30:00 UTC)])
+    );
+    assert_eq!(
+        calendar.history.as_ref().map(|history| history.0.clone()),
+        Some(vec![datetime!(2021-12-01 08:00:00 UTC)])
+    );
+
+    assert_eq!(serde_json::to_string(&calendar).unwrap(), json);
+}
+
+#[test]
+fn test_datetime_time_collections_empty() {
+    let json = r#"{"holidays":[],"postponed":null}"#;
+
+    let calendar: Calendar = serde_json::from_str(json).unwrap();
+    assert!(calendar.holidays.is_empty());
+    assert!(calendar.reminders.is_empty());
+    assert_eq!(calendar.postponed, None);
+
+    assert_eq!(
+        serde_json::to_string(&calendar).unwrap(),
+        r#"{"holidays":[]}"#
+    );
+    assert!(serde_json::from_str::<Calendar>(r#"{"holidays":["24 December"]}"#).is_err());
+}
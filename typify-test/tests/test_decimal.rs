@@ -0,0 +1,36 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use typify_test::{decimal, decimal_float};
+
+#[test]
+fn test_decimal_round_trip() {
+    let json = r#"{"discount":"0.2","subtotal":"0.1","tax":"0.30"}"#;
+
+    let invoice: decimal::Invoice = serde_json::from_str(json).unwrap();
+    // Unlike f64, the sum is exact.
+    assert_eq!(
+        invoice.subtotal + invoice.discount.unwrap(),
+        Decimal::from_str("0.3").unwrap()
+    );
+    assert_eq!(invoice.tax, Decimal::from_str("0.30").unwrap());
+
+    assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+}
+
+#[test]
+fn test_decimal_float_round_trip() {
+    let json = r#"{"subtotal":0.1,"tax":0.2}"#;
+
+    let invoice: decimal_float::Invoice = serde_json::from_str(json).unwrap();
+    assert_eq!(invoice.discount, None);
+    assert_eq!(
+        invoice.subtotal + invoice.tax,
+        Decimal::from_str("0.3").unwrap()
+    );
+    assert_ne!(0.1 + 0.2, 0.3);
+
+    assert_eq!(serde_json::to_string(&invoice).unwrap(), json);
+}
@@ -0,0 +1,45 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::defaults::{Config, Limits, Mode, Point, Shape};
+
+#[test]
+fn test_defaults() {
+    let config = Config::default();
+    assert_eq!(config.retries, 3);
+    assert_eq!(config.name, None);
+    assert_eq!(config.mode, Mode::Slow);
+    assert_eq!(
+        config.limits,
+        Limits {
+            soft: 1,
+            hard: None
+        }
+    );
+    assert_eq!(config.labels.get("env").map(String::as_str), Some("test"));
+    assert_eq!(config.shape, Shape::Circle { radius: 1.5 });
+
+    assert_eq!(Point::default(), Point(1, 2));
+}
+
+#[test]
+fn test_serde_defaults() {
+    let config: Config = serde_json::from_str(r#"{ "mode": "fast" }"#).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            mode: Mode::Fast,
+            ..Config::default()
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"mode":"fast"}"#
+    );
+
+    let config: Config = serde_json::from_str(r#"{ "mode": "slow", "retries": 5 }"#).unwrap();
+    assert_eq!(config.retries, 5);
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"mode":"slow","retries":5}"#
+    );
+}
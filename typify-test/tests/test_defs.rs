@@ -0,0 +1,19 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::defs::{Author, Book, Library};
+
+#[test]
+fn test_defs_round_trip() {
+    let json = r#"{"books":[{"author":{"name":"Ursula"},"title":"Earthsea"}]}"#;
+
+    let library: Library = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        library.books.as_slice(),
+        [Book {
+            author: Author { name },
+            ..
+        }] if name == "Ursula"
+    ));
+
+    assert_eq!(serde_json::to_string(&library).unwrap(), json);
+}
@@ -0,0 +1,46 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryInto;
+
+use typify_test::dependent_required::{error::ConversionError, Payment};
+
+#[test]
+fn test_dependent_required_deserialize() {
+    let json = r#"{"billing_address":"1 Main St","credit_card":"4111","name":"Ann","security_codes":["123"]}"#;
+    let payment: Payment = serde_json::from_str(json).unwrap();
+    assert_eq!(payment.credit_card.as_deref(), Some("4111"));
+    assert_eq!(serde_json::to_string(&payment).unwrap(), json);
+
+    // Without a credit card nothing else is needed.
+    let payment: Payment = serde_json::from_str(r#"{"name":"Ann"}"#).unwrap();
+    assert!(payment.validate().is_ok());
+
+    let err =
+        serde_json::from_str::<Payment>(r#"{"credit_card":"4111","name":"Ann"}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "credit_card requires billing_address, security_codes"
+    );
+}
+
+#[test]
+fn test_dependent_required_builder() {
+    let result: Result<Payment, ConversionError> = Payment::builder()
+        .name("Ann")
+        .credit_card(Some("4111".to_string()))
+        .security_codes(vec!["123".to_string()])
+        .try_into();
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "credit_card requires billing_address"
+    );
+
+    let payment: Payment = Payment::builder()
+        .name("Ann")
+        .credit_card(Some("4111".to_string()))
+        .billing_address(Some("1 Main St".to_string()))
+        .security_codes(vec!["123".to_string()])
+        .try_into()
+        .unwrap();
+    assert!(payment.validate().is_ok());
+}
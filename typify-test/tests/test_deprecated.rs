@@ -0,0 +1,19 @@
+// Copyright 2021 Oxide Computer Company
+
+#![allow(deprecated)]
+
+use std::convert::TryInto;
+
+use typify_test::deprecated::{Size, Thing};
+
+#[test]
+fn test_deprecated() {
+    let thing: Thing = serde_json::from_str(r#"{ "name": "x" }"#).unwrap();
+    assert_eq!(thing.legacy, 7);
+
+    let thing: Thing = Thing::builder().name("x").try_into().unwrap();
+    assert_eq!(thing.legacy, 7);
+
+    let size: Size = serde_json::from_str(r#""huge""#).unwrap();
+    assert_eq!(size, Size::Huge);
+}
@@ -0,0 +1,34 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::derives::{Item, Order};
+
+#[test]
+fn test_derives_eq() {
+    let json = r#"{"id":"o-1","items":[{"quantity":2,"sku":"widget"}]}"#;
+    let order: Order = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        order,
+        Order {
+            id: "o-1".to_string(),
+            items: vec![Item {
+                quantity: 2,
+                sku: "widget".to_string(),
+            }],
+            note: None,
+        }
+    );
+    assert_ne!(
+        order,
+        Order {
+            note: Some("rush".to_string()),
+            ..order.clone()
+        }
+    );
+}
+
+#[test]
+fn test_derives_json_schema() {
+    let schema = schemars::schema_for!(Order);
+    let schema = serde_json::to_value(&schema).unwrap();
+    assert!(schema.pointer("/properties/items").is_some());
+}
@@ -0,0 +1,20 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::discriminator::Pet;
+
+#[test]
+fn test_discriminator_round_trip() {
+    let json = r#"[{"petType":"dog","bark":true},{"petType":"cat","lives":9}]"#;
+
+    let pets: Vec<Pet> = serde_json::from_str(json).unwrap();
+    assert!(matches!(pets[0], Pet::Dog { bark: true }));
+    assert!(matches!(pets[1], Pet::Cat { lives: 9 }));
+
+    assert_eq!(serde_json::to_string(&pets).unwrap(), json);
+}
+
+#[test]
+fn test_discriminator_unknown_tag() {
+    let err = serde_json::from_str::<Pet>(r#"{"petType":"fish"}"#).unwrap_err();
+    assert!(err.to_string().contains("unknown variant `fish`"));
+}
@@ -0,0 +1,21 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::draft04::{Reading, Sensor};
+
+#[test]
+fn test_draft04_round_trip() {
+    let json = r#"{"channel":255,"name":"probe","reading":0.5}"#;
+
+    let sensor: Sensor = serde_json::from_str(json).unwrap();
+    let channel: u8 = sensor.channel;
+    assert_eq!((sensor.name.as_str(), channel), ("probe", 255));
+    assert_eq!(serde_json::to_string(&sensor).unwrap(), json);
+
+    // The boolean exclusiveMinimum excludes the minimum itself.
+    assert!(Reading::try_from(0.0).is_err());
+    assert!(Reading::try_from(100.0).is_ok());
+    assert!(serde_json::from_str::<Sensor>(r#"{"channel":256,"name":"probe"}"#).is_err());
+    assert!(serde_json::from_str::<Sensor>(r#"{"channel":1}"#).is_err());
+}
@@ -0,0 +1,37 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::embedded_json::{Event, EventContext, EventPayload, EventPayloadContent};
+
+#[test]
+fn test_embedded_json_round_trip() {
+    // The inner document's strings have quotes and backslashes of their own.
+    let json =
+        r#"{"context":"[1,{\"a\":null}]","payload":"{\"message\":\"say \\\"hi\\\" \\\\ bye\"}"}"#;
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert_eq!(event.payload.message, r#"say "hi" \ bye"#);
+    assert_eq!(
+        *event.context.unwrap(),
+        serde_json::json!([1, { "a": null }])
+    );
+
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_string(&event).unwrap(), json);
+}
+
+#[test]
+fn test_embedded_json_conversions() {
+    let payload = EventPayload::try_from(r#"{"message":"hi"}"#).unwrap();
+    assert_eq!(
+        payload,
+        EventPayload::from(EventPayloadContent {
+            message: "hi".to_string()
+        })
+    );
+    assert!(EventPayload::try_from(r#"{"message":1}"#).is_err());
+    assert!(EventContext::try_from("not json").is_err());
+
+    let json = r#"{"payload":"{\"message\""}"#;
+    assert!(serde_json::from_str::<Event>(json).is_err());
+}
@@ -0,0 +1,45 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryInto;
+
+use typify_test::explicit_nulls::{Owner, PetPatch};
+
+#[test]
+fn test_explicit_nulls_round_trip() {
+    // Absent, null, and present values each survive a round trip.
+    for (json, nickname, owner) in [
+        (r#"{"id":"a"}"#, None, None),
+        (
+            r#"{"id":"a","nickname":null,"owner":null}"#,
+            Some(None),
+            Some(None),
+        ),
+        (
+            r#"{"id":"a","nickname":"Rex","owner":{"name":"Ada"}}"#,
+            Some(Some("Rex".to_string())),
+            Some(Some(Owner {
+                name: "Ada".to_string(),
+            })),
+        ),
+    ] {
+        let patch: PetPatch = serde_json::from_str(json).unwrap();
+        assert_eq!(patch.nickname, nickname, "{}", json);
+        assert_eq!(patch.owner, owner, "{}", json);
+        assert_eq!(serde_json::to_string(&patch).unwrap(), json);
+    }
+
+    // Properties that aren't nullable are plain options.
+    let patch: PetPatch = serde_json::from_str(r#"{"id":"a","name":"Rex"}"#).unwrap();
+    assert_eq!(patch.name, Some("Rex".to_string()));
+
+    // Builders start with the properties absent.
+    let patch: PetPatch = PetPatch::builder()
+        .id("a")
+        .nickname(Some(None))
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&patch).unwrap(),
+        r#"{"id":"a","nickname":null}"#
+    );
+}
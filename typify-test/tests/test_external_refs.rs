@@ -0,0 +1,28 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::external_refs::{Address, Customer, Order, Product};
+
+#[test]
+fn test_external_refs_round_trip() {
+    let json =
+        r#"{"customer":{"address":{"city":"Paris"},"name":"Ada"},"products":[{"sku":"A1"}]}"#;
+
+    let order: Order = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        &order.customer,
+        Customer {
+            address: Address { city },
+            ..
+        } if city == "Paris"
+    ));
+    assert!(matches!(
+        order.products.as_slice(),
+        [Product {
+            sku,
+            orders,
+            supplier: None,
+        }] if sku == "A1" && orders.is_empty()
+    ));
+
+    assert_eq!(serde_json::to_string(&order).unwrap(), json);
+}
@@ -0,0 +1,21 @@
+// Copyright 2021 Oxide Computer Company
+
+use serde_json::json;
+use typify_test::extra::Event;
+
+#[test]
+fn test_extra_round_trip() {
+    let json = r#"{"extra":"declared","kind":"click","target":{"id":7},"x":10}"#;
+
+    let event: Event = serde_json::from_str(json).unwrap();
+    assert_eq!(event.extra.as_deref(), Some("declared"));
+    assert_eq!(event.extra_1.len(), 2);
+    assert_eq!(event.extra_1.get("target"), Some(&json!({"id": 7})));
+    assert_eq!(event.extra_1.get("x"), Some(&json!(10)));
+
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(
+        value,
+        serde_json::from_str::<serde_json::Value>(json).unwrap()
+    );
+}
@@ -0,0 +1,27 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::fixed_array::{Point, Segment};
+
+#[test]
+fn test_fixed_array_round_trip() {
+    let json = r#"[{"coordinates":[0.0,0.0,0.0]},{"coordinates":[1.0,2.5,-3.0]}]"#;
+
+    let segment: Segment = serde_json::from_str(json).unwrap();
+    let [start, end] = &*segment;
+    assert_eq!(start.coordinates, [0.0; 3]);
+    assert_eq!(end.coordinates, [1.0, 2.5, -3.0]);
+
+    assert_eq!(serde_json::to_string(&segment).unwrap(), json);
+}
+
+#[test]
+fn test_fixed_array_wrong_length() {
+    let json = r#"{"coordinates":[1.0,2.0]}"#;
+    assert!(serde_json::from_str::<Point>(json).is_err());
+
+    let json = r#"{"coordinates":[1.0,2.0,3.0,4.0]}"#;
+    assert!(serde_json::from_str::<Point>(json).is_err());
+
+    let json = r#"[{"coordinates":[0.0,0.0,0.0]}]"#;
+    assert!(serde_json::from_str::<Segment>(json).is_err());
+}
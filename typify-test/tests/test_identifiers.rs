@@ -0,0 +1,46 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::identifiers::{Encoding, Request};
+
+#[test]
+fn test_property_identifiers() {
+    let json = serde_json::json!({
+        "content-type": "text/plain",
+        "$schema": "request.json",
+        "2fa_enabled": true,
+        "foo-bar": "x",
+        "foo_bar": 1,
+        "ünïcödé": "u",
+        "日本": "j",
+    });
+    let request = serde_json::from_value::<Request>(json.clone()).unwrap();
+    assert_eq!(request.content_type, "text/plain");
+    assert_eq!(request.schema.as_deref(), Some("request.json"));
+    assert_eq!(request._2_fa_enabled, Some(true));
+    // The first of the colliding properties keeps its name.
+    assert_eq!(request.foo_bar.as_deref(), Some("x"));
+    assert_eq!(request.foo_bar_2, Some(1));
+    assert_eq!(request.unicode.as_deref(), Some("u"));
+    assert_eq!(request.u65e5_u672c.as_deref(), Some("j"));
+    assert_eq!(serde_json::to_value(request).unwrap(), json);
+}
+
+#[test]
+fn test_variant_identifiers() {
+    for (value, encoding) in [
+        ("utf-8", Encoding::Utf8),
+        ("utf_8", Encoding::Utf8_2),
+        ("+1", Encoding::Plus1),
+        ("-1", Encoding::Minus1),
+        ("1", Encoding::_1),
+        ("ünïcödé", Encoding::Unicode),
+        ("日本", Encoding::U65e5U672c),
+    ] {
+        let json = serde_json::Value::String(value.to_string());
+        assert_eq!(
+            serde_json::from_value::<Encoding>(json.clone()).unwrap(),
+            encoding
+        );
+        assert_eq!(serde_json::to_value(encoding).unwrap(), json);
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::if_then_else::Payment;
+
+#[test]
+fn test_if_then_else_round_trip() {
+    let json = r#"[{"method":"card","card_number":"4111"},{"method":"cash","change":true}]"#;
+
+    let payments: Vec<Payment> = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        &payments[0],
+        Payment::Card { card_number, change: None } if card_number == "4111"
+    ));
+    assert!(matches!(
+        payments[1],
+        Payment::Cash {
+            card_number: None,
+            change: true
+        }
+    ));
+
+    assert_eq!(serde_json::to_string(&payments).unwrap(), json);
+}
+
+#[test]
+fn test_if_then_else_missing_property() {
+    let err = serde_json::from_str::<Payment>(r#"{"method":"card"}"#).unwrap_err();
+    assert!(err.to_string().contains("missing field `card_number`"));
+}
@@ -0,0 +1,32 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::integer_enums::{Level, Priority, Status};
+
+#[test]
+fn test_integer_enums() {
+    let level: Level = serde_json::from_str("-1").unwrap();
+    assert_eq!(level, Level::ValueMinus1);
+    assert_eq!(serde_json::to_string(&Level::Value2).unwrap(), "2");
+    assert!(serde_json::from_str::<Level>("1").is_err());
+    assert_eq!(
+        Level::try_from(1).unwrap_err().to_string(),
+        "invalid value 1"
+    );
+
+    let priority: Priority = serde_json::from_str("5").unwrap();
+    assert_eq!(priority, Priority::Medium);
+    assert_eq!(i64::from(Priority::High), 10);
+    assert_eq!(Priority::VARIANTS.len(), 3);
+}
+
+#[test]
+fn test_enum_names() {
+    let status: Status = serde_json::from_str(r#""in-progress""#).unwrap();
+    assert_eq!(status, Status::Working);
+    assert_eq!(
+        serde_json::to_string(&Status::Finished).unwrap(),
+        r#""done""#
+    );
+}
@@ -0,0 +1,20 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::internally_tagged::Shape;
+
+#[test]
+fn test_internally_tagged_round_trip() {
+    let json = r#"[{"kind":"circle","radius":1.5},{"kind":"square","side":2.0}]"#;
+
+    let shapes: Vec<Shape> = serde_json::from_str(json).unwrap();
+    assert!(matches!(shapes[0], Shape::Circle { radius } if radius == 1.5));
+    assert!(matches!(shapes[1], Shape::Square { side } if side == 2.0));
+
+    assert_eq!(serde_json::to_string(&shapes).unwrap(), json);
+}
+
+#[test]
+fn test_internally_tagged_unknown_tag() {
+    let err = serde_json::from_str::<Shape>(r#"{"kind":"hexagon"}"#).unwrap_err();
+    assert!(err.to_string().contains("unknown variant `hexagon`"));
+}
@@ -0,0 +1,40 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::items::{NonEmptyTeamMembers, Team, TeamReviewers};
+
+#[test]
+fn test_items_round_trip() {
+    let json = r#"{"members":["ada","grace"],"reviewers":["linus"]}"#;
+
+    let team: Team = serde_json::from_str(json).unwrap();
+    assert_eq!(team.members.len(), 2);
+    assert_eq!(team.members.as_ref(), ["ada", "grace"]);
+    assert_eq!(
+        team.reviewers.as_ref().map(|reviewers| reviewers.to_vec()),
+        Some(vec!["linus".to_string()])
+    );
+    let names = (&team.members).into_iter().cloned().collect::<Vec<_>>();
+    assert_eq!(names, ["ada", "grace"]);
+
+    assert_eq!(serde_json::to_string(&team).unwrap(), json);
+}
+
+#[test]
+fn test_items_bounds() {
+    assert!(NonEmptyTeamMembers::try_from(vec![]).is_err());
+    assert!(NonEmptyTeamMembers::try_from(vec!["ada".to_string()]).is_ok());
+
+    let reviewers = ["a", "b", "c", "d"].map(String::from).to_vec();
+    assert_eq!(
+        TeamReviewers::try_from(reviewers).unwrap_err().to_string(),
+        "more than maxItems 3"
+    );
+
+    let json = r#"{"members":[]}"#;
+    assert!(serde_json::from_str::<Team>(json).is_err());
+
+    let json = r#"{"members":["ada"],"reviewers":[]}"#;
+    assert!(serde_json::from_str::<Team>(json).is_err());
+}
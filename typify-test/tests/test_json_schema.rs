@@ -0,0 +1,53 @@
+// Copyright 2021 Oxide Computer Company
+
+use schemars::schema_for;
+use typify::{TypeSpace, TypeSpaceSettings};
+use typify_test::json_schema::{Kind, Name, Pet, Shelter};
+
+fn types(add: impl FnOnce(&mut TypeSpace)) -> String {
+    let mut type_space = TypeSpace::new(&TypeSpaceSettings::default());
+    add(&mut type_space);
+    type_space.to_stream().to_string()
+}
+
+#[test]
+fn test_json_schema_round_trip() {
+    let original = types(|type_space| {
+        type_space
+            .add_schema_file("schemas/json_schema.json")
+            .unwrap();
+    });
+    let generated = types(|type_space| {
+        type_space.add_root_schema(schema_for!(Shelter)).unwrap();
+    });
+    assert_eq!(generated, original);
+}
+
+#[test]
+fn test_json_schema_definitions() {
+    let schema = schema_for!(Pet);
+    assert_eq!(
+        schema.definitions.keys().collect::<Vec<_>>(),
+        ["Kind", "Name", "Pet"]
+    );
+    assert_eq!(
+        serde_json::to_value(&schema.definitions["Kind"]).unwrap(),
+        serde_json::json!({ "type": "string", "enum": ["cat", "dog", "rabbit"] })
+    );
+
+    let name = schema_for!(Name);
+    assert!(name.definitions.is_empty());
+    assert_eq!(
+        serde_json::to_value(&name.schema).unwrap(),
+        serde_json::json!({
+            "title": "Name",
+            "type": "string",
+            "minLength": 1
+        })
+    );
+
+    assert_eq!(
+        serde_json::to_value(Kind::Rabbit).unwrap(),
+        serde_json::json!("rabbit")
+    );
+}
@@ -0,0 +1,43 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::keywords::{Keyword, Keywords};
+
+#[test]
+fn test_keyword_properties() {
+    let json = serde_json::json!({
+        "type": 1,
+        "ref": 2,
+        "self": 3,
+        "crate": 4,
+        "super": 5,
+        "async": 6,
+        "gen": 7,
+    });
+    let keywords = serde_json::from_value::<Keywords>(json.clone()).unwrap();
+    assert_eq!(keywords.r#type, Some(1));
+    assert_eq!(keywords.r#ref, Some(2));
+    assert_eq!(keywords.self_, Some(3));
+    assert_eq!(keywords.crate_, Some(4));
+    assert_eq!(keywords.super_, Some(5));
+    assert_eq!(keywords.r#async, Some(6));
+    assert_eq!(keywords.r#gen, Some(7));
+    assert_eq!(serde_json::to_value(keywords).unwrap(), json);
+}
+
+#[test]
+fn test_keyword_variants() {
+    for (value, keyword) in [
+        ("Self", Keyword::Self_),
+        ("crate", Keyword::Crate),
+        ("type", Keyword::Type),
+        ("yield", Keyword::Yield),
+    ] {
+        let json = serde_json::Value::String(value.to_string());
+        assert_eq!(
+            serde_json::from_value::<Keyword>(json.clone()).unwrap(),
+            keyword
+        );
+        assert_eq!(serde_json::to_value(keyword).unwrap(), json);
+        assert_eq!(keyword.to_string(), value);
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::length::{Account, AccountCode, AccountName};
+
+#[test]
+fn test_length_round_trip() {
+    // Lengths are counted in characters rather than bytes.
+    let json = r#"{"code":"NO","name":"Ærøskøbing"}"#;
+    assert!(serde_json::from_str::<Account>(json).is_err());
+
+    let json = r#"{"code":"NO","name":"Ærø"}"#;
+    let account: Account = serde_json::from_str(json).unwrap();
+    assert_eq!(&*account.name, "Ærø");
+    assert_eq!(account.code.as_deref(), Some("NO"));
+
+    assert_eq!(serde_json::to_string(&account).unwrap(), json);
+}
+
+#[test]
+fn test_length_bounds() {
+    assert_eq!(
+        AccountName::try_from("").unwrap_err().to_string(),
+        "shorter than minLength 1"
+    );
+    assert_eq!(
+        AccountName::try_from("ninechars").unwrap_err().to_string(),
+        "longer than maxLength 8"
+    );
+    assert_eq!(
+        AccountCode::try_from("no").unwrap_err().to_string(),
+        r#"doesn't match pattern "^[A-Z]+$""#
+    );
+    assert!(AccountCode::try_from("NOR").is_err());
+    assert!(AccountCode::try_from("NO").is_ok());
+
+    let err = serde_json::from_str::<Account>(r#"{"name":""}"#).unwrap_err();
+    assert!(err.to_string().contains("shorter than minLength 1"));
+}
+
+#[test]
+fn test_length_unchecked() {
+    let name = AccountName::new_unchecked("ninechars".to_string());
+    assert_eq!(AsRef::<str>::as_ref(&name), "ninechars");
+    assert_eq!(name.into_inner(), "ninechars");
+}
@@ -0,0 +1,34 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::collections::BTreeMap;
+
+use indexmap::IndexMap;
+
+#[test]
+fn test_maps_sorted() {
+    let json = r#"{"dependencies":{"serde":"1.0","anyhow":"1.0","quote":"1.0"},"name":"demo"}"#;
+
+    let manifest: typify_test::maps::Manifest = serde_json::from_str(json).unwrap();
+    let dependencies: &BTreeMap<String, String> = &manifest.dependencies;
+    assert_eq!(
+        dependencies.keys().collect::<Vec<_>>(),
+        ["anyhow", "quote", "serde"]
+    );
+    assert_eq!(
+        serde_json::to_string(&manifest).unwrap(),
+        r#"{"dependencies":{"anyhow":"1.0","quote":"1.0","serde":"1.0"},"name":"demo"}"#
+    );
+}
+
+#[test]
+fn test_maps_ordered() {
+    let json = r#"{"dependencies":{"serde":"1.0","anyhow":"1.0","quote":"1.0"},"name":"demo"}"#;
+
+    let manifest: typify_test::maps_ordered::Manifest = serde_json::from_str(json).unwrap();
+    let dependencies: &IndexMap<String, String> = &manifest.dependencies;
+    assert_eq!(
+        dependencies.keys().collect::<Vec<_>>(),
+        ["serde", "anyhow", "quote"]
+    );
+    assert_eq!(serde_json::to_string(&manifest).unwrap(), json);
+}
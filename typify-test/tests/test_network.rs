@@ -0,0 +1,33 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use typify_test::network::Interface;
+
+#[test]
+fn test_network_round_trip() {
+    let json = r#"{"address":"192.168.1.2","dns":["1.1.1.1","2606:4700:4700::1111"],"gateway":"fe80::1","peer":"::1"}"#;
+
+    let interface: Interface = serde_json::from_str(json).unwrap();
+    assert_eq!(interface.address, Ipv4Addr::new(192, 168, 1, 2));
+    assert_eq!(interface.gateway, "fe80::1".parse::<Ipv6Addr>().unwrap());
+    assert_eq!(interface.peer, IpAddr::V6(Ipv6Addr::LOCALHOST));
+    assert_eq!(
+        interface.dns,
+        vec![
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            "2606:4700:4700::1111".parse::<IpAddr>().unwrap(),
+        ]
+    );
+
+    assert_eq!(serde_json::to_string(&interface).unwrap(), json);
+}
+
+#[test]
+fn test_network_invalid() {
+    let json = r#"{"address":"fe80::1","gateway":"fe80::1","peer":"::1"}"#;
+    assert!(serde_json::from_str::<Interface>(json).is_err());
+
+    let json = r#"{"address":"10.0.0.1","gateway":"fe80::1","peer":"not an address"}"#;
+    assert!(serde_json::from_str::<Interface>(json).is_err());
+}
@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::no_std::{Host, HostName, Protocol};
+
+#[test]
+fn test_no_std() {
+    let json =
+        r#"{"address":"10.0.0.1","labels":{"zone":"a"},"name":"db","port":5432,"protocol":"tcp"}"#;
+    let host = serde_json::from_str::<Host>(json).unwrap();
+    assert_eq!(
+        host.address,
+        "10.0.0.1".parse::<core::net::IpAddr>().unwrap()
+    );
+    assert_eq!(host.labels.get("zone").map(String::as_str), Some("a"));
+    assert_eq!(host.protocol, Some(Protocol::Tcp));
+    assert_eq!(serde_json::to_string(&host).unwrap(), json);
+
+    assert!(HostName::try_from("").is_err());
+    assert!(
+        serde_json::from_str::<Host>(r#"{"address":"10.0.0.1","name":"db","port":0}"#).is_err()
+    );
+}
@@ -0,0 +1,25 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryInto;
+
+use typify_test::non_exhaustive::{Event, EventKind};
+
+#[test]
+fn test_non_exhaustive() {
+    let event: Event = Event::builder()
+        .kind(EventKind::Created)
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"kind":"created"}"#
+    );
+
+    // Other crates must match with a wildcard arm.
+    let name = match event.kind {
+        EventKind::Created => "created",
+        EventKind::Deleted => "deleted",
+        _ => "other",
+    };
+    assert_eq!(name, "created");
+}
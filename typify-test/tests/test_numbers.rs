@@ -0,0 +1,43 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::numbers::{Reading, ReadingPercent, ReadingRatio, ReadingStep};
+
+#[test]
+fn test_numbers_round_trip() {
+    let json = r#"{"percent":42,"quarter":1.75,"ratio":0.5,"step":-15}"#;
+
+    let reading: Reading = serde_json::from_str(json).unwrap();
+    assert_eq!(*reading.percent, 42);
+    assert_eq!(*reading.ratio, 0.5);
+    assert_eq!(*reading.step, -15);
+    assert_eq!(reading.quarter.as_deref().copied(), Some(1.75));
+
+    assert_eq!(serde_json::to_string(&reading).unwrap(), json);
+}
+
+#[test]
+fn test_numbers_invalid() {
+    assert_eq!(
+        ReadingPercent::try_from(101).unwrap_err().to_string(),
+        "greater than maximum 100"
+    );
+    assert_eq!(
+        ReadingRatio::try_from(0.0).unwrap_err().to_string(),
+        "not greater than exclusiveMinimum 0"
+    );
+    assert!(ReadingRatio::try_from(1.0).is_err());
+    assert_eq!(
+        ReadingStep::try_from(7).unwrap_err().to_string(),
+        "not a multiple of 5"
+    );
+    assert!(ReadingStep::try_from(-55).is_err());
+
+    let json = r#"{"percent":42,"quarter":1.7,"ratio":0.5,"step":-15}"#;
+    let err = serde_json::from_str::<Reading>(json).unwrap_err();
+    assert!(err.to_string().contains("not a multiple of 0.25"));
+
+    let json = r#"{"percent":420,"ratio":0.5,"step":-15}"#;
+    assert!(serde_json::from_str::<Reading>(json).is_err());
+}
@@ -0,0 +1,27 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::pattern::{Host, HostLabel, HostTag};
+
+#[test]
+fn test_pattern_round_trip() {
+    let json = r#"{"label":"web-01","tag":"\"prod\""}"#;
+
+    let host: Host = serde_json::from_str(json).unwrap();
+    assert_eq!(&*host.label, "web-01");
+    assert_eq!(host.tag.to_string(), "\"prod\"");
+
+    assert_eq!(serde_json::to_string(&host).unwrap(), json);
+}
+
+#[test]
+fn test_pattern_invalid() {
+    assert!(HostLabel::try_from("Web_01").is_err());
+    assert!(HostLabel::try_from(String::new()).is_err());
+    assert!(HostTag::try_from(r#""back\slash""#).is_err());
+
+    let json = r#"{"label":"Web_01","tag":"\"prod\""}"#;
+    let err = serde_json::from_str::<Host>(json).unwrap_err();
+    assert!(err.to_string().contains("doesn't match pattern"));
+}
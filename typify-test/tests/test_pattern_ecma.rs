@@ -0,0 +1,25 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::pattern_ecma::{Account, Line, Password, Repeated};
+
+#[test]
+fn test_pattern_ecma() {
+    assert!(Password::try_from("secret123").is_ok());
+    assert!(Password::try_from("secretive").is_err());
+    assert!(Password::try_from("abc1").is_err());
+
+    assert!(Repeated::try_from("ab-ab").is_ok());
+    assert!(Repeated::try_from("ab-cd").is_err());
+
+    assert!(Line::try_from("one line").is_ok());
+    assert!(Line::try_from("two\nlines").is_err());
+
+    let json = r#"{"note":"hi","password":"secret123"}"#;
+    let account: Account = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_string(&account).unwrap(), json);
+
+    let err = serde_json::from_str::<Password>(r#""secretive""#).unwrap_err();
+    assert!(err.to_string().contains("doesn't match pattern"));
+}
@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::pattern_properties::{Quota, QuotaLimitsKey};
+
+#[test]
+fn test_pattern_properties_round_trip() {
+    let json = r#"{"limits":{"cpu":4}}"#;
+
+    let quota: Quota = serde_json::from_str(json).unwrap();
+    let key = QuotaLimitsKey::try_from("cpu").unwrap();
+    assert_eq!(quota.limits.get(&key), Some(&4));
+
+    assert_eq!(serde_json::to_string(&quota).unwrap(), json);
+}
+
+#[test]
+fn test_pattern_properties_invalid_key() {
+    let json = r#"{"limits":{"cpu":4,"GPU":1}}"#;
+    assert!(serde_json::from_str::<Quota>(json).is_err());
+
+    assert!(QuotaLimitsKey::try_from("GPU").is_err());
+}
@@ -0,0 +1,15 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::pointers::{Deployment, Primary};
+
+#[test]
+fn test_pointers_round_trip() {
+    let json = r#"{"backup":{"host":"b","port":8080},"primary":{"host":"a","port":80}}"#;
+
+    let deployment: Deployment = serde_json::from_str(json).unwrap();
+    let Primary { host, port } = &deployment.backup;
+    assert_eq!((host.as_str(), *port), ("b", 8080u16));
+    assert_eq!(deployment.primary.port, 80);
+
+    assert_eq!(serde_json::to_string(&deployment).unwrap(), json);
+}
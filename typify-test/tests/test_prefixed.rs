@@ -0,0 +1,17 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::prefixed::types::{ApiConfig, ApiError};
+
+#[test]
+fn test_prefixed() {
+    let config = ApiConfig {
+        name: "x".to_string(),
+        error: Some(ApiError {
+            message: "oops".to_string(),
+        }),
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(json, r#"{"error":{"message":"oops"},"name":"x"}"#);
+    let config: ApiConfig = serde_json::from_str(&json).unwrap();
+    assert_eq!(config.error.unwrap().message, "oops");
+}
@@ -0,0 +1,26 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::property_names::{Inventory, InventoryCountsKey};
+
+#[test]
+fn test_property_names_round_trip() {
+    let json = r#"{"counts":{"red-apples":3}}"#;
+
+    let inventory: Inventory = serde_json::from_str(json).unwrap();
+    let key = InventoryCountsKey::try_from("red-apples").unwrap();
+    assert_eq!(key.to_string(), "red-apples");
+    assert_eq!(inventory.counts.get(&key), Some(&3));
+
+    assert_eq!(serde_json::to_string(&inventory).unwrap(), json);
+}
+
+#[test]
+fn test_property_names_invalid_key() {
+    let json = r#"{"counts":{"red-apples":3,"Bad Key":1}}"#;
+    let err = serde_json::from_str::<Inventory>(json).unwrap_err();
+    assert!(err.to_string().contains("\"Bad Key\""));
+
+    assert!(InventoryCountsKey::try_from("far-too-long-for-a-key".to_string()).is_err());
+}
@@ -0,0 +1,30 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::read_write::{PetRequest, PetResponse, PetsRequest, PetsResponse};
+
+#[test]
+fn test_read_write() {
+    let response: PetsResponse =
+        serde_json::from_str(r#"{ "pets": [{ "id": 1, "name": "Rex" }] }"#).unwrap();
+    assert_eq!(response.pets[0].id, 1);
+
+    let request = PetsRequest {
+        pets: vec![PetRequest {
+            name: "Rex".to_string(),
+            token: Some("secret".to_string()),
+        }],
+    };
+    assert_eq!(
+        serde_json::to_string(&request).unwrap(),
+        r#"{"pets":[{"name":"Rex","token":"secret"}]}"#
+    );
+
+    // Responses don't have the (optional) writeOnly token, but requests
+    // can't be made into responses as they lack the (required) readOnly id.
+    let request = PetRequest::from(PetResponse {
+        id: 1,
+        name: "Rex".to_string(),
+    });
+    assert_eq!(request.name, "Rex");
+    assert_eq!(request.token, None);
+}
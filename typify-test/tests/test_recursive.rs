@@ -0,0 +1,16 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::recursive::{Link, Node};
+
+#[test]
+fn test_recursive_round_trip() {
+    let json = r#"{"children":[{"name":"leaf"}],"link":{"target":{"name":"other"}},"name":"root","parent":{"name":"up"}}"#;
+
+    let node: Node = serde_json::from_str(json).unwrap();
+    assert_eq!(node.children[0].name, "leaf");
+    assert_eq!(node.parent.as_ref().unwrap().name, "up");
+    let Link { target } = node.link.as_deref().unwrap();
+    assert_eq!(target.name, "other");
+
+    assert_eq!(serde_json::to_string(&node).unwrap(), json);
+}
@@ -0,0 +1,36 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryFrom;
+
+use typify_test::redacted::{Credential, Login};
+
+#[test]
+fn test_redacted_struct() {
+    let login = Login {
+        password: "hunter2".to_string(),
+        pin: Some("1234".to_string()),
+        token: Some("abcdef".to_string()),
+        user: "amy".to_string(),
+    };
+    assert_eq!(
+        format!("{:?}", login),
+        r#"Login { password: "***", pin: "***", token: "***", user: "amy" }"#,
+    );
+
+    // The builder holds the values as well.
+    let builder = Login::builder().user("amy").password("hunter2");
+    let debug = format!("{:?}", builder);
+    assert!(!debug.contains("hunter2"));
+    assert!(debug.contains("amy"));
+    assert_eq!(Login::try_from(builder).unwrap().password, "hunter2");
+}
+
+#[test]
+fn test_redacted_enum() {
+    let credential: Credential =
+        serde_json::from_str(r#"{"kind":"key","secret":"s3cret"}"#).unwrap();
+    assert_eq!(format!("{:?}", credential), r#"Key { secret: "***" }"#);
+
+    let credential: Credential = serde_json::from_str(r#"{"kind":"user","user":"amy"}"#).unwrap();
+    assert_eq!(format!("{:?}", credential), r#"User { user: "amy" }"#);
+}
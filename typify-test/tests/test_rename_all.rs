@@ -0,0 +1,45 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::rename_all::{Color, Limits, Person, Status};
+
+#[test]
+fn test_rename_all_properties() {
+    let person = Person {
+        first_name: "Ada".to_string(),
+        last_name: "Lovelace".to_string(),
+        email_address: "ada@example.com".to_string(),
+        id: 1,
+        r#type: "human".to_string(),
+    };
+    let json = serde_json::to_string(&person).unwrap();
+    assert_eq!(
+        json,
+        r#"{"emailAddress":"ada@example.com","firstName":"Ada","ID":1,"lastName":"Lovelace","type":"human"}"#
+    );
+    assert_eq!(serde_json::from_str::<Person>(&json).unwrap(), person);
+
+    let limits = Limits {
+        max_size: 10,
+        min_size: 1,
+        timeout: 30,
+    };
+    let json = serde_json::to_string(&limits).unwrap();
+    assert_eq!(json, r#"{"MAX_SIZE":10,"MIN_SIZE":1,"TIMEOUT":30}"#);
+    assert_eq!(serde_json::from_str::<Limits>(&json).unwrap(), limits);
+}
+
+#[test]
+fn test_rename_all_variants() {
+    for color in Color::VARIANTS {
+        let json = serde_json::to_value(color).unwrap();
+        assert_eq!(json, serde_json::Value::String(color.to_string()));
+        assert_eq!(serde_json::from_value::<Color>(json).unwrap(), *color);
+    }
+    for status in Status::VARIANTS {
+        let json = serde_json::to_value(status).unwrap();
+        assert_eq!(json, serde_json::Value::String(status.to_string()));
+        assert_eq!(serde_json::from_value::<Status>(json).unwrap(), *status);
+    }
+    assert_eq!(Color::Other.to_string(), "Other");
+    assert_eq!(Status::NotStarted.to_string(), "NOT_STARTED");
+}
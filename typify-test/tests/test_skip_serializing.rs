@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::skip_serializing::Profile;
+
+#[test]
+fn test_skip_serializing() {
+    // Absent properties that aren't nullable are still omitted, while
+    // nullable ones and empty collections are serialized.
+    let profile: Profile = serde_json::from_str(r#"{"id":"a"}"#).unwrap();
+    assert_eq!(profile.bio, None);
+    assert_eq!(
+        serde_json::to_string(&profile).unwrap(),
+        r#"{"id":"a","links":{},"tags":[],"website":null}"#
+    );
+
+    let profile: Profile = serde_json::from_str(
+        r#"{"id":"a","bio":"hi","links":{"home":"x"},"tags":["t"],"website":"w"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::to_string(&profile).unwrap(),
+        r#"{"bio":"hi","id":"a","links":{"home":"x"},"tags":["t"],"website":"w"}"#
+    );
+}
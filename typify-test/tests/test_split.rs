@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::split::{self, Cart, CartLines};
+
+#[test]
+fn test_split_modules() {
+    // Each document's types are in a module named for it, and the inline
+    // type goes with the type that uses it.
+    let _: fn(split::people::Customer) -> split::split::Cart = |owner| Cart {
+        owner,
+        lines: Vec::new(),
+    };
+    let _: Option<split::split::CartLines> = None;
+    let _: Option<split::products::Product> = None;
+    let _: Option<split::external_refs::Order> = None;
+
+    let json =
+        r#"{"lines":[{"product":{"sku":"A1"}}],"owner":{"address":{"city":"Paris"},"name":"Ada"}}"#;
+    let cart: Cart = serde_json::from_str(json).unwrap();
+    assert!(matches!(
+        cart.lines.as_slice(),
+        [CartLines { quantity: 1, .. }]
+    ));
+}
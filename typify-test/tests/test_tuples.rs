@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::tuples::{Pixel, Sprite};
+
+#[test]
+fn test_tuples_round_trip() {
+    let json = r#"{"anchor":[0.5,1.0],"name":"cursor","pixels":[[0,0,"red"],[1,2,"blue"]]}"#;
+
+    let sprite: Sprite = serde_json::from_str(json).unwrap();
+    assert_eq!(sprite.anchor, Some((0.5, 1.0)));
+    let Pixel(x, y, color) = &sprite.pixels[1];
+    assert_eq!((*x, *y), (1, 2));
+    assert_eq!(color.as_str(), "blue");
+
+    assert_eq!(serde_json::to_string(&sprite).unwrap(), json);
+}
+
+#[test]
+fn test_tuples_invalid() {
+    assert!(serde_json::from_str::<Pixel>(r#"[0,0]"#).is_err());
+    assert!(serde_json::from_str::<Pixel>(r#"[0,0,"red","extra"]"#).is_err());
+    assert!(serde_json::from_str::<Pixel>(r#"[0,"red",0]"#).is_err());
+    assert!(serde_json::from_str::<Pixel>(r#"[0,0,0]"#).is_err());
+}
@@ -0,0 +1,33 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::type_arrays::{Setting, SettingValue};
+
+#[test]
+fn test_type_arrays_round_trip() {
+    let json = r#"{"comment":"verbosity","key":"level","value":3}"#;
+    let setting: Setting = serde_json::from_str(json).unwrap();
+    assert!(matches!(setting.value, SettingValue::Integer(3)));
+    assert_eq!(setting.comment.as_deref(), Some("verbosity"));
+    assert_eq!(serde_json::to_string(&setting).unwrap(), json);
+
+    let json = r#"{"comment":null,"key":"name","value":"short"}"#;
+    let setting: Setting = serde_json::from_str(json).unwrap();
+    match &setting.value {
+        SettingValue::String(value) => assert_eq!(value.to_string(), "short"),
+        _ => panic!("unexpected variant {:?}", setting.value),
+    }
+    assert_eq!(setting.comment, None);
+
+    let json = r#"{"key":"enabled","value":true}"#;
+    let setting: Setting = serde_json::from_str(json).unwrap();
+    assert!(matches!(setting.value, SettingValue::Boolean(true)));
+}
+
+#[test]
+fn test_type_arrays_invalid() {
+    let json = r#"{"key":"name","value":"much too long for the limit"}"#;
+    assert!(serde_json::from_str::<Setting>(json).is_err());
+
+    let json = r#"{"key":"ratio","value":0.5}"#;
+    assert!(serde_json::from_str::<Setting>(json).is_err());
+}
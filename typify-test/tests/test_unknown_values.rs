@@ -0,0 +1,24 @@
+// Copyright 2021 Oxide Computer Company
+
+use typify_test::unknown_values::{Color, Size};
+
+#[test]
+fn test_other() {
+    let color: Color = serde_json::from_str(r#""red""#).unwrap();
+    assert_eq!(color, Color::Red);
+    let color: Color = serde_json::from_str(r#""other""#).unwrap();
+    assert_eq!(color, Color::Other);
+
+    let color: Color = serde_json::from_str(r#""blue""#).unwrap();
+    assert_eq!(color, Color::Other_("blue".to_string()));
+    assert_eq!(serde_json::to_string(&color).unwrap(), r#""blue""#);
+    assert_eq!(color.to_string(), "blue");
+    assert_eq!(Color::VARIANTS.len(), 3);
+}
+
+#[test]
+fn test_unknown() {
+    let size: Size = serde_json::from_str(r#""medium""#).unwrap();
+    assert_eq!(size, Size::Unknown);
+    assert_eq!(serde_json::to_string(&Size::Large).unwrap(), r#""large""#);
+}
@@ -0,0 +1,19 @@
+// Copyright 2021 Oxide Computer Company
+
+use std::convert::TryInto;
+
+use typify_test::visibility::Account;
+
+#[test]
+fn test_private_fields() {
+    let account: Account = Account::builder()
+        .owner("ada")
+        .balance(10)
+        .try_into()
+        .unwrap();
+    assert_eq!(account.owner(), "ada");
+    assert_eq!(*account.balance(), 10);
+
+    let account: Account = serde_json::from_str(r#"{"owner":"bob","balance":3}"#).unwrap();
+    assert_eq!(account.owner(), "bob");
+}
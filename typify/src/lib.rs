@@ -34,6 +34,20 @@
 //! script or something more complex like a generated that includes types as
 //! part of its definition.
 //!
+//! ```
+//! # use typify::TypeSpace;
+//! let mut type_space = TypeSpace::default();
+//! type_space.add_schema_file("../example.json").unwrap();
+//! let code = type_space.to_string();
+//! ```
+//!
+//! [`TypeSpace::add_root_schema`] (which the macro uses for inline schemas)
+//! adds the definitions of a `RootSchema` and returns the [Type] of its root
+//! schema, if that has a title rather than merely holding the definitions.
+//! [`TypeSpace::add_type_with_name`] adds a single schema, naming its type
+//! for the given hint if the schema has no title, once the definitions it
+//! refers to have been added with [`TypeSpace::add_ref_types`].
+//!
 //! # Mapping JSON Schema to Rust
 //!
 //! JSON Schema allows for extreme flexibility. As such, there are some schemas
@@ -42,7 +56,28 @@
 //! more information, see the project's
 //! [README.md](https://github.com/oxidecomputer/typify).
 
+pub use typify_impl::path_type_name;
+pub use typify_impl::AllOfStrategy;
+pub use typify_impl::DateTimeCrate;
+pub use typify_impl::DecimalImpl;
 pub use typify_impl::Error;
+pub use typify_impl::GeneratedFile;
+pub use typify_impl::MapType;
+pub use typify_impl::ModuleSplit;
+pub use typify_impl::NamedSimpleTypes;
+pub use typify_impl::NonExhaustive;
+pub use typify_impl::PathSegment;
+pub use typify_impl::PatternEngine;
+pub use typify_impl::ReadWriteOnly;
+pub use typify_impl::SchemaLocation;
+pub use typify_impl::SkipSerializingNone;
 pub use typify_impl::Type;
+pub use typify_impl::TypeDetails;
+pub use typify_impl::TypeId;
+pub use typify_impl::TypePatch;
 pub use typify_impl::TypeSpace;
+pub use typify_impl::TypeSpaceSettings;
+pub use typify_impl::TypeVariant;
+pub use typify_impl::UnknownValues;
+pub use typify_impl::Warning;
 pub use typify_macro::import_types;